@@ -0,0 +1,537 @@
+//! Syntax and semantic checks for `ecl check`.
+//!
+//! An expression is checked in three passes, each reporting [`Diagnostic`]s
+//! with a byte position, line and column in the source:
+//!
+//! 1. Syntax: the parser's error, if the expression does not parse.
+//! 2. Identifiers: every concept ID written in the expression must be a
+//!    well-formed SCTID, i.e. have 6 to 18 digits, a concept partition
+//!    identifier and a valid Verhoeff check digit.
+//! 3. Store (only with `--rf2`, `--snapshot` or `--sqlite`): every concept
+//!    ID must be in the store, inactive concepts are reported as warnings,
+//!    and so are term labels (`|...|`) that match none of the concept's
+//!    descriptions.
+//!
+//! Only IDs written in the expression are checked; IDs that the parser
+//! substitutes for aliases such as `type = syn` are well known.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::Serialize;
+use snomed_ecl::{EclError, EclExpression, EclFilter, SctId};
+use snomed_ecl_executor::EclQueryable;
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The expression is invalid or cannot be executed as intended.
+    Error,
+    /// The expression is valid but probably not what was meant.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found in an expression.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// Error or warning.
+    pub severity: Severity,
+    /// The 1-based line of the position, if known.
+    pub line: Option<usize>,
+    /// The 1-based column (in characters) of the position, if known.
+    pub column: Option<usize>,
+    /// The byte offset in the source, if known.
+    pub position: Option<usize>,
+    /// What is wrong.
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn new(
+        severity: Severity,
+        source: &str,
+        position: Option<usize>,
+        message: impl Into<String>,
+    ) -> Self {
+        let (line, column) = match position {
+            Some(position) => {
+                let (line, column) = line_and_column(source, position);
+                (Some(line), Some(column))
+            }
+            None => (None, None),
+        };
+        Self {
+            severity,
+            line,
+            column,
+            position,
+            message: message.into(),
+        }
+    }
+
+    /// Creates the diagnostic for a parse error.
+    ///
+    /// The parser reports positions in the trimmed expression; they are
+    /// shifted back into `source`.
+    pub fn syntax(source: &str, error: &EclError) -> Self {
+        let leading = source.len() - source.trim_start().len();
+        match error {
+            EclError::ParseError { position, message } => {
+                let position = leading + position;
+                let message = if position == source.trim_end().len() {
+                    "unexpected end of expression".to_string()
+                } else {
+                    message.clone()
+                };
+                Self::new(Severity::Error, source, Some(position), message)
+            }
+            EclError::Incomplete(_) => Self::new(
+                Severity::Error,
+                source,
+                Some(source.trim_end().len()),
+                error.to_string(),
+            ),
+            _ => Self::new(Severity::Error, source, None, error.to_string()),
+        }
+    }
+
+    /// Renders the diagnostic followed by the source line it points to and
+    /// a caret under the position.
+    pub fn render(&self, source: &str) -> String {
+        let mut out = format!("{}\n", self);
+        if let (Some(position), Some(column)) = (self.position, self.column) {
+            let start = source[..position].rfind('\n').map_or(0, |i| i + 1);
+            let end = source[position..]
+                .find('\n')
+                .map_or(source.len(), |i| position + i);
+            out.push_str(&format!("  {}\n", &source[start..end]));
+            out.push_str(&format!("  {}^\n", " ".repeat(column - 1)));
+        }
+        out
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(
+                    f,
+                    "{} at {}:{}: {}",
+                    self.severity, line, column, self.message
+                )
+            }
+            _ => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
+
+/// Checks an expression, against `store` if one is given.
+///
+/// Returns the diagnostics ordered by position; an empty vector means the
+/// expression is valid.
+pub fn check(source: &str, store: Option<&dyn EclQueryable>) -> Vec<Diagnostic> {
+    let expr = match snomed_ecl::parse(source) {
+        Ok(expr) => expr,
+        Err(error) => return vec![Diagnostic::syntax(source, &error)],
+    };
+
+    let mut referenced = HashSet::new();
+    collect_expression(&expr, &mut referenced);
+
+    let mut diagnostics = Vec::new();
+    for token in scan_ids(source)
+        .into_iter()
+        .filter(|token| referenced.contains(&token.id))
+    {
+        let mut report = |severity, message: String| {
+            diagnostics.push(Diagnostic::new(
+                severity,
+                source,
+                Some(token.position),
+                message,
+            ));
+        };
+
+        if let Some(problem) = sctid_problem(token.id) {
+            report(Severity::Error, problem);
+            continue;
+        }
+        let Some(store) = store else {
+            continue;
+        };
+        if !store.has_concept(token.id) {
+            report(Severity::Error, format!("concept {} not found", token.id));
+            continue;
+        }
+        if !store.is_concept_active(token.id) {
+            report(
+                Severity::Warning,
+                format!("concept {} is inactive", describe(store, token.id)),
+            );
+        }
+        if let Some(label) = token.label {
+            if !label_matches(store, token.id, label) {
+                report(
+                    Severity::Warning,
+                    format!(
+                        "term |{}| matches no description of {}",
+                        label,
+                        describe(store, token.id)
+                    ),
+                );
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Formats a concept as `id |preferred term|`, or just the ID without a term.
+fn describe(store: &dyn EclQueryable, id: SctId) -> String {
+    match store.get_preferred_term(id) {
+        Some(term) => format!("{} |{}|", id, term),
+        None => id.to_string(),
+    }
+}
+
+/// Returns true if `label` is the term of one of the concept's active
+/// descriptions, ignoring case and the semantic tag of a fully specified
+/// name. Concepts without descriptions in the store match any label.
+fn label_matches(store: &dyn EclQueryable, id: SctId, label: &str) -> bool {
+    let descriptions = store.get_descriptions(id);
+    descriptions.is_empty()
+        || descriptions
+            .iter()
+            .filter(|description| description.active)
+            .any(|description| {
+                let term = description.term.as_str();
+                term.eq_ignore_ascii_case(label)
+                    || without_semantic_tag(term).eq_ignore_ascii_case(label)
+            })
+}
+
+fn without_semantic_tag(term: &str) -> &str {
+    match term.rfind(" (") {
+        Some(index) if term.ends_with(')') => &term[..index],
+        _ => term,
+    }
+}
+
+/// Returns why `id` is not a well-formed concept SCTID, if it is not.
+fn sctid_problem(id: SctId) -> Option<String> {
+    let digits = id.to_string();
+    if !(6..=18).contains(&digits.len()) {
+        return Some(format!(
+            "{} is not an SCTID: SCTIDs have 6 to 18 digits",
+            id
+        ));
+    }
+    if !verhoeff_valid(&digits) {
+        return Some(format!("{} is not an SCTID: its check digit is wrong", id));
+    }
+    match &digits[digits.len() - 3..digits.len() - 1] {
+        "00" | "10" => None,
+        "01" | "11" => Some(format!("{} is a description ID, not a concept ID", id)),
+        "02" | "12" => Some(format!("{} is a relationship ID, not a concept ID", id)),
+        partition => Some(format!(
+            "{} is not a concept ID: unknown partition identifier {}",
+            id, partition
+        )),
+    }
+}
+
+/// Verhoeff multiplication table.
+const VERHOEFF_D: [[u8; 10]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+    [1, 2, 3, 4, 0, 6, 7, 8, 9, 5],
+    [2, 3, 4, 0, 1, 7, 8, 9, 5, 6],
+    [3, 4, 0, 1, 2, 8, 9, 5, 6, 7],
+    [4, 0, 1, 2, 3, 9, 5, 6, 7, 8],
+    [5, 9, 8, 7, 6, 0, 4, 3, 2, 1],
+    [6, 5, 9, 8, 7, 1, 0, 4, 3, 2],
+    [7, 6, 5, 9, 8, 2, 1, 0, 4, 3],
+    [8, 7, 6, 5, 9, 3, 2, 1, 0, 4],
+    [9, 8, 7, 6, 5, 4, 3, 2, 1, 0],
+];
+
+/// Verhoeff permutation table.
+const VERHOEFF_P: [[u8; 10]; 8] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+    [1, 5, 7, 6, 2, 8, 3, 0, 9, 4],
+    [5, 8, 0, 3, 7, 9, 6, 1, 4, 2],
+    [8, 9, 1, 6, 0, 4, 3, 5, 2, 7],
+    [9, 4, 5, 3, 1, 2, 6, 8, 7, 0],
+    [4, 2, 8, 6, 5, 7, 3, 9, 0, 1],
+    [2, 7, 9, 3, 8, 0, 6, 4, 1, 5],
+    [7, 0, 4, 6, 9, 1, 3, 2, 5, 8],
+];
+
+/// Returns true if the last digit of `digits` is its Verhoeff check digit.
+fn verhoeff_valid(digits: &str) -> bool {
+    let mut check = 0;
+    for (i, digit) in digits.bytes().rev().enumerate() {
+        let digit = usize::from(digit - b'0');
+        check = usize::from(VERHOEFF_D[check][usize::from(VERHOEFF_P[i % 8][digit])]);
+    }
+    check == 0
+}
+
+/// A number written in the expression, with the term label after it.
+#[derive(Debug, PartialEq, Eq)]
+struct IdToken<'a> {
+    position: usize,
+    id: SctId,
+    label: Option<&'a str>,
+}
+
+/// Finds the numbers in `source` that may be concept IDs, skipping term
+/// labels, strings, comments and concrete values.
+fn scan_ids(source: &str) -> Vec<IdToken<'_>> {
+    let bytes = source.as_bytes();
+    let mut tokens: Vec<IdToken<'_>> = Vec::new();
+    // Whether only whitespace separates the last ID token from `i`
+    let mut after_id = false;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'|' => {
+                let end = source[i + 1..]
+                    .find('|')
+                    .map_or(source.len(), |e| i + 1 + e);
+                if after_id {
+                    if let Some(token) = tokens.last_mut() {
+                        token.label = Some(source[i + 1..end].trim());
+                    }
+                }
+                after_id = false;
+                i = end + 1;
+            }
+            b'"' => {
+                let mut j = i + 1;
+                while j < bytes.len() && bytes[j] != b'"' {
+                    j += if bytes[j] == b'\\' { 2 } else { 1 };
+                }
+                after_id = false;
+                i = j + 1;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let end = source[i + 2..]
+                    .find("*/")
+                    .map_or(source.len(), |e| i + 2 + e + 2);
+                after_id = false;
+                i = end;
+            }
+            b if b.is_ascii_whitespace() => i += 1,
+            b if b.is_ascii_alphanumeric() => {
+                let start = i;
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.') {
+                    i += 1;
+                }
+                let word = &source[start..i];
+                // Concrete values such as `#250` and `#-5` are not IDs
+                let concrete = start > 0 && matches!(bytes[start - 1], b'#' | b'-');
+                after_id = false;
+                if !concrete && word.bytes().all(|b| b.is_ascii_digit()) {
+                    if let Ok(id) = word.parse() {
+                        tokens.push(IdToken {
+                            position: start,
+                            id,
+                            label: None,
+                        });
+                        after_id = true;
+                    }
+                }
+            }
+            _ => {
+                after_id = false;
+                i += 1;
+            }
+        }
+    }
+    tokens
+}
+
+/// Returns the 1-based line and character column of a byte offset.
+fn line_and_column(source: &str, position: usize) -> (usize, usize) {
+    let before = &source[..position];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// Adds the concept IDs referenced by `expr` to `ids`.
+fn collect_expression(expr: &EclExpression, ids: &mut HashSet<SctId>) {
+    match expr {
+        EclExpression::ConceptReference { concept_id, .. } => {
+            ids.insert(*concept_id);
+        }
+        EclExpression::ConceptSet(concept_ids) => ids.extend(concept_ids),
+        EclExpression::DescendantOf(inner)
+        | EclExpression::DescendantOrSelfOf(inner)
+        | EclExpression::ChildOf(inner)
+        | EclExpression::ChildOrSelfOf(inner)
+        | EclExpression::AncestorOf(inner)
+        | EclExpression::AncestorOrSelfOf(inner)
+        | EclExpression::ParentOf(inner)
+        | EclExpression::ParentOrSelfOf(inner)
+        | EclExpression::Nested(inner)
+        | EclExpression::TopOfSet(inner)
+        | EclExpression::BottomOfSet(inner) => collect_expression(inner, ids),
+        EclExpression::And(left, right)
+        | EclExpression::Or(left, right)
+        | EclExpression::Minus(left, right) => {
+            collect_expression(left, ids);
+            collect_expression(right, ids);
+        }
+        EclExpression::MemberOf { refset, .. } => collect_expression(refset, ids),
+        EclExpression::Refined { focus, refinement } => {
+            collect_expression(focus, ids);
+            let grouped = refinement.groups.iter().flat_map(|g| &g.constraints);
+            for constraint in refinement.ungrouped.iter().chain(grouped) {
+                collect_expression(&constraint.attribute_type, ids);
+                collect_expression(&constraint.value, ids);
+            }
+        }
+        EclExpression::DotNotation {
+            source,
+            attribute_type,
+        } => {
+            collect_expression(source, ids);
+            collect_expression(attribute_type, ids);
+        }
+        EclExpression::Filtered {
+            expression,
+            filters,
+        } => {
+            collect_expression(expression, ids);
+            for filter in filters {
+                collect_filter(filter, ids);
+            }
+        }
+        EclExpression::Any
+        | EclExpression::AlternateIdentifier { .. }
+        | EclExpression::Concrete { .. } => {}
+    }
+}
+
+/// Adds the concept IDs referenced by `filter` to `ids`.
+///
+/// `id` filters are skipped: they match descriptions as well as concepts.
+fn collect_filter(filter: &EclFilter, ids: &mut HashSet<SctId>) {
+    match filter {
+        EclFilter::DescriptionType { types: expr }
+        | EclFilter::Dialect { dialects: expr, .. }
+        | EclFilter::Module { modules: expr } => collect_expression(expr, ids),
+        EclFilter::History {
+            subset: Some(subset),
+            ..
+        } => collect_expression(subset, ids),
+        EclFilter::CaseSignificance {
+            case_significance_id,
+        } => {
+            ids.insert(*case_significance_id);
+        }
+        EclFilter::PreferredIn { refset_ids }
+        | EclFilter::AcceptableIn { refset_ids }
+        | EclFilter::LanguageRefSet { refset_ids } => ids.extend(refset_ids),
+        EclFilter::DomainQualified { filter, .. } => collect_filter(filter, ids),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verhoeff() {
+        for id in ["73211009", "404684003", "138875005", "900000000000207008"] {
+            assert!(verhoeff_valid(id), "{}", id);
+        }
+        for id in ["73211008", "404684013", "138875050"] {
+            assert!(!verhoeff_valid(id), "{}", id);
+        }
+    }
+
+    #[test]
+    fn test_sctid_problem() {
+        assert_eq!(sctid_problem(73211009), None);
+        assert_eq!(sctid_problem(900000000000207008), None);
+        assert!(sctid_problem(73211008).unwrap().contains("check digit"));
+        assert!(sctid_problem(12345).unwrap().contains("6 to 18 digits"));
+        // Description of Diabetes mellitus
+        assert!(sctid_problem(121589010).unwrap().contains("description ID"));
+    }
+
+    #[test]
+    fn test_scan_ids() {
+        let tokens = scan_ids("<< 73211009 |Diabetes mellitus| : 363698007 = #250 {{ term = \"1234567\" }} /* 404684003 */");
+        assert_eq!(
+            tokens,
+            vec![
+                IdToken {
+                    position: 3,
+                    id: 73211009,
+                    label: Some("Diabetes mellitus"),
+                },
+                IdToken {
+                    position: 34,
+                    id: 363698007,
+                    label: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_ids_skips_decimals_but_keeps_filter_numbers() {
+        let tokens = scan_ids("< 27113001 : 1142135004 >= #3.14 {{ effectiveTime >= 20200101 }}");
+        let ids: Vec<SctId> = tokens.iter().map(|t| t.id).collect();
+        // The date is still scanned; `check` drops it because the
+        // expression does not reference it as a concept
+        assert_eq!(ids, vec![27113001, 1142135004, 20200101]);
+    }
+
+    #[test]
+    fn test_line_and_column() {
+        let source = "<< 73211009\n  AND ñ 404684003";
+        assert_eq!(line_and_column(source, 0), (1, 1));
+        assert_eq!(line_and_column(source, 3), (1, 4));
+        assert_eq!(line_and_column(source, source.find("404").unwrap()), (2, 9));
+    }
+
+    #[test]
+    fn test_syntax_diagnostic_is_shifted_past_leading_whitespace() {
+        let source = "  << 73211009 AND AND 64572001";
+        let diagnostics = check(source, None);
+        assert_eq!(diagnostics.len(), 1);
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.position, Some(18));
+        assert_eq!(
+            diagnostic.render(source),
+            "error at 1:19: unexpected input at: 'AND 64572001'\n    \
+             << 73211009 AND AND 64572001\n                    ^\n"
+        );
+    }
+
+    #[test]
+    fn test_check_reports_each_malformed_id() {
+        let source = "<< 73211008 OR (73211009 12345)";
+        let diagnostics = check(source, None);
+        let positions: Vec<Option<usize>> = diagnostics.iter().map(|d| d.position).collect();
+        assert_eq!(positions, vec![Some(3), Some(25)]);
+        assert!(check("<< 73211009 {{ term = \"73211008\" }}", None).is_empty());
+    }
+}
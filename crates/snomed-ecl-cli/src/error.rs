@@ -0,0 +1,75 @@
+//! Error types for the command-line tool.
+
+use crate::output::Format;
+
+/// Result type for command-line operations.
+pub type CliResult<T> = Result<T, CliError>;
+
+/// Errors that end a command with a non-zero exit status.
+#[derive(Debug, thiserror::Error)]
+pub enum CliError {
+    /// I/O error while reading the expression or writing output.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The expression is not valid ECL. Holds the rendered diagnostic.
+    #[error("{0}")]
+    InvalidExpression(String),
+
+    /// The store could not be loaded, or a query against it failed.
+    #[error("{0}")]
+    Store(#[from] snomed_ecl_store::StoreError),
+
+    /// The expression could not be executed.
+    #[error("{0}")]
+    Execution(#[from] snomed_ecl_executor::EclExecutorError),
+
+    /// Saved statistics could not be loaded.
+    #[error("cannot load statistics from {}: {source}", path.display())]
+    Statistics {
+        /// The statistics file.
+        path: std::path::PathBuf,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+
+    /// The command needs a store but none was given.
+    #[error("`{command}` needs a store: pass --rf2 <DIR>, --snapshot <FILE> or --sqlite <FILE>")]
+    MissingStore {
+        /// The subcommand.
+        command: &'static str,
+    },
+
+    /// The command cannot write the requested output format.
+    #[error("`{command}` does not support {format} output")]
+    UnsupportedFormat {
+        /// The subcommand.
+        command: &'static str,
+        /// The requested format.
+        format: Format,
+    },
+
+    /// JSON output could not be written.
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// CSV output could not be written.
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+}
+
+impl CliError {
+    /// Returns true if the error is a closed output pipe, as with `ecl run ... | head`.
+    pub fn is_broken_pipe(&self) -> bool {
+        let kind = match self {
+            CliError::Io(e) => Some(e.kind()),
+            CliError::Json(e) => e.io_error_kind(),
+            CliError::Csv(e) => match e.kind() {
+                csv::ErrorKind::Io(e) => Some(e.kind()),
+                _ => None,
+            },
+            _ => None,
+        };
+        kind == Some(std::io::ErrorKind::BrokenPipe)
+    }
+}
@@ -0,0 +1,495 @@
+//! `ecl`: parse, check, format, run and explain SNOMED CT ECL expressions.
+//!
+//! ```text
+//! ecl parse "<< 73211009 |Diabetes mellitus|"
+//! ecl fmt "<<73211009:363698007=<<39057004"
+//! ecl check --rf2 ./SnomedCT_InternationalRF2 "<< 73211009 |Heart attack|"
+//! ecl run --snapshot snomed.ecls --format csv "<< 73211009" > diabetes.csv
+//! ecl run --sqlite snomed.db "^ 700043003"
+//! ecl explain --snapshot snomed.ecls --stats "<< 73211009 AND ^ 700043003"
+//! ecl explain --statistics snomed.stats "<< 73211009 AND ^ 700043003"
+//! ```
+//!
+//! The expression is read from standard input when it is omitted or `-`.
+//! Every command writes to standard output in the format selected with
+//! `--format` and exits with status 1 on an error; `check` also exits with
+//! status 1 when it reports an error.
+
+mod check;
+mod error;
+mod output;
+
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand};
+use serde::Serialize;
+use snomed_ecl::{EclExpression, SctId};
+use snomed_ecl_executor::{
+    EclExecutor, EclQueryable, QueryPlanner, StatisticsBuilder, StatisticsService,
+};
+use snomed_ecl_store::{Rf2Store, SqliteStore};
+
+use crate::check::{Diagnostic, Severity};
+use crate::error::{CliError, CliResult};
+use crate::output::{write_csv, write_json, Format};
+
+/// Parse, check, format, run and explain SNOMED CT ECL expressions.
+#[derive(Debug, Parser)]
+#[command(name = "ecl", version)]
+struct Cli {
+    /// Output format [default: json for `parse`, text otherwise]
+    #[arg(long, short, global = true, value_enum)]
+    format: Option<Format>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Print the syntax tree of an expression
+    Parse(InputArgs),
+
+    /// Print an expression in canonical form
+    Fmt(InputArgs),
+
+    /// Report syntax errors, malformed IDs and, with a store, unknown or
+    /// inactive concepts and mismatched terms
+    Check {
+        #[command(flatten)]
+        input: InputArgs,
+        #[command(flatten)]
+        store: StoreArgs,
+    },
+
+    /// Execute an expression and print the matching concepts with their
+    /// preferred terms
+    Run {
+        #[command(flatten)]
+        input: InputArgs,
+        #[command(flatten)]
+        store: StoreArgs,
+        /// Print at most this many concepts, in ID order
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+
+    /// Print the query plan of an expression without executing it; with
+    /// --stats or --statistics, estimates use statistics of the release
+    Explain {
+        #[command(flatten)]
+        input: InputArgs,
+        #[command(flatten)]
+        store: StoreArgs,
+        /// Collect statistics from the store for the estimates, which visits
+        /// every concept
+        #[arg(long, conflicts_with = "statistics")]
+        stats: bool,
+        /// Load the statistics for the estimates from a file written by
+        /// `StatisticsService::save`
+        #[arg(long, value_name = "FILE")]
+        statistics: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Args)]
+struct InputArgs {
+    /// The ECL expression; read from standard input when omitted or `-`
+    ecl: Option<String>,
+}
+
+impl InputArgs {
+    fn read(&self) -> CliResult<String> {
+        match self.ecl.as_deref() {
+            Some(ecl) if ecl != "-" => Ok(ecl.to_string()),
+            _ => {
+                let mut source = String::new();
+                io::stdin().read_to_string(&mut source)?;
+                Ok(source)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct StoreArgs {
+    /// RF2 release directory (the release root or its Snapshot directory)
+    #[arg(long, value_name = "DIR")]
+    rf2: Option<PathBuf>,
+
+    /// Binary snapshot saved with `Rf2Store::save_binary`
+    #[arg(long, value_name = "FILE", conflicts_with = "rf2")]
+    snapshot: Option<PathBuf>,
+
+    /// Fail unless the binary snapshot was saved from this release
+    #[arg(long, requires = "snapshot")]
+    release: Option<String>,
+
+    /// SQLite database created with `SqliteStore::import`
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["rf2", "snapshot"])]
+    sqlite: Option<PathBuf>,
+
+    /// Language reference sets to take preferred terms from, in order
+    /// [default: US English, then GB English]
+    #[arg(long = "dialect", value_name = "REFSET_ID")]
+    dialects: Vec<SctId>,
+}
+
+impl StoreArgs {
+    /// Loads the store, if one was given.
+    fn load(&self) -> CliResult<Option<Store>> {
+        let store = match (&self.rf2, &self.snapshot, &self.sqlite) {
+            (Some(dir), _, _) => Store::Rf2(Box::new(Rf2Store::load_snapshot(dir)?)),
+            (None, Some(file), _) => Store::Rf2(Box::new(Rf2Store::load_binary(
+                file,
+                self.release.as_deref(),
+            )?)),
+            (None, None, Some(file)) => Store::Sqlite(Box::new(SqliteStore::open(file)?)),
+            (None, None, None) => return Ok(None),
+        };
+        Ok(Some(if self.dialects.is_empty() {
+            store
+        } else {
+            store.with_dialects(self.dialects.iter().copied())
+        }))
+    }
+
+    /// Loads the store, failing if none was given.
+    fn require(&self, command: &'static str) -> CliResult<Store> {
+        self.load()?.ok_or(CliError::MissingStore { command })
+    }
+}
+
+/// A store loaded from the command-line options.
+enum Store {
+    Rf2(Box<Rf2Store>),
+    Sqlite(Box<SqliteStore>),
+}
+
+impl Store {
+    fn with_dialects(self, dialects: impl IntoIterator<Item = SctId>) -> Self {
+        match self {
+            Store::Rf2(store) => Store::Rf2(Box::new(store.with_dialects(dialects))),
+            Store::Sqlite(store) => Store::Sqlite(Box::new(store.with_dialects(dialects))),
+        }
+    }
+
+    fn queryable(&self) -> &dyn EclQueryable {
+        match self {
+            Store::Rf2(store) => store.as_ref(),
+            Store::Sqlite(store) => store.as_ref(),
+        }
+    }
+
+    /// Fails with the first error a SQLite query hit, so that a failed query
+    /// is not reported as an empty result.
+    fn check_errors(&self) -> CliResult<()> {
+        match self {
+            Store::Sqlite(store) => store.take_error().map_or(Ok(()), |e| Err(e.into())),
+            Store::Rf2(_) => Ok(()),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let mut out = io::stdout().lock();
+
+    match run(&cli, &mut out).and_then(|code| out.flush().map(|_| code).map_err(Into::into)) {
+        Ok(code) => code,
+        Err(err) if err.is_broken_pipe() => ExitCode::SUCCESS,
+        Err(CliError::InvalidExpression(rendered)) => {
+            eprint!("{}", rendered);
+            ExitCode::FAILURE
+        }
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: &Cli, out: &mut dyn Write) -> CliResult<ExitCode> {
+    match &cli.command {
+        Command::Parse(input) => {
+            parse(&input.read()?, cli.format.unwrap_or(Format::Json), out)?;
+        }
+        Command::Fmt(input) => fmt(&input.read()?, cli.format.unwrap_or(Format::Text), out)?,
+        Command::Check { input, store } => {
+            let source = input.read()?;
+            let store = store.load()?;
+            return check(
+                &source,
+                store.as_ref(),
+                cli.format.unwrap_or(Format::Text),
+                out,
+            );
+        }
+        Command::Run {
+            input,
+            store,
+            limit,
+        } => {
+            let source = input.read()?;
+            let store = store.require("run")?;
+            execute(
+                &source,
+                &store,
+                *limit,
+                cli.format.unwrap_or(Format::Text),
+                out,
+            )?;
+        }
+        Command::Explain {
+            input,
+            store,
+            stats,
+            statistics,
+        } => {
+            let source = input.read()?;
+            let store = store.load()?;
+            let statistics = match (statistics, &store) {
+                (Some(path), _) => Some(StatisticsService::load(path, None).map_err(|source| {
+                    CliError::Statistics {
+                        path: path.clone(),
+                        source,
+                    }
+                })?),
+                (None, Some(store)) if *stats => {
+                    let statistics = StatisticsBuilder::new().build(store.queryable());
+                    store.check_errors()?;
+                    Some(statistics)
+                }
+                (None, None) if *stats => {
+                    return Err(CliError::MissingStore {
+                        command: "explain --stats",
+                    })
+                }
+                _ => None,
+            };
+            explain(
+                &source,
+                store.as_ref(),
+                statistics,
+                cli.format.unwrap_or(Format::Text),
+                out,
+            )?;
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Parses `source`, rendering a parse error as a diagnostic.
+fn parse_expression(source: &str) -> CliResult<EclExpression> {
+    snomed_ecl::parse(source)
+        .map_err(|e| CliError::InvalidExpression(Diagnostic::syntax(source, &e).render(source)))
+}
+
+fn parse(source: &str, format: Format, out: &mut dyn Write) -> CliResult<()> {
+    let expr = parse_expression(source)?;
+    match format {
+        Format::Text => writeln!(out, "{:#?}", expr)?,
+        Format::Json => write_json(out, &expr)?,
+        Format::Csv => {
+            return Err(CliError::UnsupportedFormat {
+                command: "parse",
+                format,
+            })
+        }
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Formatted {
+    ecl: String,
+}
+
+fn fmt(source: &str, format: Format, out: &mut dyn Write) -> CliResult<()> {
+    let formatted = Formatted {
+        ecl: parse_expression(source)?.to_string(),
+    };
+    match format {
+        Format::Text => writeln!(out, "{}", formatted.ecl)?,
+        Format::Json => write_json(out, &formatted)?,
+        Format::Csv => write_csv(out, &["ecl"], [formatted])?,
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct CheckReport<'a> {
+    valid: bool,
+    errors: usize,
+    warnings: usize,
+    diagnostics: &'a [Diagnostic],
+}
+
+fn check(
+    source: &str,
+    store: Option<&Store>,
+    format: Format,
+    out: &mut dyn Write,
+) -> CliResult<ExitCode> {
+    let diagnostics = check::check(source, store.map(Store::queryable));
+    if let Some(store) = store {
+        store.check_errors()?;
+    }
+    let count = |severity| {
+        diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .count()
+    };
+    let report = CheckReport {
+        valid: count(Severity::Error) == 0,
+        errors: count(Severity::Error),
+        warnings: count(Severity::Warning),
+        diagnostics: &diagnostics,
+    };
+
+    match format {
+        Format::Text => {
+            for diagnostic in &diagnostics {
+                write!(out, "{}", diagnostic.render(source))?;
+            }
+            if diagnostics.is_empty() {
+                writeln!(out, "ok")?;
+            } else {
+                writeln!(
+                    out,
+                    "{} error(s), {} warning(s)",
+                    report.errors, report.warnings
+                )?;
+            }
+        }
+        Format::Json => write_json(out, &report)?,
+        Format::Csv => write_csv(
+            out,
+            &["severity", "line", "column", "position", "message"],
+            &diagnostics,
+        )?,
+    }
+
+    Ok(if report.valid {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+#[derive(Serialize)]
+struct ConceptRow {
+    id: SctId,
+    term: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RunReport<'a> {
+    ecl: &'a str,
+    count: usize,
+    concepts: Vec<ConceptRow>,
+}
+
+fn execute(
+    source: &str,
+    store: &Store,
+    limit: Option<usize>,
+    format: Format,
+    out: &mut dyn Write,
+) -> CliResult<()> {
+    let expr = parse_expression(source)?;
+    // A failed query can surface as an execution error such as a missing
+    // concept, so report the query error first
+    let result = EclExecutor::new(store.queryable()).execute_ast(&expr);
+    store.check_errors()?;
+    let result = result?;
+
+    let mut ids = result.to_vec();
+    ids.sort_unstable();
+    ids.truncate(limit.unwrap_or(usize::MAX));
+    let concepts: Vec<ConceptRow> = ids
+        .into_iter()
+        .map(|id| ConceptRow {
+            id,
+            term: store.queryable().get_preferred_term(id),
+        })
+        .collect();
+    store.check_errors()?;
+
+    match format {
+        Format::Text => {
+            for concept in &concepts {
+                match &concept.term {
+                    Some(term) => writeln!(out, "{} |{}|", concept.id, term)?,
+                    None => writeln!(out, "{}", concept.id)?,
+                }
+            }
+        }
+        Format::Json => write_json(
+            out,
+            &RunReport {
+                ecl: source.trim(),
+                count: result.count(),
+                concepts,
+            },
+        )?,
+        Format::Csv => write_csv(out, &["id", "term"], concepts)?,
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct StepRow<'a> {
+    step: usize,
+    parent: Option<usize>,
+    operation: &'a str,
+    expression: &'a str,
+    estimated_cardinality: usize,
+    cost_estimate: f64,
+}
+
+fn explain(
+    source: &str,
+    store: Option<&Store>,
+    statistics: Option<StatisticsService>,
+    format: Format,
+    out: &mut dyn Write,
+) -> CliResult<()> {
+    let expr = parse_expression(source)?;
+    let planner = match statistics {
+        Some(statistics) => QueryPlanner::with_statistics(statistics),
+        None => QueryPlanner::new(),
+    }
+    .with_attribute_lookup(
+        store.is_some_and(|store| store.queryable().supports_attribute_lookup()),
+    );
+    let plan = planner.plan(source.trim(), &expr);
+
+    match format {
+        Format::Text => write!(out, "{}", plan)?,
+        Format::Json => writeln!(out, "{}", plan.to_json())?,
+        Format::Csv => write_csv(
+            out,
+            &[
+                "step",
+                "parent",
+                "operation",
+                "expression",
+                "estimated_cardinality",
+                "cost_estimate",
+            ],
+            plan.steps.iter().enumerate().map(|(step, s)| StepRow {
+                step,
+                parent: s.parent,
+                operation: &s.operation,
+                expression: &s.expression,
+                estimated_cardinality: s.estimated_cardinality,
+                cost_estimate: s.cost_estimate,
+            }),
+        )?,
+    }
+    Ok(())
+}
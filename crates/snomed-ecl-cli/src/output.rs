@@ -0,0 +1,58 @@
+//! Output formats.
+//!
+//! Every command writes to standard output in one of three formats: text
+//! for reading, JSON for `jq` and other programs, and CSV for spreadsheets
+//! and `cut`/`sort` pipelines. CSV output always starts with a header row.
+
+use std::fmt;
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::error::CliResult;
+
+/// An output format, selected with `--format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Human-readable text.
+    Text,
+    /// Pretty-printed JSON.
+    Json,
+    /// Comma-separated values with a header row.
+    Csv,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Text => write!(f, "text"),
+            Format::Json => write!(f, "JSON"),
+            Format::Csv => write!(f, "CSV"),
+        }
+    }
+}
+
+/// Writes `value` as pretty-printed JSON followed by a newline.
+pub fn write_json<T: Serialize + ?Sized>(out: &mut dyn Write, value: &T) -> CliResult<()> {
+    serde_json::to_writer_pretty(&mut *out, value)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Writes `headers` and then `rows` as CSV. The header row is written even
+/// when there are no rows, so empty results still have a well-formed file.
+pub fn write_csv<T: Serialize>(
+    out: &mut dyn Write,
+    headers: &[&str],
+    rows: impl IntoIterator<Item = T>,
+) -> CliResult<()> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(out);
+    writer.write_record(headers)?;
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,413 @@
+//! Integration tests for the `ecl` binary.
+//!
+//! Commands that need a store use the store crate's
+//! `tests/fixtures/rf2-integration` release: a diabetes hierarchy of 15
+//! concepts with FSNs only, one of them (38341003) inactive, and the simple
+//! reference sets 700043003 and 723264001 with two members each.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+use snomed_ecl_store::{Rf2Loader, Rf2Store, SqliteStore};
+
+fn fixture_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../snomed-ecl-store/tests/fixtures/rf2-integration")
+}
+
+/// Runs `ecl` with `args`, writing `stdin` to its standard input.
+fn ecl_with_stdin(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ecl"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("ecl should start");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn ecl(args: &[&str]) -> Output {
+    ecl_with_stdin(args, "")
+}
+
+/// Runs `ecl` against the fixture release.
+fn ecl_rf2(command: &str, args: &[&str]) -> Output {
+    let dir = fixture_dir();
+    let mut all = vec![command, "--rf2", dir.to_str().unwrap()];
+    all.extend_from_slice(args);
+    ecl(&all)
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+fn json(output: &Output) -> serde_json::Value {
+    serde_json::from_slice(&output.stdout).expect("stdout should be JSON")
+}
+
+// ============================================================================
+// parse and fmt
+// ============================================================================
+
+#[test]
+fn test_parse_dumps_ast_as_json() {
+    let output = ecl(&["parse", "<< 73211009 |Diabetes mellitus|"]);
+    assert!(output.status.success());
+
+    let ast: snomed_ecl::EclExpression = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        ast,
+        snomed_ecl::parse("<< 73211009 |Diabetes mellitus|").unwrap()
+    );
+}
+
+#[test]
+fn test_parse_text_and_csv() {
+    let output = ecl(&["parse", "--format", "text", "73211009"]);
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("ConceptReference {"));
+
+    let output = ecl(&["parse", "--format", "csv", "73211009"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        "error: `parse` does not support CSV output\n"
+    );
+}
+
+#[test]
+fn test_fmt_canonicalizes() {
+    let output = ecl(&["fmt", "<<73211009:363698007=<<39057004"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "<< 73211009 : 363698007 = << 39057004\n");
+
+    let output = ecl(&["fmt", "-f", "json", "<<  73211009"]);
+    assert_eq!(json(&output), serde_json::json!({ "ecl": "<< 73211009" }));
+
+    let output = ecl(&["fmt", "-f", "csv", "<<  73211009"]);
+    assert_eq!(stdout(&output), "ecl\n<< 73211009\n");
+}
+
+#[test]
+fn test_reads_expression_from_stdin() {
+    let output = ecl_with_stdin(&["fmt"], "<<73211009\n  OR <<64572001\n");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "<< 73211009 OR << 64572001\n");
+
+    let output = ecl_with_stdin(&["fmt", "-"], "73211009");
+    assert_eq!(stdout(&output), "73211009\n");
+}
+
+#[test]
+fn test_syntax_error_is_rendered_with_position() {
+    let output = ecl(&["fmt", "<< 73211009 AND"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        stderr(&output),
+        "error at 1:16: unexpected end of expression\n  << 73211009 AND\n                 ^\n"
+    );
+}
+
+// ============================================================================
+// check
+// ============================================================================
+
+#[test]
+fn test_check_valid_expression() {
+    let output = ecl(&["check", "<< 73211009 : 363698007 = << 39057004"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "ok\n");
+}
+
+#[test]
+fn test_check_reports_syntax_error_position() {
+    let output = ecl_with_stdin(&["check", "-f", "json"], "<< 73211009\n  AND AND 64572001");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        json(&output),
+        serde_json::json!({
+            "valid": false,
+            "errors": 1,
+            "warnings": 0,
+            "diagnostics": [{
+                "severity": "error",
+                "line": 2,
+                "column": 7,
+                "position": 18,
+                "message": "unexpected input at: 'AND 64572001'",
+            }],
+        })
+    );
+}
+
+#[test]
+fn test_check_reports_malformed_ids() {
+    let output = ecl(&["check", "<< 73211008 OR 121589010"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "error at 1:4: 73211008 is not an SCTID: its check digit is wrong\n\
+         \x20 << 73211008 OR 121589010\n\
+         \x20    ^\n\
+         error at 1:16: 121589010 is a description ID, not a concept ID\n\
+         \x20 << 73211008 OR 121589010\n\
+         \x20                ^\n\
+         2 error(s), 0 warning(s)\n"
+    );
+}
+
+#[test]
+fn test_check_against_store() {
+    let output = ecl_rf2(
+        "check",
+        &[
+            "-f",
+            "csv",
+            "<< 73211009 |Diabetes mellitus| OR 38341003 OR 80146002 |Appendectomy| OR 46635009 |Heart attack|",
+        ],
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "severity,line,column,position,message\n\
+         warning,1,36,35,\"concept 38341003 |Hypertensive disorder, systemic arterial (disorder)| is inactive\"\n\
+         error,1,48,47,concept 80146002 not found\n\
+         warning,1,75,74,term |Heart attack| matches no description of 46635009 |Type 1 diabetes mellitus (disorder)|\n"
+    );
+}
+
+#[test]
+fn test_check_warnings_only_succeeds() {
+    let output = ecl_rf2("check", &["-f", "json", "<< 38341003"]);
+    assert!(output.status.success());
+    let report = json(&output);
+    assert_eq!(report["valid"], true);
+    assert_eq!(report["warnings"], 1);
+}
+
+// ============================================================================
+// run
+// ============================================================================
+
+#[test]
+fn test_run_prints_ids_with_terms() {
+    let output = ecl_rf2("run", &["<< 73211009"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "44054006 |Type 2 diabetes mellitus (disorder)|\n\
+         46635009 |Type 1 diabetes mellitus (disorder)|\n\
+         73211009 |Diabetes mellitus (disorder)|\n"
+    );
+}
+
+#[test]
+fn test_run_csv_and_json() {
+    let output = ecl_rf2(
+        "run",
+        &["--format", "csv", "<< 64572001 {{ C active = false }}"],
+    );
+    assert_eq!(
+        stdout(&output),
+        "id,term\n38341003,\"Hypertensive disorder, systemic arterial (disorder)\"\n"
+    );
+
+    let output = ecl_rf2("run", &["--format", "json", "--limit", "1", "^ 700043003"]);
+    let report = json(&output);
+    assert_eq!(report["ecl"], "^ 700043003");
+    assert_eq!(report["count"], 2);
+    assert_eq!(report["concepts"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_run_empty_result_csv_has_header() {
+    let output = ecl_rf2("run", &["-f", "csv", "<< 73211009 AND << 123037004"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "id,term\n");
+}
+
+#[test]
+fn test_run_against_binary_snapshot() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("snomed.ecls");
+    Rf2Store::load_snapshot(fixture_dir())
+        .unwrap()
+        .save_binary(&path, "20240101")
+        .unwrap();
+    let path = path.to_str().unwrap();
+
+    let from_rf2 = ecl_rf2("run", &["< 404684003"]);
+    let output = ecl(&[
+        "run",
+        "--snapshot",
+        path,
+        "--release",
+        "20240101",
+        "< 404684003",
+    ]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), stdout(&from_rf2));
+
+    let output = ecl(&[
+        "run",
+        "--snapshot",
+        path,
+        "--release",
+        "20250101",
+        "< 404684003",
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("Release mismatch"));
+}
+
+#[test]
+fn test_run_against_sqlite_database() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("snomed.db");
+    SqliteStore::import(&path, &Rf2Loader::snapshot(fixture_dir())).unwrap();
+    let path = path.to_str().unwrap();
+
+    for (command, expression) in [
+        ("run", "<< 64572001 {{ C active = false }} OR ^ 700043003"),
+        ("check", "<< 73211009 |Heart attack| OR 38341003"),
+    ] {
+        let from_rf2 = ecl_rf2(command, &[expression]);
+        let output = ecl(&[command, "--sqlite", path, expression]);
+        assert_eq!(output.status.code(), from_rf2.status.code());
+        assert_eq!(stdout(&output), stdout(&from_rf2));
+    }
+
+    // Not a database created by the importer
+    let output = ecl(&[
+        "run",
+        "--sqlite",
+        fixture_dir().join("Snapshot/Terminology").to_str().unwrap(),
+        "<< 73211009",
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("error: "));
+}
+
+#[test]
+fn test_run_errors() {
+    let output = ecl(&["run", "<< 73211009"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("needs a store"));
+
+    let output = ecl_rf2("run", &["<< 80146002"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "error: Concept not found: 80146002\n");
+
+    let output = ecl(&["run", "--rf2", "/nonexistent", "<< 73211009"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("error: "));
+}
+
+// ============================================================================
+// explain
+// ============================================================================
+
+#[test]
+fn test_explain_text() {
+    let output = ecl(&["explain", "<< 73211009 AND ^ 700043003"]);
+    assert!(output.status.success());
+    let text = stdout(&output);
+    assert!(text.starts_with("Query Plan for: << 73211009 AND ^ 700043003\n"));
+    assert!(text.contains("Intersect"));
+}
+
+#[test]
+fn test_explain_json_round_trips() {
+    let output = ecl_rf2(
+        "explain",
+        &["--stats", "-f", "json", "<< 73211009 AND ^ 700043003"],
+    );
+    assert!(output.status.success());
+    let plan = snomed_ecl_executor::QueryPlan::from_json(&stdout(&output)).unwrap();
+    assert_eq!(plan.ecl, "<< 73211009 AND ^ 700043003");
+    // Statistics come from the store: << 73211009 has three concepts
+    let descendants = plan
+        .steps
+        .iter()
+        .find(|step| step.expression == "<< 73211009")
+        .unwrap();
+    assert_eq!(descendants.estimated_cardinality, 3);
+}
+
+#[test]
+fn test_explain_statistics_are_opt_in() {
+    let estimate = |output: &Output| {
+        let plan = snomed_ecl_executor::QueryPlan::from_json(&stdout(output)).unwrap();
+        plan.steps[0].estimated_cardinality
+    };
+    let without = ecl_rf2("explain", &["-f", "json", "<< 73211009"]);
+    let with = ecl_rf2("explain", &["--stats", "-f", "json", "<< 73211009"]);
+    assert!(without.status.success());
+    assert_ne!(estimate(&without), estimate(&with));
+    assert_eq!(estimate(&with), 3);
+
+    let output = ecl(&["explain", "--stats", "<< 73211009"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("`explain --stats` needs a store"));
+}
+
+#[test]
+fn test_explain_with_saved_statistics() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("snomed.stats");
+    let store = Rf2Store::load_snapshot(fixture_dir()).unwrap();
+    snomed_ecl_executor::StatisticsBuilder::new()
+        .build(&store)
+        .save(&path, "20240101")
+        .unwrap();
+
+    // No store is needed
+    let output = ecl(&[
+        "explain",
+        "--statistics",
+        path.to_str().unwrap(),
+        "-f",
+        "json",
+        "<< 73211009",
+    ]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let plan = snomed_ecl_executor::QueryPlan::from_json(&stdout(&output)).unwrap();
+    assert_eq!(plan.steps[0].estimated_cardinality, 3);
+
+    let missing = dir.path().join("missing.stats");
+    let output = ecl(&[
+        "explain",
+        "--statistics",
+        missing.to_str().unwrap(),
+        "<< 73211009",
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("cannot load statistics from"));
+}
+
+#[test]
+fn test_explain_csv() {
+    let output = ecl(&["explain", "-f", "csv", "<< 73211009 OR << 64572001"]);
+    assert!(output.status.success());
+    let text = stdout(&output);
+    let mut lines = text.lines();
+    assert_eq!(
+        lines.next(),
+        Some("step,parent,operation,expression,estimated_cardinality,cost_estimate")
+    );
+    assert_eq!(lines.count(), 3);
+}
@@ -0,0 +1,132 @@
+//! Sequential vs parallel execution on a large synthetic hierarchy.
+//!
+//! Run with:
+//!
+//! ```text
+//! cargo bench -p snomed-ecl-executor --features parallel --bench parallel_bench
+//! ```
+
+use std::collections::{HashMap, HashSet};
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use snomed_ecl_executor::{
+    DescriptionInfo, EclExecutor, EclQueryable, ExecutorConfig, RelationshipInfo, SctId,
+};
+
+/// Attribute type used by every synthetic concept.
+const FINDING_SITE: SctId = 363698007;
+/// Number of children per concept.
+const FAN_OUT: SctId = 8;
+
+/// In-memory store with a complete `FAN_OUT`-ary IS-A tree rooted at `1`.
+///
+/// Concept `n` has parent `(n - 2) / FAN_OUT + 1`, one attribute pointing at
+/// a concept in the upper levels of the tree and one synonym.
+struct SyntheticStore {
+    size: SctId,
+    children: HashMap<SctId, Vec<SctId>>,
+    parents: HashMap<SctId, Vec<SctId>>,
+}
+
+impl SyntheticStore {
+    fn new(size: SctId) -> Self {
+        let mut children: HashMap<SctId, Vec<SctId>> = HashMap::new();
+        let mut parents: HashMap<SctId, Vec<SctId>> = HashMap::new();
+
+        for id in 2..=size {
+            let parent = (id - 2) / FAN_OUT + 1;
+            children.entry(parent).or_default().push(id);
+            parents.entry(id).or_default().push(parent);
+        }
+
+        Self {
+            size,
+            children,
+            parents,
+        }
+    }
+}
+
+impl EclQueryable for SyntheticStore {
+    fn get_children(&self, concept_id: SctId) -> Vec<SctId> {
+        self.children.get(&concept_id).cloned().unwrap_or_default()
+    }
+
+    fn get_parents(&self, concept_id: SctId) -> Vec<SctId> {
+        self.parents.get(&concept_id).cloned().unwrap_or_default()
+    }
+
+    fn has_concept(&self, concept_id: SctId) -> bool {
+        (1..=self.size).contains(&concept_id) || concept_id == FINDING_SITE
+    }
+
+    fn all_concept_ids(&self) -> Box<dyn Iterator<Item = SctId> + '_> {
+        Box::new(1..=self.size)
+    }
+
+    fn get_refset_members(&self, _refset_id: SctId) -> Vec<SctId> {
+        Vec::new()
+    }
+
+    fn get_attributes(&self, concept_id: SctId) -> Vec<RelationshipInfo> {
+        vec![RelationshipInfo {
+            type_id: FINDING_SITE,
+            destination_id: concept_id % 73 + 2,
+            group: 0,
+        }]
+    }
+
+    fn get_descriptions(&self, concept_id: SctId) -> Vec<DescriptionInfo> {
+        vec![DescriptionInfo {
+            description_id: concept_id * 10,
+            term: format!("Synthetic disorder number {} (disorder)", concept_id),
+            language_code: "en".to_string(),
+            type_id: 900000000000013009,
+            case_significance_id: 900000000000448009,
+            active: true,
+            effective_time: None,
+            module_id: 900000000000207008,
+        }]
+    }
+}
+
+fn bench_parallel(c: &mut Criterion) {
+    let store = SyntheticStore::new(200_000);
+
+    let sequential = EclExecutor::with_config(&store, ExecutorConfig::builder().build());
+    let parallel = EclExecutor::with_config(
+        &store,
+        ExecutorConfig::builder().with_parallel(true).build(),
+    );
+
+    let queries = [
+        ("descendants", "<< 1"),
+        ("compound", "(<< 2 OR << 3) MINUS (<< 4 AND << 20)"),
+        ("refinement", "<< 1 : 363698007 = << 3"),
+        ("term_filter", "<< 1 {{ term = \"number 7\" }}"),
+    ];
+
+    // Parallel and sequential execution must agree
+    for (_, ecl) in &queries {
+        let expected: HashSet<SctId> = sequential.execute(ecl).unwrap().into_iter().collect();
+        let actual: HashSet<SctId> = parallel.execute(ecl).unwrap().into_iter().collect();
+        assert_eq!(expected, actual, "results differ for {}", ecl);
+    }
+
+    let mut group = c.benchmark_group("parallel_execution");
+    group.sample_size(10);
+
+    for (name, ecl) in &queries {
+        group.bench_with_input(BenchmarkId::new("sequential", name), ecl, |b, ecl| {
+            b.iter(|| sequential.execute(black_box(ecl)).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("parallel", name), ecl, |b, ecl| {
+            b.iter(|| parallel.execute(black_box(ecl)).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_parallel);
+criterion_main!(benches);
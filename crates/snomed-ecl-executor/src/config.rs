@@ -24,8 +24,16 @@ pub struct ExecutorConfig {
     /// Enable parallel execution (requires `parallel` feature).
     pub parallel: bool,
     /// Maximum number of results to return (None = unlimited).
+    ///
+    /// This is a running budget: hierarchy traversals and unions fail with
+    /// `ResultTooLarge` as soon as the set they build exceeds it, so
+    /// `<< 138875005` stops early instead of building the whole hierarchy.
+    /// `*` and the final result are checked once they are complete.
     pub max_results: Option<usize>,
     /// Query timeout duration (None = no timeout).
+    ///
+    /// Checked during hierarchy traversal, refinement and filter evaluation;
+    /// exceeding it aborts execution with `Timeout`.
     pub timeout: Option<Duration>,
 }
 
@@ -0,0 +1,245 @@
+//! Description filters evaluated per description.
+//!
+//! ECL requires every description filter in one `{{ }}` block to be met by
+//! the same description: `{{ term = "heart", language = en, type = syn }}`
+//! selects concepts with an English synonym matching "heart", not concepts
+//! with some description matching "heart" and some other English synonym.
+//! [`DescriptionFilter`] holds the description conditions of a block,
+//! compiled once, and tests them together against each description.
+//!
+//! Concept filters such as `active` or `moduleId` are not description
+//! filters and stay concept-level, as do separate `{{ }}` blocks. With the
+//! `D` domain prefix they test the description instead:
+//! `{{ D active = true, D moduleId = X }}` selects concepts with an active
+//! description in module `X`.
+
+use std::collections::HashSet;
+
+use snomed_ecl::{
+    ComparisonOperator, EclExpression, EclFilter, FilterAcceptability, FilterDomain, SctId,
+};
+
+use crate::error::EclResult;
+use crate::term::TermMatcher;
+use crate::traits::{Acceptability, DescriptionInfo, EclQueryable, LanguageRefsetMember};
+
+/// A single compiled description condition.
+#[derive(Debug)]
+enum DescriptionCondition {
+    /// Any of the search terms matches the term.
+    Term(Vec<TermMatcher>),
+    /// The language code is one of the codes.
+    Language(Vec<String>),
+    /// The description type is one of the types.
+    Type(HashSet<SctId>),
+    /// The description is in one of the dialects, optionally with an
+    /// acceptability.
+    Dialect {
+        dialect_ids: HashSet<SctId>,
+        acceptability: Option<Acceptability>,
+    },
+    /// The case significance is the given one.
+    CaseSignificance(SctId),
+    /// The description is in one of the language reference sets, optionally
+    /// with an acceptability.
+    LanguageRefSet {
+        refset_ids: HashSet<SctId>,
+        acceptability: Option<Acceptability>,
+    },
+    /// `D active`: the description's active flag is the given one.
+    Active(bool),
+    /// `D moduleId`: the description is in one of the modules.
+    Module(HashSet<SctId>),
+    /// `D effectiveTime`: the description's effective time compares with the
+    /// date.
+    EffectiveTime {
+        operator: ComparisonOperator,
+        date: u32,
+    },
+    /// `D id`: the description is one of the descriptions.
+    Id(HashSet<SctId>),
+}
+
+impl DescriptionCondition {
+    /// Compiles a filter, or returns `None` if it is not a description filter.
+    fn compile<F>(filter: &EclFilter, resolve: &mut F) -> EclResult<Option<Self>>
+    where
+        F: FnMut(&EclExpression) -> EclResult<HashSet<SctId>>,
+    {
+        Ok(Some(match filter {
+            EclFilter::Term { terms } => Self::Term(
+                terms
+                    .iter()
+                    .map(|t| TermMatcher::compile(&t.match_type, &t.term))
+                    .collect::<EclResult<_>>()?,
+            ),
+            EclFilter::Language { codes } => Self::Language(codes.clone()),
+            EclFilter::DescriptionType { types } => Self::Type(resolve(types)?),
+            EclFilter::Dialect { dialects, acceptability } => Self::Dialect {
+                dialect_ids: resolve(dialects)?,
+                acceptability: acceptability.as_ref().map(|acc| match acc {
+                    FilterAcceptability::Preferred => Acceptability::Preferred,
+                    FilterAcceptability::Acceptable => Acceptability::Acceptable,
+                }),
+            },
+            EclFilter::CaseSignificance { case_significance_id } => {
+                Self::CaseSignificance(*case_significance_id)
+            }
+            EclFilter::PreferredIn { refset_ids } => Self::LanguageRefSet {
+                refset_ids: refset_ids.iter().copied().collect(),
+                acceptability: Some(Acceptability::Preferred),
+            },
+            EclFilter::AcceptableIn { refset_ids } => Self::LanguageRefSet {
+                refset_ids: refset_ids.iter().copied().collect(),
+                acceptability: Some(Acceptability::Acceptable),
+            },
+            EclFilter::LanguageRefSet { refset_ids } => Self::LanguageRefSet {
+                refset_ids: refset_ids.iter().copied().collect(),
+                acceptability: None,
+            },
+            EclFilter::DomainQualified {
+                domain: FilterDomain::Description,
+                filter,
+            } => match filter.as_ref() {
+                EclFilter::Active(active) => Self::Active(*active),
+                EclFilter::Module { modules } => Self::Module(resolve(modules)?),
+                EclFilter::EffectiveTime { operator, date } => Self::EffectiveTime {
+                    operator: *operator,
+                    date: *date,
+                },
+                EclFilter::Id { ids } => Self::Id(ids.iter().copied().collect()),
+                other => return Self::compile(other, resolve),
+            },
+            _ => return Ok(None),
+        }))
+    }
+
+    /// Returns true if the condition needs the description's language
+    /// reference set memberships.
+    fn needs_language_refsets(&self) -> bool {
+        matches!(self, Self::Dialect { .. } | Self::LanguageRefSet { .. })
+    }
+
+    fn matches(&self, desc: &DescriptionInfo, memberships: &[LanguageRefsetMember]) -> bool {
+        let in_refsets = |refset_ids: &HashSet<SctId>, acceptability: &Option<Acceptability>| {
+            memberships.iter().any(|membership| {
+                refset_ids.contains(&membership.refset_id)
+                    && acceptability.is_none_or(|acc| membership.acceptability == acc)
+            })
+        };
+
+        match self {
+            Self::Term(matchers) => matchers
+                .iter()
+                .any(|matcher| matcher.matches(&desc.term, desc.case_significance_id)),
+            Self::Language(codes) => codes.contains(&desc.language_code.to_lowercase()),
+            Self::Type(type_ids) => type_ids.contains(&desc.type_id),
+            Self::Dialect {
+                dialect_ids,
+                acceptability,
+            } => in_refsets(dialect_ids, acceptability),
+            Self::CaseSignificance(case_significance_id) => {
+                desc.case_significance_id == *case_significance_id
+            }
+            Self::LanguageRefSet {
+                refset_ids,
+                acceptability,
+            } => in_refsets(refset_ids, acceptability),
+            Self::Active(active) => desc.active == *active,
+            Self::Module(module_ids) => module_ids.contains(&desc.module_id),
+            Self::EffectiveTime { operator, date } => {
+                desc.effective_time.is_some_and(|effective_time| match operator {
+                    ComparisonOperator::Equal => effective_time == *date,
+                    ComparisonOperator::NotEqual => effective_time != *date,
+                    ComparisonOperator::LessThan => effective_time < *date,
+                    ComparisonOperator::LessThanOrEqual => effective_time <= *date,
+                    ComparisonOperator::GreaterThan => effective_time > *date,
+                    ComparisonOperator::GreaterThanOrEqual => effective_time >= *date,
+                })
+            }
+            Self::Id(ids) => ids.contains(&desc.description_id),
+        }
+    }
+}
+
+/// The description filters of one filter block, all of which must be met by
+/// a single description.
+#[derive(Debug)]
+pub(crate) struct DescriptionFilter {
+    conditions: Vec<DescriptionCondition>,
+}
+
+impl DescriptionFilter {
+    /// Returns true if `filter` is a description filter.
+    pub(crate) fn is_description_filter(filter: &EclFilter) -> bool {
+        match filter {
+            EclFilter::Term { .. }
+            | EclFilter::Language { .. }
+            | EclFilter::DescriptionType { .. }
+            | EclFilter::Dialect { .. }
+            | EclFilter::CaseSignificance { .. }
+            | EclFilter::PreferredIn { .. }
+            | EclFilter::AcceptableIn { .. }
+            | EclFilter::LanguageRefSet { .. } => true,
+            EclFilter::DomainQualified {
+                domain: FilterDomain::Description,
+                filter,
+            } => filter.applies_to(FilterDomain::Description),
+            _ => false,
+        }
+    }
+
+    /// Compiles the description filters among `filters`, ignoring the others.
+    ///
+    /// # Arguments
+    ///
+    /// * `filters` - The filters of one filter block
+    /// * `resolve` - Resolves a type or dialect constraint to its id set
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a term filter has an invalid search term, or if
+    /// a constraint cannot be resolved.
+    pub(crate) fn compile<'f, F>(
+        filters: impl IntoIterator<Item = &'f EclFilter>,
+        mut resolve: F,
+    ) -> EclResult<Self>
+    where
+        F: FnMut(&EclExpression) -> EclResult<HashSet<SctId>>,
+    {
+        let mut conditions = Vec::new();
+        for filter in filters {
+            if let Some(condition) = DescriptionCondition::compile(filter, &mut resolve)? {
+                conditions.push(condition);
+            }
+        }
+        Ok(Self { conditions })
+    }
+
+    /// Returns true if one description meets every condition.
+    ///
+    /// Language reference set memberships are only fetched for descriptions
+    /// that get as far as a dialect or acceptability condition.
+    pub(crate) fn matches(&self, store: &dyn EclQueryable, desc: &DescriptionInfo) -> bool {
+        let mut memberships: Option<Vec<LanguageRefsetMember>> = None;
+
+        self.conditions.iter().all(|condition| {
+            let memberships: &[LanguageRefsetMember] = if condition.needs_language_refsets() {
+                memberships.get_or_insert_with(|| {
+                    store.get_description_language_refsets(desc.description_id)
+                })
+            } else {
+                &[]
+            };
+            condition.matches(desc, memberships)
+        })
+    }
+
+    /// Returns true if any description of the concept meets every condition.
+    pub(crate) fn matches_concept(&self, store: &dyn EclQueryable, concept_id: SctId) -> bool {
+        store
+            .get_descriptions(concept_id)
+            .iter()
+            .any(|desc| self.matches(store, desc))
+    }
+}
@@ -2,7 +2,7 @@
 
 use std::time::Duration;
 
-use snomed_ecl::SctId;
+use snomed_ecl::{FilterDomain, SctId};
 use thiserror::Error;
 
 /// Errors that can occur during ECL execution.
@@ -33,6 +33,28 @@ pub enum EclExecutorError {
     #[error("Query timeout after {0:?}")]
     Timeout(Duration),
 
+    /// Query execution was cancelled via a `CancelToken`.
+    #[error("Query cancelled")]
+    Cancelled,
+
+    /// A term filter pattern could not be compiled.
+    #[error("Invalid term pattern {pattern:?}: {reason}")]
+    InvalidTermPattern {
+        /// The pattern as written in the ECL.
+        pattern: String,
+        /// Why the pattern was rejected.
+        reason: String,
+    },
+
+    /// A domain-qualified filter does not apply to its domain.
+    #[error("Filter `{filter}` does not apply to domain {domain}")]
+    InvalidFilterDomain {
+        /// The filter as written in the ECL.
+        filter: String,
+        /// The domain it was qualified with.
+        domain: FilterDomain,
+    },
+
     /// ECL feature not yet supported by the executor.
     #[error("Unsupported ECL feature: {0}")]
     UnsupportedFeature(String),
@@ -79,6 +101,12 @@ mod tests {
         assert_eq!(err.to_string(), "Query timeout after 30s");
     }
 
+    #[test]
+    fn test_error_display_cancelled() {
+        let err = EclExecutorError::Cancelled;
+        assert_eq!(err.to_string(), "Query cancelled");
+    }
+
     #[test]
     fn test_error_display_unsupported_feature() {
         let err = EclExecutorError::UnsupportedFeature("refinement filters".to_string());
@@ -88,6 +116,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_error_display_invalid_term_pattern() {
+        let err = EclExecutorError::InvalidTermPattern {
+            pattern: "diab(".to_string(),
+            reason: "unclosed group".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Invalid term pattern \"diab(\": unclosed group"
+        );
+    }
+
+    #[test]
+    fn test_error_display_invalid_filter_domain() {
+        let err = EclExecutorError::InvalidFilterDomain {
+            filter: "definitionStatus = primitive".to_string(),
+            domain: FilterDomain::Description,
+        };
+        assert_eq!(
+            err.to_string(),
+            "Filter `definitionStatus = primitive` does not apply to domain D"
+        );
+    }
+
     #[test]
     fn test_error_from_ecl_error() {
         let ecl_err = snomed_ecl::EclError::EmptyExpression;
@@ -5,13 +5,22 @@ use std::sync::Arc;
 use std::time::Instant;
 
 use snomed_ecl::EclExpression;
+use snomed_ecl::EclFilter;
 use snomed_ecl::SctId;
 
 use crate::cache::{normalize_cache_key, QueryCache};
 use crate::config::ExecutorConfig;
+use crate::description::DescriptionFilter;
 use crate::error::{EclExecutorError, EclResult};
-use crate::planner::{QueryPlan, QueryPlanner};
-use crate::result::{ExecutionStats, QueryResult};
+use crate::guard::{CancelToken, ExecutionGuard, GUARD_CHECK_INTERVAL};
+use crate::member::{project_rows, MemberFilter, MemberProjection};
+use crate::membership::Membership;
+use crate::physical::{PlanNode, PlanOperator};
+use crate::planner::{QueryPlan, QueryPlanner, RefinementStrategy};
+use crate::profile::{CountingStore, Profiler, QueryProfile};
+use crate::refinement::CompiledRefinement;
+use crate::result::{ExecutionStats, HistoryResult, MemberRowsResult, QueryResult};
+use crate::statistics::StatisticsService;
 use crate::traits::EclQueryable;
 use crate::traverser::HierarchyTraverser;
 
@@ -43,6 +52,10 @@ pub struct EclExecutor<'a> {
     config: ExecutorConfig,
     /// Query result cache (optional).
     cache: Option<Arc<QueryCache>>,
+    /// Planner (and statistics) used for physical plans and `explain()`.
+    planner: QueryPlanner,
+    /// Records per-node figures during `execute_profiled()`.
+    profiler: Option<Profiler<'a>>,
 }
 
 impl<'a> EclExecutor<'a> {
@@ -63,6 +76,8 @@ impl<'a> EclExecutor<'a> {
             store,
             config: ExecutorConfig::default(),
             cache: None,
+            planner: QueryPlanner::new().with_attribute_lookup(store.supports_attribute_lookup()),
+            profiler: None,
         }
     }
 
@@ -92,9 +107,36 @@ impl<'a> EclExecutor<'a> {
             store,
             config,
             cache,
+            planner: QueryPlanner::new().with_attribute_lookup(store.supports_attribute_lookup()),
+            profiler: None,
         }
     }
 
+    /// Sets the statistics used for cost-based planning.
+    ///
+    /// The statistics drive execution-time choices such as AND operand
+    /// order, membership checks and the [`RefinementStrategy`], as well as
+    /// the estimates reported by [`explain`](Self::explain).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut statistics = StatisticsService::new();
+    /// statistics.register_descendant_count(404684003, 120_000);
+    ///
+    /// let executor = EclExecutor::new(&store).with_statistics(statistics);
+    /// ```
+    pub fn with_statistics(mut self, statistics: StatisticsService) -> Self {
+        self.planner = QueryPlanner::with_statistics(statistics)
+            .with_attribute_lookup(self.store.supports_attribute_lookup());
+        self
+    }
+
+    /// Returns the statistics used for cost-based planning.
+    pub fn statistics(&self) -> &StatisticsService {
+        self.planner.statistics()
+    }
+
     /// Returns a reference to the cache if enabled.
     pub fn cache(&self) -> Option<&QueryCache> {
         self.cache.as_ref().map(|c| c.as_ref())
@@ -126,14 +168,57 @@ impl<'a> EclExecutor<'a> {
     /// println!("Found {} concepts", result.count());
     /// ```
     pub fn execute(&self, ecl: &str) -> EclResult<QueryResult> {
+        self.execute_guarded(ecl, ExecutionGuard::from_config(&self.config))
+    }
+
+    /// Executes an ECL expression string that can be cancelled from another thread.
+    ///
+    /// The token is checked during hierarchy traversal, refinement and filter
+    /// evaluation. Once it is cancelled, execution stops with
+    /// [`EclExecutorError::Cancelled`]. Partial results of a cancelled query
+    /// are never written to the cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `ecl` - ECL expression string
+    /// * `token` - Cancellation token; keep a clone to cancel the query
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let token = CancelToken::new();
+    ///
+    /// // On the next keystroke, cancel the previous query
+    /// let previous = std::mem::replace(&mut current_token, token.clone());
+    /// previous.cancel();
+    ///
+    /// match executor.execute_with_cancel("<< 404684003", &token) {
+    ///     Err(EclExecutorError::Cancelled) => { /* superseded */ }
+    ///     other => render(other?),
+    /// }
+    /// ```
+    pub fn execute_with_cancel(&self, ecl: &str, token: &CancelToken) -> EclResult<QueryResult> {
+        let guard = ExecutionGuard::from_config(&self.config).with_cancel_token(token.clone());
+        self.execute_guarded(ecl, guard)
+    }
+
+    /// Parses and executes an ECL string under the given execution guard.
+    fn execute_guarded(&self, ecl: &str, guard: ExecutionGuard) -> EclResult<QueryResult> {
         let start = Instant::now();
 
+        // Fail fast if the query was cancelled before it started
+        guard.check()?;
+
         // Normalize the ECL for cache key
         let cache_key = normalize_cache_key(ecl);
 
         // Check cache first
         if let Some(ref cache) = self.cache {
             if let Some(cached_result) = cache.get(&cache_key) {
+                guard.check_size(cached_result.len())?;
+                if self.profiler.is_some() {
+                    self.profile_cache_hit(&snomed_ecl::parse(ecl)?, cached_result.len());
+                }
                 let stats = ExecutionStats::new(start.elapsed(), 0, true);
                 return Ok(QueryResult::new(cached_result, stats));
             }
@@ -143,18 +228,85 @@ impl<'a> EclExecutor<'a> {
         let expr = snomed_ecl::parse(ecl)?;
 
         // Execute the parsed AST (without caching, since we handle it here)
-        let traverser = HierarchyTraverser::new(self.store);
+        let traverser = self.traverser(guard);
         let (concept_ids, concepts_traversed) = self.execute_expression(&expr, &traverser)?;
+        traverser.guard().check_size(concept_ids.len())?;
 
         // Store in cache if enabled
         if let Some(ref cache) = self.cache {
             cache.set(cache_key, concept_ids.clone());
+            self.profile_cache_miss();
         }
 
         let stats = ExecutionStats::new(start.elapsed(), concepts_traversed, false);
         Ok(QueryResult::new(concept_ids, stats))
     }
 
+    /// Executes a member-of query with a field selection and returns the
+    /// selected fields of each matching member row.
+    ///
+    /// Member filters around the member-of restrict the rows, and any other
+    /// filters restrict their referenced components. Only active rows are
+    /// returned unless a member filter tests `active`. Results are not cached.
+    ///
+    /// # Errors
+    ///
+    /// * [`EclExecutorError::UnsupportedFeature`] if the expression is not a
+    ///   member-of with a field selection
+    /// * Any error that [`execute`](Self::execute) can return
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let result = executor.execute_member_rows("^ [referencedComponentId, mapTarget] 447562003")?;
+    /// assert_eq!(result.columns, ["referencedComponentId", "mapTarget"]);
+    /// ```
+    pub fn execute_member_rows(&self, ecl: &str) -> EclResult<MemberRowsResult> {
+        let start = Instant::now();
+        let guard = ExecutionGuard::from_config(&self.config);
+        guard.check()?;
+
+        let expr = snomed_ecl::parse(ecl)?;
+        let projection = MemberProjection::find(&expr).ok_or_else(|| {
+            EclExecutorError::UnsupportedFeature(
+                "member rows require a member-of with a field selection such as `^ [*] X`"
+                    .to_string(),
+            )
+        })?;
+
+        let traverser = self.traverser(guard);
+        let (components, concepts_traversed) = self.execute_expression(&expr, &traverser)?;
+        let (refset_ids, _) = self.execute_with_intermediate_cache(projection.refset, &traverser)?;
+
+        let member_filter = MemberFilter::compile(projection.filters.iter().copied(), |e| {
+            self.resolve_filter_constraint(e, &traverser)
+        })?;
+        let mut refset_ids: Vec<SctId> = refset_ids.into_iter().collect();
+        refset_ids.sort_unstable();
+
+        let mut members = Vec::new();
+        for refset_id in refset_ids {
+            for member in self.store.get_refset_member_rows(refset_id) {
+                traverser.guard().check()?;
+                if components.contains(&member.referenced_component_id)
+                    && member_filter.matches(&member)
+                {
+                    members.push(member);
+                }
+            }
+            traverser.guard().check_size(members.len())?;
+        }
+        members.sort_by_key(|member| member.referenced_component_id);
+
+        let (columns, rows) = project_rows(&members, projection.fields);
+        let stats = ExecutionStats::new(start.elapsed(), concepts_traversed, false);
+        Ok(MemberRowsResult {
+            columns,
+            rows,
+            stats,
+        })
+    }
+
     /// Executes a pre-parsed ECL expression.
     ///
     /// Use this when you have already parsed the ECL expression and want to
@@ -181,13 +333,15 @@ impl<'a> EclExecutor<'a> {
         // Check cache first
         if let Some(ref cache) = self.cache {
             if let Some(cached_result) = cache.get(&cache_key) {
+                ExecutionGuard::from_config(&self.config).check_size(cached_result.len())?;
                 let stats = ExecutionStats::new(start.elapsed(), 0, true);
                 return Ok(QueryResult::new(cached_result, stats));
             }
         }
 
-        let traverser = HierarchyTraverser::new(self.store);
+        let traverser = self.traverser(ExecutionGuard::from_config(&self.config));
         let (concept_ids, concepts_traversed) = self.execute_expression(expr, &traverser)?;
+        traverser.guard().check_size(concept_ids.len())?;
 
         // Store in cache if enabled
         if let Some(ref cache) = self.cache {
@@ -198,11 +352,81 @@ impl<'a> EclExecutor<'a> {
         Ok(QueryResult::new(concept_ids, stats))
     }
 
+    /// Executes an ECL expression with a history supplement and keeps the
+    /// concepts the supplement added apart.
+    ///
+    /// The outermost filter block must hold a history supplement, as in
+    /// `<< 73211009 {{ +HISTORY }}` or
+    /// `<< 73211009 {{ +HISTORY (<< 900000000000527005) }}`. The expression
+    /// without the supplement gives the members, and the supplement is
+    /// applied to them to find the added inactive concepts. Results are
+    /// not cached.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EclExecutorError::UnsupportedFeature`] if the outermost
+    /// filter block has no history supplement.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let result = executor.execute_with_history("<< 73211009 {{ +HISTORY-MIN }}")?;
+    /// println!("{} members, {} added", result.members.len(), result.added.len());
+    /// ```
+    pub fn execute_with_history(&self, ecl: &str) -> EclResult<HistoryResult> {
+        let start = Instant::now();
+        let guard = ExecutionGuard::from_config(&self.config);
+        guard.check()?;
+
+        let expr = snomed_ecl::parse(ecl)?;
+        let (base, supplements) = split_history_supplements(&expr).ok_or_else(|| {
+            EclExecutorError::UnsupportedFeature(
+                "history results require a history supplement such as `{{ +HISTORY }}` \
+                 on the outermost expression"
+                    .to_string(),
+            )
+        })?;
+
+        let traverser = self.traverser(guard);
+        let (members, concepts_traversed) = self.execute_expression(&base, &traverser)?;
+
+        let mut concepts = members.clone();
+        for supplement in supplements {
+            concepts = self.apply_filter(&concepts, supplement, &traverser)?;
+        }
+        traverser.guard().check_size(concepts.len())?;
+        let added = concepts.difference(&members).copied().collect();
+
+        let stats = ExecutionStats::new(start.elapsed(), concepts_traversed, false);
+        Ok(HistoryResult {
+            members,
+            added,
+            stats,
+        })
+    }
+
     /// Internal method to execute an ECL expression recursively.
     ///
+    /// Every subexpression checks the traverser's execution guard for
+    /// cancellation and the deadline before it runs. The result-size limit
+    /// is checked while hierarchy operators and unions grow their sets, and
+    /// on the final result by the public entry points.
+    fn execute_expression(
+        &self,
+        expr: &EclExpression,
+        traverser: &HierarchyTraverser<'_>,
+    ) -> EclResult<(HashSet<SctId>, usize)> {
+        self.profiled(expr, || {
+            traverser.guard().check()?;
+            self.evaluate_expression(expr, traverser)
+        })
+    }
+
+    /// Evaluates a single ECL expression node.
+    ///
     /// This method handles caching of intermediate results when cache_intermediates
     /// is enabled in the configuration.
-    fn execute_expression(
+    fn evaluate_expression(
         &self,
         expr: &EclExpression,
         traverser: &HierarchyTraverser<'_>,
@@ -222,106 +446,80 @@ impl<'a> EclExecutor<'a> {
                 Ok((result, 1))
             }
 
-            // Descendant of: < concept
+            // Descendant of: < expression
             EclExpression::DescendantOf(inner) => {
-                let concept_id = self.get_focus_concept(inner)?;
-                let descendants = traverser.get_descendants(concept_id);
-                let count = descendants.len();
+                let (focus, focus_count) = self.get_focus_concepts(inner, traverser)?;
+                let descendants = traverser.try_get_descendants_of_set(&focus)?;
+                let count = descendants.len() + focus_count;
                 Ok((descendants, count))
             }
 
-            // Descendant or self of: << concept
+            // Descendant or self of: << expression
             EclExpression::DescendantOrSelfOf(inner) => {
-                let concept_id = self.get_focus_concept(inner)?;
-                let result = traverser.get_descendants_or_self(concept_id);
-                let count = result.len();
+                let (focus, focus_count) = self.get_focus_concepts(inner, traverser)?;
+                let result = traverser.try_get_descendants_or_self_of_set(&focus)?;
+                let count = result.len() + focus_count;
                 Ok((result, count))
             }
 
-            // Ancestor of: > concept
+            // Ancestor of: > expression
             EclExpression::AncestorOf(inner) => {
-                let concept_id = self.get_focus_concept(inner)?;
-                let ancestors = traverser.get_ancestors(concept_id);
-                let count = ancestors.len();
+                let (focus, focus_count) = self.get_focus_concepts(inner, traverser)?;
+                let ancestors = traverser.try_get_ancestors_of_set(&focus)?;
+                let count = ancestors.len() + focus_count;
                 Ok((ancestors, count))
             }
 
-            // Ancestor or self of: >> concept
+            // Ancestor or self of: >> expression
             EclExpression::AncestorOrSelfOf(inner) => {
-                let concept_id = self.get_focus_concept(inner)?;
-                let result = traverser.get_ancestors_or_self(concept_id);
-                let count = result.len();
+                let (focus, focus_count) = self.get_focus_concepts(inner, traverser)?;
+                let result = traverser.try_get_ancestors_or_self_of_set(&focus)?;
+                let count = result.len() + focus_count;
                 Ok((result, count))
             }
 
-            // Child of: <! concept (direct children only)
+            // Child of: <! expression (direct children only)
             EclExpression::ChildOf(inner) => {
-                let concept_id = self.get_focus_concept(inner)?;
-                let children = traverser.get_direct_children(concept_id);
-                let count = children.len();
+                let (focus, focus_count) = self.get_focus_concepts(inner, traverser)?;
+                let children = traverser.try_get_direct_children_of_set(&focus)?;
+                let count = children.len() + focus_count;
                 Ok((children, count))
             }
 
-            // Child or self of: <<! concept
+            // Child or self of: <<! expression
             EclExpression::ChildOrSelfOf(inner) => {
-                let concept_id = self.get_focus_concept(inner)?;
-                let mut result = traverser.get_direct_children(concept_id);
-                result.insert(concept_id);
-                let count = result.len();
+                let (focus, focus_count) = self.get_focus_concepts(inner, traverser)?;
+                let mut result = traverser.try_get_direct_children_of_set(&focus)?;
+                result.extend(focus);
+                let count = result.len() + focus_count;
                 Ok((result, count))
             }
 
-            // Parent of: >! concept (direct parents only)
+            // Parent of: >! expression (direct parents only)
             EclExpression::ParentOf(inner) => {
-                let concept_id = self.get_focus_concept(inner)?;
-                let parents = traverser.get_direct_parents(concept_id);
-                let count = parents.len();
+                let (focus, focus_count) = self.get_focus_concepts(inner, traverser)?;
+                let parents = traverser.try_get_direct_parents_of_set(&focus)?;
+                let count = parents.len() + focus_count;
                 Ok((parents, count))
             }
 
-            // Parent or self of: >>! concept
+            // Parent or self of: >>! expression
             EclExpression::ParentOrSelfOf(inner) => {
-                let concept_id = self.get_focus_concept(inner)?;
-                let mut result = traverser.get_direct_parents(concept_id);
-                result.insert(concept_id);
-                let count = result.len();
+                let (focus, focus_count) = self.get_focus_concepts(inner, traverser)?;
+                let mut result = traverser.try_get_direct_parents_of_set(&focus)?;
+                result.extend(focus);
+                let count = result.len() + focus_count;
                 Ok((result, count))
             }
 
-            // AND: intersection of two expressions
-            EclExpression::And(left, right) => {
-                let (left_result, left_count) =
-                    self.execute_with_intermediate_cache(left, traverser)?;
-                let (right_result, right_count) =
-                    self.execute_with_intermediate_cache(right, traverser)?;
-                let result: HashSet<SctId> =
-                    left_result.intersection(&right_result).copied().collect();
-                Ok((result, left_count + right_count))
-            }
-
-            // OR: union of two expressions
-            EclExpression::Or(left, right) => {
-                let (left_result, left_count) =
-                    self.execute_with_intermediate_cache(left, traverser)?;
-                let (right_result, right_count) =
-                    self.execute_with_intermediate_cache(right, traverser)?;
-                let result: HashSet<SctId> = left_result.union(&right_result).copied().collect();
-                Ok((result, left_count + right_count))
-            }
-
-            // MINUS: difference of two expressions
-            EclExpression::Minus(left, right) => {
-                let (left_result, left_count) =
-                    self.execute_with_intermediate_cache(left, traverser)?;
-                let (right_result, right_count) =
-                    self.execute_with_intermediate_cache(right, traverser)?;
-                let result: HashSet<SctId> =
-                    left_result.difference(&right_result).copied().collect();
-                Ok((result, left_count + right_count))
+            // AND, OR and MINUS run through the cost-based physical plan
+            EclExpression::And(..) | EclExpression::Or(..) | EclExpression::Minus(..) => {
+                let plan = self.planner.physical_plan(expr);
+                self.execute_plan(&plan, traverser)
             }
 
             // Member of: ^ refset_id or ^ (expression)
-            EclExpression::MemberOf { refset } => {
+            EclExpression::MemberOf { refset, .. } => {
                 // First, evaluate the refset expression to get the set of refset IDs
                 let (refset_ids, refset_count) = self.execute_expression(refset, traverser)?;
 
@@ -349,7 +547,14 @@ impl<'a> EclExecutor<'a> {
 
             // Any: * (all concepts)
             EclExpression::Any => {
-                let all: HashSet<SctId> = self.store.all_concept_ids().collect();
+                let guard = traverser.guard();
+                let mut all = HashSet::new();
+                for concept_id in self.store.all_concept_ids() {
+                    if all.len().is_multiple_of(GUARD_CHECK_INTERVAL) {
+                        guard.check()?;
+                    }
+                    all.insert(concept_id);
+                }
                 let count = all.len();
                 Ok((all, count))
             }
@@ -377,77 +582,7 @@ impl<'a> EclExecutor<'a> {
 
             // Refined expression: focus : refinement
             EclExpression::Refined { focus, refinement } => {
-                // Execute the focus expression first
-                let (focus_concepts, focus_count) =
-                    self.execute_with_intermediate_cache(focus, traverser)?;
-
-                // Filter concepts based on refinement
-                let mut result = HashSet::new();
-                let mut total_count = focus_count;
-
-                for concept_id in focus_concepts {
-                    // Get both outbound and inbound relationships (lazily, only if needed)
-                    let outbound_attrs = self.store.get_attributes(concept_id);
-                    let mut inbound_attrs: Option<Vec<_>> = None;
-                    let mut matches = true;
-
-                    // Check ungrouped attribute constraints
-                    for constraint in &refinement.ungrouped {
-                        // Get appropriate relationships based on reverse flag
-                        let attrs: &[crate::traits::RelationshipInfo] = if constraint.reverse {
-                            // Lazy initialization of inbound relationships
-                            if inbound_attrs.is_none() {
-                                inbound_attrs = Some(self.store.get_inbound_relationships(concept_id));
-                            }
-                            inbound_attrs.as_ref().unwrap()
-                        } else {
-                            &outbound_attrs
-                        };
-
-                        if !self.evaluate_attribute_constraint(
-                            concept_id,
-                            constraint,
-                            attrs,
-                            traverser,
-                        )? {
-                            matches = false;
-                            break;
-                        }
-                    }
-
-                    // Check grouped attribute constraints
-                    if matches {
-                        for group in &refinement.groups {
-                            // For groups, check if any constraint has reverse flag
-                            let has_reverse = group.constraints.iter().any(|c| c.reverse);
-                            let attrs: &[crate::traits::RelationshipInfo] = if has_reverse {
-                                if inbound_attrs.is_none() {
-                                    inbound_attrs = Some(self.store.get_inbound_relationships(concept_id));
-                                }
-                                inbound_attrs.as_ref().unwrap()
-                            } else {
-                                &outbound_attrs
-                            };
-
-                            if !self.evaluate_attribute_group(
-                                concept_id,
-                                group,
-                                attrs,
-                                traverser,
-                            )? {
-                                matches = false;
-                                break;
-                            }
-                        }
-                    }
-
-                    if matches {
-                        result.insert(concept_id);
-                    }
-                    total_count += outbound_attrs.len();
-                }
-
-                Ok((result, total_count))
+                self.execute_refined(focus, refinement, traverser)
             }
 
             // Dot notation: expression.attributeType
@@ -468,6 +603,7 @@ impl<'a> EclExecutor<'a> {
 
                 // For each source concept, get attribute values
                 for concept_id in source_concepts {
+                    traverser.guard().check()?;
                     let attributes = self.store.get_attributes(concept_id);
                     total_count += attributes.len();
 
@@ -495,9 +631,50 @@ impl<'a> EclExecutor<'a> {
                 let (mut concepts, mut count) =
                     self.execute_with_intermediate_cache(expression, traverser)?;
 
+                // Description filters in one block must be met by the same
+                // description, and member filters by the same member row, so
+                // each group is applied together in place of its first filter
+                let description_filter = DescriptionFilter::compile(filters, |e| {
+                    self.resolve_filter_constraint(e, traverser)
+                })?;
+                let member_filter = MemberFilter::compile(filters, |e| {
+                    self.resolve_filter_constraint(e, traverser)
+                })?;
+                let mut description_filter_applied = false;
+                let mut member_filter_applied = false;
+
                 // Apply each filter
                 for filter in filters {
-                    let filtered = self.apply_filter(&concepts, filter)?;
+                    let filtered = if DescriptionFilter::is_description_filter(filter) {
+                        if description_filter_applied {
+                            continue;
+                        }
+                        description_filter_applied = true;
+                        self.filter_concepts(&concepts, traverser.guard(), |concept_id| {
+                            description_filter.matches_concept(self.store, concept_id)
+                        })?
+                    } else if MemberFilter::is_member_filter(filter) {
+                        if member_filter_applied {
+                            continue;
+                        }
+                        member_filter_applied = true;
+                        let refset = member_of_refset(expression).ok_or_else(|| {
+                            EclExecutorError::UnsupportedFeature(format!(
+                                "member filter `{}` outside a member-of expression",
+                                filter
+                            ))
+                        })?;
+                        let (refset_ids, _) =
+                            self.execute_with_intermediate_cache(refset, traverser)?;
+                        let members = member_filter.matching_components(
+                            self.store,
+                            &refset_ids,
+                            traverser.guard(),
+                        )?;
+                        concepts.intersection(&members).copied().collect()
+                    } else {
+                        self.apply_filter(&concepts, filter, traverser)?
+                    };
                     count += concepts.len(); // Count filter evaluations
                     concepts = filtered;
                 }
@@ -512,7 +689,8 @@ impl<'a> EclExecutor<'a> {
                 // Find concepts that have no ancestors within the set
                 let mut result = HashSet::new();
                 for &concept_id in &concepts {
-                    let ancestors = traverser.get_ancestors(concept_id);
+                    traverser.guard().check()?;
+                    let ancestors = traverser.try_get_ancestors(concept_id)?;
                     let has_ancestor_in_set = ancestors.iter().any(|a| concepts.contains(a));
                     if !has_ancestor_in_set {
                         result.insert(concept_id);
@@ -529,7 +707,8 @@ impl<'a> EclExecutor<'a> {
                 // Find concepts that have no descendants within the set
                 let mut result = HashSet::new();
                 for &concept_id in &concepts {
-                    let descendants = traverser.get_descendants(concept_id);
+                    traverser.guard().check()?;
+                    let descendants = traverser.try_get_descendants(concept_id)?;
                     let has_descendant_in_set = descendants.iter().any(|d| concepts.contains(d));
                     if !has_descendant_in_set {
                         result.insert(concept_id);
@@ -551,507 +730,80 @@ impl<'a> EclExecutor<'a> {
         }
     }
 
-    /// Evaluates a single attribute constraint against a concept's attributes.
-    fn evaluate_attribute_constraint(
-        &self,
-        concept_id: SctId,
-        constraint: &snomed_ecl::AttributeConstraint,
-        attributes: &[crate::traits::RelationshipInfo],
-        traverser: &HierarchyTraverser<'_>,
-    ) -> EclResult<bool> {
-        use snomed_ecl::RefinementOperator;
-
-        // Get the set of acceptable attribute types
-        let attr_types = self.execute_expression(&constraint.attribute_type, traverser)?;
-
-        // Check if this is a concrete value constraint
-        if let snomed_ecl::EclExpression::Concrete { value, operator } = constraint.value.as_ref() {
-            return self.evaluate_concrete_constraint(
-                concept_id,
-                constraint,
-                &attr_types.0,
-                value,
-                *operator,
-            );
-        }
-
-        // Get the set of acceptable values for concept-to-concept relationships
-        let acceptable_values = self.execute_expression(&constraint.value, traverser)?;
-
-        // Find matching attributes
-        let matching_count = attributes
-            .iter()
-            .filter(|rel| {
-                // Check if attribute type matches (or wildcard)
-                let type_matches = matches!(
-                    constraint.attribute_type.as_ref(),
-                    snomed_ecl::EclExpression::Any
-                ) || attr_types.0.contains(&rel.type_id);
-
-                if !type_matches {
-                    return false;
-                }
-
-                // Check if value matches based on operator
-                match constraint.operator {
-                    RefinementOperator::Equal | RefinementOperator::DescendantOrSelfOf => {
-                        acceptable_values.0.contains(&rel.destination_id)
-                    }
-                    RefinementOperator::NotEqual => {
-                        !acceptable_values.0.contains(&rel.destination_id)
-                    }
-                    RefinementOperator::DescendantOf => {
-                        // Value must be a proper descendant
-                        let value_ancestors = traverser.get_ancestors(rel.destination_id);
-                        acceptable_values
-                            .0
-                            .iter()
-                            .any(|v| value_ancestors.contains(v))
-                    }
-                    RefinementOperator::AncestorOf | RefinementOperator::AncestorOrSelfOf => {
-                        // Value must be an ancestor
-                        acceptable_values.0.iter().any(|v| {
-                            let v_ancestors = traverser.get_ancestors(*v);
-                            v_ancestors.contains(&rel.destination_id)
-                                || (matches!(
-                                    constraint.operator,
-                                    RefinementOperator::AncestorOrSelfOf
-                                ) && *v == rel.destination_id)
-                        })
-                    }
-                }
-            })
-            .count();
-
-        // Check cardinality
-        if let Some(ref card) = constraint.cardinality {
-            Ok(card.matches(matching_count))
-        } else {
-            // No cardinality means at least one match required
-            Ok(matching_count > 0)
-        }
-    }
-
-    /// Evaluates a concrete value constraint against a concept's concrete relationships.
-    fn evaluate_concrete_constraint(
-        &self,
-        concept_id: SctId,
-        constraint: &snomed_ecl::AttributeConstraint,
-        attr_types: &HashSet<SctId>,
-        target_value: &snomed_ecl::ConcreteValue,
-        operator: snomed_ecl::ComparisonOperator,
-    ) -> EclResult<bool> {
-        // Get concrete relationships for this concept
-        let concrete_rels = self.store.get_concrete_values(concept_id);
-
-        // Find matching concrete relationships
-        let matching_count = concrete_rels
-            .iter()
-            .filter(|rel| {
-                // Check if attribute type matches (or wildcard)
-                let type_matches = matches!(
-                    constraint.attribute_type.as_ref(),
-                    snomed_ecl::EclExpression::Any
-                ) || attr_types.contains(&rel.type_id);
-
-                if !type_matches {
-                    return false;
-                }
-
-                // Compare the concrete value
-                Self::compare_concrete_values(&rel.value, target_value, operator)
-            })
-            .count();
-
-        // Check cardinality
-        if let Some(ref card) = constraint.cardinality {
-            Ok(card.matches(matching_count))
-        } else {
-            // No cardinality means at least one match required
-            Ok(matching_count > 0)
-        }
-    }
-
-    /// Compares a concrete relationship value against a target value using the given operator.
-    fn compare_concrete_values(
-        actual: &crate::traits::ConcreteValueRef,
-        target: &snomed_ecl::ConcreteValue,
-        operator: snomed_ecl::ComparisonOperator,
-    ) -> bool {
-        use snomed_ecl::ComparisonOperator;
-        use snomed_ecl::ConcreteValue;
-        use crate::traits::ConcreteValueRef;
-
-        match (actual, target) {
-            // Integer comparisons
-            (ConcreteValueRef::Integer(a), ConcreteValue::Integer(t)) => {
-                match operator {
-                    ComparisonOperator::Equal => *a == *t,
-                    ComparisonOperator::NotEqual => *a != *t,
-                    ComparisonOperator::LessThan => *a < *t,
-                    ComparisonOperator::LessThanOrEqual => *a <= *t,
-                    ComparisonOperator::GreaterThan => *a > *t,
-                    ComparisonOperator::GreaterThanOrEqual => *a >= *t,
-                }
-            }
-            // Decimal comparisons
-            (ConcreteValueRef::Decimal(a), ConcreteValue::Decimal(t)) => {
-                match operator {
-                    ComparisonOperator::Equal => (*a - *t).abs() < f64::EPSILON,
-                    ComparisonOperator::NotEqual => (*a - *t).abs() >= f64::EPSILON,
-                    ComparisonOperator::LessThan => *a < *t,
-                    ComparisonOperator::LessThanOrEqual => *a <= *t,
-                    ComparisonOperator::GreaterThan => *a > *t,
-                    ComparisonOperator::GreaterThanOrEqual => *a >= *t,
-                }
-            }
-            // Integer vs Decimal (promote integer to decimal)
-            (ConcreteValueRef::Integer(a), ConcreteValue::Decimal(t)) => {
-                let a_f = *a as f64;
-                match operator {
-                    ComparisonOperator::Equal => (a_f - *t).abs() < f64::EPSILON,
-                    ComparisonOperator::NotEqual => (a_f - *t).abs() >= f64::EPSILON,
-                    ComparisonOperator::LessThan => a_f < *t,
-                    ComparisonOperator::LessThanOrEqual => a_f <= *t,
-                    ComparisonOperator::GreaterThan => a_f > *t,
-                    ComparisonOperator::GreaterThanOrEqual => a_f >= *t,
-                }
-            }
-            (ConcreteValueRef::Decimal(a), ConcreteValue::Integer(t)) => {
-                let t_f = *t as f64;
-                match operator {
-                    ComparisonOperator::Equal => (*a - t_f).abs() < f64::EPSILON,
-                    ComparisonOperator::NotEqual => (*a - t_f).abs() >= f64::EPSILON,
-                    ComparisonOperator::LessThan => *a < t_f,
-                    ComparisonOperator::LessThanOrEqual => *a <= t_f,
-                    ComparisonOperator::GreaterThan => *a > t_f,
-                    ComparisonOperator::GreaterThanOrEqual => *a >= t_f,
-                }
-            }
-            // String comparisons (only = and != make sense)
-            (ConcreteValueRef::String(a), ConcreteValue::String(t)) => {
-                match operator {
-                    ComparisonOperator::Equal => a == t,
-                    ComparisonOperator::NotEqual => a != t,
-                    // Lexicographic comparison for strings
-                    ComparisonOperator::LessThan => a < t,
-                    ComparisonOperator::LessThanOrEqual => a <= t,
-                    ComparisonOperator::GreaterThan => a > t,
-                    ComparisonOperator::GreaterThanOrEqual => a >= t,
-                }
-            }
-            // Boolean comparisons (only = and != make sense)
-            (ConcreteValueRef::Integer(a), ConcreteValue::Boolean(t)) => {
-                // Treat 0 as false, non-zero as true
-                let a_bool = *a != 0;
-                match operator {
-                    ComparisonOperator::Equal => a_bool == *t,
-                    ComparisonOperator::NotEqual => a_bool != *t,
-                    _ => false, // Other comparisons don't make sense for booleans
-                }
-            }
-            // Type mismatches
-            _ => false,
-        }
-    }
-
-    /// Evaluates an attribute group against a concept's attributes.
-    fn evaluate_attribute_group(
-        &self,
-        concept_id: SctId,
-        group: &snomed_ecl::AttributeGroup,
-        attributes: &[crate::traits::RelationshipInfo],
-        traverser: &HierarchyTraverser<'_>,
-    ) -> EclResult<bool> {
-        // Group constraints must be satisfied within the same relationship group
-        // Get unique group numbers (excluding 0 which is ungrouped)
-        let group_numbers: HashSet<u16> = attributes
-            .iter()
-            .filter(|r| r.group > 0)
-            .map(|r| r.group)
-            .collect();
-
-        if group_numbers.is_empty() && !group.constraints.is_empty() {
-            // No groups and we have constraints - check if cardinality allows zero
-            if let Some(ref card) = group.cardinality {
-                return Ok(card.matches(0));
-            }
-            return Ok(false);
-        }
-
-        let mut matching_groups = 0;
-
-        for group_num in group_numbers {
-            // Get attributes in this group
-            let group_attrs: Vec<_> = attributes
-                .iter()
-                .filter(|r| r.group == group_num)
-                .cloned()
-                .collect();
-
-            // Check if all constraints are satisfied within this group
-            let mut all_constraints_met = true;
-            for constraint in &group.constraints {
-                if !self.evaluate_attribute_constraint(
-                    concept_id,
-                    constraint,
-                    &group_attrs,
-                    traverser,
-                )? {
-                    all_constraints_met = false;
-                    break;
-                }
-            }
-
-            if all_constraints_met {
-                matching_groups += 1;
-            }
-        }
-
-        // Check group cardinality
-        if let Some(ref card) = group.cardinality {
-            Ok(card.matches(matching_groups))
-        } else {
-            // No cardinality means at least one matching group required
-            Ok(matching_groups > 0)
-        }
-    }
-
     /// Applies a filter to a set of concepts.
+    ///
+    /// The guard's deadline is checked once per candidate concept.
     fn apply_filter(
         &self,
         concepts: &HashSet<SctId>,
         filter: &snomed_ecl::EclFilter,
+        traverser: &HierarchyTraverser<'_>,
     ) -> EclResult<HashSet<SctId>> {
-        use crate::traits::{Acceptability, HistoryAssociationType};
-        use snomed_ecl::{EclFilter, HistoryProfile, TermMatchType};
-
-        match filter {
-            EclFilter::Term { match_type, value } => {
-                let mut result = HashSet::new();
-                let search_term = value.to_lowercase();
-
-                for &concept_id in concepts {
-                    let descriptions = self.store.get_descriptions(concept_id);
-
-                    let matches = descriptions.iter().any(|desc| {
-                        let term_lower = desc.term.to_lowercase();
-                        match match_type {
-                            TermMatchType::Contains => term_lower.contains(&search_term),
-                            TermMatchType::StartsWith => term_lower.starts_with(&search_term),
-                            TermMatchType::Exact => term_lower == search_term,
-                            TermMatchType::Regex => {
-                                // Basic regex support - could use regex crate for full support
-                                term_lower.contains(&search_term)
-                            }
-                            TermMatchType::Wildcard => {
-                                // Convert wildcard pattern (* and ?) to simple matching
-                                let pattern = search_term
-                                    .replace('*', "")
-                                    .replace('?', "");
-                                term_lower.contains(&pattern)
-                            }
-                        }
-                    });
-
-                    if matches {
-                        result.insert(concept_id);
-                    }
-                }
+        use crate::traits::HistoryAssociationType;
+        use snomed_ecl::{FilterDomain, HistoryProfile};
 
-                Ok(result)
-            }
-
-            EclFilter::Language { codes } => {
-                let mut result = HashSet::new();
-                for &concept_id in concepts {
-                    let descriptions = self.store.get_descriptions(concept_id);
-                    let matches = descriptions.iter().any(|desc| {
-                        codes.iter().any(|code| desc.language_code.to_lowercase() == *code)
-                    });
-                    if matches {
-                        result.insert(concept_id);
-                    }
-                }
-                Ok(result)
-            }
+        let guard = traverser.guard();
 
-            EclFilter::DescriptionType { type_ids } => {
-                let mut result = HashSet::new();
-                for &concept_id in concepts {
-                    let descriptions = self.store.get_descriptions(concept_id);
-                    let matches = descriptions.iter().any(|desc| {
-                        type_ids.contains(&desc.type_id)
-                    });
-                    if matches {
-                        result.insert(concept_id);
-                    }
-                }
-                Ok(result)
-            }
-
-            EclFilter::Dialect { dialect_ids, acceptability } => {
-                let mut result = HashSet::new();
-                for &concept_id in concepts {
-                    let descriptions = self.store.get_descriptions(concept_id);
-                    let matches = descriptions.iter().any(|desc| {
-                        let refsets = self.store.get_description_language_refsets(desc.description_id);
-                        refsets.iter().any(|membership| {
-                            let dialect_match = dialect_ids.contains(&membership.refset_id);
-                            let acc_match = acceptability.as_ref().map_or(true, |acc| {
-                                match acc {
-                                    snomed_ecl::FilterAcceptability::Preferred => {
-                                        membership.acceptability == Acceptability::Preferred
-                                    }
-                                    snomed_ecl::FilterAcceptability::Acceptable => {
-                                        membership.acceptability == Acceptability::Acceptable
-                                    }
-                                }
-                            });
-                            dialect_match && acc_match
-                        })
-                    });
-                    if matches {
-                        result.insert(concept_id);
-                    }
-                }
-                Ok(result)
-            }
-
-            EclFilter::CaseSignificance { case_significance_id } => {
-                let mut result = HashSet::new();
-                for &concept_id in concepts {
-                    let descriptions = self.store.get_descriptions(concept_id);
-                    let matches = descriptions.iter().any(|desc| {
-                        desc.case_significance_id == *case_significance_id
-                    });
-                    if matches {
-                        result.insert(concept_id);
-                    }
-                }
-                Ok(result)
-            }
-
-            EclFilter::Active(active) => {
-                let mut result = HashSet::new();
-                for &concept_id in concepts {
-                    if self.store.is_concept_active(concept_id) == *active {
-                        result.insert(concept_id);
-                    }
-                }
-                Ok(result)
+        match filter {
+            EclFilter::Term { .. }
+            | EclFilter::Language { .. }
+            | EclFilter::DescriptionType { .. }
+            | EclFilter::Dialect { .. }
+            | EclFilter::CaseSignificance { .. }
+            | EclFilter::PreferredIn { .. }
+            | EclFilter::AcceptableIn { .. }
+            | EclFilter::LanguageRefSet { .. } => {
+                // Compile once, not once per description
+                let description_filter = DescriptionFilter::compile([filter], |e| {
+                    self.resolve_filter_constraint(e, traverser)
+                })?;
+                self.filter_concepts(concepts, guard, |concept_id| {
+                    description_filter.matches_concept(self.store, concept_id)
+                })
             }
 
-            EclFilter::Module { module_ids } => {
-                let mut result = HashSet::new();
-                for &concept_id in concepts {
-                    if let Some(module_id) = self.store.get_concept_module(concept_id) {
-                        if module_ids.contains(&module_id) {
-                            result.insert(concept_id);
-                        }
-                    }
-                }
-                Ok(result)
+            EclFilter::Active(active) => self.filter_concepts(concepts, guard, |concept_id| {
+                self.store.is_concept_active(concept_id) == *active
+            }),
+
+            EclFilter::Module { modules } => {
+                let module_ids = self.resolve_filter_constraint(modules, traverser)?;
+                self.filter_concepts(concepts, guard, |concept_id| {
+                    self.store
+                        .get_concept_module(concept_id)
+                        .is_some_and(|module_id| module_ids.contains(&module_id))
+                })
             }
 
             EclFilter::EffectiveTime { operator, date } => {
                 use snomed_ecl::ComparisonOperator;
-                let mut result = HashSet::new();
-                for &concept_id in concepts {
-                    if let Some(effective_time) = self.store.get_concept_effective_time(concept_id) {
-                        let matches = match operator {
+                self.filter_concepts(concepts, guard, |concept_id| {
+                    match self.store.get_concept_effective_time(concept_id) {
+                        Some(effective_time) => match operator {
                             ComparisonOperator::Equal => effective_time == *date,
                             ComparisonOperator::NotEqual => effective_time != *date,
                             ComparisonOperator::LessThan => effective_time < *date,
                             ComparisonOperator::LessThanOrEqual => effective_time <= *date,
                             ComparisonOperator::GreaterThan => effective_time > *date,
                             ComparisonOperator::GreaterThanOrEqual => effective_time >= *date,
-                        };
-                        if matches {
-                            result.insert(concept_id);
-                        }
+                        },
+                        None => false,
                     }
-                }
-                Ok(result)
+                })
             }
 
             EclFilter::DefinitionStatus { is_primitive } => {
-                let mut result = HashSet::new();
-                for &concept_id in concepts {
-                    if let Some(primitive) = self.store.is_concept_primitive(concept_id) {
-                        if primitive == *is_primitive {
-                            result.insert(concept_id);
-                        }
-                    }
-                }
-                Ok(result)
-            }
-
-            EclFilter::SemanticTag { tags } => {
-                let mut result = HashSet::new();
-                for &concept_id in concepts {
-                    if let Some(tag) = self.store.get_semantic_tag(concept_id) {
-                        let tag_lower = tag.to_lowercase();
-                        if tags.iter().any(|t| t.to_lowercase() == tag_lower) {
-                            result.insert(concept_id);
-                        }
-                    }
-                }
-                Ok(result)
-            }
-
-            EclFilter::PreferredIn { refset_ids } => {
-                let mut result = HashSet::new();
-                for &concept_id in concepts {
-                    let descriptions = self.store.get_descriptions(concept_id);
-                    let matches = descriptions.iter().any(|desc| {
-                        let refsets = self.store.get_description_language_refsets(desc.description_id);
-                        refsets.iter().any(|membership| {
-                            refset_ids.contains(&membership.refset_id)
-                                && membership.acceptability == Acceptability::Preferred
-                        })
-                    });
-                    if matches {
-                        result.insert(concept_id);
-                    }
-                }
-                Ok(result)
+                self.filter_concepts(concepts, guard, |concept_id| {
+                    self.store.is_concept_primitive(concept_id) == Some(*is_primitive)
+                })
             }
 
-            EclFilter::AcceptableIn { refset_ids } => {
-                let mut result = HashSet::new();
-                for &concept_id in concepts {
-                    let descriptions = self.store.get_descriptions(concept_id);
-                    let matches = descriptions.iter().any(|desc| {
-                        let refsets = self.store.get_description_language_refsets(desc.description_id);
-                        refsets.iter().any(|membership| {
-                            refset_ids.contains(&membership.refset_id)
-                                && membership.acceptability == Acceptability::Acceptable
-                        })
-                    });
-                    if matches {
-                        result.insert(concept_id);
-                    }
-                }
-                Ok(result)
-            }
-
-            EclFilter::LanguageRefSet { refset_ids } => {
-                let mut result = HashSet::new();
-                for &concept_id in concepts {
-                    let descriptions = self.store.get_descriptions(concept_id);
-                    let matches = descriptions.iter().any(|desc| {
-                        let refsets = self.store.get_description_language_refsets(desc.description_id);
-                        refsets.iter().any(|membership| {
-                            refset_ids.contains(&membership.refset_id)
-                        })
-                    });
-                    if matches {
-                        result.insert(concept_id);
-                    }
-                }
-                Ok(result)
-            }
+            EclFilter::SemanticTag { tags } => self.filter_concepts(concepts, guard, |concept_id| {
+                self.store.get_semantic_tag(concept_id).is_some_and(|tag| {
+                    let tag_lower = tag.to_lowercase();
+                    tags.iter().any(|t| t.to_lowercase() == tag_lower)
+                })
+            }),
 
             EclFilter::Id { ids } => {
                 // Filter to only concepts that are in the ID list
@@ -1059,23 +811,38 @@ impl<'a> EclExecutor<'a> {
                 Ok(concepts.intersection(&id_set).copied().collect())
             }
 
-            EclFilter::History { profile } => {
-                // Include historical associations based on profile
+            EclFilter::History { profile, subset } => {
+                // An association subset is resolved once, to the ids of the
+                // historical association reference sets to follow
+                let association_refsets = subset
+                    .as_ref()
+                    .map(|subset| self.resolve_filter_constraint(subset, traverser))
+                    .transpose()?;
+
+                // Include historical associations based on subset or profile
                 let mut result = concepts.clone();
                 for &concept_id in concepts {
-                    let historical = match profile {
-                        None | Some(HistoryProfile::Max) => {
+                    guard.check()?;
+                    let historical = match (&association_refsets, profile) {
+                        (Some(refset_ids), _) => self
+                            .store
+                            .get_historical_associations_by_refset(concept_id)
+                            .into_iter()
+                            .filter(|(refset_id, _)| refset_ids.contains(refset_id))
+                            .flat_map(|(_, associated)| associated)
+                            .collect(),
+                        (None, None | Some(HistoryProfile::Max)) => {
                             // All historical associations
                             self.store.get_historical_associations(concept_id)
                         }
-                        Some(HistoryProfile::Min) => {
+                        (None, Some(HistoryProfile::Min)) => {
                             // SAME_AS only
                             self.store.get_historical_associations_by_type(
                                 concept_id,
                                 HistoryAssociationType::SameAs,
                             )
                         }
-                        Some(HistoryProfile::Mod) => {
+                        (None, Some(HistoryProfile::Mod)) => {
                             // SAME_AS, REPLACED_BY, POSSIBLY_EQUIVALENT_TO
                             let mut assocs = self.store.get_historical_associations_by_type(
                                 concept_id,
@@ -1097,23 +864,60 @@ impl<'a> EclExecutor<'a> {
                 Ok(result)
             }
 
-            EclFilter::Member { .. } => {
-                // Member filters require refset access - return as-is for now
-                // Full implementation would filter based on refset member fields
-                Ok(concepts.clone())
-            }
+            EclFilter::Member { .. } => Err(EclExecutorError::UnsupportedFeature(format!(
+                "member filter `{}` outside a member-of expression",
+                filter
+            ))),
 
-            EclFilter::DomainQualified { domain: _, filter } => {
-                // Domain-qualified filters: the domain indicates which component type to filter
-                // For now, we delegate to the inner filter since most filters already have
-                // implicit domain semantics (e.g., term filters are always description-based)
-                // Full implementation would use domain to disambiguate when filters can
-                // apply to multiple domains (like active, effectiveTime, moduleId)
-                self.apply_filter(concepts, filter)
+            EclFilter::DomainQualified { domain, filter: inner } => {
+                if !inner.applies_to(*domain) {
+                    return Err(EclExecutorError::InvalidFilterDomain {
+                        filter: inner.to_string(),
+                        domain: *domain,
+                    });
+                }
+                match domain {
+                    // Concept filters test the concept itself
+                    FilterDomain::Concept => self.apply_filter(concepts, inner, traverser),
+                    FilterDomain::Description => {
+                        let description_filter = DescriptionFilter::compile([filter], |e| {
+                            self.resolve_filter_constraint(e, traverser)
+                        })?;
+                        self.filter_concepts(concepts, guard, |concept_id| {
+                            description_filter.matches_concept(self.store, concept_id)
+                        })
+                    }
+                    FilterDomain::Member => Err(EclExecutorError::UnsupportedFeature(format!(
+                        "member filter `{}` outside a member-of expression",
+                        filter
+                    ))),
+                }
             }
         }
     }
 
+    /// Resolves the constraint of a module, type or dialect filter to an id
+    /// set.
+    ///
+    /// Literal ids, such as `moduleId = 900000000000207008` or the ids of a
+    /// `type = syn` alias, are taken as they are, so a store need not hold
+    /// the module or metadata concepts to filter by them. Any other
+    /// constraint, such as `<< 900000000000445007`, is executed like a
+    /// sub-expression.
+    fn resolve_filter_constraint(
+        &self,
+        constraint: &EclExpression,
+        traverser: &HierarchyTraverser<'_>,
+    ) -> EclResult<HashSet<SctId>> {
+        match constraint.unwrap_nested() {
+            EclExpression::ConceptReference { concept_id, .. } => Ok(HashSet::from([*concept_id])),
+            EclExpression::ConceptSet(ids) => Ok(ids.iter().copied().collect()),
+            other => self
+                .execute_with_intermediate_cache(other, traverser)
+                .map(|(ids, _)| ids),
+        }
+    }
+
     /// Executes a subexpression with intermediate caching for compound queries.
     ///
     /// This is used for the left and right operands of AND, OR, and MINUS operations.
@@ -1123,6 +927,20 @@ impl<'a> EclExecutor<'a> {
         expr: &EclExpression,
         traverser: &HierarchyTraverser<'_>,
     ) -> EclResult<(HashSet<SctId>, usize)> {
+        self.with_intermediate_cache(expr, || {
+            self.execute_expression(expr, traverser)
+        })
+    }
+
+    /// Looks `expr` up in the intermediate cache, computing and caching it on a miss.
+    fn with_intermediate_cache<F>(
+        &self,
+        expr: &EclExpression,
+        compute: F,
+    ) -> EclResult<(HashSet<SctId>, usize)>
+    where
+        F: FnOnce() -> EclResult<(HashSet<SctId>, usize)>,
+    {
         // Check if we should cache intermediates
         let should_cache = self
             .cache
@@ -1131,7 +949,7 @@ impl<'a> EclExecutor<'a> {
             .unwrap_or(false);
 
         if !should_cache {
-            return self.execute_expression(expr, traverser);
+            return compute();
         }
 
         // Generate cache key from expression
@@ -1140,39 +958,456 @@ impl<'a> EclExecutor<'a> {
         // Check cache first
         if let Some(ref cache) = self.cache {
             if let Some(cached_result) = cache.get(&cache_key) {
+                self.profile_cache_hit(expr, cached_result.len());
                 return Ok((cached_result, 0)); // 0 traversed since it's cached
             }
         }
 
         // Execute the expression
-        let (result, count) = self.execute_expression(expr, traverser)?;
+        let (result, count) = compute()?;
 
         // Cache the result
         if let Some(ref cache) = self.cache {
             cache.set(cache_key, result.clone());
+            self.profile_cache_miss();
         }
 
         Ok((result, count))
     }
 
-    /// Extracts the focus concept ID from an expression.
+    /// Creates the hierarchy traverser used for a single query execution.
+    fn traverser(&self, guard: ExecutionGuard) -> HierarchyTraverser<'a> {
+        HierarchyTraverser::with_guard(self.store, guard).with_parallel(self.config.parallel)
+    }
+
+    /// Executes a node of a physical plan.
     ///
-    /// For hierarchy operations like `< concept`, `<< concept`, etc.,
-    /// the inner expression must resolve to a single concept.
-    fn get_focus_concept(&self, expr: &EclExpression) -> EclResult<SctId> {
-        let expr = expr.unwrap_nested();
+    /// Intersections run their children in plan order and stop as soon as
+    /// the intermediate result is empty; differences skip the subtracted
+    /// operand when the left side is empty. Membership-check children test
+    /// the intermediate result concept by concept.
+    fn execute_plan(
+        &self,
+        node: &PlanNode,
+        traverser: &HierarchyTraverser<'_>,
+    ) -> EclResult<(HashSet<SctId>, usize)> {
+        match node.operator {
+            PlanOperator::Scan | PlanOperator::MembershipCheck => {
+                self.execute_with_intermediate_cache(&node.expression, traverser)
+            }
 
-        match expr {
-            EclExpression::ConceptReference { concept_id, .. } => {
-                // Verify concept exists
-                if !self.store.has_concept(*concept_id) {
-                    return Err(EclExecutorError::ConceptNotFound(*concept_id));
+            PlanOperator::Intersect => {
+                let (driver, rest) = node
+                    .children
+                    .split_first()
+                    .expect("intersection has a driving operand");
+                let (mut result, mut count) = self.execute_plan_child(driver, traverser)?;
+                if result.is_empty() {
+                    return Ok((result, count));
+                }
+
+                // Operands run one at a time, in plan order, so that an empty
+                // intermediate result skips the remaining (more expensive) ones
+                for child in rest {
+                    if result.is_empty() {
+                        break;
+                    }
+
+                    if child.operator == PlanOperator::MembershipCheck {
+                        let (members, checked) =
+                            self.filter_members(&result, child, true, traverser)?;
+                        result = members;
+                        count += checked;
+                    } else {
+                        let (operand, operand_count) = self.execute_plan_child(child, traverser)?;
+                        result.retain(|id| operand.contains(id));
+                        count += operand_count;
+                    }
                 }
-                Ok(*concept_id)
+
+                Ok((result, count))
+            }
+
+            PlanOperator::Union => {
+                let mut result = HashSet::new();
+                let mut count = 0;
+                for (child, prefetched) in node
+                    .children
+                    .iter()
+                    .zip(self.prefetch_children(&node.children, traverser)?)
+                {
+                    let (operand, operand_count) = match prefetched {
+                        Some(operand) => operand,
+                        None => self.execute_plan_child(child, traverser)?,
+                    };
+                    result.extend(operand);
+                    count += operand_count;
+                    traverser.guard().check_size(result.len())?;
+                }
+                Ok((result, count))
+            }
+
+            PlanOperator::Difference => {
+                let [left, right] = node.children.as_slice() else {
+                    unreachable!("difference has exactly two operands")
+                };
+                let (mut result, mut count) = self.execute_plan_child(left, traverser)?;
+                if result.is_empty() {
+                    return Ok((result, count));
+                }
+
+                if right.operator == PlanOperator::MembershipCheck {
+                    let (members, checked) =
+                        self.filter_members(&result, right, false, traverser)?;
+                    result = members;
+                    count += checked;
+                } else {
+                    let (operand, operand_count) = self.execute_plan_child(right, traverser)?;
+                    result.retain(|id| !operand.contains(id));
+                    count += operand_count;
+                }
+                Ok((result, count))
             }
-            _ => Err(EclExecutorError::UnsupportedFeature(
-                "Hierarchy operators require a concept reference".to_string(),
-            )),
+        }
+    }
+
+    /// Executes a child node, caching compound intermediates like scans.
+    fn execute_plan_child(
+        &self,
+        node: &PlanNode,
+        traverser: &HierarchyTraverser<'_>,
+    ) -> EclResult<(HashSet<SctId>, usize)> {
+        if node.is_compound() {
+            self.with_intermediate_cache(&node.expression, || {
+                self.profiled_plan_node(node, || self.execute_plan(node, traverser))
+            })
+        } else {
+            self.execute_plan(node, traverser)
+        }
+    }
+
+    /// Evaluates the children of a union ahead of time when running in parallel.
+    ///
+    /// Returns one entry per child: the child's result if it was evaluated
+    /// here, or `None` if the caller should evaluate it lazily (always the
+    /// case in sequential mode, and for membership checks). Errors are
+    /// reported in child order so that they are deterministic.
+    ///
+    /// Intersections and differences are not prefetched: they evaluate their
+    /// operands in plan order and stop early once the result is empty.
+    #[allow(clippy::type_complexity)]
+    fn prefetch_children(
+        &self,
+        children: &[PlanNode],
+        traverser: &HierarchyTraverser<'_>,
+    ) -> EclResult<Vec<Option<(HashSet<SctId>, usize)>>> {
+        #[cfg(feature = "parallel")]
+        if traverser.is_parallel() && children.len() > 1 {
+            use rayon::prelude::*;
+
+            let results: Vec<EclResult<Option<(HashSet<SctId>, usize)>>> = children
+                .par_iter()
+                .map(|child| {
+                    if child.operator == PlanOperator::MembershipCheck {
+                        Ok(None)
+                    } else {
+                        self.execute_plan_child(child, traverser).map(Some)
+                    }
+                })
+                .collect();
+            return results.into_iter().collect();
+        }
+
+        #[cfg(not(feature = "parallel"))]
+        let _ = traverser;
+        Ok(children.iter().map(|_| None).collect())
+    }
+
+    /// Keeps the concepts that are (or, with `keep_members == false`, are not)
+    /// members of a membership-check node's expression, testing each concept
+    /// individually.
+    fn filter_members(
+        &self,
+        concepts: &HashSet<SctId>,
+        node: &PlanNode,
+        keep_members: bool,
+        traverser: &HierarchyTraverser<'_>,
+    ) -> EclResult<(HashSet<SctId>, usize)> {
+        self.profiled_plan_node(node, || {
+            let membership = Membership::compile(&node.expression, traverser, &mut |e| {
+                self.execute_expression(e, traverser).map(|(concepts, _)| concepts)
+            })?;
+
+            let members = self.try_filter_concepts(concepts, traverser.guard(), |concept_id| {
+                Ok(membership.contains(concept_id, self.store, traverser)? == keep_members)
+            })?;
+            Ok((members, concepts.len()))
+        })
+    }
+
+    /// Runs `compute` as a profiled node for `expr` when profiling.
+    fn profiled<F>(&self, expr: &EclExpression, compute: F) -> EclResult<(HashSet<SctId>, usize)>
+    where
+        F: FnOnce() -> EclResult<(HashSet<SctId>, usize)>,
+    {
+        match self.profiler {
+            Some(ref profiler) => {
+                let (operation, estimate, cost) = self.planner.estimate_node(expr);
+                profiler.record(&operation, expr, (estimate, cost), compute)
+            }
+            None => compute(),
+        }
+    }
+
+    /// Runs `compute` as a profiled node for a physical plan node when profiling.
+    fn profiled_plan_node<F>(
+        &self,
+        node: &PlanNode,
+        compute: F,
+    ) -> EclResult<(HashSet<SctId>, usize)>
+    where
+        F: FnOnce() -> EclResult<(HashSet<SctId>, usize)>,
+    {
+        match self.profiler {
+            Some(ref profiler) => profiler.record(
+                &node.operator.to_string(),
+                &node.expression,
+                (node.estimated_cardinality, node.estimated_cost),
+                compute,
+            ),
+            None => compute(),
+        }
+    }
+
+    /// Records a cached result for `expr` when profiling.
+    fn profile_cache_hit(&self, expr: &EclExpression, rows: usize) {
+        if let Some(ref profiler) = self.profiler {
+            let (operation, estimate, cost) = self.planner.estimate_node(expr);
+            profiler.record_cache_hit(&operation, expr, (estimate, cost), rows);
+        }
+    }
+
+    /// Marks the node that was just computed as cached when profiling.
+    fn profile_cache_miss(&self) {
+        if let Some(ref profiler) = self.profiler {
+            profiler.mark_cache_miss();
+        }
+    }
+
+    /// Executes a refined expression: `focus : refinement`.
+    ///
+    /// The refinement is compiled once, then evaluated either by scanning the
+    /// focus concepts or, if the store has a reverse attribute index and the
+    /// statistics say it is cheaper, by looking up the concepts that have the
+    /// required attribute values and intersecting them with the focus. A
+    /// wildcard focus (`* : ...`) is never materialised for a reverse lookup.
+    fn execute_refined(
+        &self,
+        focus: &EclExpression,
+        refinement: &snomed_ecl::Refinement,
+        traverser: &HierarchyTraverser<'_>,
+    ) -> EclResult<(HashSet<SctId>, usize)> {
+        let focus_is_any = matches!(focus.unwrap_nested(), EclExpression::Any);
+
+        // Execute the focus expression first, unless it is the wildcard
+        let focus_result = if focus_is_any {
+            None
+        } else {
+            let (focus_concepts, focus_count) =
+                self.execute_with_intermediate_cache(focus, traverser)?;
+            if focus_concepts.is_empty() {
+                return Ok((focus_concepts, focus_count));
+            }
+            Some((focus_concepts, focus_count))
+        };
+
+        // Resolve attribute types and values once
+        let compiled = CompiledRefinement::compile(refinement, traverser, |expression| {
+            self.execute_expression(expression, traverser)
+                .map(|(concepts, _)| concepts)
+        })?;
+
+        let lookup = compiled
+            .attribute_lookup()
+            .filter(|_| self.store.supports_attribute_lookup());
+        let focus_size = focus_result
+            .as_ref()
+            .map_or(self.statistics().estimated_total_concepts(), |(concepts, _)| {
+                concepts.len()
+            });
+        let strategy = RefinementStrategy::choose(
+            self.statistics(),
+            focus_size,
+            lookup.as_ref().map(|l| l.lookups()),
+            lookup
+                .as_ref()
+                .map_or(0, |l| l.estimated_candidates(self.statistics())),
+        );
+
+        // The strategy actually taken can differ from the planner's, which
+        // only has estimates for the focus size
+        let lookup = lookup.filter(|_| strategy == RefinementStrategy::ReverseLookup);
+        if let Some(ref profiler) = self.profiler {
+            let taken = match lookup {
+                Some(_) => RefinementStrategy::ReverseLookup,
+                None => RefinementStrategy::ForwardScan,
+            };
+            profiler.set_operation(format!("Refined ({})", taken));
+        }
+
+        match lookup {
+            Some(lookup) => {
+                let candidates = lookup.candidates(self.store, traverser.guard())?;
+                let (candidates, focus_count) = match focus_result {
+                    Some((focus_concepts, focus_count)) => (
+                        candidates
+                            .into_iter()
+                            .filter(|id| focus_concepts.contains(id))
+                            .collect(),
+                        focus_count,
+                    ),
+                    None => (
+                        candidates
+                            .into_iter()
+                            .filter(|&id| self.store.has_concept(id))
+                            .collect(),
+                        0,
+                    ),
+                };
+                let (result, refinement_count) =
+                    self.filter_refined(&candidates, &compiled, traverser)?;
+                Ok((result, focus_count + lookup.lookups() + refinement_count))
+            }
+            None => {
+                let (focus_concepts, focus_count) = match focus_result {
+                    Some(focus_result) => focus_result,
+                    None => self.execute_with_intermediate_cache(focus, traverser)?,
+                };
+                let (result, refinement_count) =
+                    self.filter_refined(&focus_concepts, &compiled, traverser)?;
+                Ok((result, focus_count + refinement_count))
+            }
+        }
+    }
+
+    /// Keeps the focus concepts that satisfy a refinement.
+    ///
+    /// Returns the matching concepts and the number of relationships inspected.
+    /// Concepts are checked in parallel when the traverser is parallel.
+    fn filter_refined(
+        &self,
+        focus_concepts: &HashSet<SctId>,
+        refinement: &CompiledRefinement<'_>,
+        traverser: &HierarchyTraverser<'_>,
+    ) -> EclResult<(HashSet<SctId>, usize)> {
+        #[cfg(feature = "parallel")]
+        if traverser.is_parallel() {
+            use rayon::prelude::*;
+
+            return focus_concepts
+                .par_iter()
+                .try_fold(
+                    || (HashSet::new(), 0),
+                    |(mut result, count), &concept_id| {
+                        let (matches, inspected) =
+                            refinement.matches(concept_id, self.store, traverser)?;
+                        if matches {
+                            result.insert(concept_id);
+                        }
+                        Ok((result, count + inspected))
+                    },
+                )
+                .try_reduce(
+                    || (HashSet::new(), 0),
+                    |(mut left, left_count), (right, right_count)| {
+                        left.extend(right);
+                        Ok((left, left_count + right_count))
+                    },
+                );
+        }
+
+        let mut result = HashSet::new();
+        let mut total_count = 0;
+        for &concept_id in focus_concepts {
+            let (matches, inspected) = refinement.matches(concept_id, self.store, traverser)?;
+            if matches {
+                result.insert(concept_id);
+            }
+            total_count += inspected;
+        }
+        Ok((result, total_count))
+    }
+
+    /// Keeps the concepts that satisfy a per-concept filter predicate.
+    ///
+    /// The guard's deadline is checked once per candidate concept. With the
+    /// `parallel` feature and `ExecutorConfig::parallel` enabled the predicate
+    /// is evaluated on the rayon pool.
+    fn filter_concepts<F>(
+        &self,
+        concepts: &HashSet<SctId>,
+        guard: &ExecutionGuard,
+        predicate: F,
+    ) -> EclResult<HashSet<SctId>>
+    where
+        F: Fn(SctId) -> bool + Sync + Send,
+    {
+        self.try_filter_concepts(concepts, guard, |concept_id| Ok(predicate(concept_id)))
+    }
+
+    /// Like [`filter_concepts`](Self::filter_concepts), for a fallible predicate.
+    fn try_filter_concepts<F>(
+        &self,
+        concepts: &HashSet<SctId>,
+        guard: &ExecutionGuard,
+        predicate: F,
+    ) -> EclResult<HashSet<SctId>>
+    where
+        F: Fn(SctId) -> EclResult<bool> + Sync + Send,
+    {
+        #[cfg(feature = "parallel")]
+        if self.config.parallel {
+            use rayon::prelude::*;
+
+            return concepts
+                .par_iter()
+                .try_fold(HashSet::new, |mut result, &concept_id| {
+                    guard.check()?;
+                    if predicate(concept_id)? {
+                        result.insert(concept_id);
+                    }
+                    Ok(result)
+                })
+                .try_reduce(HashSet::new, |mut left, right| {
+                    left.extend(right);
+                    Ok(left)
+                });
+        }
+
+        let mut result = HashSet::new();
+        for &concept_id in concepts {
+            guard.check()?;
+            if predicate(concept_id)? {
+                result.insert(concept_id);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Expands the operand of a hierarchy operator to its focus concept set.
+    ///
+    /// For `< concept`, `<< concept`, etc. this is a single concept, which must
+    /// exist in the store. Any other set-valued operand (`< (A OR B)`, `<< ^ X`,
+    /// `>> (< X . Y)`, ...) is evaluated first and the hierarchy operator is then
+    /// applied to every concept in the resulting set.
+    fn get_focus_concepts(
+        &self,
+        expr: &EclExpression,
+        traverser: &HierarchyTraverser<'_>,
+    ) -> EclResult<(HashSet<SctId>, usize)> {
+        match expr.unwrap_nested() {
+            EclExpression::ConceptReference { .. } => self.execute_expression(expr, traverser),
+            _ => self.execute_with_intermediate_cache(expr, traverser),
         }
     }
 
@@ -1202,17 +1437,72 @@ impl<'a> EclExecutor<'a> {
     /// println!("Estimated result: {} concepts", plan.estimated_total);
     /// println!("Estimated cost: {:.2}ms", plan.total_cost());
     ///
-    /// for hint in &plan.optimization_hints {
-    ///     println!("Optimization: {}", hint);
+    /// for hint in &plan.optimization_hints {
+    ///     println!("Optimization: {}", hint);
+    /// }
+    /// ```
+    pub fn explain(&self, ecl: &str) -> EclResult<QueryPlan> {
+        // Parse the ECL expression
+        let expr = snomed_ecl::parse(ecl)?;
+
+        // Use the query planner to generate the plan
+        Ok(self.planner.plan(ecl, &expr))
+    }
+
+    /// Executes an ECL expression string and profiles every evaluated node.
+    ///
+    /// Like `EXPLAIN ANALYZE`, the returned profile pairs the planner's
+    /// estimates with the actual row count, elapsed time, number of store
+    /// calls and cache outcome of each node. Profiled queries always run
+    /// sequentially so that time and store calls are attributed to a single
+    /// node; the result is the same as that of [`execute`](Self::execute)
+    /// and the cache is used and populated in the same way.
+    ///
+    /// # Arguments
+    ///
+    /// * `ecl` - ECL expression string
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(QueryProfile)` - The query result and its profile tree
+    /// * `Err(EclExecutorError)` - If parsing or execution fails
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let profile = executor.execute_profiled("<< 404684003 AND ^ 700043003")?;
+    /// println!("{}", profile);
+    ///
+    /// // Find where the time goes
+    /// for node in profile.root.nodes() {
+    ///     if node.self_elapsed() > Duration::from_millis(10) {
+    ///         println!("{}: {:?}", node.expression, node.self_elapsed());
+    ///     }
     /// }
     /// ```
-    pub fn explain(&self, ecl: &str) -> EclResult<QueryPlan> {
-        // Parse the ECL expression
-        let expr = snomed_ecl::parse(ecl)?;
+    pub fn execute_profiled(&self, ecl: &str) -> EclResult<QueryProfile> {
+        let store = CountingStore::new(self.store);
+        let mut config = self.config.clone();
+        config.parallel = false;
 
-        // Use the query planner to generate the plan
-        let planner = QueryPlanner::new();
-        Ok(planner.plan(ecl, &expr))
+        let executor = EclExecutor {
+            store: &store,
+            config,
+            cache: self.cache.clone(),
+            planner: self.planner.clone(),
+            profiler: Some(Profiler::new(&store)),
+        };
+        let result = executor.execute_guarded(ecl, ExecutionGuard::from_config(&self.config))?;
+
+        let root = executor
+            .profiler
+            .and_then(Profiler::into_root)
+            .expect("a successful query records a root node");
+        Ok(QueryProfile {
+            ecl: ecl.to_string(),
+            result,
+            root,
+        })
     }
 
     /// Checks if a concept matches an ECL constraint.
@@ -1455,10 +1745,53 @@ impl<'a> EclExecutor<'a> {
     }
 }
 
+/// Returns the refset expression of the member-of that a filtered
+/// expression's member filters apply to, looking through nesting and
+/// earlier filter blocks.
+fn member_of_refset(expression: &EclExpression) -> Option<&EclExpression> {
+    match expression.unwrap_nested() {
+        EclExpression::MemberOf { refset, .. } => Some(refset),
+        EclExpression::Filtered { expression, .. } => member_of_refset(expression),
+        _ => None,
+    }
+}
+
+/// Splits the history supplements off the outermost filter block of an
+/// expression, returning the expression without them and the supplements.
+fn split_history_supplements(expression: &EclExpression) -> Option<(EclExpression, Vec<&EclFilter>)> {
+    let EclExpression::Filtered {
+        expression,
+        filters,
+    } = expression.unwrap_nested()
+    else {
+        return None;
+    };
+
+    let (supplements, rest): (Vec<&EclFilter>, Vec<&EclFilter>) = filters
+        .iter()
+        .partition(|filter| matches!(filter, EclFilter::History { .. }));
+    if supplements.is_empty() {
+        return None;
+    }
+
+    let base = if rest.is_empty() {
+        expression.as_ref().clone()
+    } else {
+        EclExpression::Filtered {
+            expression: expression.clone(),
+            filters: rest.into_iter().cloned().collect(),
+        }
+    };
+    Some((base, supplements))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::CacheConfig;
+    use crate::profile::CacheStatus;
     use std::collections::HashMap;
+    use std::time::Duration;
 
     /// Mock store for testing.
     struct MockStore {
@@ -1487,7 +1820,6 @@ mod tests {
             self.parents.entry(child).or_default().push(parent);
         }
 
-        #[allow(dead_code)]
         fn add_refset_member(&mut self, refset_id: SctId, member: SctId) {
             self.refsets.entry(refset_id).or_default().push(member);
         }
@@ -1853,6 +2185,132 @@ mod tests {
         assert!(result.contains(600));
     }
 
+    #[test]
+    fn test_compound_with_membership_checks() {
+        let store = create_test_store();
+        let executor = EclExecutor::new(&store);
+
+        // Single concepts drive; the hierarchy operands are tested per concept
+        let result = executor.execute("400 AND << 200 AND < 100").unwrap();
+        assert_eq!(result.count(), 1);
+        assert!(result.contains(400));
+
+        let result = executor.execute("600 AND << 200").unwrap();
+        assert!(result.is_empty());
+
+        let result = executor.execute("(400 OR 600) MINUS << 200").unwrap();
+        assert_eq!(result.count(), 1);
+        assert!(result.contains(600));
+    }
+
+    #[test]
+    fn test_compound_short_circuits_on_empty_operand() {
+        let store = create_test_store();
+        let executor = EclExecutor::new(&store);
+
+        // The empty operand drives, so the missing concept is never looked up
+        let result = executor.execute("<< 999 AND (400 MINUS 400)").unwrap();
+        assert!(result.is_empty());
+
+        let result = executor.execute("(400 MINUS 400) MINUS << 999").unwrap();
+        assert!(result.is_empty());
+    }
+
+    // Set-valued hierarchy operand tests
+
+    #[test]
+    fn test_descendant_of_compound_operand() {
+        let store = create_test_store();
+        let executor = EclExecutor::new(&store);
+
+        // Descendants of 200 (400, 500) plus descendants of 300 (600)
+        let result = executor.execute("< (200 OR 300)").unwrap();
+
+        assert_eq!(result.count(), 3);
+        assert!(result.contains(400));
+        assert!(result.contains(500));
+        assert!(result.contains(600));
+    }
+
+    #[test]
+    fn test_descendant_or_self_of_member_of() {
+        let mut store = create_test_store();
+        store.add_concept(900);
+        store.add_refset_member(900, 200);
+        store.add_refset_member(900, 600);
+        let executor = EclExecutor::new(&store);
+
+        let result = executor.execute("<< ^ 900").unwrap();
+
+        assert_eq!(result.count(), 4);
+        assert!(result.contains(200));
+        assert!(result.contains(400));
+        assert!(result.contains(500));
+        assert!(result.contains(600));
+    }
+
+    #[test]
+    fn test_child_of_minus_operand() {
+        let store = create_test_store();
+        let executor = EclExecutor::new(&store);
+
+        let result = executor.execute("<! (< 100 MINUS << 400)").unwrap();
+
+        // Focus set: 200, 300, 500, 600. 400 is excluded from the focus but
+        // is still a child of 200; 500 and 600 have no children.
+        assert_eq!(result.count(), 3);
+        assert!(result.contains(400));
+        assert!(result.contains(500));
+        assert!(result.contains(600));
+    }
+
+    #[test]
+    fn test_ancestor_or_self_of_overlapping_operand() {
+        let store = create_diamond_store();
+        let executor = EclExecutor::new(&store);
+
+        // 400's ancestors overlap with 200's, so 100 is visited once
+        let result = executor.execute(">> (400 OR 200)").unwrap();
+
+        assert_eq!(result.count(), 4);
+        assert!(result.contains(100));
+        assert!(result.contains(200));
+        assert!(result.contains(300));
+        assert!(result.contains(400));
+    }
+
+    #[test]
+    fn test_parent_or_self_of_compound_operand() {
+        let store = create_test_store();
+        let executor = EclExecutor::new(&store);
+
+        let result = executor.execute(">>! (400 OR 600)").unwrap();
+
+        assert_eq!(result.count(), 4);
+        assert!(result.contains(200));
+        assert!(result.contains(300));
+        assert!(result.contains(400));
+        assert!(result.contains(600));
+    }
+
+    #[test]
+    fn test_hierarchy_operator_empty_operand() {
+        let store = create_test_store();
+        let executor = EclExecutor::new(&store);
+
+        let result = executor.execute("< (200 AND 300)").unwrap();
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_hierarchy_operator_missing_concept_in_operand() {
+        let store = create_test_store();
+        let executor = EclExecutor::new(&store);
+
+        let result = executor.execute("< (200 OR 999)");
+        assert!(matches!(result, Err(EclExecutorError::ConceptNotFound(999))));
+    }
+
     // Subsumption tests
 
     #[test]
@@ -1928,6 +2386,18 @@ mod tests {
         assert_eq!(plan.steps[2].operation, "Intersect");
     }
 
+    #[test]
+    fn test_explain_includes_physical_plan() {
+        let store = create_test_store();
+        let executor = EclExecutor::new(&store);
+
+        let plan = executor.explain("<< 100 AND 400").unwrap();
+        let physical = plan.physical_plan.unwrap();
+        assert_eq!(physical.operator, PlanOperator::Intersect);
+        assert_eq!(physical.children[0].expression.to_string(), "400");
+        assert_eq!(physical.children[1].operator, PlanOperator::MembershipCheck);
+    }
+
     #[test]
     fn test_explain_has_estimates() {
         let store = create_test_store();
@@ -1942,6 +2412,97 @@ mod tests {
         assert!(plan.steps[0].cost_estimate >= 0.0);
     }
 
+    // Profiling tests
+
+    #[test]
+    fn test_execute_profiled_matches_execute() {
+        let store = create_test_store();
+        let executor = EclExecutor::new(&store);
+
+        let profile = executor.execute_profiled("< 100 MINUS < 200").unwrap();
+        let result = executor.execute("< 100 MINUS < 200").unwrap();
+        assert_eq!(profile.result.to_vec(), result.to_vec());
+
+        let root = &profile.root;
+        assert_eq!(root.operation, "Difference");
+        assert_eq!(root.actual_rows, 3);
+        assert!(root.store_calls > 0);
+        assert_eq!(profile.store_calls(), root.store_calls);
+        assert_eq!(root.children.len(), 2);
+        assert_eq!(root.children[0].operation, "Descendants");
+        assert_eq!(root.children[0].actual_rows, 5);
+        assert!(root.children[0].estimated_cardinality > 0);
+    }
+
+    #[test]
+    fn test_execute_profiled_membership_check() {
+        let store = create_test_store();
+        let executor = EclExecutor::new(&store);
+
+        let profile = executor.execute_profiled("<< 100 AND 400").unwrap();
+        let check = profile
+            .root
+            .nodes()
+            .into_iter()
+            .find(|n| n.operation == "MembershipCheck")
+            .unwrap();
+        assert_eq!(check.expression, "<< 100");
+        assert_eq!(check.actual_rows, 1);
+
+        // The focus of the check is resolved as a child node
+        assert_eq!(check.children.len(), 1);
+        assert_eq!(check.children[0].expression, "100");
+    }
+
+    #[test]
+    fn test_execute_profiled_cache_status() {
+        let store = create_test_store();
+        let config = ExecutorConfig::builder()
+            .with_cache(CacheConfig {
+                cache_intermediates: true,
+                ..CacheConfig::default()
+            })
+            .build();
+        let executor = EclExecutor::with_config(&store, config);
+
+        executor.execute("< 200").unwrap();
+
+        let profile = executor.execute_profiled("< 200 OR < 300").unwrap();
+        assert_eq!(profile.root.cache, CacheStatus::Miss);
+        assert_eq!(profile.root.children[0].cache, CacheStatus::Hit);
+        assert_eq!(profile.root.children[0].store_calls, 0);
+        assert_eq!(profile.root.children[1].cache, CacheStatus::Miss);
+
+        // The whole query is now cached
+        let profile = executor.execute_profiled("< 200 OR < 300").unwrap();
+        assert_eq!(profile.root.cache, CacheStatus::Hit);
+        assert!(profile.root.children.is_empty());
+        assert_eq!(profile.result.count(), 3);
+    }
+
+    #[test]
+    fn test_execute_profiled_errors() {
+        let store = create_test_store();
+        let executor = EclExecutor::new(&store);
+
+        assert!(matches!(
+            executor.execute_profiled("<< 999"),
+            Err(EclExecutorError::ConceptNotFound(999))
+        ));
+        assert!(executor.execute_profiled("invalid ecl !!!").is_err());
+    }
+
+    #[test]
+    fn test_query_profile_display() {
+        let store = create_test_store();
+        let executor = EclExecutor::new(&store);
+
+        let output = executor.execute_profiled("< 200").unwrap().to_string();
+        assert!(output.starts_with("Profile for: < 200\nTotal: 2 concepts in "));
+        assert!(output.contains("Descendants: < 200 (est. "));
+        assert!(output.contains("(actual 2 concepts, "));
+    }
+
     // Error handling tests
 
     #[test]
@@ -1970,6 +2531,246 @@ mod tests {
         ));
     }
 
+    // Execution limit tests
+
+    #[test]
+    fn test_max_results_exceeded() {
+        let store = create_test_store();
+        let config = ExecutorConfig::builder().with_max_results(3).build();
+        let executor = EclExecutor::with_config(&store, config);
+
+        // << 100 has 6 concepts
+        let result = executor.execute("<< 100");
+        match result {
+            Err(EclExecutorError::ResultTooLarge { count, limit }) => {
+                assert!(count > 3);
+                assert_eq!(limit, 3);
+            }
+            other => panic!("Expected ResultTooLarge, got {:?}", other),
+        }
+
+        // Queries within the budget still succeed
+        let result = executor.execute("<< 200").unwrap();
+        assert_eq!(result.count(), 3);
+    }
+
+    #[test]
+    fn test_max_results_is_a_running_budget() {
+        let store = create_test_store();
+        let config = ExecutorConfig::builder().with_max_results(2).build();
+        let executor = EclExecutor::with_config(&store, config);
+
+        // `*` is not built up, so it may exceed the limit as an operand
+        let result = executor.execute("* AND < 200").unwrap();
+        assert_eq!(result.count(), 2);
+        assert!(matches!(
+            executor.execute("* MINUS << 200"),
+            Err(EclExecutorError::ResultTooLarge { count: 3, limit: 2 })
+        ));
+
+        // Hierarchy operators stop as soon as they exceed the limit, even
+        // when a later operation would shrink the result
+        assert!(matches!(
+            executor.execute("<< 100 MINUS << 200"),
+            Err(EclExecutorError::ResultTooLarge { limit: 2, .. })
+        ));
+
+        // So do unions
+        assert!(matches!(
+            executor.execute("(400 OR 500 OR 600) MINUS 400"),
+            Err(EclExecutorError::ResultTooLarge { count: 3, limit: 2 })
+        ));
+        let result = executor.execute("400 OR 500").unwrap();
+        assert_eq!(result.count(), 2);
+    }
+
+    #[test]
+    fn test_timeout_exceeded() {
+        let store = create_test_store();
+        let config = ExecutorConfig::builder()
+            .with_timeout(Duration::ZERO)
+            .build();
+        let executor = EclExecutor::with_config(&store, config);
+
+        let result = executor.execute("> *");
+        assert!(matches!(result, Err(EclExecutorError::Timeout(_))));
+    }
+
+    #[test]
+    fn test_timeout_not_exceeded() {
+        let store = create_test_store();
+        let config = ExecutorConfig::builder()
+            .with_timeout(Duration::from_secs(60))
+            .build();
+        let executor = EclExecutor::with_config(&store, config);
+
+        let result = executor.execute("<< 100 {{ C active = true }}").unwrap();
+        assert_eq!(result.count(), 6);
+    }
+
+    #[test]
+    fn test_limit_errors_are_not_cached() {
+        let store = create_test_store();
+        let config = ExecutorConfig::builder()
+            .with_cache(CacheConfig::default())
+            .with_max_results(3)
+            .build();
+        let executor = EclExecutor::with_config(&store, config);
+
+        assert!(executor.execute("<< 100").is_err());
+        assert!(!executor.cache().unwrap().contains("<< 100"));
+    }
+
+    // Cancellation tests
+
+    #[test]
+    fn test_execute_with_cancel_not_cancelled() {
+        let store = create_test_store();
+        let executor = EclExecutor::new(&store);
+        let token = CancelToken::new();
+
+        let result = executor.execute_with_cancel("<< 200", &token).unwrap();
+        assert_eq!(result.count(), 3);
+    }
+
+    #[test]
+    fn test_execute_with_cancel_cancelled() {
+        let store = create_test_store();
+        let executor = EclExecutor::new(&store);
+        let token = CancelToken::new();
+        token.cancel();
+
+        let result = executor.execute_with_cancel("<< 100 {{ C active = true }}", &token);
+        assert!(matches!(result, Err(EclExecutorError::Cancelled)));
+    }
+
+    #[test]
+    fn test_cancelled_query_does_not_poison_cache() {
+        let store = create_test_store();
+        let config = ExecutorConfig::builder()
+            .with_cache(CacheConfig::default())
+            .build();
+        let executor = EclExecutor::with_config(&store, config);
+
+        let token = CancelToken::new();
+        token.cancel();
+        assert!(executor.execute_with_cancel("<< 200 OR << 300", &token).is_err());
+        assert!(executor.cache().unwrap().is_empty());
+
+        // A fresh run computes and caches the full result
+        let result = executor.execute("<< 200 OR << 300").unwrap();
+        assert_eq!(result.count(), 5);
+        assert!(!result.stats.cache_hit);
+    }
+
+    #[test]
+    fn test_cancel_from_another_thread() {
+        // Wide hierarchy large enough that traversal takes a while
+        let mut store = MockStore::new();
+        store.add_concept(1);
+        for i in 2..50_000 {
+            store.add_concept(i);
+            store.add_is_a(i, i / 2);
+        }
+        let executor = EclExecutor::new(&store);
+        let token = CancelToken::new();
+
+        let result = std::thread::scope(|scope| {
+            let handle = token.clone();
+            scope.spawn(move || handle.cancel());
+            loop {
+                match executor.execute_with_cancel("<< 1", &token) {
+                    Ok(_) => continue,
+                    other => break other,
+                }
+            }
+        });
+        assert!(matches!(result, Err(EclExecutorError::Cancelled)));
+    }
+
+    // Parallel execution tests
+
+    #[cfg(feature = "parallel")]
+    fn create_wide_store() -> MockStore {
+        // 1 -> 2..=9, each of which has 8 children, and so on
+        let mut store = MockStore::new();
+        store.add_concept(1);
+        for id in 2..=5_000 {
+            store.add_concept(id);
+            store.add_is_a(id, (id - 2) / 8 + 1);
+        }
+        store.add_concept(900);
+        for id in (100..2_000).step_by(7) {
+            store.add_refset_member(900, id);
+        }
+        store
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let store = create_wide_store();
+        let sequential = EclExecutor::new(&store);
+        let parallel =
+            EclExecutor::with_config(&store, ExecutorConfig::builder().with_parallel(true).build());
+
+        for ecl in [
+            "<< 1",
+            "> (<< 300)",
+            "<! (<< 2 MINUS << 10)",
+            "(<< 2 OR << 3) AND ^ 900",
+            "<< 1 MINUS ^ 900",
+            "<< ^ 900",
+            "<< 2 {{ C active = true }}",
+        ] {
+            let expected = sequential.execute(ecl).unwrap();
+            let actual = parallel.execute(ecl).unwrap();
+            assert_eq!(expected.to_vec(), actual.to_vec(), "results differ for {}", ecl);
+            assert_eq!(
+                expected.stats.concepts_traversed, actual.stats.concepts_traversed,
+                "traversal counts differ for {}",
+                ecl
+            );
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_intersection_short_circuits() {
+        let store = create_test_store();
+        let executor =
+            EclExecutor::with_config(&store, ExecutorConfig::builder().with_parallel(true).build());
+
+        // 400 AND 600 is empty, so the missing reference set is never read
+        let result = executor.execute("400 AND 600 AND ^ 999").unwrap();
+        assert!(result.is_empty());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_respects_limits() {
+        let store = create_wide_store();
+        let config = ExecutorConfig::builder()
+            .with_parallel(true)
+            .with_max_results(100)
+            .build();
+        let executor = EclExecutor::with_config(&store, config);
+
+        assert!(matches!(
+            executor.execute("<< 1 AND << 2"),
+            Err(EclExecutorError::ResultTooLarge { limit: 100, .. })
+        ));
+
+        let token = CancelToken::new();
+        token.cancel();
+        let executor =
+            EclExecutor::with_config(&store, ExecutorConfig::builder().with_parallel(true).build());
+        assert!(matches!(
+            executor.execute_with_cancel("<< 1", &token),
+            Err(EclExecutorError::Cancelled)
+        ));
+    }
+
     // Matches method tests
 
     #[test]
@@ -0,0 +1,223 @@
+//! Execution limits enforced while a query runs.
+//!
+//! An [`ExecutionGuard`] is created for every query from the executor's
+//! [`ExecutorConfig`] and is consulted inside the expensive loops (hierarchy
+//! traversal, refinement evaluation and filtering) so that a runaway query
+//! such as `> *` or `<< 138875005 : * = *` aborts promptly instead of running
+//! to completion. A guard can also carry a [`CancelToken`] so that another
+//! thread can stop a query that is no longer needed.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::config::ExecutorConfig;
+use crate::error::{EclExecutorError, EclResult};
+
+/// Number of concepts a per-concept loop visits between guard checks.
+///
+/// Loops that do little work per concept check the guard every this many
+/// concepts instead of on every one.
+pub(crate) const GUARD_CHECK_INTERVAL: usize = 1024;
+
+/// Cooperative cancellation handle for a running query.
+///
+/// Clones share the same flag, so a token can be handed to
+/// [`EclExecutor::execute_with_cancel`](crate::EclExecutor::execute_with_cancel)
+/// while another clone is kept to cancel it later.
+///
+/// # Example
+///
+/// ```rust
+/// use snomed_ecl_executor::CancelToken;
+///
+/// let token = CancelToken::new();
+/// let handle = token.clone();
+///
+/// assert!(!token.is_cancelled());
+/// handle.cancel();
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    /// Creates a new, uncancelled token.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests cancellation of every query using this token.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if cancellation has been requested.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Deadline, result-size limit and cancellation state for a single query execution.
+///
+/// # Example
+///
+/// ```rust
+/// use snomed_ecl_executor::ExecutionGuard;
+/// use std::time::Duration;
+///
+/// let guard = ExecutionGuard::new(Some(Duration::from_secs(5)), Some(1_000));
+/// assert!(guard.check().is_ok());
+/// assert!(guard.check_size(500).is_ok());
+/// assert!(guard.check_size(5_000).is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExecutionGuard {
+    /// Configured timeout, reported in [`EclExecutorError::Timeout`].
+    timeout: Option<Duration>,
+    /// Point in time after which execution must stop.
+    deadline: Option<Instant>,
+    /// Maximum size of any set built during execution and of the result.
+    max_results: Option<usize>,
+    /// Optional cancellation token.
+    cancel: Option<CancelToken>,
+}
+
+impl Default for ExecutionGuard {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
+
+impl ExecutionGuard {
+    /// Creates a guard whose deadline starts counting now.
+    pub fn new(timeout: Option<Duration>, max_results: Option<usize>) -> Self {
+        Self {
+            timeout,
+            deadline: timeout.map(|t| Instant::now() + t),
+            max_results,
+            cancel: None,
+        }
+    }
+
+    /// Attaches a cancellation token to this guard.
+    pub fn with_cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Creates a guard from the executor configuration.
+    pub fn from_config(config: &ExecutorConfig) -> Self {
+        Self::new(config.timeout, config.max_results)
+    }
+
+    /// Creates a guard that never aborts execution.
+    pub fn unlimited() -> Self {
+        Self {
+            timeout: None,
+            deadline: None,
+            max_results: None,
+            cancel: None,
+        }
+    }
+
+    /// Returns an error if the query was cancelled or the deadline has passed.
+    #[inline]
+    pub fn check(&self) -> EclResult<()> {
+        if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            return Err(EclExecutorError::Cancelled);
+        }
+        match (self.deadline, self.timeout) {
+            (Some(deadline), Some(timeout)) if Instant::now() >= deadline => {
+                Err(EclExecutorError::Timeout(timeout))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns an error if a result of `count` concepts exceeds the limit.
+    #[inline]
+    pub fn check_size(&self, count: usize) -> EclResult<()> {
+        match self.max_results {
+            Some(limit) if count > limit => Err(EclExecutorError::ResultTooLarge { count, limit }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the configured result-size limit.
+    pub fn max_results(&self) -> Option<usize> {
+        self.max_results
+    }
+
+    /// Returns the configured timeout.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlimited_guard() {
+        let guard = ExecutionGuard::unlimited();
+        assert!(guard.check().is_ok());
+        assert!(guard.check_size(usize::MAX).is_ok());
+    }
+
+    #[test]
+    fn test_expired_deadline() {
+        let guard = ExecutionGuard::new(Some(Duration::ZERO), None);
+        match guard.check() {
+            Err(EclExecutorError::Timeout(timeout)) => assert_eq!(timeout, Duration::ZERO),
+            other => panic!("Expected Timeout, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_size_budget() {
+        let guard = ExecutionGuard::new(None, Some(10));
+        assert!(guard.check_size(10).is_ok());
+        match guard.check_size(11) {
+            Err(EclExecutorError::ResultTooLarge { count, limit }) => {
+                assert_eq!(count, 11);
+                assert_eq!(limit, 10);
+            }
+            other => panic!("Expected ResultTooLarge, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cancelled_guard() {
+        let token = CancelToken::new();
+        let guard = ExecutionGuard::unlimited().with_cancel_token(token.clone());
+        assert!(guard.check().is_ok());
+
+        token.cancel();
+        assert!(matches!(guard.check(), Err(EclExecutorError::Cancelled)));
+    }
+
+    #[test]
+    fn test_cancellation_takes_precedence_over_timeout() {
+        let token = CancelToken::new();
+        token.cancel();
+        let guard = ExecutionGuard::new(Some(Duration::ZERO), None).with_cancel_token(token);
+        assert!(matches!(guard.check(), Err(EclExecutorError::Cancelled)));
+    }
+
+    #[test]
+    fn test_from_config() {
+        let config = ExecutorConfig::builder()
+            .with_max_results(5)
+            .with_timeout(Duration::from_secs(30))
+            .build();
+        let guard = ExecutionGuard::from_config(&config);
+        assert_eq!(guard.max_results(), Some(5));
+        assert_eq!(guard.timeout(), Some(Duration::from_secs(30)));
+        assert!(guard.check().is_ok());
+    }
+}
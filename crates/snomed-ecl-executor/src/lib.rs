@@ -72,7 +72,12 @@
 //!
 //! ## Feature Flags
 //!
-//! - `parallel` - Enables parallel query execution using rayon
+//! - `parallel` - Enables parallel query execution using rayon. When
+//!   `ExecutorConfig::parallel` is also set, AND/OR/MINUS operands,
+//!   per-concept refinement and filter checks, and BFS frontier expansion run
+//!   on the rayon pool. Results are identical to sequential execution.
+//! - `regex` - Enables `term regex` filters. Without it such filters fail
+//!   with `EclExecutorError::UnsupportedFeature`.
 //!
 //! ## Architecture
 //!
@@ -96,11 +101,20 @@
 
 mod cache;
 mod config;
+mod description;
 mod error;
 mod executor;
+mod guard;
+mod member;
+mod membership;
+mod physical;
 mod planner;
+mod profile;
+mod render;
+mod refinement;
 mod result;
 mod statistics;
+mod term;
 mod traits;
 mod traverser;
 
@@ -109,11 +123,16 @@ pub use cache::{normalize_cache_key, CacheStats, QueryCache};
 pub use config::{CacheConfig, ExecutorConfig, ExecutorConfigBuilder};
 pub use error::{EclExecutorError, EclResult};
 pub use executor::EclExecutor;
-pub use planner::{QueryPlan, QueryPlanner, QueryStep};
-pub use result::{ExecutionStats, QueryResult};
-pub use statistics::{cost, heuristics, well_known, StatisticsService};
+pub use guard::{CancelToken, ExecutionGuard};
+pub use physical::{PlanNode, PlanOperator};
+pub use planner::{QueryPlan, QueryPlanner, QueryStep, RefinementStrategy};
+pub use profile::{CacheStatus, ProfileNode, QueryProfile};
+pub use result::{ExecutionStats, HistoryResult, MemberRowsResult, QueryResult};
+pub use statistics::{cost, heuristics, well_known, StatisticsBuilder, StatisticsService};
 pub use traits::{
-    ConcreteRelationshipInfo, ConcreteValueRef, DescriptionInfo, EclQueryable, RelationshipInfo,
+    Acceptability, ConceptInfo, ConcreteRelationshipInfo, ConcreteValueRef, DescriptionInfo,
+    EclQueryable, HistoryAssociationType, LanguageRefsetMember, RefsetFieldValue, RefsetMember,
+    RelationshipInfo,
 };
 pub use traverser::HierarchyTraverser;
 
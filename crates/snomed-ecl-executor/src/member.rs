@@ -0,0 +1,463 @@
+//! Member filters evaluated against reference set member rows.
+//!
+//! `^ 447562003 {{ M mapTarget = "J45.9" }}` selects the referenced
+//! components of members of `447562003` whose `mapTarget` field is `J45.9`.
+//! [`MemberFilter`] holds the member conditions of a `{{ }}` block; like
+//! description filters, they must all be met by the same member row.
+//!
+//! Only active member rows are considered, unless the block constrains the
+//! `active` field itself, as in `{{ M active = false }}`.
+//!
+//! A member-of with a field selection, `^ [referencedComponentId, mapTarget]
+//! 447562003`, returns the selected fields of the matching rows instead of
+//! concepts; see [`project_rows`].
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use snomed_ecl::{
+    ComparisonOperator, EclExpression, EclFilter, FilterDomain, MemberFieldSelection,
+    MemberFieldValue, SctId,
+};
+
+use crate::error::EclResult;
+use crate::guard::ExecutionGuard;
+use crate::traits::{EclQueryable, RefsetFieldValue, RefsetMember};
+
+/// A single member condition.
+#[derive(Debug)]
+enum MemberCondition {
+    /// `field operator value`, e.g. `mapGroup >= 2`.
+    Compare {
+        field: String,
+        operator: ComparisonOperator,
+        value: MemberFieldValue,
+    },
+    /// The field is one of the components, e.g. `moduleId = << X`.
+    AnyOf { field: String, ids: HashSet<SctId> },
+}
+
+impl MemberCondition {
+    /// Returns true if `filter` is a member filter.
+    fn is_member_filter(filter: &EclFilter) -> bool {
+        match filter {
+            EclFilter::Member { .. } => true,
+            EclFilter::DomainQualified {
+                domain: FilterDomain::Member,
+                filter,
+            } => filter.applies_to(FilterDomain::Member),
+            _ => false,
+        }
+    }
+
+    /// Compiles a filter, or returns `None` if it is not a member filter.
+    fn compile<F>(filter: &EclFilter, resolve: &mut F) -> EclResult<Option<Self>>
+    where
+        F: FnMut(&EclExpression) -> EclResult<HashSet<SctId>>,
+    {
+        Ok(match filter {
+            EclFilter::Member {
+                field,
+                operator,
+                value,
+            } => Some(Self::Compare {
+                field: field.clone(),
+                operator: *operator,
+                value: value.clone(),
+            }),
+            EclFilter::DomainQualified {
+                domain: FilterDomain::Member,
+                filter,
+            } => match filter.as_ref() {
+                EclFilter::Active(active) => Some(Self::Compare {
+                    field: "active".to_string(),
+                    operator: ComparisonOperator::Equal,
+                    value: MemberFieldValue::Boolean(*active),
+                }),
+                EclFilter::Module { modules } => Some(Self::AnyOf {
+                    field: "moduleId".to_string(),
+                    ids: resolve(modules)?,
+                }),
+                EclFilter::EffectiveTime { operator, date } => Some(Self::Compare {
+                    field: "effectiveTime".to_string(),
+                    operator: *operator,
+                    value: MemberFieldValue::Integer((*date).into()),
+                }),
+                other => return Self::compile(other, resolve),
+            },
+            _ => None,
+        })
+    }
+
+    fn field(&self) -> &str {
+        match self {
+            Self::Compare { field, .. } | Self::AnyOf { field, .. } => field,
+        }
+    }
+
+    fn matches(&self, member: &RefsetMember) -> bool {
+        let Some(actual) = member.field(self.field()) else {
+            return false;
+        };
+
+        match self {
+            Self::Compare {
+                operator, value, ..
+            } => compare_field(&actual, *operator, value),
+            Self::AnyOf { ids, .. } => {
+                matches!(actual, RefsetFieldValue::Component(id) if ids.contains(&id))
+            }
+        }
+    }
+}
+
+/// The member filters of one filter block, all of which must be met by a
+/// single member row.
+#[derive(Debug)]
+pub(crate) struct MemberFilter {
+    conditions: Vec<MemberCondition>,
+    /// True if a condition tests the `active` field, so that inactive rows
+    /// are not skipped.
+    constrains_active: bool,
+}
+
+impl MemberFilter {
+    /// Returns true if `filter` is a member filter.
+    pub(crate) fn is_member_filter(filter: &EclFilter) -> bool {
+        MemberCondition::is_member_filter(filter)
+    }
+
+    /// Compiles the member filters among `filters`, ignoring the others.
+    ///
+    /// # Arguments
+    ///
+    /// * `filters` - The filters of one filter block
+    /// * `resolve` - Resolves a module constraint to its id set
+    pub(crate) fn compile<'f, F>(
+        filters: impl IntoIterator<Item = &'f EclFilter>,
+        mut resolve: F,
+    ) -> EclResult<Self>
+    where
+        F: FnMut(&EclExpression) -> EclResult<HashSet<SctId>>,
+    {
+        let mut conditions = Vec::new();
+        for filter in filters {
+            if let Some(condition) = MemberCondition::compile(filter, &mut resolve)? {
+                conditions.push(condition);
+            }
+        }
+        let constrains_active = conditions
+            .iter()
+            .any(|condition| condition.field().eq_ignore_ascii_case("active"));
+
+        Ok(Self {
+            conditions,
+            constrains_active,
+        })
+    }
+
+    /// Returns true if one member row meets every condition.
+    pub(crate) fn matches(&self, member: &RefsetMember) -> bool {
+        (member.active || self.constrains_active)
+            && self.conditions.iter().all(|condition| condition.matches(member))
+    }
+
+    /// Returns the referenced components of the rows of `refset_ids` that
+    /// meet every condition.
+    ///
+    /// The guard's deadline is checked once per member row.
+    pub(crate) fn matching_components(
+        &self,
+        store: &dyn EclQueryable,
+        refset_ids: &HashSet<SctId>,
+        guard: &ExecutionGuard,
+    ) -> EclResult<HashSet<SctId>> {
+        let mut result = HashSet::new();
+        for &refset_id in refset_ids {
+            for member in store.get_refset_member_rows(refset_id) {
+                guard.check()?;
+                if self.matches(&member) {
+                    result.insert(member.referenced_component_id);
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// The standard columns of every reference set, in RF2 order.
+const STANDARD_COLUMNS: [&str; 6] = [
+    "id",
+    "effectiveTime",
+    "active",
+    "moduleId",
+    "refsetId",
+    "referencedComponentId",
+];
+
+/// A member-of with a field selection, and the filters of the blocks
+/// applied to it.
+pub(crate) struct MemberProjection<'e> {
+    /// The reference set expression.
+    pub(crate) refset: &'e EclExpression,
+    /// The selected fields.
+    pub(crate) fields: &'e MemberFieldSelection,
+    /// Filters of every `{{ }}` block around the member-of.
+    pub(crate) filters: Vec<&'e EclFilter>,
+}
+
+impl<'e> MemberProjection<'e> {
+    /// Finds the member-of with a field selection at the top of an
+    /// expression, looking through nesting and filter blocks.
+    pub(crate) fn find(expression: &'e EclExpression) -> Option<Self> {
+        match expression.unwrap_nested() {
+            EclExpression::MemberOf {
+                refset,
+                fields: Some(fields),
+            } => Some(Self {
+                refset,
+                fields,
+                filters: Vec::new(),
+            }),
+            EclExpression::Filtered {
+                expression,
+                filters,
+            } => {
+                let mut projection = Self::find(expression)?;
+                projection.filters.extend(filters);
+                Some(projection)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Projects member rows onto the selected fields.
+///
+/// `[*]` selects the standard columns followed by every additional field,
+/// in the order first seen. A row that lacks a field has `None` in its
+/// column.
+pub(crate) fn project_rows(
+    members: &[RefsetMember],
+    fields: &MemberFieldSelection,
+) -> (Vec<String>, Vec<Vec<Option<RefsetFieldValue>>>) {
+    let columns: Vec<String> = match fields {
+        MemberFieldSelection::Fields(fields) => fields.clone(),
+        MemberFieldSelection::All => {
+            let mut columns: Vec<String> = STANDARD_COLUMNS.iter().map(|c| c.to_string()).collect();
+            for member in members {
+                for (field, _) in &member.fields {
+                    if !columns.iter().any(|c| c.eq_ignore_ascii_case(field)) {
+                        columns.push(field.clone());
+                    }
+                }
+            }
+            columns
+        }
+    };
+
+    let rows = members
+        .iter()
+        .map(|member| columns.iter().map(|column| member.field(column)).collect())
+        .collect();
+
+    (columns, rows)
+}
+
+/// A numeric field or filter value.
+#[derive(Clone, Copy)]
+enum Number {
+    Integer(i128),
+    Decimal(f64),
+}
+
+impl Number {
+    fn of_field(value: &RefsetFieldValue) -> Option<Self> {
+        match value {
+            RefsetFieldValue::Component(id) => Some(Self::Integer((*id).into())),
+            RefsetFieldValue::Integer(n) => Some(Self::Integer((*n).into())),
+            RefsetFieldValue::Decimal(n) => Some(Self::Decimal(*n)),
+            RefsetFieldValue::String(_) | RefsetFieldValue::Boolean(_) => None,
+        }
+    }
+
+    fn of_filter(value: &MemberFieldValue) -> Option<Self> {
+        match value {
+            MemberFieldValue::SctId(id) => Some(Self::Integer((*id).into())),
+            MemberFieldValue::Integer(n) => Some(Self::Integer((*n).into())),
+            MemberFieldValue::Decimal(n) => Some(Self::Decimal(*n)),
+            MemberFieldValue::String(_) | MemberFieldValue::Boolean(_) => None,
+        }
+    }
+
+    fn partial_cmp(self, other: Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::Integer(a), Self::Integer(b)) => Some(a.cmp(&b)),
+            (Self::Integer(a), Self::Decimal(b)) => (a as f64).partial_cmp(&b),
+            (Self::Decimal(a), Self::Integer(b)) => a.partial_cmp(&(b as f64)),
+            (Self::Decimal(a), Self::Decimal(b)) => a.partial_cmp(&b),
+        }
+    }
+}
+
+/// Compares a member field with a filter value.
+///
+/// Numbers, ids and dates compare numerically and strings lexicographically;
+/// booleans only support `=` and `!=`. Values of different kinds never match.
+fn compare_field(
+    actual: &RefsetFieldValue,
+    operator: ComparisonOperator,
+    expected: &MemberFieldValue,
+) -> bool {
+    let ordering = match (actual, expected) {
+        (RefsetFieldValue::String(a), MemberFieldValue::String(b)) => Some(a.as_str().cmp(b)),
+        (RefsetFieldValue::Boolean(a), MemberFieldValue::Boolean(b)) => {
+            return match operator {
+                ComparisonOperator::Equal => a == b,
+                ComparisonOperator::NotEqual => a != b,
+                _ => false,
+            };
+        }
+        _ => Number::of_field(actual)
+            .zip(Number::of_filter(expected))
+            .and_then(|(a, b)| a.partial_cmp(b)),
+    };
+
+    ordering.is_some_and(|ordering| match operator {
+        ComparisonOperator::Equal => ordering == Ordering::Equal,
+        ComparisonOperator::NotEqual => ordering != Ordering::Equal,
+        ComparisonOperator::LessThan => ordering == Ordering::Less,
+        ComparisonOperator::LessThanOrEqual => ordering != Ordering::Greater,
+        ComparisonOperator::GreaterThan => ordering == Ordering::Greater,
+        ComparisonOperator::GreaterThanOrEqual => ordering != Ordering::Less,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(active: bool, fields: Vec<(&str, RefsetFieldValue)>) -> RefsetMember {
+        RefsetMember {
+            id: "00000000-0000-0000-0000-000000000001".to_string(),
+            effective_time: Some(20200131),
+            active,
+            module_id: 449080006,
+            refset_id: 447562003,
+            referenced_component_id: 195967001,
+            fields: fields
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        }
+    }
+
+    fn filter(ecl_filters: &str) -> MemberFilter {
+        let expr = snomed_ecl::parse(&format!("^ 447562003 {{{{ {} }}}}", ecl_filters)).unwrap();
+        match expr {
+            snomed_ecl::EclExpression::Filtered { filters, .. } => {
+                MemberFilter::compile(&filters, resolve).unwrap()
+            }
+            _ => panic!("Expected Filtered expression"),
+        }
+    }
+
+    /// Resolves concept references and `<< 900000000000445007`, whose
+    /// descendants include the module `449080006`.
+    fn resolve(expression: &EclExpression) -> EclResult<HashSet<SctId>> {
+        Ok(match expression {
+            EclExpression::ConceptReference { concept_id, .. } => HashSet::from([*concept_id]),
+            EclExpression::DescendantOrSelfOf(inner) => {
+                assert_eq!(inner.as_concept_id(), Some(900000000000445007));
+                HashSet::from([900000000000445007, 449080006])
+            }
+            other => panic!("Unexpected constraint {}", other),
+        })
+    }
+
+    #[test]
+    fn test_compare_field() {
+        use ComparisonOperator::*;
+
+        let target = RefsetFieldValue::String("J45.9".to_string());
+        assert!(compare_field(&target, Equal, &MemberFieldValue::String("J45.9".into())));
+        assert!(compare_field(&target, GreaterThan, &MemberFieldValue::String("J45".into())));
+        assert!(!compare_field(&target, Equal, &MemberFieldValue::Integer(45)));
+
+        let group = RefsetFieldValue::Integer(2);
+        assert!(compare_field(&group, GreaterThanOrEqual, &MemberFieldValue::Integer(2)));
+        assert!(compare_field(&group, LessThan, &MemberFieldValue::Decimal(2.5)));
+        assert!(compare_field(&group, NotEqual, &MemberFieldValue::Integer(1)));
+
+        let concept = RefsetFieldValue::Component(900000000000548007);
+        assert!(compare_field(&concept, Equal, &MemberFieldValue::SctId(900000000000548007)));
+
+        let flag = RefsetFieldValue::Boolean(true);
+        assert!(compare_field(&flag, Equal, &MemberFieldValue::Boolean(true)));
+        assert!(!compare_field(&flag, GreaterThan, &MemberFieldValue::Boolean(false)));
+    }
+
+    #[test]
+    fn test_member_filter_same_row() {
+        let row = member(
+            true,
+            vec![
+                ("mapGroup", RefsetFieldValue::Integer(1)),
+                ("mapTarget", RefsetFieldValue::String("J45.9".to_string())),
+            ],
+        );
+
+        assert!(filter(r#"M mapTarget = "J45.9""#).matches(&row));
+        assert!(filter(r#"M mapTarget = "J45.9", M mapGroup = 1"#).matches(&row));
+        assert!(!filter(r#"M mapTarget = "J45.9", M mapGroup = 2"#).matches(&row));
+        assert!(filter(r#"M effectiveTime >= 20200101"#).matches(&row));
+        assert!(!filter(r#"M mapPriority = 1"#).matches(&row));
+    }
+
+    #[test]
+    fn test_project_rows() {
+        let rows = [
+            member(true, vec![("mapTarget", RefsetFieldValue::String("J45.9".to_string()))]),
+            member(true, vec![("mapAdvice", RefsetFieldValue::String("ALWAYS".to_string()))]),
+        ];
+
+        let selection = MemberFieldSelection::Fields(vec![
+            "referencedComponentId".to_string(),
+            "mapTarget".to_string(),
+        ]);
+        let (columns, values) = project_rows(&rows, &selection);
+        assert_eq!(columns, vec!["referencedComponentId", "mapTarget"]);
+        assert_eq!(
+            values,
+            vec![
+                vec![
+                    Some(RefsetFieldValue::Component(195967001)),
+                    Some(RefsetFieldValue::String("J45.9".to_string())),
+                ],
+                vec![Some(RefsetFieldValue::Component(195967001)), None],
+            ]
+        );
+
+        let (columns, values) = project_rows(&rows, &MemberFieldSelection::All);
+        assert_eq!(&columns[..6], &STANDARD_COLUMNS);
+        assert_eq!(&columns[6..], &["mapTarget", "mapAdvice"]);
+        assert_eq!(values[1][6], None);
+    }
+
+    #[test]
+    fn test_member_filter_inactive_rows() {
+        let row = member(false, vec![("mapTarget", RefsetFieldValue::String("J45.9".to_string()))]);
+
+        assert!(!filter(r#"M mapTarget = "J45.9""#).matches(&row));
+        assert!(filter(r#"M mapTarget = "J45.9", M active = false"#).matches(&row));
+    }
+
+    #[test]
+    fn test_member_filter_module_constraint() {
+        let row = member(true, vec![]);
+
+        assert!(filter("M moduleId = 449080006").matches(&row));
+        assert!(filter("M moduleId = << 900000000000445007").matches(&row));
+        assert!(!filter("M moduleId = 900000000000445007").matches(&row));
+    }
+}
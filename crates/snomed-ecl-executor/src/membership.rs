@@ -0,0 +1,303 @@
+//! Per-concept membership tests for ECL expressions.
+//!
+//! Intersecting a small set with `<< 404684003` does not require expanding
+//! the 400K descendants of `404684003`: it is enough to check, for each
+//! concept in the small set, whether it has `404684003` among its ancestors.
+//! [`Membership`] compiles an expression into such a test. Focus sets of
+//! hierarchy operators and refinements are resolved once, at compile time.
+
+use std::collections::HashSet;
+
+use snomed_ecl::{EclExpression, SctId};
+
+use crate::error::EclResult;
+use crate::refinement::CompiledRefinement;
+use crate::traits::EclQueryable;
+use crate::traverser::HierarchyTraverser;
+
+/// A compiled membership test for an expression.
+pub(crate) enum Membership<'e> {
+    /// `*`: every concept in the store.
+    Any,
+    /// An explicit set of concepts (concept references, concept sets).
+    Concepts(HashSet<SctId>),
+    /// `<` and `<<`: one of the concept's ancestors is in the focus set.
+    Descendant {
+        focus: HashSet<SctId>,
+        include_self: bool,
+    },
+    /// `<!` and `<<!`: one of the concept's parents is in the focus set.
+    Child {
+        focus: HashSet<SctId>,
+        include_self: bool,
+    },
+    /// `>!` and `>>!`: one of the concept's children is in the focus set.
+    Parent {
+        focus: HashSet<SctId>,
+        include_self: bool,
+    },
+    /// Both operands match.
+    And(Box<Membership<'e>>, Box<Membership<'e>>),
+    /// Either operand matches.
+    Or(Box<Membership<'e>>, Box<Membership<'e>>),
+    /// The left operand matches and the right one doesn't.
+    Minus(Box<Membership<'e>>, Box<Membership<'e>>),
+    /// The focus matches and the concept satisfies the refinement.
+    Refined {
+        focus: Box<Membership<'e>>,
+        refinement: CompiledRefinement<'e>,
+    },
+}
+
+impl<'e> Membership<'e> {
+    /// Compiles a membership test.
+    ///
+    /// The expression must satisfy [`supports_membership_check`].
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - The expression to test membership of
+    /// * `traverser` - Traverser used to compile nested refinements
+    /// * `resolve` - Executes a sub-expression and returns its concept set
+    pub(crate) fn compile<F>(
+        expression: &'e EclExpression,
+        traverser: &HierarchyTraverser<'_>,
+        resolve: &mut F,
+    ) -> EclResult<Self>
+    where
+        F: FnMut(&EclExpression) -> EclResult<HashSet<SctId>>,
+    {
+        let expression = expression.unwrap_nested();
+
+        Ok(match expression {
+            EclExpression::Any => Self::Any,
+            EclExpression::DescendantOf(inner) => Self::Descendant {
+                focus: resolve(inner)?,
+                include_self: false,
+            },
+            EclExpression::DescendantOrSelfOf(inner) => Self::Descendant {
+                focus: resolve(inner)?,
+                include_self: true,
+            },
+            EclExpression::ChildOf(inner) => Self::Child {
+                focus: resolve(inner)?,
+                include_self: false,
+            },
+            EclExpression::ChildOrSelfOf(inner) => Self::Child {
+                focus: resolve(inner)?,
+                include_self: true,
+            },
+            EclExpression::ParentOf(inner) => Self::Parent {
+                focus: resolve(inner)?,
+                include_self: false,
+            },
+            EclExpression::ParentOrSelfOf(inner) => Self::Parent {
+                focus: resolve(inner)?,
+                include_self: true,
+            },
+            EclExpression::And(left, right) => Self::And(
+                Box::new(Self::compile(left, traverser, resolve)?),
+                Box::new(Self::compile(right, traverser, resolve)?),
+            ),
+            EclExpression::Or(left, right) => Self::Or(
+                Box::new(Self::compile(left, traverser, resolve)?),
+                Box::new(Self::compile(right, traverser, resolve)?),
+            ),
+            EclExpression::Minus(left, right) => Self::Minus(
+                Box::new(Self::compile(left, traverser, resolve)?),
+                Box::new(Self::compile(right, traverser, resolve)?),
+            ),
+            EclExpression::Refined { focus, refinement } => Self::Refined {
+                focus: Box::new(Self::compile(focus, traverser, resolve)?),
+                refinement: CompiledRefinement::compile(refinement, traverser, &mut *resolve)?,
+            },
+            // Concept references, concept sets and anything else are materialised
+            other => Self::Concepts(resolve(other)?),
+        })
+    }
+
+    /// Returns true if the concept is a member of the compiled expression.
+    pub(crate) fn contains(
+        &self,
+        concept_id: SctId,
+        store: &dyn EclQueryable,
+        traverser: &HierarchyTraverser<'_>,
+    ) -> EclResult<bool> {
+        Ok(match self {
+            Self::Any => store.has_concept(concept_id),
+            Self::Concepts(concepts) => concepts.contains(&concept_id),
+            Self::Descendant {
+                focus,
+                include_self,
+            } => {
+                (*include_self && focus.contains(&concept_id))
+                    || traverser.try_has_ancestor_in(concept_id, focus)?
+            }
+            Self::Child {
+                focus,
+                include_self,
+            } => {
+                (*include_self && focus.contains(&concept_id))
+                    || store
+                        .get_parents(concept_id)
+                        .iter()
+                        .any(|p| focus.contains(p))
+            }
+            Self::Parent {
+                focus,
+                include_self,
+            } => {
+                (*include_self && focus.contains(&concept_id))
+                    || store
+                        .get_children(concept_id)
+                        .iter()
+                        .any(|c| focus.contains(c))
+            }
+            Self::And(left, right) => {
+                left.contains(concept_id, store, traverser)?
+                    && right.contains(concept_id, store, traverser)?
+            }
+            Self::Or(left, right) => {
+                left.contains(concept_id, store, traverser)?
+                    || right.contains(concept_id, store, traverser)?
+            }
+            Self::Minus(left, right) => {
+                left.contains(concept_id, store, traverser)?
+                    && !right.contains(concept_id, store, traverser)?
+            }
+            Self::Refined { focus, refinement } => {
+                focus.contains(concept_id, store, traverser)?
+                    && refinement.matches(concept_id, store, traverser)?.0
+            }
+        })
+    }
+}
+
+/// Returns true if membership of `expression` can be tested per concept
+/// without materialising the expression's full result.
+///
+/// Hierarchy operators that expand downwards (`<`, `<<`, `<!`, `<<!`) and
+/// refinements of such expressions benefit most; other operators such as
+/// `^` or filters have no cheaper per-concept test.
+pub(crate) fn supports_membership_check(expression: &EclExpression) -> bool {
+    match expression.unwrap_nested() {
+        EclExpression::ConceptReference { .. }
+        | EclExpression::ConceptSet(_)
+        | EclExpression::Any
+        | EclExpression::DescendantOf(_)
+        | EclExpression::DescendantOrSelfOf(_)
+        | EclExpression::ChildOf(_)
+        | EclExpression::ChildOrSelfOf(_)
+        | EclExpression::ParentOf(_)
+        | EclExpression::ParentOrSelfOf(_) => true,
+        EclExpression::And(left, right)
+        | EclExpression::Or(left, right)
+        | EclExpression::Minus(left, right) => {
+            supports_membership_check(left) && supports_membership_check(right)
+        }
+        EclExpression::Refined { focus, .. } => supports_membership_check(focus),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Hierarchy:
+    /// ```text
+    ///       1
+    ///      / \
+    ///     2   3
+    ///    / \
+    ///   4   5
+    /// ```
+    struct MockStore {
+        children: HashMap<SctId, Vec<SctId>>,
+        parents: HashMap<SctId, Vec<SctId>>,
+    }
+
+    impl MockStore {
+        fn new() -> Self {
+            let mut store = Self {
+                children: HashMap::new(),
+                parents: HashMap::new(),
+            };
+            for (child, parent) in [(2, 1), (3, 1), (4, 2), (5, 2)] {
+                store.children.entry(parent).or_default().push(child);
+                store.parents.entry(child).or_default().push(parent);
+            }
+            store
+        }
+    }
+
+    impl EclQueryable for MockStore {
+        fn get_children(&self, concept_id: SctId) -> Vec<SctId> {
+            self.children.get(&concept_id).cloned().unwrap_or_default()
+        }
+
+        fn get_parents(&self, concept_id: SctId) -> Vec<SctId> {
+            self.parents.get(&concept_id).cloned().unwrap_or_default()
+        }
+
+        fn has_concept(&self, concept_id: SctId) -> bool {
+            (1..=5).contains(&concept_id)
+        }
+
+        fn all_concept_ids(&self) -> Box<dyn Iterator<Item = SctId> + '_> {
+            Box::new(1..=5)
+        }
+
+        fn get_refset_members(&self, _refset_id: SctId) -> Vec<SctId> {
+            Vec::new()
+        }
+    }
+
+    /// Returns the concepts 1..=5 that are members of `ecl`.
+    fn members(ecl: &str) -> Vec<SctId> {
+        let store = MockStore::new();
+        let traverser = HierarchyTraverser::new(&store);
+        let expression = snomed_ecl::parse(ecl).unwrap();
+        assert!(supports_membership_check(&expression));
+
+        let membership = Membership::compile(&expression, &traverser, &mut |expr| {
+            Ok(match expr.unwrap_nested() {
+                EclExpression::ConceptReference { concept_id, .. } => HashSet::from([*concept_id]),
+                EclExpression::ConceptSet(ids) => ids.iter().copied().collect(),
+                other => panic!("unexpected resolution of {}", other),
+            })
+        })
+        .unwrap();
+
+        (1..=5)
+            .filter(|&id| membership.contains(id, &store, &traverser).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_hierarchy_membership() {
+        assert_eq!(members("< 2"), vec![4, 5]);
+        assert_eq!(members("<< 2"), vec![2, 4, 5]);
+        assert_eq!(members("<! 1"), vec![2, 3]);
+        assert_eq!(members("<<! 1"), vec![1, 2, 3]);
+        assert_eq!(members(">! 4"), vec![2]);
+        assert_eq!(members(">>! 4"), vec![2, 4]);
+    }
+
+    #[test]
+    fn test_compound_membership() {
+        assert_eq!(members("<< 1 MINUS << 2"), vec![1, 3]);
+        assert_eq!(members("< 1 AND <! 2"), vec![4, 5]);
+        assert_eq!(members("3 OR (4 OR 5)"), vec![3, 4, 5]);
+        assert_eq!(members("*"), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_unsupported_expressions() {
+        for ecl in ["^ 700043003", "> 4", "<< 1 {{ term = \"x\" }}"] {
+            let expression = snomed_ecl::parse(ecl).unwrap();
+            assert!(!supports_membership_check(&expression), "{}", ecl);
+        }
+    }
+}
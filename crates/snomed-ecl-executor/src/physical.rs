@@ -0,0 +1,150 @@
+//! Physical execution plans.
+//!
+//! The [`QueryPlanner`](crate::QueryPlanner) turns the compound parts of an
+//! ECL expression (AND, OR, MINUS) into a tree of [`PlanNode`]s which the
+//! executor runs as-is. Compared with the AST the plan:
+//!
+//! - flattens `A AND B AND C` and `A OR B OR C` chains into n-ary nodes,
+//! - runs the most selective AND operand first and stops as soon as an
+//!   intermediate result is empty,
+//! - replaces the full expansion of a large operand by a per-concept
+//!   [`PlanOperator::MembershipCheck`] when the driving set is small.
+
+use std::fmt;
+
+use snomed_ecl::EclExpression;
+
+/// A physical plan operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PlanOperator {
+    /// Materialise the concepts of a non-compound expression.
+    Scan,
+    /// Intersect the children in order. The first child drives; execution
+    /// stops as soon as the intermediate result is empty.
+    Intersect,
+    /// Union of all children.
+    Union,
+    /// The first child minus the second. The second child is skipped when
+    /// the first is empty.
+    Difference,
+    /// Test each concept of the parent's intermediate result for membership
+    /// of the node's expression instead of materialising it.
+    ///
+    /// Only appears as a non-driving child of `Intersect` or `Difference`.
+    MembershipCheck,
+}
+
+impl fmt::Display for PlanOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Scan => write!(f, "Scan"),
+            Self::Intersect => write!(f, "Intersect"),
+            Self::Union => write!(f, "Union"),
+            Self::Difference => write!(f, "Difference"),
+            Self::MembershipCheck => write!(f, "MembershipCheck"),
+        }
+    }
+}
+
+/// A node of a physical execution plan.
+///
+/// # Example
+///
+/// ```ignore
+/// let planner = QueryPlanner::new();
+/// let plan = planner.physical_plan(&snomed_ecl::parse("73211009 AND << 404684003")?);
+///
+/// assert_eq!(plan.operator, PlanOperator::Intersect);
+/// assert_eq!(plan.children[1].operator, PlanOperator::MembershipCheck);
+/// println!("{}", plan);
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlanNode {
+    /// The operator executed by this node.
+    pub operator: PlanOperator,
+    /// The sub-expression this node computes (or tests, for membership checks).
+    ///
+    /// Serialized as ECL text.
+    #[cfg_attr(feature = "serde", serde(with = "ecl_text"))]
+    pub expression: EclExpression,
+    /// Estimated number of concepts produced.
+    pub estimated_cardinality: usize,
+    /// Estimated cost of this node including its children (in milliseconds).
+    pub estimated_cost: f64,
+    /// Child nodes, in execution order.
+    pub children: Vec<PlanNode>,
+}
+
+impl PlanNode {
+    /// Creates a leaf node.
+    pub fn leaf(
+        operator: PlanOperator,
+        expression: EclExpression,
+        estimated_cardinality: usize,
+        estimated_cost: f64,
+    ) -> Self {
+        Self {
+            operator,
+            expression,
+            estimated_cardinality,
+            estimated_cost,
+            children: Vec::new(),
+        }
+    }
+
+    /// Returns true if this node is a set operation over child nodes.
+    pub fn is_compound(&self) -> bool {
+        matches!(
+            self.operator,
+            PlanOperator::Intersect | PlanOperator::Union | PlanOperator::Difference
+        )
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        write!(f, "{:indent$}", "", indent = depth * 2)?;
+        if self.is_compound() {
+            write!(f, "{}", self.operator)?;
+        } else {
+            write!(f, "{}: {}", self.operator, self.expression)?;
+        }
+        writeln!(
+            f,
+            " (est. {} concepts, {:.4}ms)",
+            self.estimated_cardinality, self.estimated_cost
+        )?;
+
+        for child in &self.children {
+            child.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PlanNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+/// Serializes expressions as ECL text rather than as an AST.
+#[cfg(feature = "serde")]
+mod ecl_text {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use snomed_ecl::EclExpression;
+
+    pub fn serialize<S: Serializer>(
+        expr: &EclExpression,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(expr)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<EclExpression, D::Error> {
+        let ecl = String::deserialize(deserializer)?;
+        snomed_ecl::parse(&ecl).map_err(de::Error::custom)
+    }
+}
@@ -9,6 +9,9 @@ use std::fmt;
 use snomed_ecl::EclExpression;
 use snomed_ecl::SctId;
 
+use crate::membership::supports_membership_check;
+use crate::physical::{PlanNode, PlanOperator};
+use crate::refinement::indexable_constraints;
 use crate::statistics::{heuristics, StatisticsService};
 
 /// A complete execution plan for an ECL query.
@@ -16,6 +19,13 @@ use crate::statistics::{heuristics, StatisticsService};
 /// Contains the steps to execute the query, estimated total cardinality,
 /// and optimization hints for improving query performance.
 ///
+/// Steps form a tree: each step links to the steps of its operands through
+/// [`QueryStep::children`] and to the step that consumes it through
+/// [`QueryStep::parent`]. Steps are stored in execution order, so operands
+/// come before the operation that uses them and the root is the last step.
+/// The plan can be rendered as an indented text tree, Graphviz DOT or (with
+/// the `serde` feature) JSON.
+///
 /// # Example
 ///
 /// ```ignore
@@ -32,6 +42,7 @@ use crate::statistics::{heuristics, StatisticsService};
 /// }
 /// ```
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct QueryPlan {
     /// The original ECL expression.
     pub ecl: String,
@@ -41,6 +52,8 @@ pub struct QueryPlan {
     pub estimated_total: usize,
     /// Optimization hints and suggestions.
     pub optimization_hints: Vec<String>,
+    /// The physical plan the executor runs, if one was generated.
+    pub physical_plan: Option<PlanNode>,
 }
 
 impl QueryPlan {
@@ -51,6 +64,7 @@ impl QueryPlan {
             steps: Vec::new(),
             estimated_total: 0,
             optimization_hints: Vec::new(),
+            physical_plan: None,
         }
     }
 
@@ -59,6 +73,34 @@ impl QueryPlan {
         self.steps.push(step);
     }
 
+    /// Makes `parent` the parent of every step from `first` onwards that
+    /// has no parent yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` is not a step of this plan.
+    pub fn adopt_steps(&mut self, parent: usize, first: usize) {
+        for index in first..self.steps.len() {
+            if index != parent && self.steps[index].parent.is_none() {
+                self.steps[index].parent = Some(parent);
+                self.steps[parent].children.push(index);
+            }
+        }
+    }
+
+    /// Returns the root step (the step producing the final result).
+    pub fn root(&self) -> Option<&QueryStep> {
+        self.steps.last().filter(|step| step.parent.is_none())
+    }
+
+    /// Returns the child steps of the step at `index`, in execution order.
+    pub fn children_of(&self, index: usize) -> impl Iterator<Item = &QueryStep> {
+        self.steps
+            .get(index)
+            .into_iter()
+            .flat_map(|step| step.children.iter().map(|&child| &self.steps[child]))
+    }
+
     /// Adds an optimization hint.
     pub fn add_hint(&mut self, hint: impl Into<String>) {
         self.optimization_hints.push(hint.into());
@@ -83,8 +125,16 @@ impl fmt::Display for QueryPlan {
         writeln!(f)?;
 
         writeln!(f, "Steps:")?;
-        for (i, step) in self.steps.iter().enumerate() {
-            writeln!(f, "  {}. {}", i + 1, step)?;
+        for line in self.to_text().lines() {
+            writeln!(f, "  {}", line)?;
+        }
+
+        if let Some(ref physical_plan) = self.physical_plan {
+            writeln!(f)?;
+            writeln!(f, "Physical Plan:")?;
+            for line in physical_plan.to_string().lines() {
+                writeln!(f, "  {}", line)?;
+            }
         }
 
         if !self.optimization_hints.is_empty() {
@@ -101,6 +151,7 @@ impl fmt::Display for QueryPlan {
 
 /// A single step in the query execution plan.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct QueryStep {
     /// Type of operation (e.g., "Descendants", "Intersect", "Union").
     pub operation: String,
@@ -110,6 +161,10 @@ pub struct QueryStep {
     pub estimated_cardinality: usize,
     /// Estimated execution cost (in milliseconds).
     pub cost_estimate: f64,
+    /// Index in [`QueryPlan::steps`] of the step consuming this step's result.
+    pub parent: Option<usize>,
+    /// Indices in [`QueryPlan::steps`] of the steps producing this step's operands.
+    pub children: Vec<usize>,
 }
 
 impl QueryStep {
@@ -125,6 +180,8 @@ impl QueryStep {
             expression: expression.into(),
             estimated_cardinality,
             cost_estimate,
+            parent: None,
+            children: Vec::new(),
         }
     }
 }
@@ -139,16 +196,65 @@ impl fmt::Display for QueryStep {
     }
 }
 
+/// How a refinement (`focus : attribute = value`) is evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefinementStrategy {
+    /// Check the attributes of every focus concept.
+    ForwardScan,
+    /// Look up the concepts having the attribute values in the store's
+    /// reverse index, then intersect with the focus set.
+    ReverseLookup,
+}
+
+impl RefinementStrategy {
+    /// Chooses the cheaper strategy from statistics estimates.
+    ///
+    /// # Arguments
+    ///
+    /// * `statistics` - Statistics used for cost estimation
+    /// * `focus_size` - Number of focus concepts (actual or estimated)
+    /// * `lookups` - Number of attribute type/value pairs to look up, or
+    ///   `None` if the refinement can't be answered from a reverse index
+    /// * `estimated_candidates` - Estimated number of concepts returned by the lookups
+    pub fn choose(
+        statistics: &StatisticsService,
+        focus_size: usize,
+        lookups: Option<usize>,
+        estimated_candidates: usize,
+    ) -> Self {
+        match lookups {
+            Some(lookups)
+                if statistics.cost_reverse_refinement(lookups, estimated_candidates)
+                    < statistics.cost_forward_refinement(focus_size) =>
+            {
+                Self::ReverseLookup
+            }
+            _ => Self::ForwardScan,
+        }
+    }
+}
+
+impl fmt::Display for RefinementStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ForwardScan => write!(f, "forward scan"),
+            Self::ReverseLookup => write!(f, "reverse lookup"),
+        }
+    }
+}
+
 /// Query planner for generating execution plans from ECL expressions.
 ///
 /// The planner walks the ECL AST and generates a plan with:
 /// - Execution steps in order
 /// - Cardinality estimates for each step
 /// - Optimization hints for improving performance
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct QueryPlanner {
     /// Statistics service for cardinality estimation.
     statistics: StatisticsService,
+    /// Whether the store can answer reverse attribute lookups.
+    attribute_lookup: bool,
 }
 
 impl Default for QueryPlanner {
@@ -162,12 +268,25 @@ impl QueryPlanner {
     pub fn new() -> Self {
         Self {
             statistics: StatisticsService::new(),
+            attribute_lookup: false,
         }
     }
 
     /// Creates a query planner with custom statistics.
     pub fn with_statistics(statistics: StatisticsService) -> Self {
-        Self { statistics }
+        Self {
+            statistics,
+            attribute_lookup: false,
+        }
+    }
+
+    /// Sets whether the store supports reverse attribute lookups.
+    ///
+    /// When enabled, refinements may be planned as
+    /// [`RefinementStrategy::ReverseLookup`].
+    pub fn with_attribute_lookup(mut self, enabled: bool) -> Self {
+        self.attribute_lookup = enabled;
+        self
     }
 
     /// Returns a reference to the statistics service.
@@ -190,6 +309,7 @@ impl QueryPlanner {
         // Recursively plan the expression
         let (estimate, _cost) = self.plan_expression(expr, &mut plan);
         plan.estimated_total = estimate;
+        plan.physical_plan = Some(self.physical_plan(expr));
 
         // Generate optimization hints based on the full plan
         self.generate_hints(&mut plan, expr);
@@ -197,8 +317,185 @@ impl QueryPlanner {
         plan
     }
 
+    /// Builds the physical plan the executor runs for an expression.
+    ///
+    /// AND and OR chains are flattened, AND operands are ordered by estimated
+    /// cardinality (most selective first), and operands that would be much
+    /// more expensive to expand than to test per concept of the driving set
+    /// become [`PlanOperator::MembershipCheck`] nodes. Non-compound
+    /// expressions become a single [`PlanOperator::Scan`] node.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let plan = planner.physical_plan(&snomed_ecl::parse("73211009 AND << 404684003")?);
+    /// println!("{}", plan);
+    /// // Intersect (est. 1 concepts, ...)
+    /// //   Scan: 73211009 (est. 1 concepts, ...)
+    /// //   MembershipCheck: << 404684003 (est. 400001 concepts, ...)
+    /// ```
+    pub fn physical_plan(&self, expr: &EclExpression) -> PlanNode {
+        let expr = expr.unwrap_nested();
+
+        match expr {
+            EclExpression::And(..) => self.plan_intersect(expr),
+
+            EclExpression::Or(..) => {
+                let mut operands = Vec::new();
+                flatten(expr, &mut operands, |e| match e {
+                    EclExpression::Or(left, right) => Some((left, right)),
+                    _ => None,
+                });
+
+                let children: Vec<PlanNode> =
+                    operands.iter().map(|e| self.physical_plan(e)).collect();
+                let estimate = children
+                    .iter()
+                    .map(|c| c.estimated_cardinality)
+                    .reduce(|acc, e| self.statistics.estimated_or(acc, e))
+                    .unwrap_or(0);
+                let cost = children.iter().map(|c| c.estimated_cost).sum::<f64>()
+                    + self
+                        .statistics
+                        .cost_union(children.iter().map(|c| c.estimated_cardinality).sum());
+
+                PlanNode {
+                    operator: PlanOperator::Union,
+                    expression: expr.clone(),
+                    estimated_cardinality: estimate,
+                    estimated_cost: cost,
+                    children,
+                }
+            }
+
+            EclExpression::Minus(left, right) => {
+                let left = self.physical_plan(left);
+                let right = self.check_or_plan(right, left.estimated_cardinality);
+
+                let estimate = self
+                    .statistics
+                    .estimated_minus(left.estimated_cardinality, right.estimated_cardinality);
+                let cost = left.estimated_cost
+                    + right.estimated_cost
+                    + self.statistics.cost_difference(left.estimated_cardinality);
+
+                PlanNode {
+                    operator: PlanOperator::Difference,
+                    expression: expr.clone(),
+                    estimated_cardinality: estimate,
+                    estimated_cost: cost,
+                    children: vec![left, right],
+                }
+            }
+
+            _ => {
+                let (estimate, cost) = self.estimate(expr);
+                PlanNode::leaf(PlanOperator::Scan, expr.clone(), estimate, cost)
+            }
+        }
+    }
+
+    /// Plans a flattened AND chain.
+    fn plan_intersect(&self, expr: &EclExpression) -> PlanNode {
+        let mut operands = Vec::new();
+        flatten(expr, &mut operands, |e| match e {
+            EclExpression::And(left, right) => Some((left, right)),
+            _ => None,
+        });
+
+        // Most selective operand first; the sort is stable so ties keep source order
+        let mut planned: Vec<(&EclExpression, usize)> = operands
+            .into_iter()
+            .map(|e| (e, self.estimate_cardinality(e)))
+            .collect();
+        planned.sort_by_key(|&(_, estimate)| estimate);
+
+        let mut planned = planned.into_iter();
+        let (driver, _) = planned.next().expect("AND has at least two operands");
+        let driver = self.physical_plan(driver);
+
+        let mut estimate = driver.estimated_cardinality;
+        let mut cost = driver.estimated_cost;
+        let mut children = vec![driver];
+
+        for (operand, operand_estimate) in planned {
+            let child = self.check_or_plan(operand, estimate);
+            cost += child.estimated_cost;
+            if child.operator != PlanOperator::MembershipCheck {
+                cost += self
+                    .statistics
+                    .cost_intersection(estimate.min(operand_estimate));
+            }
+            estimate = self.statistics.estimated_and(estimate, operand_estimate);
+            children.push(child);
+        }
+
+        PlanNode {
+            operator: PlanOperator::Intersect,
+            expression: expr.clone(),
+            estimated_cardinality: estimate,
+            estimated_cost: cost,
+            children,
+        }
+    }
+
+    /// Plans a non-driving operand of an intersection or difference.
+    ///
+    /// Returns a membership check if testing each of the `driving_estimate`
+    /// concepts is cheaper than materialising the operand.
+    fn check_or_plan(&self, expr: &EclExpression, driving_estimate: usize) -> PlanNode {
+        let node = self.physical_plan(expr);
+        let check_cost = self.statistics.cost_membership_check(driving_estimate);
+
+        if supports_membership_check(expr) && check_cost < node.estimated_cost {
+            PlanNode::leaf(
+                PlanOperator::MembershipCheck,
+                expr.unwrap_nested().clone(),
+                node.estimated_cardinality,
+                check_cost,
+            )
+        } else {
+            node
+        }
+    }
+
+    /// Returns the estimated cardinality and cost of an expression.
+    fn estimate(&self, expr: &EclExpression) -> (usize, f64) {
+        let mut scratch = QueryPlan::new("");
+        self.plan_expression(expr, &mut scratch)
+    }
+
+    /// Returns the operation name, estimated cardinality and cost of an
+    /// expression, as reported for it by `plan()`.
+    pub(crate) fn estimate_node(&self, expr: &EclExpression) -> (String, usize, f64) {
+        let mut scratch = QueryPlan::new("");
+        let (estimate, cost) = self.plan_expression(expr, &mut scratch);
+
+        // An expression's own step is added after those of its operands
+        let operation = scratch
+            .steps
+            .pop()
+            .map(|step| step.operation)
+            .unwrap_or_else(|| "Concrete".to_string());
+        (operation, estimate, cost)
+    }
+
     /// Plans a single expression and returns (estimated_cardinality, cost).
+    ///
+    /// The expression's own step is added last and becomes the parent of
+    /// the steps added for its operands.
     fn plan_expression(&self, expr: &EclExpression, plan: &mut QueryPlan) -> (usize, f64) {
+        let first = plan.steps.len();
+        let result = self.plan_node(expr, plan);
+
+        if plan.steps.len() > first {
+            plan.adopt_steps(plan.steps.len() - 1, first);
+        }
+        result
+    }
+
+    /// Adds the steps of an expression and its operands.
+    fn plan_node(&self, expr: &EclExpression, plan: &mut QueryPlan) -> (usize, f64) {
         let expr = expr.unwrap_nested();
 
         match expr {
@@ -403,9 +700,8 @@ impl QueryPlanner {
                 (result_estimate, total_cost)
             }
 
-            EclExpression::MemberOf { refset } => {
-                // Reference sets have variable size; use a conservative estimate
-                let estimate = heuristics::DEFAULT_DESCENDANT_ESTIMATE;
+            EclExpression::MemberOf { refset, .. } => {
+                let estimate = self.estimate_refset(refset);
                 let cost = self.statistics.cost_lookup();
 
                 plan.add_step(QueryStep::new(
@@ -420,7 +716,7 @@ impl QueryPlanner {
 
             EclExpression::Any => {
                 // All concepts - this is very large
-                let estimate = 500_000; // Approximate total SNOMED concepts
+                let estimate = self.statistics.estimated_total_concepts();
                 let cost = self.statistics.cost_descendants(estimate);
 
                 plan.add_step(QueryStep::new("Any", "*", estimate, cost));
@@ -454,14 +750,35 @@ impl QueryPlanner {
             // =========================================================================
             // Advanced ECL Features (Story 10.9)
             // =========================================================================
-            EclExpression::Refined { focus, .. } => {
-                let (focus_estimate, focus_cost) = self.plan_expression(focus, plan);
+            EclExpression::Refined { focus, refinement } => {
+                let focus_estimate = self.estimate_cardinality(focus);
+                let (strategy, lookups, candidates) =
+                    self.refinement_strategy(refinement, focus_estimate);
+
+                // A reverse lookup with a wildcard focus never materialises the focus
+                let focus_cost = if strategy == RefinementStrategy::ReverseLookup
+                    && matches!(focus.unwrap_nested(), EclExpression::Any)
+                {
+                    0.0
+                } else {
+                    self.plan_expression(focus, plan).1
+                };
+
                 // Refinement typically filters to a fraction of the focus
                 let estimate = (focus_estimate as f64 * 0.1).max(1.0) as usize;
-                let cost =
-                    focus_cost + self.statistics.cost_lookup() * (focus_estimate as f64).sqrt();
+                let (description, cost) = match strategy {
+                    RefinementStrategy::ForwardScan => (
+                        format!("with refinement ({})", strategy),
+                        self.statistics.cost_forward_refinement(focus_estimate),
+                    ),
+                    RefinementStrategy::ReverseLookup => (
+                        format!("with refinement ({}, {} lookups)", strategy, lookups),
+                        self.statistics
+                            .cost_reverse_refinement(lookups, candidates.min(focus_estimate)),
+                    ),
+                };
 
-                plan.add_step(QueryStep::new("Refined", "with refinement", estimate, cost));
+                plan.add_step(QueryStep::new("Refined", description, estimate, cost));
 
                 (estimate, focus_cost + cost)
             }
@@ -536,6 +853,42 @@ impl QueryPlanner {
         }
     }
 
+    /// Chooses how a refinement is evaluated.
+    ///
+    /// Returns the strategy, the number of reverse lookups and the estimated
+    /// number of candidates produced by those lookups.
+    fn refinement_strategy(
+        &self,
+        refinement: &snomed_ecl::Refinement,
+        focus_estimate: usize,
+    ) -> (RefinementStrategy, usize, usize) {
+        let lookup = if self.attribute_lookup {
+            indexable_constraints(refinement)
+                .map(|constraint| {
+                    let lookups = self.estimate_cardinality(&constraint.attribute_type)
+                        * self.estimate_cardinality(&constraint.value);
+                    let candidates = lookups
+                        * self.statistics.estimated_concepts_with_attribute(
+                            self.get_focus_concept_id(&constraint.attribute_type),
+                            self.get_focus_concept_id(&constraint.value),
+                        );
+                    (lookups, candidates)
+                })
+                .min()
+        } else {
+            None
+        };
+
+        let (lookups, candidates) = lookup.unwrap_or_default();
+        let strategy = RefinementStrategy::choose(
+            &self.statistics,
+            focus_estimate,
+            lookup.map(|(lookups, _)| lookups),
+            candidates,
+        );
+        (strategy, lookups, candidates)
+    }
+
     /// Extracts the focus concept ID from an expression.
     fn get_focus_concept_id(&self, expr: &EclExpression) -> SctId {
         let expr = expr.unwrap_nested();
@@ -546,40 +899,14 @@ impl QueryPlanner {
     }
 
     /// Generates optimization hints based on query analysis.
+    ///
+    /// AND operand ordering needs no hint: the physical plan already runs
+    /// the most selective operand first.
     fn generate_hints(&self, plan: &mut QueryPlan, expr: &EclExpression) {
-        // Analyze AND expressions for reordering opportunities
-        self.analyze_and_ordering(plan, expr);
-
         // Check for repeated subexpressions (candidates for caching)
         self.analyze_subexpression_reuse(plan, expr);
     }
 
-    /// Analyzes AND expressions to suggest optimal operand ordering.
-    fn analyze_and_ordering(&self, plan: &mut QueryPlan, expr: &EclExpression) {
-        let expr = expr.unwrap_nested();
-
-        if let EclExpression::And(left, right) = expr {
-            let left_estimate = self.estimate_cardinality(left);
-            let right_estimate = self.estimate_cardinality(right);
-
-            // If right operand is smaller, suggest reordering
-            if right_estimate < left_estimate {
-                let savings =
-                    ((left_estimate - right_estimate) as f64) * heuristics::AND_SELECTIVITY_FACTOR;
-                if savings > 1000.0 {
-                    plan.add_hint(format!(
-                        "Consider reordering AND operands: right operand ({} est.) is smaller than left ({} est.)",
-                        right_estimate, left_estimate
-                    ));
-                }
-            }
-
-            // Recursively analyze nested ANDs
-            self.analyze_and_ordering(plan, left);
-            self.analyze_and_ordering(plan, right);
-        }
-    }
-
     /// Analyzes for repeated subexpressions that could benefit from caching.
     fn analyze_subexpression_reuse(&self, plan: &mut QueryPlan, expr: &EclExpression) {
         // Count occurrences of concept references
@@ -660,6 +987,19 @@ impl QueryPlanner {
         }
     }
 
+    /// Estimates the size of a reference set.
+    ///
+    /// Uses the registered refset size for a single refset concept and a
+    /// conservative default otherwise.
+    fn estimate_refset(&self, refset: &EclExpression) -> usize {
+        match refset.unwrap_nested() {
+            EclExpression::ConceptReference { concept_id, .. } => {
+                self.statistics.estimated_refset_members(*concept_id)
+            }
+            _ => heuristics::DEFAULT_DESCENDANT_ESTIMATE,
+        }
+    }
+
     /// Estimates cardinality for an expression without creating a full plan.
     pub fn estimate_cardinality(&self, expr: &EclExpression) -> usize {
         let expr = expr.unwrap_nested();
@@ -715,8 +1055,8 @@ impl QueryPlanner {
                 let right_est = self.estimate_cardinality(right);
                 self.statistics.estimated_minus(left_est, right_est)
             }
-            EclExpression::MemberOf { .. } => heuristics::DEFAULT_DESCENDANT_ESTIMATE,
-            EclExpression::Any => 500_000,
+            EclExpression::MemberOf { refset, .. } => self.estimate_refset(refset),
+            EclExpression::Any => self.statistics.estimated_total_concepts(),
             EclExpression::AlternateIdentifier { .. } => 1,
             EclExpression::Nested(_) => unreachable!("Nested expressions are unwrapped"),
 
@@ -744,6 +1084,21 @@ impl QueryPlanner {
     }
 }
 
+/// Collects the operands of a chain of the same binary operator.
+fn flatten<'e, F>(expr: &'e EclExpression, operands: &mut Vec<&'e EclExpression>, split: F)
+where
+    F: Fn(&'e EclExpression) -> Option<(&'e EclExpression, &'e EclExpression)> + Copy,
+{
+    let expr = expr.unwrap_nested();
+    match split(expr) {
+        Some((left, right)) => {
+            flatten(left, operands, split);
+            flatten(right, operands, split);
+        }
+        None => operands.push(expr),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -898,6 +1253,142 @@ mod tests {
         assert!(display.contains("1000"));
     }
 
+    #[test]
+    fn test_refinement_strategy_choose() {
+        let stats = StatisticsService::new();
+
+        assert_eq!(
+            RefinementStrategy::choose(&stats, 400_000, Some(1), 50),
+            RefinementStrategy::ReverseLookup
+        );
+        assert_eq!(
+            RefinementStrategy::choose(&stats, 10, Some(1), 50),
+            RefinementStrategy::ForwardScan
+        );
+        // Not answerable from a reverse index
+        assert_eq!(
+            RefinementStrategy::choose(&stats, 400_000, None, 0),
+            RefinementStrategy::ForwardScan
+        );
+    }
+
+    #[test]
+    fn test_plan_refinement_strategy() {
+        let ecl = "* : 246075003 = 387517004";
+        let expr = parse_ecl(ecl);
+
+        // Without a reverse index the focus must be scanned
+        let plan = QueryPlanner::new().plan(ecl, &expr);
+        let refined = plan
+            .steps
+            .iter()
+            .find(|s| s.operation == "Refined")
+            .unwrap();
+        assert!(refined.expression.contains("forward scan"));
+        assert!(plan.steps.iter().any(|s| s.operation == "Any"));
+
+        // With one, the wildcard focus is never materialised
+        let plan = QueryPlanner::new()
+            .with_attribute_lookup(true)
+            .plan(ecl, &expr);
+        let refined = plan
+            .steps
+            .iter()
+            .find(|s| s.operation == "Refined")
+            .unwrap();
+        assert!(refined.expression.contains("reverse lookup, 1 lookups"));
+        assert!(!plan.steps.iter().any(|s| s.operation == "Any"));
+    }
+
+    #[test]
+    fn test_plan_refinement_cardinality_zero_scans() {
+        let ecl = "* : [0..1] 246075003 = 387517004";
+        let expr = parse_ecl(ecl);
+
+        let plan = QueryPlanner::new()
+            .with_attribute_lookup(true)
+            .plan(ecl, &expr);
+        let refined = plan
+            .steps
+            .iter()
+            .find(|s| s.operation == "Refined")
+            .unwrap();
+        assert!(refined.expression.contains("forward scan"));
+    }
+
+    #[test]
+    fn test_physical_plan_orders_and_operands() {
+        let planner = QueryPlanner::new();
+        let expr = parse_ecl("<< 404684003 AND (^ 700043003 AND 73211009)");
+        let plan = planner.physical_plan(&expr);
+
+        // The chain is flattened and the single concept drives the intersection
+        assert_eq!(plan.operator, PlanOperator::Intersect);
+        assert_eq!(plan.children.len(), 3);
+        assert_eq!(plan.children[0].operator, PlanOperator::Scan);
+        assert_eq!(plan.children[0].expression.to_string(), "73211009");
+
+        // The large hierarchy operand is checked per concept, the refset is not
+        let check = plan
+            .children
+            .iter()
+            .find(|c| c.operator == PlanOperator::MembershipCheck)
+            .unwrap();
+        assert_eq!(check.expression.to_string(), "<< 404684003");
+        assert!(plan
+            .children
+            .iter()
+            .any(|c| c.operator == PlanOperator::Scan
+                && matches!(c.expression, EclExpression::MemberOf { .. })));
+    }
+
+    #[test]
+    fn test_physical_plan_union_and_difference() {
+        let planner = QueryPlanner::new();
+
+        let plan = planner.physical_plan(&parse_ecl("1 OR (2 OR 3)"));
+        assert_eq!(plan.operator, PlanOperator::Union);
+        assert_eq!(plan.children.len(), 3);
+
+        let plan = planner.physical_plan(&parse_ecl("73211009 MINUS << 404684003"));
+        assert_eq!(plan.operator, PlanOperator::Difference);
+        assert_eq!(plan.children[0].operator, PlanOperator::Scan);
+        assert_eq!(plan.children[1].operator, PlanOperator::MembershipCheck);
+
+        // Both sides large: the subtracted operand is materialised
+        let plan = planner.physical_plan(&parse_ecl("<< 404684003 MINUS << 64572001"));
+        assert_eq!(plan.children[1].operator, PlanOperator::Scan);
+    }
+
+    #[test]
+    fn test_physical_plan_display() {
+        let planner = QueryPlanner::new();
+        let expr = parse_ecl("73211009 AND << 404684003");
+
+        let plan = planner.plan("73211009 AND << 404684003", &expr);
+        let output = format!("{}", plan);
+        assert!(output.contains("Physical Plan:"));
+        assert!(output.contains("MembershipCheck: << 404684003"));
+    }
+
+    #[test]
+    fn test_estimates_use_collected_statistics() {
+        let mut statistics = StatisticsService::new();
+        statistics.register_refset_size(700043003, 42);
+        statistics.register_total_concepts(1_000);
+        let planner = QueryPlanner::with_statistics(statistics);
+
+        assert_eq!(planner.estimate_cardinality(&parse_ecl("^ 700043003")), 42);
+        assert_eq!(planner.estimate_cardinality(&parse_ecl("*")), 1_000);
+        assert_eq!(
+            planner.estimate_cardinality(&parse_ecl("^ 123456")),
+            heuristics::DEFAULT_DESCENDANT_ESTIMATE
+        );
+
+        let plan = planner.plan("^ 700043003", &parse_ecl("^ 700043003"));
+        assert_eq!(plan.estimated_total, 42);
+    }
+
     #[test]
     fn test_total_cost() {
         let planner = QueryPlanner::new();
@@ -0,0 +1,595 @@
+//! Profiled query execution (EXPLAIN ANALYZE).
+//!
+//! [`EclExecutor::execute_profiled`](crate::EclExecutor::execute_profiled)
+//! executes a query and records, for every evaluated node, the planner's
+//! estimates next to the actual row count, elapsed time, number of store
+//! calls and intermediate cache outcome. Comparing the two shows which part
+//! of a long expression is slow and where the statistics are off.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use snomed_ecl::{EclExpression, SctId};
+
+use crate::error::EclResult;
+use crate::result::QueryResult;
+use crate::traits::{
+    ConceptInfo, ConcreteRelationshipInfo, DescriptionInfo, EclQueryable, HistoryAssociationType,
+    LanguageRefsetMember, RefsetMember, RelationshipInfo,
+};
+
+/// Cache outcome of a profiled node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheStatus {
+    /// The node's result is not cached (caching disabled for this node).
+    Uncached,
+    /// The result was served from the cache.
+    Hit,
+    /// The result was computed and written to the cache.
+    Miss,
+}
+
+impl fmt::Display for CacheStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Uncached => write!(f, "uncached"),
+            Self::Hit => write!(f, "cache hit"),
+            Self::Miss => write!(f, "cache miss"),
+        }
+    }
+}
+
+/// A node of a profiled query with estimated and actual figures.
+///
+/// Elapsed time and store calls include the node's children.
+#[derive(Debug, Clone)]
+pub struct ProfileNode {
+    /// Type of operation (e.g., "DescendantsOrSelf", "Intersect", "MembershipCheck").
+    ///
+    /// Refinements report the strategy they took at run time, e.g.
+    /// "Refined (forward scan)", which may differ from the query plan's.
+    pub operation: String,
+    /// The ECL subexpression evaluated by this node.
+    pub expression: String,
+    /// Estimated number of concepts produced.
+    pub estimated_cardinality: usize,
+    /// Estimated cost of this node including its children (in milliseconds).
+    pub estimated_cost: f64,
+    /// Actual number of concepts produced.
+    pub actual_rows: usize,
+    /// Wall-clock time spent in this node.
+    pub elapsed: Duration,
+    /// Number of calls made to the store by this node.
+    pub store_calls: usize,
+    /// Whether the result came from the cache.
+    pub cache: CacheStatus,
+    /// Nodes evaluated on behalf of this node, in execution order.
+    pub children: Vec<ProfileNode>,
+}
+
+impl ProfileNode {
+    /// Returns the time spent in this node excluding its children.
+    pub fn self_elapsed(&self) -> Duration {
+        let children: Duration = self.children.iter().map(|c| c.elapsed).sum();
+        self.elapsed.saturating_sub(children)
+    }
+
+    /// Returns this node and all its descendants, depth-first.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let slowest = profile
+    ///     .root
+    ///     .nodes()
+    ///     .into_iter()
+    ///     .max_by_key(|node| node.self_elapsed())
+    ///     .unwrap();
+    /// println!("Slowest: {} ({:?})", slowest.expression, slowest.self_elapsed());
+    /// ```
+    pub fn nodes(&self) -> Vec<&ProfileNode> {
+        let mut nodes = vec![self];
+        for child in &self.children {
+            nodes.extend(child.nodes());
+        }
+        nodes
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        write!(f, "{:indent$}", "", indent = depth * 2)?;
+        write!(
+            f,
+            "{}: {} (est. {} concepts, {:.4}ms) (actual {} concepts, {:.3}ms, {} store calls",
+            self.operation,
+            self.expression,
+            self.estimated_cardinality,
+            self.estimated_cost,
+            self.actual_rows,
+            self.elapsed.as_secs_f64() * 1000.0,
+            self.store_calls
+        )?;
+        if self.cache != CacheStatus::Uncached {
+            write!(f, ", {}", self.cache)?;
+        }
+        writeln!(f, ")")?;
+
+        for child in &self.children {
+            child.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ProfileNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+/// Result of [`EclExecutor::execute_profiled`](crate::EclExecutor::execute_profiled).
+///
+/// # Example
+///
+/// ```ignore
+/// let profile = executor.execute_profiled("<< 404684003 AND ^ 700043003")?;
+///
+/// println!("{} concepts", profile.result.count());
+/// println!("{}", profile);
+/// // Profile for: << 404684003 AND ^ 700043003
+/// // Total: 120 concepts in 4.210ms, 812 store calls
+/// //
+/// // Intersect: ... (est. 1000 concepts, ...) (actual 120 concepts, 4.180ms, 812 store calls)
+/// //   MemberOf: ^ 700043003 (...) (actual 150 concepts, 0.020ms, 1 store calls)
+/// //   MembershipCheck: << 404684003 (...) (actual 120 concepts, 4.100ms, 811 store calls)
+/// ```
+#[derive(Debug, Clone)]
+pub struct QueryProfile {
+    /// The original ECL expression.
+    pub ecl: String,
+    /// The query result, identical to what `execute()` returns.
+    pub result: QueryResult,
+    /// The profiled root node.
+    pub root: ProfileNode,
+}
+
+impl QueryProfile {
+    /// Returns the total number of store calls made by the query.
+    pub fn store_calls(&self) -> usize {
+        self.root.store_calls
+    }
+}
+
+impl fmt::Display for QueryProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Profile for: {}", self.ecl)?;
+        writeln!(
+            f,
+            "Total: {} concepts in {:.3}ms, {} store calls",
+            self.result.count(),
+            self.result.stats.duration.as_secs_f64() * 1000.0,
+            self.store_calls()
+        )?;
+        writeln!(f)?;
+        write!(f, "{}", self.root)
+    }
+}
+
+/// Records profile nodes while a query executes.
+///
+/// Nodes are recorded in a stack of frames: entering a node pushes a frame
+/// collecting its children, leaving it pops the frame. Profiled execution is
+/// always sequential, so the stack matches the call tree.
+pub(crate) struct Profiler<'a> {
+    store: &'a CountingStore<'a>,
+    frames: Mutex<Vec<Frame>>,
+}
+
+/// A node being computed: the children recorded so far and, if it was
+/// overridden by [`Profiler::set_operation`], its operation.
+#[derive(Default)]
+struct Frame {
+    children: Vec<ProfileNode>,
+    operation: Option<String>,
+}
+
+impl<'a> Profiler<'a> {
+    /// Creates a profiler that reads call counts from `store`.
+    pub(crate) fn new(store: &'a CountingStore<'a>) -> Self {
+        Self {
+            store,
+            frames: Mutex::new(vec![Frame::default()]),
+        }
+    }
+
+    /// Runs `compute` as a profiled node.
+    ///
+    /// The node is only recorded if `compute` succeeds.
+    pub(crate) fn record<F>(
+        &self,
+        operation: &str,
+        expression: &EclExpression,
+        estimate: (usize, f64),
+        compute: F,
+    ) -> EclResult<(HashSet<SctId>, usize)>
+    where
+        F: FnOnce() -> EclResult<(HashSet<SctId>, usize)>,
+    {
+        self.frames().push(Frame::default());
+        let start = Instant::now();
+        let calls = self.store.calls();
+
+        let result = compute();
+
+        let frame = self.frames().pop().unwrap_or_default();
+        if let Ok((concepts, _)) = &result {
+            self.push(ProfileNode {
+                operation: frame.operation.unwrap_or_else(|| operation.to_string()),
+                expression: expression.to_string(),
+                estimated_cardinality: estimate.0,
+                estimated_cost: estimate.1,
+                actual_rows: concepts.len(),
+                elapsed: start.elapsed(),
+                store_calls: self.store.calls() - calls,
+                cache: CacheStatus::Uncached,
+                children: frame.children,
+            });
+        }
+        result
+    }
+
+    /// Records a node whose result was served from the cache.
+    pub(crate) fn record_cache_hit(
+        &self,
+        operation: &str,
+        expression: &EclExpression,
+        estimate: (usize, f64),
+        rows: usize,
+    ) {
+        self.push(ProfileNode {
+            operation: operation.to_string(),
+            expression: expression.to_string(),
+            estimated_cardinality: estimate.0,
+            estimated_cost: estimate.1,
+            actual_rows: rows,
+            elapsed: Duration::ZERO,
+            store_calls: 0,
+            cache: CacheStatus::Hit,
+            children: Vec::new(),
+        });
+    }
+
+    /// Replaces the planner's operation of the node currently being computed.
+    ///
+    /// Used when the node decides how to evaluate itself at run time, so the
+    /// profile shows what was done rather than what was estimated.
+    pub(crate) fn set_operation(&self, operation: String) {
+        if let Some(frame) = self.frames().last_mut() {
+            frame.operation = Some(operation);
+        }
+    }
+
+    /// Marks the most recently recorded node as computed and cached.
+    pub(crate) fn mark_cache_miss(&self) {
+        if let Some(node) = self
+            .frames()
+            .last_mut()
+            .and_then(|frame| frame.children.last_mut())
+        {
+            node.cache = CacheStatus::Miss;
+        }
+    }
+
+    /// Returns the root node, if one was recorded.
+    pub(crate) fn into_root(self) -> Option<ProfileNode> {
+        self.frames
+            .into_inner()
+            .unwrap_or_else(|e| e.into_inner())
+            .into_iter()
+            .next()
+            .and_then(|mut roots| roots.children.pop())
+    }
+
+    fn push(&self, node: ProfileNode) {
+        if let Some(frame) = self.frames().last_mut() {
+            frame.children.push(node);
+        }
+    }
+
+    fn frames(&self) -> std::sync::MutexGuard<'_, Vec<Frame>> {
+        self.frames.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Store wrapper that counts the calls made through it.
+pub(crate) struct CountingStore<'a> {
+    inner: &'a dyn EclQueryable,
+    calls: AtomicUsize,
+}
+
+impl<'a> CountingStore<'a> {
+    /// Wraps a store.
+    pub(crate) fn new(inner: &'a dyn EclQueryable) -> Self {
+        Self {
+            inner,
+            calls: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of calls made so far.
+    pub(crate) fn calls(&self) -> usize {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    fn count(&self) -> &dyn EclQueryable {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.inner
+    }
+}
+
+impl EclQueryable for CountingStore<'_> {
+    fn get_children(&self, concept_id: SctId) -> Vec<SctId> {
+        self.count().get_children(concept_id)
+    }
+
+    fn get_parents(&self, concept_id: SctId) -> Vec<SctId> {
+        self.count().get_parents(concept_id)
+    }
+
+    fn has_concept(&self, concept_id: SctId) -> bool {
+        self.count().has_concept(concept_id)
+    }
+
+    fn all_concept_ids(&self) -> Box<dyn Iterator<Item = SctId> + '_> {
+        self.count().all_concept_ids()
+    }
+
+    fn get_refset_members(&self, refset_id: SctId) -> Vec<SctId> {
+        self.count().get_refset_members(refset_id)
+    }
+
+    fn get_attributes(&self, concept_id: SctId) -> Vec<RelationshipInfo> {
+        self.count().get_attributes(concept_id)
+    }
+
+    fn get_concepts_with_attribute(
+        &self,
+        attribute_type_id: SctId,
+        target_id: SctId,
+    ) -> Vec<SctId> {
+        self.count()
+            .get_concepts_with_attribute(attribute_type_id, target_id)
+    }
+
+    fn supports_attribute_lookup(&self) -> bool {
+        self.inner.supports_attribute_lookup()
+    }
+
+    fn get_concrete_values(&self, concept_id: SctId) -> Vec<ConcreteRelationshipInfo> {
+        self.count().get_concrete_values(concept_id)
+    }
+
+    fn get_descriptions(&self, concept_id: SctId) -> Vec<DescriptionInfo> {
+        self.count().get_descriptions(concept_id)
+    }
+
+    fn get_preferred_term(&self, concept_id: SctId) -> Option<String> {
+        self.count().get_preferred_term(concept_id)
+    }
+
+    fn get_historical_associations(&self, concept_id: SctId) -> Vec<SctId> {
+        self.count().get_historical_associations(concept_id)
+    }
+
+    fn is_concept_active(&self, concept_id: SctId) -> bool {
+        self.count().is_concept_active(concept_id)
+    }
+
+    fn get_concept_module(&self, concept_id: SctId) -> Option<SctId> {
+        self.count().get_concept_module(concept_id)
+    }
+
+    fn get_concept_info(&self, concept_id: SctId) -> Option<ConceptInfo> {
+        self.count().get_concept_info(concept_id)
+    }
+
+    fn get_description_language_refsets(&self, description_id: SctId) -> Vec<LanguageRefsetMember> {
+        self.count()
+            .get_description_language_refsets(description_id)
+    }
+
+    fn get_refset_member_rows(&self, refset_id: SctId) -> Vec<RefsetMember> {
+        self.count().get_refset_member_rows(refset_id)
+    }
+
+    fn get_semantic_tag(&self, concept_id: SctId) -> Option<String> {
+        self.count().get_semantic_tag(concept_id)
+    }
+
+    fn get_concept_effective_time(&self, concept_id: SctId) -> Option<u32> {
+        self.count().get_concept_effective_time(concept_id)
+    }
+
+    fn is_concept_primitive(&self, concept_id: SctId) -> Option<bool> {
+        self.count().is_concept_primitive(concept_id)
+    }
+
+    fn get_inbound_relationships(&self, concept_id: SctId) -> Vec<RelationshipInfo> {
+        self.count().get_inbound_relationships(concept_id)
+    }
+
+    fn get_historical_associations_by_type(
+        &self,
+        concept_id: SctId,
+        association_type: HistoryAssociationType,
+    ) -> Vec<SctId> {
+        self.count()
+            .get_historical_associations_by_type(concept_id, association_type)
+    }
+
+    fn get_historical_associations_by_refset(
+        &self,
+        concept_id: SctId,
+    ) -> HashMap<SctId, Vec<SctId>> {
+        self.count().get_historical_associations_by_refset(concept_id)
+    }
+
+    fn resolve_alternate_identifier(&self, scheme: &str, identifier: &str) -> Option<SctId> {
+        self.count()
+            .resolve_alternate_identifier(scheme, identifier)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockStore;
+
+    impl EclQueryable for MockStore {
+        fn get_children(&self, concept_id: SctId) -> Vec<SctId> {
+            if concept_id == 1 {
+                vec![2]
+            } else {
+                Vec::new()
+            }
+        }
+
+        fn get_parents(&self, concept_id: SctId) -> Vec<SctId> {
+            if concept_id == 2 {
+                vec![1]
+            } else {
+                Vec::new()
+            }
+        }
+
+        fn has_concept(&self, concept_id: SctId) -> bool {
+            concept_id == 1 || concept_id == 2
+        }
+
+        fn all_concept_ids(&self) -> Box<dyn Iterator<Item = SctId> + '_> {
+            Box::new([1, 2].into_iter())
+        }
+
+        fn get_refset_members(&self, _refset_id: SctId) -> Vec<SctId> {
+            Vec::new()
+        }
+    }
+
+    fn concept(id: SctId) -> EclExpression {
+        EclExpression::ConceptReference {
+            concept_id: id,
+            term: None,
+        }
+    }
+
+    #[test]
+    fn test_counting_store() {
+        let store = MockStore;
+        let counting = CountingStore::new(&store);
+
+        assert!(counting.has_concept(1));
+        assert_eq!(counting.get_children(1), vec![2]);
+        // Default methods are delegated too, so store overrides still apply
+        assert!(counting.is_concept_active(2));
+        assert_eq!(counting.calls(), 3);
+    }
+
+    #[test]
+    fn test_profiler_records_tree() {
+        let store = MockStore;
+        let counting = CountingStore::new(&store);
+        let profiler = Profiler::new(&counting);
+
+        profiler
+            .record("Union", &concept(1), (2, 0.5), || {
+                let (left, _) = profiler.record("Self", &concept(1), (1, 0.1), || {
+                    counting.has_concept(1);
+                    Ok((HashSet::from([1]), 1))
+                })?;
+                profiler.record_cache_hit("Self", &concept(2), (1, 0.1), 1);
+                profiler.mark_cache_miss();
+                Ok((left.into_iter().chain([2]).collect(), 2))
+            })
+            .unwrap();
+
+        let root = profiler.into_root().unwrap();
+        assert_eq!(root.operation, "Union");
+        assert_eq!(root.actual_rows, 2);
+        assert_eq!(root.store_calls, 1);
+        assert_eq!(root.children.len(), 2);
+        assert_eq!(root.children[0].store_calls, 1);
+        assert_eq!(root.children[0].cache, CacheStatus::Uncached);
+        assert_eq!(root.children[1].cache, CacheStatus::Miss);
+        assert_eq!(root.nodes().len(), 3);
+        assert!(root.self_elapsed() <= root.elapsed);
+    }
+
+    #[test]
+    fn test_profiler_set_operation() {
+        let store = MockStore;
+        let counting = CountingStore::new(&store);
+        let profiler = Profiler::new(&counting);
+
+        profiler
+            .record("Refined", &concept(1), (1, 0.1), || {
+                profiler.record("Self", &concept(1), (1, 0.1), || {
+                    Ok((HashSet::from([1]), 1))
+                })?;
+                profiler.set_operation("Refined (forward scan)".to_string());
+                Ok((HashSet::from([1]), 1))
+            })
+            .unwrap();
+
+        let root = profiler.into_root().unwrap();
+        assert_eq!(root.operation, "Refined (forward scan)");
+        assert_eq!(root.children[0].operation, "Self");
+    }
+
+    #[test]
+    fn test_profiler_skips_failed_nodes() {
+        let store = MockStore;
+        let counting = CountingStore::new(&store);
+        let profiler = Profiler::new(&counting);
+
+        let result = profiler.record("Self", &concept(3), (1, 0.1), || {
+            Err(crate::error::EclExecutorError::ConceptNotFound(3))
+        });
+        assert!(result.is_err());
+        assert!(profiler.into_root().is_none());
+    }
+
+    #[test]
+    fn test_profile_node_display() {
+        let node = ProfileNode {
+            operation: "Intersect".to_string(),
+            expression: "1 AND 2".to_string(),
+            estimated_cardinality: 1,
+            estimated_cost: 0.01,
+            actual_rows: 0,
+            elapsed: Duration::from_micros(1500),
+            store_calls: 3,
+            cache: CacheStatus::Miss,
+            children: vec![ProfileNode {
+                operation: "Self".to_string(),
+                expression: "1".to_string(),
+                estimated_cardinality: 1,
+                estimated_cost: 0.001,
+                actual_rows: 1,
+                elapsed: Duration::from_micros(10),
+                store_calls: 1,
+                cache: CacheStatus::Uncached,
+                children: Vec::new(),
+            }],
+        };
+
+        let output = node.to_string();
+        assert!(output.starts_with(
+            "Intersect: 1 AND 2 (est. 1 concepts, 0.0100ms) (actual 0 concepts, 1.500ms, 3 store calls, cache miss)"
+        ));
+        assert!(output.contains("\n  Self: 1 (est. 1 concepts"));
+        assert!(!output.contains("uncached"));
+    }
+}
@@ -0,0 +1,791 @@
+//! Compiled attribute refinements.
+//!
+//! A refinement such as `<< 404684003 : 363698007 = << 39057004` used to
+//! re-execute its attribute-type and value sub-expressions for every focus
+//! concept. [`CompiledRefinement`] resolves those sub-expressions once per
+//! query, so checking a focus concept only costs a `get_attributes` call and
+//! a few set lookups. Ancestor sets needed by the `<` and `>` operators are
+//! computed once and shared between focus concepts.
+//!
+//! When the store has a reverse attribute index, a compiled refinement can
+//! also produce its candidate concepts directly from the value set (see
+//! [`AttributeLookup`]) instead of scanning every focus concept.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use snomed_ecl::{
+    AttributeConstraint, AttributeGroup, Cardinality, ComparisonOperator, ConcreteValue,
+    EclExpression, Refinement, RefinementOperator, SctId,
+};
+
+use crate::error::EclResult;
+use crate::guard::ExecutionGuard;
+use crate::statistics::StatisticsService;
+use crate::traits::{ConcreteValueRef, EclQueryable, RelationshipInfo};
+use crate::traverser::HierarchyTraverser;
+
+/// A set of concepts that may be the wildcard `*`.
+///
+/// Keeping the wildcard symbolic avoids materialising every concept in the
+/// store for constraints such as `* = *`.
+enum ConceptMatcher {
+    /// Matches any concept.
+    Any,
+    /// Matches the concepts in the set.
+    Set(HashSet<SctId>),
+}
+
+impl ConceptMatcher {
+    /// Resolves an expression, keeping `*` symbolic.
+    fn resolve<F>(expression: &EclExpression, resolve: &mut F) -> EclResult<Self>
+    where
+        F: FnMut(&EclExpression) -> EclResult<HashSet<SctId>>,
+    {
+        match expression {
+            EclExpression::Any => Ok(Self::Any),
+            other => Ok(Self::Set(resolve(other)?)),
+        }
+    }
+
+    #[inline]
+    fn contains(&self, concept_id: SctId) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Set(set) => set.contains(&concept_id),
+        }
+    }
+}
+
+/// How a relationship's destination (or concrete value) is tested.
+enum ValueMatcher<'e> {
+    /// `=`: the destination is in the value set.
+    ///
+    /// `<<` is evaluated the same way.
+    Equal(ConceptMatcher),
+    /// `!=`: the destination is not in the value set.
+    NotEqual(ConceptMatcher),
+    /// `<`: one of the destination's ancestors is in the value set.
+    DescendantOf(ConceptMatcher),
+    /// `>` and `>>`: the destination is in the precomputed ancestor closure
+    /// of the value set.
+    AncestorOf(HashSet<SctId>),
+    /// Concrete value comparison such as `>= #10`.
+    Concrete {
+        value: &'e ConcreteValue,
+        operator: ComparisonOperator,
+    },
+}
+
+/// An attribute constraint with its sub-expressions resolved.
+struct CompiledConstraint<'e> {
+    cardinality: Option<&'e Cardinality>,
+    reverse: bool,
+    /// Every matching concept has an outbound relationship matched by this constraint.
+    indexable: bool,
+    attribute_types: ConceptMatcher,
+    value: ValueMatcher<'e>,
+}
+
+/// An attribute group with its constraints compiled.
+struct CompiledGroup<'e> {
+    cardinality: Option<&'e Cardinality>,
+    has_reverse: bool,
+    constraints: Vec<CompiledConstraint<'e>>,
+}
+
+/// A refinement compiled once per query and evaluated per focus concept.
+///
+/// The compiled form is `Sync`, so it can be shared between rayon workers.
+pub(crate) struct CompiledRefinement<'e> {
+    ungrouped: Vec<CompiledConstraint<'e>>,
+    groups: Vec<CompiledGroup<'e>>,
+    /// Memoised `get_ancestors` results for relationship destinations.
+    ancestors: Mutex<HashMap<SctId, Arc<HashSet<SctId>>>>,
+}
+
+impl<'e> CompiledRefinement<'e> {
+    /// Compiles a refinement.
+    ///
+    /// # Arguments
+    ///
+    /// * `refinement` - The refinement to compile
+    /// * `traverser` - Traverser used to precompute ancestor closures
+    /// * `resolve` - Executes a sub-expression and returns its concept set
+    pub(crate) fn compile<F>(
+        refinement: &'e Refinement,
+        traverser: &HierarchyTraverser<'_>,
+        mut resolve: F,
+    ) -> EclResult<Self>
+    where
+        F: FnMut(&EclExpression) -> EclResult<HashSet<SctId>>,
+    {
+        let ungrouped = refinement
+            .ungrouped
+            .iter()
+            .map(|constraint| {
+                compile_constraint(
+                    constraint,
+                    is_indexable(constraint),
+                    traverser,
+                    &mut resolve,
+                )
+            })
+            .collect::<EclResult<Vec<_>>>()?;
+
+        let groups = refinement
+            .groups
+            .iter()
+            .map(|group| {
+                let required = group_requires_match(group);
+                Ok(CompiledGroup {
+                    cardinality: group.cardinality.as_ref(),
+                    has_reverse: group.constraints.iter().any(|c| c.reverse),
+                    constraints: group
+                        .constraints
+                        .iter()
+                        .map(|constraint| {
+                            compile_constraint(
+                                constraint,
+                                required && is_indexable(constraint),
+                                traverser,
+                                &mut resolve,
+                            )
+                        })
+                        .collect::<EclResult<Vec<_>>>()?,
+                })
+            })
+            .collect::<EclResult<Vec<_>>>()?;
+
+        Ok(Self {
+            ungrouped,
+            groups,
+            ancestors: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Returns the cheapest reverse attribute lookup for this refinement.
+    ///
+    /// Returns `None` if no constraint can be answered from
+    /// [`EclQueryable::get_concepts_with_attribute`], for example because
+    /// its attribute type or value is `*` or its cardinality allows zero.
+    pub(crate) fn attribute_lookup(&self) -> Option<AttributeLookup<'_>> {
+        self.ungrouped
+            .iter()
+            .chain(self.groups.iter().flat_map(|g| g.constraints.iter()))
+            .filter(|constraint| constraint.indexable)
+            .filter_map(
+                |constraint| match (&constraint.attribute_types, &constraint.value) {
+                    (
+                        ConceptMatcher::Set(attribute_types),
+                        ValueMatcher::Equal(ConceptMatcher::Set(values)),
+                    ) => Some(AttributeLookup {
+                        attribute_types,
+                        values,
+                    }),
+                    _ => None,
+                },
+            )
+            .min_by_key(AttributeLookup::lookups)
+    }
+
+    /// Checks a single concept against the refinement.
+    ///
+    /// Returns whether the concept matches and the number of outbound
+    /// relationships inspected.
+    pub(crate) fn matches(
+        &self,
+        concept_id: SctId,
+        store: &dyn EclQueryable,
+        traverser: &HierarchyTraverser<'_>,
+    ) -> EclResult<(bool, usize)> {
+        traverser.guard().check()?;
+
+        // Get both outbound and inbound relationships (lazily, only if needed)
+        let outbound_attrs = store.get_attributes(concept_id);
+        let mut inbound_attrs: Option<Vec<RelationshipInfo>> = None;
+        let inspected = outbound_attrs.len();
+
+        // Check ungrouped attribute constraints
+        for constraint in &self.ungrouped {
+            let attrs: &[RelationshipInfo] = if constraint.reverse {
+                inbound_attrs.get_or_insert_with(|| store.get_inbound_relationships(concept_id))
+            } else {
+                &outbound_attrs
+            };
+
+            if !self.constraint_matches(concept_id, constraint, attrs, store, traverser)? {
+                return Ok((false, inspected));
+            }
+        }
+
+        // Check grouped attribute constraints
+        for group in &self.groups {
+            let attrs: &[RelationshipInfo] = if group.has_reverse {
+                inbound_attrs.get_or_insert_with(|| store.get_inbound_relationships(concept_id))
+            } else {
+                &outbound_attrs
+            };
+
+            if !self.group_matches(concept_id, group, attrs, store, traverser)? {
+                return Ok((false, inspected));
+            }
+        }
+
+        Ok((true, inspected))
+    }
+
+    /// Evaluates a single attribute constraint against a concept's attributes.
+    fn constraint_matches(
+        &self,
+        concept_id: SctId,
+        constraint: &CompiledConstraint<'_>,
+        attributes: &[RelationshipInfo],
+        store: &dyn EclQueryable,
+        traverser: &HierarchyTraverser<'_>,
+    ) -> EclResult<bool> {
+        let matching_count = match &constraint.value {
+            ValueMatcher::Concrete { value, operator } => store
+                .get_concrete_values(concept_id)
+                .iter()
+                .filter(|rel| {
+                    constraint.attribute_types.contains(rel.type_id)
+                        && compare_concrete_values(&rel.value, value, *operator)
+                })
+                .count(),
+            value => {
+                let mut count = 0;
+                for rel in attributes {
+                    if constraint.attribute_types.contains(rel.type_id)
+                        && self.value_matches(value, rel.destination_id, traverser)?
+                    {
+                        count += 1;
+                    }
+                }
+                count
+            }
+        };
+
+        // Check cardinality
+        Ok(match constraint.cardinality {
+            Some(card) => card.matches(matching_count),
+            // No cardinality means at least one match required
+            None => matching_count > 0,
+        })
+    }
+
+    /// Tests a relationship destination against a compiled value.
+    fn value_matches(
+        &self,
+        value: &ValueMatcher<'_>,
+        destination_id: SctId,
+        traverser: &HierarchyTraverser<'_>,
+    ) -> EclResult<bool> {
+        Ok(match value {
+            ValueMatcher::Equal(values) => values.contains(destination_id),
+            ValueMatcher::NotEqual(values) => !values.contains(destination_id),
+            ValueMatcher::DescendantOf(values) => {
+                // Value must be a proper descendant
+                self.ancestors_of(destination_id, traverser)?
+                    .iter()
+                    .any(|&a| values.contains(a))
+            }
+            ValueMatcher::AncestorOf(closure) => closure.contains(&destination_id),
+            ValueMatcher::Concrete { .. } => false,
+        })
+    }
+
+    /// Evaluates an attribute group against a concept's attributes.
+    fn group_matches(
+        &self,
+        concept_id: SctId,
+        group: &CompiledGroup<'_>,
+        attributes: &[RelationshipInfo],
+        store: &dyn EclQueryable,
+        traverser: &HierarchyTraverser<'_>,
+    ) -> EclResult<bool> {
+        // Group constraints must be satisfied within the same relationship group
+        // Get unique group numbers (excluding 0 which is ungrouped)
+        let group_numbers: HashSet<u16> = attributes
+            .iter()
+            .filter(|r| r.group > 0)
+            .map(|r| r.group)
+            .collect();
+
+        if group_numbers.is_empty() && !group.constraints.is_empty() {
+            // No groups and we have constraints - check if cardinality allows zero
+            return Ok(group.cardinality.is_some_and(|card| card.matches(0)));
+        }
+
+        let mut matching_groups = 0;
+        'groups: for group_num in group_numbers {
+            let group_attrs: Vec<RelationshipInfo> = attributes
+                .iter()
+                .filter(|r| r.group == group_num)
+                .cloned()
+                .collect();
+
+            // Check if all constraints are satisfied within this group
+            for constraint in &group.constraints {
+                if !self.constraint_matches(concept_id, constraint, &group_attrs, store, traverser)? {
+                    continue 'groups;
+                }
+            }
+            matching_groups += 1;
+        }
+
+        // Check group cardinality
+        Ok(match group.cardinality {
+            Some(card) => card.matches(matching_groups),
+            // No cardinality means at least one matching group required
+            None => matching_groups > 0,
+        })
+    }
+
+    /// Returns the ancestors of a concept, computing them at most once per query.
+    fn ancestors_of(
+        &self,
+        concept_id: SctId,
+        traverser: &HierarchyTraverser<'_>,
+    ) -> EclResult<Arc<HashSet<SctId>>> {
+        if let Some(ancestors) = self.cached_ancestors().get(&concept_id) {
+            return Ok(Arc::clone(ancestors));
+        }
+
+        // Traverse without holding the lock so parallel workers don't serialise
+        let ancestors = Arc::new(traverser.try_get_ancestors(concept_id)?);
+        Ok(self
+            .cached_ancestors()
+            .entry(concept_id)
+            .or_insert(ancestors)
+            .clone())
+    }
+
+    fn cached_ancestors(&self) -> std::sync::MutexGuard<'_, HashMap<SctId, Arc<HashSet<SctId>>>> {
+        // The cache only ever holds complete entries, so a poisoned lock is still usable
+        self.ancestors.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Attribute type/value pairs that every concept matching a refinement must have.
+///
+/// Looking these pairs up with [`EclQueryable::get_concepts_with_attribute`]
+/// yields a superset of the matching concepts, which is then checked against
+/// the full refinement.
+pub(crate) struct AttributeLookup<'a> {
+    attribute_types: &'a HashSet<SctId>,
+    values: &'a HashSet<SctId>,
+}
+
+impl AttributeLookup<'_> {
+    /// Returns the number of reverse index lookups needed.
+    pub(crate) fn lookups(&self) -> usize {
+        self.attribute_types.len() * self.values.len()
+    }
+
+    /// Estimates the number of candidate concepts the lookups will return.
+    pub(crate) fn estimated_candidates(&self, statistics: &StatisticsService) -> usize {
+        self.attribute_types
+            .iter()
+            .flat_map(|&type_id| {
+                self.values.iter().map(move |&value_id| {
+                    statistics.estimated_concepts_with_attribute(type_id, value_id)
+                })
+            })
+            .sum()
+    }
+
+    /// Looks up the candidate concepts in the store's reverse index.
+    pub(crate) fn candidates(
+        &self,
+        store: &dyn EclQueryable,
+        guard: &ExecutionGuard,
+    ) -> EclResult<HashSet<SctId>> {
+        let mut candidates = HashSet::new();
+        for &type_id in self.attribute_types {
+            for &value_id in self.values {
+                guard.check()?;
+                candidates.extend(store.get_concepts_with_attribute(type_id, value_id));
+            }
+        }
+        Ok(candidates)
+    }
+}
+
+/// Returns true if every concept satisfying `constraint` has an outbound
+/// relationship whose type and destination are in its (finite) sets.
+fn is_indexable(constraint: &AttributeConstraint) -> bool {
+    !constraint.reverse
+        && matches!(
+            constraint.operator,
+            RefinementOperator::Equal | RefinementOperator::DescendantOrSelfOf
+        )
+        && constraint
+            .cardinality
+            .as_ref()
+            .is_none_or(|card| card.min > 0)
+        && !matches!(
+            constraint.attribute_type.unwrap_nested(),
+            EclExpression::Any
+        )
+        && !matches!(
+            constraint.value.unwrap_nested(),
+            EclExpression::Any | EclExpression::Concrete { .. }
+        )
+}
+
+/// Returns true if a concept only satisfies `group` when at least one of its
+/// relationship groups matches every constraint.
+fn group_requires_match(group: &AttributeGroup) -> bool {
+    !group.constraints.iter().any(|c| c.reverse)
+        && group.cardinality.as_ref().is_none_or(|card| card.min > 0)
+}
+
+/// Returns the constraints of a refinement that can drive a reverse attribute lookup.
+///
+/// Used by the planner to choose between a forward scan and a reverse lookup
+/// before the refinement is compiled.
+pub(crate) fn indexable_constraints(
+    refinement: &Refinement,
+) -> impl Iterator<Item = &AttributeConstraint> {
+    refinement
+        .ungrouped
+        .iter()
+        .filter(|constraint| is_indexable(constraint))
+        .chain(
+            refinement
+                .groups
+                .iter()
+                .filter(|group| group_requires_match(group))
+                .flat_map(|group| group.constraints.iter().filter(|c| is_indexable(c))),
+        )
+}
+
+/// Resolves the attribute type and value of a single constraint.
+fn compile_constraint<'e, F>(
+    constraint: &'e AttributeConstraint,
+    indexable: bool,
+    traverser: &HierarchyTraverser<'_>,
+    resolve: &mut F,
+) -> EclResult<CompiledConstraint<'e>>
+where
+    F: FnMut(&EclExpression) -> EclResult<HashSet<SctId>>,
+{
+    let attribute_types = ConceptMatcher::resolve(&constraint.attribute_type, resolve)?;
+
+    let value = if let EclExpression::Concrete { value, operator } = constraint.value.as_ref() {
+        ValueMatcher::Concrete {
+            value,
+            operator: *operator,
+        }
+    } else {
+        match constraint.operator {
+            RefinementOperator::Equal | RefinementOperator::DescendantOrSelfOf => {
+                ValueMatcher::Equal(ConceptMatcher::resolve(&constraint.value, resolve)?)
+            }
+            RefinementOperator::NotEqual => {
+                ValueMatcher::NotEqual(ConceptMatcher::resolve(&constraint.value, resolve)?)
+            }
+            RefinementOperator::DescendantOf => {
+                ValueMatcher::DescendantOf(ConceptMatcher::resolve(&constraint.value, resolve)?)
+            }
+            RefinementOperator::AncestorOf | RefinementOperator::AncestorOrSelfOf => {
+                // Value must be an ancestor of one of the values: precompute the closure once
+                let values = resolve(&constraint.value)?;
+                let mut closure = traverser.try_get_ancestors_of_set(&values)?;
+                if constraint.operator == RefinementOperator::AncestorOrSelfOf {
+                    closure.extend(values);
+                }
+                ValueMatcher::AncestorOf(closure)
+            }
+        }
+    };
+
+    Ok(CompiledConstraint {
+        cardinality: constraint.cardinality.as_ref(),
+        reverse: constraint.reverse,
+        indexable,
+        attribute_types,
+        value,
+    })
+}
+
+/// Compares a concrete relationship value against a target value using the given operator.
+fn compare_concrete_values(
+    actual: &ConcreteValueRef,
+    target: &ConcreteValue,
+    operator: ComparisonOperator,
+) -> bool {
+    match (actual, target) {
+        // Integer comparisons
+        (ConcreteValueRef::Integer(a), ConcreteValue::Integer(t)) => match operator {
+            ComparisonOperator::Equal => *a == *t,
+            ComparisonOperator::NotEqual => *a != *t,
+            ComparisonOperator::LessThan => *a < *t,
+            ComparisonOperator::LessThanOrEqual => *a <= *t,
+            ComparisonOperator::GreaterThan => *a > *t,
+            ComparisonOperator::GreaterThanOrEqual => *a >= *t,
+        },
+        // Decimal comparisons
+        (ConcreteValueRef::Decimal(a), ConcreteValue::Decimal(t)) => match operator {
+            ComparisonOperator::Equal => (*a - *t).abs() < f64::EPSILON,
+            ComparisonOperator::NotEqual => (*a - *t).abs() >= f64::EPSILON,
+            ComparisonOperator::LessThan => *a < *t,
+            ComparisonOperator::LessThanOrEqual => *a <= *t,
+            ComparisonOperator::GreaterThan => *a > *t,
+            ComparisonOperator::GreaterThanOrEqual => *a >= *t,
+        },
+        // Integer vs Decimal (promote integer to decimal)
+        (ConcreteValueRef::Integer(a), ConcreteValue::Decimal(t)) => {
+            let a_f = *a as f64;
+            match operator {
+                ComparisonOperator::Equal => (a_f - *t).abs() < f64::EPSILON,
+                ComparisonOperator::NotEqual => (a_f - *t).abs() >= f64::EPSILON,
+                ComparisonOperator::LessThan => a_f < *t,
+                ComparisonOperator::LessThanOrEqual => a_f <= *t,
+                ComparisonOperator::GreaterThan => a_f > *t,
+                ComparisonOperator::GreaterThanOrEqual => a_f >= *t,
+            }
+        }
+        (ConcreteValueRef::Decimal(a), ConcreteValue::Integer(t)) => {
+            let t_f = *t as f64;
+            match operator {
+                ComparisonOperator::Equal => (*a - t_f).abs() < f64::EPSILON,
+                ComparisonOperator::NotEqual => (*a - t_f).abs() >= f64::EPSILON,
+                ComparisonOperator::LessThan => *a < t_f,
+                ComparisonOperator::LessThanOrEqual => *a <= t_f,
+                ComparisonOperator::GreaterThan => *a > t_f,
+                ComparisonOperator::GreaterThanOrEqual => *a >= t_f,
+            }
+        }
+        // String comparisons (lexicographic)
+        (ConcreteValueRef::String(a), ConcreteValue::String(t)) => match operator {
+            ComparisonOperator::Equal => a == t,
+            ComparisonOperator::NotEqual => a != t,
+            ComparisonOperator::LessThan => a < t,
+            ComparisonOperator::LessThanOrEqual => a <= t,
+            ComparisonOperator::GreaterThan => a > t,
+            ComparisonOperator::GreaterThanOrEqual => a >= t,
+        },
+        // Boolean comparisons (only = and != make sense)
+        (ConcreteValueRef::Integer(a), ConcreteValue::Boolean(t)) => {
+            // Treat 0 as false, non-zero as true
+            let a_bool = *a != 0;
+            match operator {
+                ComparisonOperator::Equal => a_bool == *t,
+                ComparisonOperator::NotEqual => a_bool != *t,
+                _ => false, // Other comparisons don't make sense for booleans
+            }
+        }
+        // Type mismatches
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const FINDING_SITE: SctId = 363698007;
+
+    /// Store that counts hierarchy lookups.
+    ///
+    /// Hierarchy: 1 -> 2 -> 3 -> 4. Concepts 10..20 each have a finding site
+    /// of 4 (10..15) or 2 (15..20).
+    struct CountingStore {
+        parents: HashMap<SctId, Vec<SctId>>,
+        children: HashMap<SctId, Vec<SctId>>,
+        parent_calls: AtomicUsize,
+        child_calls: AtomicUsize,
+    }
+
+    impl CountingStore {
+        fn new() -> Self {
+            let mut store = Self {
+                parents: HashMap::new(),
+                children: HashMap::new(),
+                parent_calls: AtomicUsize::new(0),
+                child_calls: AtomicUsize::new(0),
+            };
+            for (child, parent) in [(2, 1), (3, 2), (4, 3)] {
+                store.parents.entry(child).or_default().push(parent);
+                store.children.entry(parent).or_default().push(child);
+            }
+            store
+        }
+    }
+
+    impl EclQueryable for CountingStore {
+        fn get_children(&self, concept_id: SctId) -> Vec<SctId> {
+            self.child_calls.fetch_add(1, Ordering::Relaxed);
+            self.children.get(&concept_id).cloned().unwrap_or_default()
+        }
+
+        fn get_parents(&self, concept_id: SctId) -> Vec<SctId> {
+            self.parent_calls.fetch_add(1, Ordering::Relaxed);
+            self.parents.get(&concept_id).cloned().unwrap_or_default()
+        }
+
+        fn has_concept(&self, concept_id: SctId) -> bool {
+            (1..=4).contains(&concept_id)
+                || (10..20).contains(&concept_id)
+                || concept_id == FINDING_SITE
+        }
+
+        fn all_concept_ids(&self) -> Box<dyn Iterator<Item = SctId> + '_> {
+            Box::new((1..=4).chain(10..20).chain(std::iter::once(FINDING_SITE)))
+        }
+
+        fn get_refset_members(&self, _refset_id: SctId) -> Vec<SctId> {
+            Vec::new()
+        }
+
+        fn get_attributes(&self, concept_id: SctId) -> Vec<RelationshipInfo> {
+            if !(10..20).contains(&concept_id) {
+                return Vec::new();
+            }
+            vec![RelationshipInfo {
+                type_id: FINDING_SITE,
+                destination_id: if concept_id < 15 { 4 } else { 2 },
+                group: 0,
+            }]
+        }
+    }
+
+    fn refinement(operator: RefinementOperator, value: EclExpression) -> Refinement {
+        Refinement {
+            ungrouped: vec![AttributeConstraint {
+                cardinality: None,
+                reverse: false,
+                attribute_type: Box::new(EclExpression::ConceptReference {
+                    concept_id: FINDING_SITE,
+                    term: None,
+                }),
+                operator,
+                value: Box::new(value),
+            }],
+            groups: Vec::new(),
+        }
+    }
+
+    fn matching(
+        store: &CountingStore,
+        refinement: &Refinement,
+        value_set: &[SctId],
+    ) -> (HashSet<SctId>, usize) {
+        let traverser = HierarchyTraverser::new(store);
+        let mut resolutions = 0;
+        let compiled = CompiledRefinement::compile(refinement, &traverser, |expr| {
+            resolutions += 1;
+            Ok(match expr {
+                EclExpression::ConceptReference { concept_id, .. }
+                    if *concept_id == FINDING_SITE =>
+                {
+                    HashSet::from([FINDING_SITE])
+                }
+                _ => value_set.iter().copied().collect(),
+            })
+        })
+        .unwrap();
+
+        let result = (10..20)
+            .filter(|&id| compiled.matches(id, store, &traverser).unwrap().0)
+            .collect();
+        (result, resolutions)
+    }
+
+    #[test]
+    fn test_sub_expressions_resolved_once() {
+        let store = CountingStore::new();
+        let refinement = refinement(
+            RefinementOperator::Equal,
+            EclExpression::ConceptReference {
+                concept_id: 4,
+                term: None,
+            },
+        );
+
+        let (result, resolutions) = matching(&store, &refinement, &[4]);
+        assert_eq!(result, (10..15).collect());
+        // One resolution for the attribute type, one for the value
+        assert_eq!(resolutions, 2);
+    }
+
+    #[test]
+    fn test_descendant_of_caches_ancestors() {
+        let store = CountingStore::new();
+        let refinement = refinement(
+            RefinementOperator::DescendantOf,
+            EclExpression::ConceptReference {
+                concept_id: 2,
+                term: None,
+            },
+        );
+
+        let (result, _) = matching(&store, &refinement, &[2]);
+        // Destination 4 is a proper descendant of 2, destination 2 is not
+        assert_eq!(result, (10..15).collect());
+        // Ancestors of 4 and 2 are computed once each: 4 + 2 get_parents calls
+        assert_eq!(store.parent_calls.load(Ordering::Relaxed), 6);
+    }
+
+    #[test]
+    fn test_ancestor_of_uses_precomputed_closure() {
+        let store = CountingStore::new();
+        let refinement = refinement(
+            RefinementOperator::AncestorOf,
+            EclExpression::ConceptReference {
+                concept_id: 3,
+                term: None,
+            },
+        );
+
+        let (result, _) = matching(&store, &refinement, &[3]);
+        // Destination 2 is an ancestor of 3, destination 4 is not
+        assert_eq!(result, (15..20).collect());
+        // Only the value's ancestors are traversed, not once per focus concept
+        assert_eq!(store.parent_calls.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn test_ancestor_or_self_of_includes_values() {
+        let store = CountingStore::new();
+        let refinement = refinement(
+            RefinementOperator::AncestorOrSelfOf,
+            EclExpression::ConceptReference {
+                concept_id: 4,
+                term: None,
+            },
+        );
+
+        let (result, _) = matching(&store, &refinement, &[4]);
+        assert_eq!(result, (10..20).collect());
+    }
+
+    #[test]
+    fn test_wildcard_value_is_not_materialised() {
+        let store = CountingStore::new();
+        let refinement = refinement(RefinementOperator::Equal, EclExpression::Any);
+
+        let (result, resolutions) = matching(&store, &refinement, &[]);
+        assert_eq!(result, (10..20).collect());
+        // Only the attribute type is resolved
+        assert_eq!(resolutions, 1);
+        assert_eq!(store.child_calls.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_compare_concrete_values() {
+        assert!(compare_concrete_values(
+            &ConcreteValueRef::Integer(5),
+            &ConcreteValue::Decimal(4.5),
+            ComparisonOperator::GreaterThan
+        ));
+        assert!(!compare_concrete_values(
+            &ConcreteValueRef::String("a".into()),
+            &ConcreteValue::Integer(1),
+            ComparisonOperator::Equal
+        ));
+    }
+}
@@ -0,0 +1,225 @@
+//! Renderers for query plans.
+//!
+//! A [`QueryPlan`] can be rendered as:
+//!
+//! - an indented text tree in the style of PostgreSQL's `EXPLAIN`
+//!   ([`QueryPlan::to_text`]),
+//! - a Graphviz DOT digraph ([`QueryPlan::to_dot`]),
+//! - JSON, with the `serde` feature ([`QueryPlan::to_json`]).
+//!
+//! The JSON document is the serde representation of [`QueryPlan`]: the
+//! fields `ecl`, `steps`, `estimated_total`, `optimization_hints` and
+//! `physical_plan`. Steps carry `operation`, `expression`,
+//! `estimated_cardinality`, `cost_estimate`, `parent` and `children`, the
+//! latter two being indices into `steps`. Physical plan nodes carry
+//! `operator`, `expression` (as ECL text), `estimated_cardinality`,
+//! `estimated_cost` and `children`.
+
+use std::fmt::Write;
+
+use crate::planner::QueryPlan;
+
+impl QueryPlan {
+    /// Renders the steps as an indented tree, root first.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let plan = executor.explain("<< 73211009 AND << 64572001")?;
+    /// println!("{}", plan.to_text());
+    /// // Intersect: AND  (cost=0.0010ms rows=50)
+    /// //   ->  DescendantsOrSelf: << 73211009  (cost=0.0101ms rows=101)
+    /// //   ->  DescendantsOrSelf: << 64572001  (cost=0.0101ms rows=101)
+    /// ```
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for root in self.top_level_steps() {
+            self.write_text(&mut out, root, 0);
+        }
+        out
+    }
+
+    /// Renders the steps as a Graphviz DOT digraph with edges from each
+    /// step to its operands.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let plan = executor.explain("<< 73211009 AND << 64572001")?;
+    /// std::fs::write("plan.dot", plan.to_dot())?;
+    /// // dot -Tsvg plan.dot > plan.svg
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        out.push_str("digraph query_plan {\n");
+        let _ = writeln!(out, "    label=\"{}\";", escape_dot(&self.ecl));
+        out.push_str("    labelloc=t;\n");
+        out.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+
+        for (index, step) in self.steps.iter().enumerate() {
+            let label = format!(
+                "{}\n{}\nest. {} concepts, {:.4}ms",
+                step.operation, step.expression, step.estimated_cardinality, step.cost_estimate
+            );
+            let _ = writeln!(out, "    step{} [label=\"{}\"];", index, escape_dot(&label));
+        }
+        for (index, step) in self.steps.iter().enumerate() {
+            for child in &step.children {
+                let _ = writeln!(out, "    step{} -> step{};", index, child);
+            }
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    /// Renders the plan as pretty-printed JSON.
+    ///
+    /// See the [module documentation](self) for the schema.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let plan = executor.explain("<< 73211009 AND << 64572001")?;
+    /// let json = plan.to_json();
+    /// let reloaded = QueryPlan::from_json(&json)?;
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("query plans are always serializable")
+    }
+
+    /// Reads a plan previously rendered with [`to_json`](Self::to_json).
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Returns the indices of steps without a parent, in execution order.
+    fn top_level_steps(&self) -> impl Iterator<Item = usize> + '_ {
+        self.steps
+            .iter()
+            .enumerate()
+            .filter(|(_, step)| step.parent.is_none())
+            .map(|(index, _)| index)
+    }
+
+    fn write_text(&self, out: &mut String, index: usize, depth: usize) {
+        let step = &self.steps[index];
+        if depth > 0 {
+            let _ = write!(out, "{:indent$}->  ", "", indent = 6 * depth - 4);
+        }
+        let _ = writeln!(
+            out,
+            "{}: {}  (cost={:.4}ms rows={})",
+            step.operation, step.expression, step.cost_estimate, step.estimated_cardinality
+        );
+
+        for &child in &step.children {
+            self.write_text(out, child, depth + 1);
+        }
+    }
+}
+
+/// Escapes a string for use inside a double-quoted DOT attribute.
+fn escape_dot(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::planner::QueryPlanner;
+
+    use super::*;
+
+    fn plan(ecl: &str) -> QueryPlan {
+        let expr = snomed_ecl::parse(ecl).expect("Failed to parse ECL");
+        QueryPlanner::new().plan(ecl, &expr)
+    }
+
+    #[test]
+    fn test_plan_tree_links() {
+        let plan = plan("<< 73211009 AND (<< 64572001 OR 404684003)");
+
+        // Steps: <<, <<, self, Union, Intersect
+        assert_eq!(plan.steps.len(), 5);
+        let root = plan.root().unwrap();
+        assert_eq!(root.operation, "Intersect");
+        assert_eq!(root.children, vec![0, 3]);
+        assert_eq!(plan.steps[3].children, vec![1, 2]);
+        assert_eq!(plan.steps[1].parent, Some(3));
+        assert_eq!(plan.steps[3].parent, Some(4));
+
+        let operations: Vec<_> = plan
+            .children_of(3)
+            .map(|step| step.operation.as_str())
+            .collect();
+        assert_eq!(operations, vec!["DescendantsOrSelf", "Self"]);
+    }
+
+    #[test]
+    fn test_to_text() {
+        let text = plan("<< 73211009 AND (<< 64572001 OR 404684003)").to_text();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].starts_with("Intersect: AND  (cost="));
+        assert!(lines[1].starts_with("  ->  DescendantsOrSelf: << 73211009  (cost="));
+        assert!(lines[2].starts_with("  ->  Union: OR"));
+        assert!(lines[3].starts_with("        ->  DescendantsOrSelf: << 64572001"));
+        assert!(lines[4].starts_with("        ->  Self: 404684003"));
+        assert!(lines[4].ends_with("rows=1)"));
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let dot = plan("<< 73211009 |Diabetes \"mellitus\"| MINUS 46635009").to_dot();
+
+        assert!(dot.starts_with("digraph query_plan {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("step0 [label=\"DescendantsOrSelf\\n<< 73211009\\nest. "));
+        assert!(dot.contains("step2 -> step0;"));
+        assert!(dot.contains("step2 -> step1;"));
+        assert!(dot.contains("\\\"mellitus\\\""));
+    }
+
+    #[test]
+    fn test_escape_dot() {
+        assert_eq!(escape_dot(r#"a "b" \c"#), r#"a \"b\" \\c"#);
+        assert_eq!(escape_dot("a\nb"), "a\\nb");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() {
+        let plan = plan("73211009 AND << 404684003");
+        let json = plan.to_json();
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["ecl"], "73211009 AND << 404684003");
+        assert_eq!(value["steps"][2]["operation"], "Intersect");
+        assert_eq!(value["steps"][2]["children"], serde_json::json!([0, 1]));
+        assert_eq!(value["steps"][0]["parent"], 2);
+        assert_eq!(value["physical_plan"]["operator"], "Intersect");
+        assert_eq!(
+            value["physical_plan"]["children"][1]["expression"],
+            "<< 404684003"
+        );
+
+        let reloaded = QueryPlan::from_json(&json).unwrap();
+        assert_eq!(reloaded.steps.len(), plan.steps.len());
+        assert_eq!(
+            reloaded.physical_plan.unwrap().children[1].operator,
+            crate::PlanOperator::MembershipCheck
+        );
+    }
+}
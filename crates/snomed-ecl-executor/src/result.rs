@@ -5,6 +5,8 @@ use std::time::Duration;
 
 use snomed_ecl::SctId;
 
+use crate::traits::RefsetFieldValue;
+
 /// Result of an ECL query execution.
 ///
 /// Contains the matching concept IDs and execution statistics.
@@ -92,6 +94,101 @@ impl<'a> IntoIterator for &'a QueryResult {
     }
 }
 
+/// Result of a member-of query with a field selection.
+///
+/// `^ [referencedComponentId, mapTarget] 447562003` returns one row per
+/// matching reference set member, with the selected fields as columns.
+///
+/// # Example
+///
+/// ```ignore
+/// let result = executor.execute_member_rows("^ [referencedComponentId, mapTarget] 447562003")?;
+///
+/// for row in &result.rows {
+///     println!("{:?} -> {:?}", row[0], row[1]);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MemberRowsResult {
+    /// Column names, in selection order.
+    pub columns: Vec<String>,
+    /// One row per member, ordered by referenced component. A member that
+    /// lacks a field has `None` in its column.
+    pub rows: Vec<Vec<Option<RefsetFieldValue>>>,
+    /// Execution statistics.
+    pub stats: ExecutionStats,
+}
+
+impl MemberRowsResult {
+    /// Returns the number of rows.
+    pub fn count(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns true if there are no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Returns the index of a column, ignoring ASCII case.
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Result of a query with a history supplement, keeping the concepts the
+/// supplement added apart from the ones the expression matched.
+///
+/// # Example
+///
+/// ```ignore
+/// let result = executor.execute_with_history("<< 73211009 {{ +HISTORY-MIN }}")?;
+///
+/// for concept_id in &result.added {
+///     println!("Inactive concept {} added by SAME AS", concept_id);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct HistoryResult {
+    /// Concepts matched by the expression without its history supplement.
+    pub members: HashSet<SctId>,
+    /// Inactive concepts added by the history supplement, excluding
+    /// concepts that are also in `members`.
+    pub added: HashSet<SctId>,
+    /// Execution statistics.
+    pub stats: ExecutionStats,
+}
+
+impl HistoryResult {
+    /// Returns the number of concepts, members and added.
+    pub fn count(&self) -> usize {
+        self.members.len() + self.added.len()
+    }
+
+    /// Returns true if there are no members and nothing was added.
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty() && self.added.is_empty()
+    }
+
+    /// Checks if a concept is a member or was added.
+    pub fn contains(&self, concept_id: SctId) -> bool {
+        self.members.contains(&concept_id) || self.added.contains(&concept_id)
+    }
+
+    /// Returns true if the concept was added by the history supplement.
+    pub fn is_added(&self, concept_id: SctId) -> bool {
+        self.added.contains(&concept_id)
+    }
+
+    /// Converts the result into a [`QueryResult`] over members and added
+    /// concepts alike.
+    pub fn into_query_result(self) -> QueryResult {
+        let mut concept_ids = self.members;
+        concept_ids.extend(self.added);
+        QueryResult::new(concept_ids, self.stats)
+    }
+}
+
 /// Statistics from ECL query execution.
 #[derive(Debug, Clone, Default)]
 pub struct ExecutionStats {
@@ -2,12 +2,30 @@
 //!
 //! Provides statistics about SNOMED CT concept hierarchies for estimating
 //! query cardinality and generating optimization hints.
+//!
+//! By default the [`StatisticsService`] uses the [`well_known`] counts and
+//! the [`heuristics`] constants. For accurate plans on a specific release,
+//! including extension and local content, collect real statistics with a
+//! [`StatisticsBuilder`] and save them alongside the release:
+//!
+//! ```ignore
+//! let statistics = StatisticsBuilder::new().build(&store);
+//! statistics.save("statistics-20240101.tsv", "20240101")?;
+//!
+//! // On startup
+//! let statistics = StatisticsService::load("statistics-20240101.tsv", Some("20240101"))?;
+//! let executor = EclExecutor::new(&store).with_statistics(statistics);
+//! ```
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
 
 use snomed_ecl::SctId;
 
 use crate::traits::EclQueryable;
+use crate::traverser::HierarchyTraverser;
 
 /// Well-known SNOMED CT concept IDs with pre-computed statistics.
 pub mod well_known {
@@ -39,6 +57,9 @@ pub mod well_known {
 
     /// SNOMED CT root concept
     pub const ROOT_CONCEPT: SctId = 138875005;
+
+    /// Reference set - ancestor of all reference set concepts
+    pub const REFERENCE_SET: SctId = 900000000000455006;
 }
 
 /// Default heuristics for cardinality estimation.
@@ -63,6 +84,12 @@ pub mod heuristics {
 
     /// Threshold for considering a traversal "large"
     pub const LARGE_TRAVERSAL_THRESHOLD: usize = 100_000;
+
+    /// Approximate total number of SNOMED CT concepts (used for `*`)
+    pub const TOTAL_CONCEPTS_ESTIMATE: usize = 500_000;
+
+    /// Average number of concepts sharing one attribute type/value pair
+    pub const AVG_CONCEPTS_PER_ATTRIBUTE_VALUE: usize = 50;
 }
 
 /// Cost model constants for query planning.
@@ -84,18 +111,46 @@ pub mod cost {
 
     /// Cost per element in set difference (relative units, ~0.0001ms)
     pub const SET_DIFFERENCE: f64 = 0.0001;
+
+    /// Cost per concept checked against a refinement (relative units, ~0.002ms)
+    pub const REFINEMENT_CHECK: f64 = 0.002;
+
+    /// Cost per reverse attribute lookup (relative units, ~0.001ms)
+    pub const ATTRIBUTE_LOOKUP: f64 = 0.001;
+
+    /// Cost per concept tested for membership by an upward traversal (relative units, ~0.01ms)
+    pub const MEMBERSHIP_CHECK: f64 = 0.01;
 }
 
 /// Statistics service for estimating query cardinality.
 ///
 /// Provides pre-computed and heuristic-based statistics for estimating
 /// the cardinality of ECL query results.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct StatisticsService {
     /// Pre-computed descendant counts for well-known concepts.
     well_known_counts: HashMap<SctId, usize>,
     /// Cached concept child counts.
     child_counts: HashMap<SctId, usize>,
+    /// Known ancestor counts.
+    ancestor_counts: HashMap<SctId, usize>,
+    /// Known direct parent counts.
+    parent_counts: HashMap<SctId, usize>,
+    /// Relationship frequencies per attribute type.
+    attribute_types: HashMap<SctId, AttributeTypeCounts>,
+    /// Known reference set sizes.
+    refset_sizes: HashMap<SctId, usize>,
+    /// Number of concepts in the store, if known.
+    total_concepts: Option<usize>,
+}
+
+/// Relationship counts for one attribute type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct AttributeTypeCounts {
+    /// Number of relationships with this type.
+    relationships: usize,
+    /// Number of distinct destination values.
+    distinct_values: usize,
 }
 
 impl Default for StatisticsService {
@@ -120,10 +175,7 @@ impl StatisticsService {
         well_known_counts.insert(well_known::EVENT, 5_000);
         well_known_counts.insert(well_known::ROOT_CONCEPT, 500_000);
 
-        Self {
-            well_known_counts,
-            child_counts: HashMap::new(),
-        }
+        Self::with_counts(well_known_counts)
     }
 
     /// Creates a statistics service with custom concept counts.
@@ -133,6 +185,11 @@ impl StatisticsService {
         Self {
             well_known_counts: concept_counts,
             child_counts: HashMap::new(),
+            ancestor_counts: HashMap::new(),
+            parent_counts: HashMap::new(),
+            attribute_types: HashMap::new(),
+            refset_sizes: HashMap::new(),
+            total_concepts: None,
         }
     }
 
@@ -174,7 +231,10 @@ impl StatisticsService {
     /// Estimates the number of ancestors for a concept.
     ///
     /// Ancestors are typically much fewer than descendants.
-    pub fn estimated_ancestors(&self, _concept_id: SctId) -> usize {
+    pub fn estimated_ancestors(&self, concept_id: SctId) -> usize {
+        if let Some(&count) = self.ancestor_counts.get(&concept_id) {
+            return count;
+        }
         // Ancestors are typically bounded by hierarchy depth
         heuristics::AVG_HIERARCHY_DEPTH
     }
@@ -193,11 +253,55 @@ impl StatisticsService {
     }
 
     /// Estimates cardinality for direct parents.
-    pub fn estimated_parents(&self, _concept_id: SctId) -> usize {
+    pub fn estimated_parents(&self, concept_id: SctId) -> usize {
+        if let Some(&count) = self.parent_counts.get(&concept_id) {
+            return count;
+        }
         // Most concepts have 1-3 parents in SNOMED CT
         2
     }
 
+    /// Estimates the number of concepts with a given attribute type and value.
+    ///
+    /// This is the expected result size of
+    /// [`EclQueryable::get_concepts_with_attribute`]. With attribute type
+    /// frequencies registered, it is the average number of relationships
+    /// per distinct value of the type.
+    pub fn estimated_concepts_with_attribute(
+        &self,
+        attribute_type_id: SctId,
+        _value_id: SctId,
+    ) -> usize {
+        match self.attribute_types.get(&attribute_type_id) {
+            Some(counts) => counts
+                .relationships
+                .div_ceil(counts.distinct_values.max(1))
+                .max(1),
+            None => heuristics::AVG_CONCEPTS_PER_ATTRIBUTE_VALUE,
+        }
+    }
+
+    /// Returns the number of relationships with an attribute type, if known.
+    pub fn attribute_type_frequency(&self, attribute_type_id: SctId) -> Option<usize> {
+        self.attribute_types
+            .get(&attribute_type_id)
+            .map(|counts| counts.relationships)
+    }
+
+    /// Estimates the number of members of a reference set.
+    pub fn estimated_refset_members(&self, refset_id: SctId) -> usize {
+        self.refset_sizes
+            .get(&refset_id)
+            .copied()
+            .unwrap_or(heuristics::DEFAULT_DESCENDANT_ESTIMATE)
+    }
+
+    /// Estimates the number of concepts in the store (the result of `*`).
+    pub fn estimated_total_concepts(&self) -> usize {
+        self.total_concepts
+            .unwrap_or(heuristics::TOTAL_CONCEPTS_ESTIMATE)
+    }
+
     /// Estimates cardinality for AND (intersection) of two sets.
     pub fn estimated_and(&self, left: usize, right: usize) -> usize {
         let smaller = left.min(right);
@@ -236,6 +340,24 @@ impl StatisticsService {
         cost::CONCEPT_LOOKUP
     }
 
+    /// Estimates execution cost for checking every focus concept against a refinement.
+    pub fn cost_forward_refinement(&self, focus_size: usize) -> f64 {
+        (focus_size as f64) * cost::REFINEMENT_CHECK
+    }
+
+    /// Estimates execution cost for evaluating a refinement from a reverse index.
+    ///
+    /// `lookups` attribute/value pairs are looked up and the resulting
+    /// `candidates` are then checked against the full refinement.
+    pub fn cost_reverse_refinement(&self, lookups: usize, candidates: usize) -> f64 {
+        (lookups as f64) * cost::ATTRIBUTE_LOOKUP + (candidates as f64) * cost::REFINEMENT_CHECK
+    }
+
+    /// Estimates execution cost for testing concepts for membership one by one.
+    pub fn cost_membership_check(&self, concept_count: usize) -> f64 {
+        (concept_count as f64) * cost::MEMBERSHIP_CHECK
+    }
+
     /// Estimates execution cost for set intersection.
     pub fn cost_intersection(&self, set_size: usize) -> f64 {
         (set_size as f64) * cost::SET_INTERSECTION
@@ -267,6 +389,330 @@ impl StatisticsService {
     pub fn register_child_count(&mut self, concept_id: SctId, count: usize) {
         self.child_counts.insert(concept_id, count);
     }
+
+    /// Registers a known ancestor count for a concept.
+    pub fn register_ancestor_count(&mut self, concept_id: SctId, count: usize) {
+        self.ancestor_counts.insert(concept_id, count);
+    }
+
+    /// Registers a direct parent count for a concept.
+    pub fn register_parent_count(&mut self, concept_id: SctId, count: usize) {
+        self.parent_counts.insert(concept_id, count);
+    }
+
+    /// Registers the frequency of an attribute type.
+    ///
+    /// # Arguments
+    ///
+    /// * `attribute_type_id` - The attribute type
+    /// * `relationships` - Number of relationships with this type
+    /// * `distinct_values` - Number of distinct destination values
+    pub fn register_attribute_type(
+        &mut self,
+        attribute_type_id: SctId,
+        relationships: usize,
+        distinct_values: usize,
+    ) {
+        self.attribute_types.insert(
+            attribute_type_id,
+            AttributeTypeCounts {
+                relationships,
+                distinct_values,
+            },
+        );
+    }
+
+    /// Registers the number of members of a reference set.
+    pub fn register_refset_size(&mut self, refset_id: SctId, count: usize) {
+        self.refset_sizes.insert(refset_id, count);
+    }
+
+    /// Registers the number of concepts in the store.
+    pub fn register_total_concepts(&mut self, count: usize) {
+        self.total_concepts = Some(count);
+    }
+
+    /// Saves the statistics to a file, tagged with the SNOMED CT release
+    /// they were collected from (e.g. `"20240101"`).
+    ///
+    /// See [`write_to`](Self::write_to) for the format.
+    pub fn save<P: AsRef<Path>>(&self, path: P, snomed_release: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer, snomed_release)?;
+        writer.flush()
+    }
+
+    /// Loads statistics previously written by [`save`](Self::save).
+    ///
+    /// If `expected_release` is given, fails unless the file was saved from
+    /// that release. The loaded service contains exactly the saved
+    /// statistics; the built-in well-known counts are not added.
+    pub fn load<P: AsRef<Path>>(path: P, expected_release: Option<&str>) -> io::Result<Self> {
+        Self::read_from(BufReader::new(File::open(path)?), expected_release)
+    }
+
+    /// Writes the statistics as tab-separated text.
+    ///
+    /// The first line is a `snomed-ecl-statistics` header with the format
+    /// version and the SNOMED CT release, followed by one record per line,
+    /// sorted for stable diffs. Fields are separated by tabs:
+    ///
+    /// ```text
+    /// snomed-ecl-statistics 1 <release>
+    /// total_concepts <count>
+    /// descendants <conceptId> <count>
+    /// ancestors <conceptId> <count>
+    /// children <conceptId> <count>
+    /// parents <conceptId> <count>
+    /// attribute <typeId> <relationships> <distinctValues>
+    /// refset <refsetId> <count>
+    /// ```
+    ///
+    /// Returns an [`io::ErrorKind::InvalidInput`] error if the release
+    /// contains a tab or a line break.
+    pub fn write_to<W: Write>(&self, mut writer: W, snomed_release: &str) -> io::Result<()> {
+        if snomed_release.contains(['\t', '\n', '\r']) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid SNOMED release: {:?}", snomed_release),
+            ));
+        }
+        writeln!(writer, "{}\t{}\t{}", FILE_HEADER, FILE_VERSION, snomed_release)?;
+        if let Some(total) = self.total_concepts {
+            writeln!(writer, "total_concepts\t{}", total)?;
+        }
+
+        for (kind, counts) in [
+            ("descendants", &self.well_known_counts),
+            ("ancestors", &self.ancestor_counts),
+            ("children", &self.child_counts),
+            ("parents", &self.parent_counts),
+        ] {
+            for (id, count) in sorted(counts) {
+                writeln!(writer, "{}\t{}\t{}", kind, id, count)?;
+            }
+        }
+        for (id, counts) in sorted(&self.attribute_types) {
+            writeln!(
+                writer,
+                "attribute\t{}\t{}\t{}",
+                id, counts.relationships, counts.distinct_values
+            )?;
+        }
+        for (id, count) in sorted(&self.refset_sizes) {
+            writeln!(writer, "refset\t{}\t{}", id, count)?;
+        }
+        Ok(())
+    }
+
+    /// Reads statistics written by [`write_to`](Self::write_to).
+    ///
+    /// Returns an [`io::ErrorKind::InvalidData`] error if the header,
+    /// version or a record is malformed, or if `expected_release` is given
+    /// and the statistics were collected from another release.
+    pub fn read_from<R: BufRead>(reader: R, expected_release: Option<&str>) -> io::Result<Self> {
+        let mut lines = reader.lines();
+
+        let header = lines.next().transpose()?.unwrap_or_default();
+        let snomed_release = header
+            .strip_prefix(&format!("{}\t{}\t", FILE_HEADER, FILE_VERSION))
+            .ok_or_else(|| {
+                invalid_data(format!("unsupported statistics header: {:?}", header))
+            })?;
+        if let Some(expected) = expected_release {
+            if snomed_release != expected {
+                return Err(invalid_data(format!(
+                    "release mismatch: expected {}, file contains {}",
+                    expected, snomed_release
+                )));
+            }
+        }
+
+        let mut stats = Self::with_counts(HashMap::new());
+        for (index, line) in lines.enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            let number = |i: usize| -> io::Result<u64> {
+                fields
+                    .get(i)
+                    .and_then(|f| f.parse().ok())
+                    .ok_or_else(|| invalid_data(format!("line {}: {:?}", index + 2, line)))
+            };
+
+            match (fields[0], fields.len()) {
+                ("total_concepts", 2) => stats.total_concepts = Some(number(1)? as usize),
+                ("descendants", 3) => {
+                    stats.register_descendant_count(number(1)?, number(2)? as usize)
+                }
+                ("ancestors", 3) => stats.register_ancestor_count(number(1)?, number(2)? as usize),
+                ("children", 3) => stats.register_child_count(number(1)?, number(2)? as usize),
+                ("parents", 3) => stats.register_parent_count(number(1)?, number(2)? as usize),
+                ("attribute", 4) => stats.register_attribute_type(
+                    number(1)?,
+                    number(2)? as usize,
+                    number(3)? as usize,
+                ),
+                ("refset", 3) => stats.register_refset_size(number(1)?, number(2)? as usize),
+                _ => {
+                    return Err(invalid_data(format!(
+                        "line {}: unknown record {:?}",
+                        index + 2,
+                        line
+                    )))
+                }
+            }
+        }
+        Ok(stats)
+    }
+}
+
+/// Header of saved statistics files.
+const FILE_HEADER: &str = "snomed-ecl-statistics";
+/// Version of the saved statistics format.
+const FILE_VERSION: u32 = 1;
+
+/// Returns map entries sorted by key.
+fn sorted<V>(map: &HashMap<SctId, V>) -> Vec<(&SctId, &V)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_unstable_by_key(|(id, _)| **id);
+    entries
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Collects statistics from a store.
+///
+/// Walks every concept of an [`EclQueryable`] store and collects exact
+/// descendant and ancestor counts, fan-out (child and parent counts),
+/// attribute type frequencies and reference set sizes. Building takes one
+/// upward traversal per concept, so it is meant to run once per release;
+/// save the result with [`StatisticsService::save`].
+///
+/// # Example
+///
+/// ```ignore
+/// let statistics = StatisticsBuilder::new()
+///     .with_refsets([723264001, 733073007])
+///     .build(&store);
+///
+/// let executor = EclExecutor::new(&store).with_statistics(statistics);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StatisticsBuilder {
+    hierarchy: bool,
+    attributes: bool,
+    refsets: Option<Vec<SctId>>,
+}
+
+impl Default for StatisticsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StatisticsBuilder {
+    /// Creates a builder collecting all statistics.
+    pub fn new() -> Self {
+        Self {
+            hierarchy: true,
+            attributes: true,
+            refsets: None,
+        }
+    }
+
+    /// Sets whether exact descendant and ancestor counts are collected.
+    ///
+    /// Disable this when the counts come from another source, such as a
+    /// precomputed transitive closure. Child and parent counts are always
+    /// collected.
+    pub fn with_hierarchy(mut self, enabled: bool) -> Self {
+        self.hierarchy = enabled;
+        self
+    }
+
+    /// Sets whether attribute type frequencies are collected.
+    pub fn with_attributes(mut self, enabled: bool) -> Self {
+        self.attributes = enabled;
+        self
+    }
+
+    /// Sets the reference sets whose sizes are collected.
+    ///
+    /// By default, every descendant of
+    /// [`well_known::REFERENCE_SET`] in the store is measured.
+    pub fn with_refsets(mut self, refset_ids: impl IntoIterator<Item = SctId>) -> Self {
+        self.refsets = Some(refset_ids.into_iter().collect());
+        self
+    }
+
+    /// Walks the store and returns the collected statistics.
+    ///
+    /// The returned service only contains counts measured in the store;
+    /// none of the built-in well-known counts are kept.
+    pub fn build(&self, store: &dyn EclQueryable) -> StatisticsService {
+        let mut stats = StatisticsService::with_counts(HashMap::new());
+        let traverser = HierarchyTraverser::new(store);
+        let mut attribute_values: HashMap<SctId, HashSet<SctId>> = HashMap::new();
+        let mut total = 0;
+
+        for concept_id in store.all_concept_ids() {
+            total += 1;
+            stats.register_child_count(concept_id, store.get_children(concept_id).len());
+            stats.register_parent_count(concept_id, store.get_parents(concept_id).len());
+
+            if self.hierarchy {
+                let ancestors = traverser.get_ancestors(concept_id);
+                stats.register_ancestor_count(concept_id, ancestors.len());
+                stats.well_known_counts.entry(concept_id).or_insert(0);
+                for ancestor in ancestors {
+                    *stats.well_known_counts.entry(ancestor).or_insert(0) += 1;
+                }
+            }
+
+            if self.attributes {
+                for rel in store.get_attributes(concept_id) {
+                    stats
+                        .attribute_types
+                        .entry(rel.type_id)
+                        .or_insert(AttributeTypeCounts {
+                            relationships: 0,
+                            distinct_values: 0,
+                        })
+                        .relationships += 1;
+                    attribute_values
+                        .entry(rel.type_id)
+                        .or_default()
+                        .insert(rel.destination_id);
+                }
+            }
+        }
+        stats.register_total_concepts(total);
+
+        for (type_id, values) in attribute_values {
+            if let Some(counts) = stats.attribute_types.get_mut(&type_id) {
+                counts.distinct_values = values.len();
+            }
+        }
+
+        let refsets = match self.refsets {
+            Some(ref refsets) => refsets.clone(),
+            None if store.has_concept(well_known::REFERENCE_SET) => traverser
+                .get_descendants(well_known::REFERENCE_SET)
+                .into_iter()
+                .collect(),
+            None => Vec::new(),
+        };
+        for refset_id in refsets {
+            stats.register_refset_size(refset_id, store.get_refset_members(refset_id).len());
+        }
+
+        stats
+    }
 }
 
 #[cfg(test)]
@@ -361,6 +807,23 @@ mod tests {
         assert!(lookup_cost > 0.0);
     }
 
+    #[test]
+    fn test_refinement_costs() {
+        let stats = StatisticsService::new();
+
+        // One lookup yielding a few candidates beats scanning a large focus set
+        let candidates = stats.estimated_concepts_with_attribute(246075003, 387517004);
+        assert!(
+            stats.cost_reverse_refinement(1, candidates) < stats.cost_forward_refinement(10_000)
+        );
+
+        // Many lookups are worse than scanning a small focus set
+        assert!(
+            stats.cost_reverse_refinement(1_000, 1_000 * candidates)
+                > stats.cost_forward_refinement(100)
+        );
+    }
+
     #[test]
     fn test_is_large_traversal() {
         let stats = StatisticsService::new();
@@ -412,4 +875,184 @@ mod tests {
         stats.register_child_count(12345, 10);
         assert_eq!(stats.estimated_children(12345), 10);
     }
+
+    /// Hierarchy:
+    /// ```text
+    ///       1                 900000000000455006
+    ///      / \                        |
+    ///     2   3                       10 (refset: 2, 4)
+    ///    / \ /
+    ///   4   5
+    /// ```
+    /// Concepts 4 and 5 have a finding site of 3; concept 4 also of 2.
+    struct MockStore {
+        children: HashMap<SctId, Vec<SctId>>,
+        parents: HashMap<SctId, Vec<SctId>>,
+    }
+
+    impl MockStore {
+        fn new() -> Self {
+            let mut store = Self {
+                children: HashMap::new(),
+                parents: HashMap::new(),
+            };
+            for (child, parent) in [
+                (2, 1),
+                (3, 1),
+                (4, 2),
+                (5, 2),
+                (5, 3),
+                (10, well_known::REFERENCE_SET),
+            ] {
+                store.children.entry(parent).or_default().push(child);
+                store.parents.entry(child).or_default().push(parent);
+            }
+            store
+        }
+    }
+
+    impl EclQueryable for MockStore {
+        fn get_children(&self, concept_id: SctId) -> Vec<SctId> {
+            self.children.get(&concept_id).cloned().unwrap_or_default()
+        }
+
+        fn get_parents(&self, concept_id: SctId) -> Vec<SctId> {
+            self.parents.get(&concept_id).cloned().unwrap_or_default()
+        }
+
+        fn has_concept(&self, concept_id: SctId) -> bool {
+            (1..=5).contains(&concept_id)
+                || concept_id == 10
+                || concept_id == well_known::REFERENCE_SET
+        }
+
+        fn all_concept_ids(&self) -> Box<dyn Iterator<Item = SctId> + '_> {
+            Box::new((1..=5).chain([10, well_known::REFERENCE_SET]))
+        }
+
+        fn get_refset_members(&self, refset_id: SctId) -> Vec<SctId> {
+            if refset_id == 10 {
+                vec![2, 4]
+            } else {
+                Vec::new()
+            }
+        }
+
+        fn get_attributes(&self, concept_id: SctId) -> Vec<crate::traits::RelationshipInfo> {
+            let site = |destination_id| crate::traits::RelationshipInfo {
+                type_id: 363698007,
+                destination_id,
+                group: 0,
+            };
+            match concept_id {
+                4 => vec![site(3), site(2)],
+                5 => vec![site(3)],
+                _ => Vec::new(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_builder_collects_statistics() {
+        let stats = StatisticsBuilder::new().build(&MockStore::new());
+
+        assert_eq!(stats.estimated_total_concepts(), 7);
+        assert_eq!(stats.estimated_descendants(1), 4);
+        assert_eq!(stats.estimated_descendants(2), 2);
+        assert_eq!(stats.estimated_descendants(4), 0);
+        assert_eq!(stats.estimated_ancestors(5), 3);
+        assert_eq!(stats.estimated_ancestors(1), 0);
+        assert_eq!(stats.estimated_children(2), 2);
+        assert_eq!(stats.estimated_parents(5), 2);
+
+        // 3 relationships over 2 distinct values
+        assert_eq!(stats.attribute_type_frequency(363698007), Some(3));
+        assert_eq!(stats.estimated_concepts_with_attribute(363698007, 3), 2);
+
+        // Refsets are discovered below the reference set concept
+        assert_eq!(stats.estimated_refset_members(10), 2);
+        // Well-known defaults are not mixed in
+        assert_eq!(
+            stats.estimated_descendants(well_known::CLINICAL_FINDING),
+            heuristics::DEFAULT_DESCENDANT_ESTIMATE
+        );
+    }
+
+    #[test]
+    fn test_builder_options() {
+        let stats = StatisticsBuilder::new()
+            .with_hierarchy(false)
+            .with_attributes(false)
+            .with_refsets([1])
+            .build(&MockStore::new());
+
+        assert_eq!(
+            stats.estimated_ancestors(5),
+            heuristics::AVG_HIERARCHY_DEPTH
+        );
+        assert_eq!(stats.estimated_children(2), 2);
+        assert_eq!(stats.attribute_type_frequency(363698007), None);
+        assert_eq!(stats.estimated_refset_members(1), 0);
+        assert_eq!(
+            stats.estimated_refset_members(10),
+            heuristics::DEFAULT_DESCENDANT_ESTIMATE
+        );
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let stats = StatisticsBuilder::new().build(&MockStore::new());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("statistics.tsv");
+
+        stats.save(&path, "20240101").unwrap();
+        let loaded = StatisticsService::load(&path, Some("20240101")).unwrap();
+
+        assert_eq!(loaded.well_known_counts, stats.well_known_counts);
+        assert_eq!(loaded.ancestor_counts, stats.ancestor_counts);
+        assert_eq!(loaded.child_counts, stats.child_counts);
+        assert_eq!(loaded.parent_counts, stats.parent_counts);
+        assert_eq!(loaded.attribute_types, stats.attribute_types);
+        assert_eq!(loaded.refset_sizes, stats.refset_sizes);
+        assert_eq!(loaded.total_concepts, Some(7));
+
+        let mut saved = Vec::new();
+        stats.write_to(&mut saved, "20240101").unwrap();
+        let text = String::from_utf8(saved).unwrap();
+        assert!(text.starts_with(
+            "snomed-ecl-statistics\t1\t20240101\ntotal_concepts\t7\ndescendants\t1\t4\n"
+        ));
+        assert!(text.contains("\nattribute\t363698007\t3\t2\n"));
+    }
+
+    #[test]
+    fn test_load_checks_release() {
+        let stats = StatisticsBuilder::new().build(&MockStore::new());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("statistics.tsv");
+        stats.save(&path, "20240101").unwrap();
+
+        assert!(StatisticsService::load(&path, None).is_ok());
+        let err = StatisticsService::load(&path, Some("20250101")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("expected 20250101, file contains 20240101"));
+
+        let err = stats.write_to(Vec::new(), "2024\t0101").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_load_rejects_invalid_data() {
+        for input in [
+            "",
+            "snomed-ecl-statistics\t1\n",
+            "snomed-ecl-statistics\t2\t20240101\n",
+            "snomed-ecl-statistics\t1\t20240101\ndescendants\t1\n",
+            "snomed-ecl-statistics\t1\t20240101\nchildren\tx\t1\n",
+            "snomed-ecl-statistics\t1\t20240101\nsiblings\t1\t1\n",
+        ] {
+            let err = StatisticsService::read_from(input.as_bytes(), None).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", input);
+        }
+    }
 }
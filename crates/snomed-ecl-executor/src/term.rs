@@ -0,0 +1,377 @@
+//! Compiled search terms for description term filters.
+//!
+//! A term filter such as `{{ term regex "^diab.*type [12]$" }}` is compiled
+//! once per filter evaluation into a [`TermMatcher`] and then tested against
+//! every description of every candidate concept. Invalid patterns are
+//! reported as [`EclExecutorError::InvalidTermPattern`] instead of silently
+//! falling back to another kind of match.
+//!
+//! The default `term = "heart att"` match is word based: every search word
+//! must be a prefix of some word of the term, in any order. Words are split
+//! on Unicode word boundaries and folded to lowercase without diacritics, so
+//! `term = "rinon"` finds the Spanish "riñón" and `term = "hjart"` the
+//! Swedish "hjärtsvikt".
+//!
+//! Wildcard terms are glob patterns over the whole term: `*` matches any run
+//! of characters and `?` exactly one. Case folding follows the description's
+//! case significance, so an entirely case-sensitive term must match the
+//! pattern's case exactly.
+//!
+//! Regular expressions require the `regex` feature. Patterns are matched
+//! case-insensitively, like every other term match type, and are limited in
+//! length and compiled size so that a single pathological pattern cannot
+//! exhaust memory. Matching itself is linear in the length of the term.
+
+use snomed_ecl::{SctId, TermMatchType};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::error::{EclExecutorError, EclResult};
+
+/// Maximum length of a regular expression, in bytes.
+#[cfg(feature = "regex")]
+pub(crate) const MAX_REGEX_LENGTH: usize = 1_000;
+
+/// Maximum size of a compiled regular expression, in bytes.
+#[cfg(feature = "regex")]
+pub(crate) const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Maximum nesting depth of groups and repetitions in a regular expression.
+#[cfg(feature = "regex")]
+pub(crate) const REGEX_NEST_LIMIT: u32 = 50;
+
+/// Case significance: the entire term is case sensitive.
+pub(crate) const ENTIRE_TERM_CASE_SENSITIVE: SctId = 900000000000020002;
+
+/// Case significance: only the initial character is case sensitive.
+pub(crate) const INITIAL_CHARACTER_CASE_SENSITIVE: SctId = 900000000000017005;
+
+/// A search term compiled for matching against description terms.
+#[derive(Debug, Clone)]
+pub(crate) enum TermMatcher {
+    /// Every folded search word prefixes some folded word of the term.
+    Match(Vec<String>),
+    /// The lowercased term starts with the search term.
+    StartsWith(String),
+    /// The lowercased term equals the search term.
+    Exact(String),
+    /// The whole term matches a glob pattern, folding case according to the
+    /// description's case significance.
+    Wildcard(Vec<char>),
+    /// The term matches a case-insensitive regular expression.
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl TermMatcher {
+    /// Compiles a search term.
+    ///
+    /// # Errors
+    ///
+    /// * [`EclExecutorError::InvalidTermPattern`] if a regular expression is
+    ///   malformed or exceeds the length, size or nesting limits
+    /// * [`EclExecutorError::UnsupportedFeature`] for regular expressions
+    ///   when the `regex` feature is disabled
+    pub(crate) fn compile(match_type: &TermMatchType, value: &str) -> EclResult<Self> {
+        let search_term = value.to_lowercase();
+
+        Ok(match match_type {
+            TermMatchType::Contains => Self::Match(fold_words(value)),
+            TermMatchType::StartsWith => Self::StartsWith(search_term),
+            TermMatchType::Exact => Self::Exact(search_term),
+            TermMatchType::Wildcard => Self::Wildcard(value.chars().collect()),
+            TermMatchType::Regex => Self::compile_regex(value)?,
+        })
+    }
+
+    /// Returns true if a description term with the given case significance
+    /// matches.
+    pub(crate) fn matches(&self, term: &str, case_significance_id: SctId) -> bool {
+        match self {
+            Self::Match(search_words) => {
+                let words = fold_words(term);
+                search_words
+                    .iter()
+                    .all(|search| words.iter().any(|word| word.starts_with(search.as_str())))
+            }
+            Self::StartsWith(search) => term.to_lowercase().starts_with(search.as_str()),
+            Self::Exact(search) => term.to_lowercase() == *search,
+            Self::Wildcard(pattern) => wildcard_matches(pattern, term, case_significance_id),
+            #[cfg(feature = "regex")]
+            Self::Regex(regex) => regex.is_match(term),
+        }
+    }
+
+    #[cfg(feature = "regex")]
+    fn compile_regex(pattern: &str) -> EclResult<Self> {
+        if pattern.len() > MAX_REGEX_LENGTH {
+            return Err(invalid_pattern(
+                pattern,
+                format!("pattern is longer than {} bytes", MAX_REGEX_LENGTH),
+            ));
+        }
+
+        regex::RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .size_limit(REGEX_SIZE_LIMIT)
+            .dfa_size_limit(REGEX_SIZE_LIMIT)
+            .nest_limit(REGEX_NEST_LIMIT)
+            .build()
+            .map(Self::Regex)
+            .map_err(|e| invalid_pattern(pattern, e.to_string()))
+    }
+
+    #[cfg(not(feature = "regex"))]
+    fn compile_regex(_pattern: &str) -> EclResult<Self> {
+        Err(EclExecutorError::UnsupportedFeature(
+            "term regex filters require the `regex` feature".to_string(),
+        ))
+    }
+}
+
+/// Splits text on Unicode word boundaries into folded words.
+///
+/// Apostrophes also separate words, so that the French elided "l'œil"
+/// yields "oeil" as well as English "Parkinson's" yielding "parkinson".
+fn fold_words(text: &str) -> Vec<String> {
+    text.unicode_words()
+        .flat_map(|word| word.split(['\'', '\u{2019}']))
+        .filter(|word| !word.is_empty())
+        .map(fold_word)
+        .collect()
+}
+
+/// Lowercases a word and strips diacritics.
+///
+/// Letters without a canonical decomposition are spelled out the way they
+/// are commonly typed without them, e.g. `ß` as `ss`.
+fn fold_word(word: &str) -> String {
+    let mut folded = String::with_capacity(word.len());
+    for c in word.nfd().flat_map(char::to_lowercase) {
+        match c {
+            c if is_combining_mark(c) => {}
+            'ß' => folded.push_str("ss"),
+            'æ' => folded.push_str("ae"),
+            'œ' => folded.push_str("oe"),
+            'ø' => folded.push('o'),
+            c => folded.push(c),
+        }
+    }
+    folded
+}
+
+/// Matches a whole term against a glob pattern.
+///
+/// Greedy matching that backtracks only to the most recent `*`, so the cost
+/// is bounded by the product of pattern and term lengths.
+fn wildcard_matches(pattern: &[char], term: &str, case_significance_id: SctId) -> bool {
+    let term: Vec<char> = term.chars().collect();
+    let char_eq = |p: char, index: usize| {
+        let t = term[index];
+        let case_sensitive = match case_significance_id {
+            ENTIRE_TERM_CASE_SENSITIVE => true,
+            INITIAL_CHARACTER_CASE_SENSITIVE => index == 0,
+            _ => false,
+        };
+        p == t || (!case_sensitive && p.to_lowercase().eq(t.to_lowercase()))
+    };
+
+    let (mut p, mut t) = (0, 0);
+    // Pattern position after the last `*` and the term position it resumed at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < term.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                backtrack = Some((p, t));
+            }
+            Some('?') => {
+                p += 1;
+                t += 1;
+            }
+            Some(&c) if char_eq(c, t) => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` absorb one more character
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(feature = "regex")]
+fn invalid_pattern(pattern: &str, reason: String) -> EclExecutorError {
+    EclExecutorError::InvalidTermPattern {
+        pattern: pattern.to_string(),
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(match_type: TermMatchType, value: &str) -> TermMatcher {
+        TermMatcher::compile(&match_type, value).unwrap()
+    }
+
+    const CASE_INSENSITIVE: SctId = 900000000000448009;
+
+    fn matches(matcher: &TermMatcher, term: &str) -> bool {
+        matcher.matches(term, CASE_INSENSITIVE)
+    }
+
+    #[test]
+    fn test_simple_match_types() {
+        assert!(matches(&matcher(TermMatchType::Contains, "Heart"), "Heart attack"));
+        assert!(!matches(&matcher(TermMatchType::Contains, "eart"), "Heart attack"));
+        assert!(matches(&matcher(TermMatchType::StartsWith, "heart"), "Heart attack"));
+        assert!(!matches(&matcher(TermMatchType::StartsWith, "attack"), "Heart attack"));
+        assert!(matches(&matcher(TermMatchType::Exact, "heart attack"), "Heart Attack"));
+        assert!(!matches(&matcher(TermMatchType::Exact, "heart"), "Heart attack"));
+    }
+
+    #[test]
+    fn test_match_word_prefixes_in_any_order() {
+        let heart_att = matcher(TermMatchType::Contains, "heart att");
+        assert!(matches(&heart_att, "Heart attack"));
+        assert!(matches(&heart_att, "Attack of the heart"));
+        assert!(matches(&heart_att, "Heart-attack"));
+        assert!(!matches(&heart_att, "Heart failure"));
+        // Not substring containment
+        assert!(!matches(&heart_att, "Sweetheart attack"));
+
+        assert!(matches(&matcher(TermMatchType::Contains, "parkinson"), "Parkinson's disease"));
+        assert!(matches(&matcher(TermMatchType::Contains, ""), "Anything"));
+    }
+
+    #[test]
+    fn test_match_folds_diacritics() {
+        // Spanish
+        assert!(matches(&matcher(TermMatchType::Contains, "rinon"), "Insuficiencia del riñón"));
+        assert!(matches(&matcher(TermMatchType::Contains, "CORAZÓN"), "Infarto de corazón"));
+        assert!(matches(&matcher(TermMatchType::Contains, "corazon"), "Infarto de CORAZÓN"));
+        // Swedish
+        assert!(matches(&matcher(TermMatchType::Contains, "hjart"), "Hjärtsvikt"));
+        assert!(matches(&matcher(TermMatchType::Contains, "lakemedel ov"), "Överkänslighet mot läkemedel"));
+        // German
+        assert!(matches(&matcher(TermMatchType::Contains, "fussball"), "Fußballverletzung"));
+        assert!(matches(&matcher(TermMatchType::Contains, "Schädel"), "Schadel-Hirn-Trauma"));
+        // French
+        assert!(matches(&matcher(TermMatchType::Contains, "oeil"), "Corps étranger dans l'œil"));
+        assert!(matches(&matcher(TermMatchType::Contains, "etranger"), "Corps étranger dans l'œil"));
+        // Decomposed input folds the same as precomposed
+        assert!(matches(&matcher(TermMatchType::Contains, "cafe\u{301}"), "Café"));
+    }
+
+    #[test]
+    fn test_wildcard_is_anchored() {
+        let wild = matcher(TermMatchType::Wildcard, "diab*tes");
+
+        assert!(matches(&wild, "Diabetes"));
+        assert!(matches(&wild, "diabtes"));
+        assert!(!matches(&wild, "tes"));
+        assert!(!matches(&wild, "Diabetes mellitus"));
+        assert!(!matches(&wild, "Type 1 diabetes"));
+    }
+
+    #[test]
+    fn test_wildcard_star_and_question_mark() {
+        let cardio = matcher(TermMatchType::Wildcard, "cardi*opathy");
+        assert!(matches(&cardio, "Cardiomyopathy"));
+        assert!(matches(&cardio, "cardiopathy"));
+        assert!(!matches(&cardio, "Cardiomyopathy due to drug"));
+
+        let itis = matcher(TermMatchType::Wildcard, "*itis");
+        assert!(matches(&itis, "Appendicitis"));
+        assert!(!matches(&itis, "Appendicitis of stump"));
+
+        let single = matcher(TermMatchType::Wildcard, "diab?tes");
+        assert!(matches(&single, "Diabetes"));
+        assert!(!matches(&single, "Diabtes"));
+        assert!(!matches(&single, "Diabeetes"));
+
+        // Backtracking past an earlier partial match
+        let repeated = matcher(TermMatchType::Wildcard, "*a*ab");
+        assert!(matches(&repeated, "aaab"));
+        assert!(!matches(&repeated, "aaba"));
+
+        assert!(matches(&matcher(TermMatchType::Wildcard, "*"), ""));
+        assert!(!matches(&matcher(TermMatchType::Wildcard, "?"), ""));
+    }
+
+    #[test]
+    fn test_wildcard_case_significance() {
+        let wild = matcher(TermMatchType::Wildcard, "parkinson*");
+
+        assert!(wild.matches("Parkinson disease", CASE_INSENSITIVE));
+        assert!(!wild.matches("Parkinson disease", INITIAL_CHARACTER_CASE_SENSITIVE));
+        assert!(!wild.matches("Parkinson disease", ENTIRE_TERM_CASE_SENSITIVE));
+
+        let wild = matcher(TermMatchType::Wildcard, "Parkinson DISEASE");
+        assert!(wild.matches("Parkinson disease", INITIAL_CHARACTER_CASE_SENSITIVE));
+        assert!(!wild.matches("Parkinson disease", ENTIRE_TERM_CASE_SENSITIVE));
+
+        let wild = matcher(TermMatchType::Wildcard, "?H");
+        assert!(wild.matches("pH", ENTIRE_TERM_CASE_SENSITIVE));
+        assert!(!wild.matches("ph", ENTIRE_TERM_CASE_SENSITIVE));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_regex() {
+        let regex = matcher(TermMatchType::Regex, "^diab.*type [12]$");
+
+        assert!(matches(&regex, "Diabetes mellitus type 1"));
+        assert!(matches(&regex, "diabetes mellitus type 2"));
+        assert!(!matches(&regex, "Diabetes mellitus type 3"));
+        // No substring fallback
+        assert!(!matches(&regex, "Gestational diabetes type 1"));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_invalid_regex() {
+        for pattern in ["diab(", "[a-", "(?<name"] {
+            let err = TermMatcher::compile(&TermMatchType::Regex, pattern).unwrap_err();
+            assert!(
+                matches!(err, EclExecutorError::InvalidTermPattern { .. }),
+                "{}",
+                pattern
+            );
+        }
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_regex_limits() {
+        let long = "a".repeat(MAX_REGEX_LENGTH + 1);
+        assert!(TermMatcher::compile(&TermMatchType::Regex, &long).is_err());
+
+        // Compiles to far more than the size limit
+        let huge = "((a{100}){100}){100}";
+        let err = TermMatcher::compile(&TermMatchType::Regex, huge).unwrap_err();
+        assert!(err.to_string().contains("size limit"), "{}", err);
+
+        let nested = format!("{}a{}", "(".repeat(100), ")".repeat(100));
+        assert!(TermMatcher::compile(&TermMatchType::Regex, &nested).is_err());
+    }
+
+    #[cfg(not(feature = "regex"))]
+    #[test]
+    fn test_regex_requires_feature() {
+        let err = TermMatcher::compile(&TermMatchType::Regex, "^diab").unwrap_err();
+        assert!(matches!(err, EclExecutorError::UnsupportedFeature(_)));
+    }
+}
@@ -48,6 +48,8 @@
 //! let result = executor.execute("< 73211009")?;
 //! ```
 
+use std::collections::HashMap;
+
 use snomed_ecl::SctId;
 
 // =============================================================================
@@ -132,6 +134,69 @@ pub struct LanguageRefsetMember {
     pub acceptability: Acceptability,
 }
 
+/// A typed value of a reference set member field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RefsetFieldValue {
+    /// Component identifier (concept, description or relationship).
+    Component(SctId),
+    /// Integer value, including YYYYMMDD dates.
+    Integer(i64),
+    /// Decimal value.
+    Decimal(f64),
+    /// String value.
+    String(String),
+    /// Boolean value.
+    Boolean(bool),
+}
+
+/// A reference set member row for member filters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefsetMember {
+    /// The member ID (a UUID in RF2).
+    pub id: String,
+    /// Effective time in YYYYMMDD format.
+    pub effective_time: Option<u32>,
+    /// Whether the member is active.
+    pub active: bool,
+    /// Module ID.
+    pub module_id: SctId,
+    /// The reference set ID.
+    pub refset_id: SctId,
+    /// The referenced component ID.
+    pub referenced_component_id: SctId,
+    /// Additional fields in column order, e.g. `("mapTarget", String("J45.9"))`
+    /// for an extended map reference set.
+    pub fields: Vec<(String, RefsetFieldValue)>,
+}
+
+impl RefsetMember {
+    /// Gets a field by its RF2 column name, ignoring ASCII case.
+    ///
+    /// Covers both the standard columns (`id`, `effectiveTime`, `active`,
+    /// `moduleId`, `refsetId`, `referencedComponentId`) and the additional
+    /// fields.
+    pub fn field(&self, name: &str) -> Option<RefsetFieldValue> {
+        let standard = match name.to_ascii_lowercase().as_str() {
+            "id" => Some(RefsetFieldValue::String(self.id.clone())),
+            "effectivetime" => self.effective_time.map(|t| RefsetFieldValue::Integer(t.into())),
+            "active" => Some(RefsetFieldValue::Boolean(self.active)),
+            "moduleid" => Some(RefsetFieldValue::Component(self.module_id)),
+            "refsetid" => Some(RefsetFieldValue::Component(self.refset_id)),
+            "referencedcomponentid" => {
+                Some(RefsetFieldValue::Component(self.referenced_component_id))
+            }
+            _ => None,
+        };
+
+        standard.or_else(|| {
+            self.fields
+                .iter()
+                .find(|(field, _)| field.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.clone())
+        })
+    }
+}
+
 /// Concept metadata for filtering.
 #[derive(Debug, Clone)]
 pub struct ConceptInfo {
@@ -212,12 +277,22 @@ pub trait EclQueryable: Send + Sync {
     /// - The concept has a relationship of type `attribute_type_id`
     /// - The relationship's destination is `target_id`
     ///
-    /// Used for reverse attribute lookups in dot notation.
+    /// Used for reverse attribute lookups in dot notation and refinements.
     fn get_concepts_with_attribute(&self, attribute_type_id: SctId, target_id: SctId) -> Vec<SctId> {
         let _ = (attribute_type_id, target_id);
         Vec::new()
     }
 
+    /// Returns true if `get_concepts_with_attribute` is backed by a reverse index.
+    ///
+    /// When true, the executor may evaluate refinements such as
+    /// `* : 246075003 = 387517004` by looking up the concepts that have the
+    /// attribute value instead of scanning every focus concept. Stores that
+    /// override `get_concepts_with_attribute` should also override this.
+    fn supports_attribute_lookup(&self) -> bool {
+        false
+    }
+
     /// Gets concrete domain values for a concept.
     ///
     /// Returns concrete relationships (numeric/string values) for the concept.
@@ -283,6 +358,16 @@ pub trait EclQueryable: Send + Sync {
         Vec::new()
     }
 
+    /// Gets the member rows of a reference set, with their additional fields.
+    ///
+    /// Used for member filters such as `^ 447562003 {{ M mapTarget = "J45.9" }}`.
+    /// Returns an empty Vec if the reference set doesn't exist or the store
+    /// doesn't expose member rows, in which case member filters match nothing.
+    fn get_refset_member_rows(&self, refset_id: SctId) -> Vec<RefsetMember> {
+        let _ = refset_id;
+        Vec::new()
+    }
+
     /// Gets the semantic tag for a concept (extracted from FSN).
     ///
     /// The semantic tag is the text in parentheses at the end of the FSN.
@@ -318,7 +403,9 @@ pub trait EclQueryable: Send + Sync {
 
     /// Gets inbound relationships (where this concept is the destination).
     ///
-    /// Used for reverse attribute queries (R flag in ECL).
+    /// Used for reverse attribute queries (R flag in ECL). Each returned
+    /// relationship has `destination_id` set to its *source* concept, so
+    /// reverse refinements can match it against the attribute value.
     fn get_inbound_relationships(&self, concept_id: SctId) -> Vec<RelationshipInfo> {
         let _ = concept_id;
         Vec::new()
@@ -340,6 +427,31 @@ pub trait EclQueryable: Send + Sync {
         Vec::new()
     }
 
+    /// Gets the historical associations of a concept, keyed by association
+    /// reference set ID.
+    ///
+    /// Returns the inactive concepts associated with the concept in each
+    /// historical association reference set. Used for history supplements
+    /// with an association subset, such as
+    /// `{{ +HISTORY (<< 900000000000527005) }}`; stores with local historical
+    /// reference sets should override it so they can be followed.
+    ///
+    /// The default implementation collects the standard associations from
+    /// [`get_historical_associations_by_type`](Self::get_historical_associations_by_type).
+    fn get_historical_associations_by_refset(
+        &self,
+        concept_id: SctId,
+    ) -> HashMap<SctId, Vec<SctId>> {
+        HistoryAssociationType::ALL
+            .iter()
+            .filter_map(|&association_type| {
+                let associated =
+                    self.get_historical_associations_by_type(concept_id, association_type);
+                (!associated.is_empty()).then(|| (association_type.refset_id(), associated))
+            })
+            .collect()
+    }
+
     /// Resolves an alternate identifier to a SNOMED CT concept ID.
     ///
     /// Alternate identifiers use different schemes to identify concepts:
@@ -349,6 +461,11 @@ pub trait EclQueryable: Send + Sync {
     ///
     /// Returns `Some(concept_id)` if the identifier can be resolved, `None` otherwise.
     ///
+    /// Stores loaded from an RF2 release also resolve identifiers from its
+    /// alternate identifier files; the scheme may then be the identifier
+    /// scheme concept's ID or any of its active terms, ignoring case
+    /// (e.g. `LOINC`).
+    ///
     /// # Arguments
     /// * `scheme` - The identifier scheme (URI prefix)
     /// * `identifier` - The identifier value within that scheme
@@ -383,6 +500,32 @@ pub enum HistoryAssociationType {
     MovedFrom,
 }
 
+impl HistoryAssociationType {
+    /// Every association type.
+    pub const ALL: [HistoryAssociationType; 7] = [
+        HistoryAssociationType::SameAs,
+        HistoryAssociationType::ReplacedBy,
+        HistoryAssociationType::PossiblyEquivalentTo,
+        HistoryAssociationType::Alternative,
+        HistoryAssociationType::WasA,
+        HistoryAssociationType::MovedTo,
+        HistoryAssociationType::MovedFrom,
+    ];
+
+    /// Returns the ID of the association's reference set.
+    pub fn refset_id(self) -> SctId {
+        match self {
+            HistoryAssociationType::SameAs => 900000000000527005,
+            HistoryAssociationType::ReplacedBy => 900000000000526001,
+            HistoryAssociationType::PossiblyEquivalentTo => 900000000000523009,
+            HistoryAssociationType::Alternative => 900000000000530003,
+            HistoryAssociationType::WasA => 900000000000528000,
+            HistoryAssociationType::MovedTo => 900000000000524003,
+            HistoryAssociationType::MovedFrom => 900000000000525002,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -435,6 +578,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_refset_member_field() {
+        let member = RefsetMember {
+            id: "d2c4e1a0-0000-0000-0000-000000000001".to_string(),
+            effective_time: Some(20200131),
+            active: true,
+            module_id: 449080006,
+            refset_id: 447562003,
+            referenced_component_id: 195967001,
+            fields: vec![
+                ("mapGroup".to_string(), RefsetFieldValue::Integer(1)),
+                ("mapTarget".to_string(), RefsetFieldValue::String("J45.9".to_string())),
+            ],
+        };
+
+        assert_eq!(member.field("active"), Some(RefsetFieldValue::Boolean(true)));
+        assert_eq!(member.field("effectiveTime"), Some(RefsetFieldValue::Integer(20200131)));
+        assert_eq!(
+            member.field("referencedComponentId"),
+            Some(RefsetFieldValue::Component(195967001))
+        );
+        assert_eq!(
+            member.field("maptarget"),
+            Some(RefsetFieldValue::String("J45.9".to_string()))
+        );
+        assert_eq!(member.field("mapPriority"), None);
+    }
+
     #[test]
     fn test_mock_store_has_concept() {
         let mut store = MockStore::new();
@@ -483,4 +654,50 @@ mod tests {
         assert!(ids.contains(&200));
         assert!(ids.contains(&300));
     }
+
+    #[test]
+    fn test_historical_associations_by_refset_default() {
+        /// Store with a SAME AS and a REPLACED BY association to 100.
+        struct HistoryStore;
+
+        impl EclQueryable for HistoryStore {
+            fn get_children(&self, _concept_id: SctId) -> Vec<SctId> {
+                Vec::new()
+            }
+
+            fn get_parents(&self, _concept_id: SctId) -> Vec<SctId> {
+                Vec::new()
+            }
+
+            fn has_concept(&self, concept_id: SctId) -> bool {
+                concept_id == 100
+            }
+
+            fn all_concept_ids(&self) -> Box<dyn Iterator<Item = SctId> + '_> {
+                Box::new(std::iter::once(100))
+            }
+
+            fn get_refset_members(&self, _refset_id: SctId) -> Vec<SctId> {
+                Vec::new()
+            }
+
+            fn get_historical_associations_by_type(
+                &self,
+                concept_id: SctId,
+                association_type: HistoryAssociationType,
+            ) -> Vec<SctId> {
+                match (concept_id, association_type) {
+                    (100, HistoryAssociationType::SameAs) => vec![200],
+                    (100, HistoryAssociationType::ReplacedBy) => vec![300, 400],
+                    _ => Vec::new(),
+                }
+            }
+        }
+
+        let by_refset = HistoryStore.get_historical_associations_by_refset(100);
+        assert_eq!(by_refset.len(), 2);
+        assert_eq!(by_refset[&900000000000527005], vec![200]);
+        assert_eq!(by_refset[&900000000000526001], vec![300, 400]);
+        assert!(HistoryStore.get_historical_associations_by_refset(200).is_empty());
+    }
 }
@@ -4,11 +4,21 @@
 //! the SNOMED CT concept hierarchy using BFS (Breadth-First Search).
 
 use std::collections::{HashSet, VecDeque};
+use std::convert::Infallible;
 
 use snomed_ecl::SctId;
 
+use crate::error::EclResult;
+use crate::guard::{ExecutionGuard, GUARD_CHECK_INTERVAL};
 use crate::traits::EclQueryable;
 
+/// Minimum BFS frontier size that is expanded in parallel.
+///
+/// Smaller frontiers are cheaper to expand on the current thread than to
+/// distribute across the rayon pool.
+#[cfg(feature = "parallel")]
+const PARALLEL_FRONTIER_THRESHOLD: usize = 64;
+
 /// Traverses SNOMED CT concept hierarchies using BFS.
 ///
 /// The traverser provides efficient methods for:
@@ -33,18 +43,52 @@ use crate::traits::EclQueryable;
 /// ```
 pub struct HierarchyTraverser<'a> {
     store: &'a dyn EclQueryable,
+    guard: ExecutionGuard,
+    parallel: bool,
 }
 
 impl<'a> HierarchyTraverser<'a> {
     /// Creates a new hierarchy traverser with the given store.
     pub fn new(store: &'a dyn EclQueryable) -> Self {
-        Self { store }
+        Self::with_guard(store, ExecutionGuard::unlimited())
+    }
+
+    /// Creates a traverser whose traversals honour the given execution limits.
+    ///
+    /// The `try_*` traversals check the guard's cancellation token and
+    /// deadline while traversing and abort with the corresponding error; the
+    /// set traversals also stop once the result-size limit is exceeded.
+    pub fn with_guard(store: &'a dyn EclQueryable, guard: ExecutionGuard) -> Self {
+        Self {
+            store,
+            guard,
+            parallel: false,
+        }
+    }
+
+    /// Enables parallel frontier expansion for the `*_of_set` traversals.
+    ///
+    /// Has no effect unless the crate is built with the `parallel` feature.
+    pub fn with_parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    /// Returns the execution guard used by this traverser.
+    pub fn guard(&self) -> &ExecutionGuard {
+        &self.guard
+    }
+
+    /// Returns true if this traverser evaluates work in parallel.
+    pub fn is_parallel(&self) -> bool {
+        cfg!(feature = "parallel") && self.parallel
     }
 
     /// Gets all descendants of a concept using BFS traversal.
     ///
     /// This returns all concepts that are reachable by following
-    /// child relationships (IS_A in reverse direction).
+    /// child relationships (IS_A in reverse direction). The traverser's
+    /// guard is not consulted; see [`try_get_descendants`](Self::try_get_descendants).
     ///
     /// # Arguments
     ///
@@ -62,26 +106,7 @@ impl<'a> HierarchyTraverser<'a> {
     /// - Target: <100ms for 50K descendants
     pub fn get_descendants(&self, concept_id: SctId) -> HashSet<SctId> {
         // Pre-allocate with estimated capacity for performance
-        let mut visited = HashSet::with_capacity(1000);
-        let mut queue = VecDeque::with_capacity(100);
-
-        // Start with the concept's direct children
-        for child in self.store.get_children(concept_id) {
-            if visited.insert(child) {
-                queue.push_back(child);
-            }
-        }
-
-        // BFS traversal
-        while let Some(current) = queue.pop_front() {
-            for child in self.store.get_children(current) {
-                if visited.insert(child) {
-                    queue.push_back(child);
-                }
-            }
-        }
-
-        visited
+        into_ok(self.traverse([concept_id], 1000, children, unlimited))
     }
 
     /// Gets all descendants of a concept, including the concept itself.
@@ -104,7 +129,8 @@ impl<'a> HierarchyTraverser<'a> {
     /// Gets all ancestors of a concept using BFS traversal.
     ///
     /// This returns all concepts that are reachable by following
-    /// parent relationships (IS_A direction).
+    /// parent relationships (IS_A direction). The traverser's guard is not
+    /// consulted; see [`try_get_ancestors`](Self::try_get_ancestors).
     ///
     /// # Arguments
     ///
@@ -121,26 +147,7 @@ impl<'a> HierarchyTraverser<'a> {
     /// can have multiple parents. This method correctly handles this case.
     pub fn get_ancestors(&self, concept_id: SctId) -> HashSet<SctId> {
         // Ancestor traversal typically returns fewer results than descendant
-        let mut visited = HashSet::with_capacity(100);
-        let mut queue = VecDeque::with_capacity(50);
-
-        // Start with the concept's direct parents
-        for parent in self.store.get_parents(concept_id) {
-            if visited.insert(parent) {
-                queue.push_back(parent);
-            }
-        }
-
-        // BFS traversal
-        while let Some(current) = queue.pop_front() {
-            for parent in self.store.get_parents(current) {
-                if visited.insert(parent) {
-                    queue.push_back(parent);
-                }
-            }
-        }
-
-        visited
+        into_ok(self.traverse([concept_id], 100, parents, unlimited))
     }
 
     /// Gets all ancestors of a concept, including the concept itself.
@@ -192,6 +199,78 @@ impl<'a> HierarchyTraverser<'a> {
         self.store.get_parents(concept_id).into_iter().collect()
     }
 
+    /// Gets all descendants of a set of concepts using a single BFS traversal.
+    ///
+    /// All source concepts seed the same queue and share one visited set,
+    /// so overlapping sub-hierarchies are traversed only once.
+    ///
+    /// # Arguments
+    ///
+    /// * `concept_ids` - The concepts to get descendants of
+    ///
+    /// # Returns
+    ///
+    /// The union of the descendants of every source concept. A source
+    /// concept is only included if it is itself a descendant of another
+    /// source concept.
+    pub fn get_descendants_of_set(&self, concept_ids: &HashSet<SctId>) -> HashSet<SctId> {
+        into_ok(self.traverse_set(concept_ids, 1000, children, unlimited))
+    }
+
+    /// Gets all descendants of a set of concepts, including the concepts themselves.
+    pub fn get_descendants_or_self_of_set(&self, concept_ids: &HashSet<SctId>) -> HashSet<SctId> {
+        let mut result = self.get_descendants_of_set(concept_ids);
+        result.extend(concept_ids.iter().copied());
+        result
+    }
+
+    /// Gets all ancestors of a set of concepts using a single BFS traversal.
+    ///
+    /// Shared ancestors (e.g. the root) are visited only once regardless of
+    /// how many source concepts reach them.
+    ///
+    /// # Arguments
+    ///
+    /// * `concept_ids` - The concepts to get ancestors of
+    ///
+    /// # Returns
+    ///
+    /// The union of the ancestors of every source concept.
+    pub fn get_ancestors_of_set(&self, concept_ids: &HashSet<SctId>) -> HashSet<SctId> {
+        into_ok(self.traverse_set(concept_ids, 100, parents, unlimited))
+    }
+
+    /// Gets all ancestors of a set of concepts, including the concepts themselves.
+    pub fn get_ancestors_or_self_of_set(&self, concept_ids: &HashSet<SctId>) -> HashSet<SctId> {
+        let mut result = self.get_ancestors_of_set(concept_ids);
+        result.extend(concept_ids.iter().copied());
+        result
+    }
+
+    /// Gets the union of the direct children of a set of concepts.
+    pub fn get_direct_children_of_set(&self, concept_ids: &HashSet<SctId>) -> HashSet<SctId> {
+        into_ok(self.neighbours_of_set(concept_ids, children, unlimited))
+    }
+
+    /// Gets the union of the direct parents of a set of concepts.
+    pub fn get_direct_parents_of_set(&self, concept_ids: &HashSet<SctId>) -> HashSet<SctId> {
+        into_ok(self.neighbours_of_set(concept_ids, parents, unlimited))
+    }
+
+    /// Returns true if any proper ancestor of a concept is in `targets`.
+    ///
+    /// This is a membership test for `< targets`: the upward traversal stops
+    /// as soon as a target is reached, so it never expands the (potentially
+    /// huge) descendant set of the targets.
+    ///
+    /// # Arguments
+    ///
+    /// * `concept_id` - The concept to test
+    /// * `targets` - Candidate ancestors
+    pub fn has_ancestor_in(&self, concept_id: SctId, targets: &HashSet<SctId>) -> bool {
+        into_ok(self.find_ancestor_in(concept_id, targets, unlimited))
+    }
+
     /// Returns the number of concepts that would be traversed for a descendant query.
     ///
     /// Useful for estimating query cost without executing.
@@ -205,6 +284,333 @@ impl<'a> HierarchyTraverser<'a> {
     pub fn count_ancestors(&self, concept_id: SctId) -> usize {
         self.get_ancestors(concept_id).len()
     }
+
+    // =========================================================================
+    // Guarded traversals
+    // =========================================================================
+
+    /// Like [`get_descendants`](Self::get_descendants), but checks the
+    /// guard's cancellation token and deadline while traversing.
+    ///
+    /// The result-size limit is not applied: the executor uses this for
+    /// per-concept lookups (`!!<`), not for results.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Cancelled` or `Timeout` if the traverser's guard trips.
+    pub fn try_get_descendants(&self, concept_id: SctId) -> EclResult<HashSet<SctId>> {
+        self.traverse([concept_id], 1000, children, self.deadline_check())
+    }
+
+    /// Like [`get_ancestors`](Self::get_ancestors), but checks the guard's
+    /// cancellation token and deadline while traversing.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Cancelled` or `Timeout` if the traverser's guard trips.
+    pub fn try_get_ancestors(&self, concept_id: SctId) -> EclResult<HashSet<SctId>> {
+        self.traverse([concept_id], 100, parents, self.deadline_check())
+    }
+
+    /// Like [`get_descendants_of_set`](Self::get_descendants_of_set), but
+    /// checks the guard while traversing.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Cancelled` or `Timeout` if the traverser's guard trips, and
+    /// `ResultTooLarge` as soon as the traversal has visited more concepts
+    /// than the guard's result-size limit.
+    pub fn try_get_descendants_of_set(
+        &self,
+        concept_ids: &HashSet<SctId>,
+    ) -> EclResult<HashSet<SctId>> {
+        self.traverse_set(concept_ids, 1000, children, self.budget_check())
+    }
+
+    /// Like [`get_descendants_or_self_of_set`](Self::get_descendants_or_self_of_set),
+    /// but checks the guard while traversing.
+    ///
+    /// # Errors
+    ///
+    /// As for [`try_get_descendants_of_set`](Self::try_get_descendants_of_set).
+    pub fn try_get_descendants_or_self_of_set(
+        &self,
+        concept_ids: &HashSet<SctId>,
+    ) -> EclResult<HashSet<SctId>> {
+        let mut result = self.try_get_descendants_of_set(concept_ids)?;
+        result.extend(concept_ids.iter().copied());
+        self.guard.check_size(result.len())?;
+        Ok(result)
+    }
+
+    /// Like [`get_ancestors_of_set`](Self::get_ancestors_of_set), but checks
+    /// the guard while traversing.
+    ///
+    /// # Errors
+    ///
+    /// As for [`try_get_descendants_of_set`](Self::try_get_descendants_of_set).
+    pub fn try_get_ancestors_of_set(
+        &self,
+        concept_ids: &HashSet<SctId>,
+    ) -> EclResult<HashSet<SctId>> {
+        self.traverse_set(concept_ids, 100, parents, self.budget_check())
+    }
+
+    /// Like [`get_ancestors_or_self_of_set`](Self::get_ancestors_or_self_of_set),
+    /// but checks the guard while traversing.
+    ///
+    /// # Errors
+    ///
+    /// As for [`try_get_descendants_of_set`](Self::try_get_descendants_of_set).
+    pub fn try_get_ancestors_or_self_of_set(
+        &self,
+        concept_ids: &HashSet<SctId>,
+    ) -> EclResult<HashSet<SctId>> {
+        let mut result = self.try_get_ancestors_of_set(concept_ids)?;
+        result.extend(concept_ids.iter().copied());
+        self.guard.check_size(result.len())?;
+        Ok(result)
+    }
+
+    /// Like [`get_direct_children_of_set`](Self::get_direct_children_of_set),
+    /// but checks the guard for every source concept.
+    ///
+    /// # Errors
+    ///
+    /// As for [`try_get_descendants_of_set`](Self::try_get_descendants_of_set).
+    pub fn try_get_direct_children_of_set(
+        &self,
+        concept_ids: &HashSet<SctId>,
+    ) -> EclResult<HashSet<SctId>> {
+        self.neighbours_of_set(concept_ids, children, self.budget_check())
+    }
+
+    /// Like [`get_direct_parents_of_set`](Self::get_direct_parents_of_set),
+    /// but checks the guard for every source concept.
+    ///
+    /// # Errors
+    ///
+    /// As for [`try_get_descendants_of_set`](Self::try_get_descendants_of_set).
+    pub fn try_get_direct_parents_of_set(
+        &self,
+        concept_ids: &HashSet<SctId>,
+    ) -> EclResult<HashSet<SctId>> {
+        self.neighbours_of_set(concept_ids, parents, self.budget_check())
+    }
+
+    /// Like [`has_ancestor_in`](Self::has_ancestor_in), but checks the
+    /// guard's cancellation token and deadline while traversing.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Cancelled` or `Timeout` if the traverser's guard trips.
+    pub fn try_has_ancestor_in(
+        &self,
+        concept_id: SctId,
+        targets: &HashSet<SctId>,
+    ) -> EclResult<bool> {
+        self.find_ancestor_in(concept_id, targets, self.deadline_check())
+    }
+
+    /// Checks the deadline every `GUARD_CHECK_INTERVAL` expanded concepts.
+    ///
+    /// Used by the single-concept traversals, which run once per concept of
+    /// a set (`!!<`, `!!>`, refinement ancestor lookups) and so must stay
+    /// cheap.
+    fn deadline_check(&self) -> impl FnMut(usize, usize) -> EclResult<()> + '_ {
+        |expanded, _| {
+            if expanded.is_multiple_of(GUARD_CHECK_INTERVAL) {
+                self.guard.check()?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Checks the deadline and the result-size budget on every step of a
+    /// traversal whose visited set is the result.
+    fn budget_check(&self) -> impl FnMut(usize, usize) -> EclResult<()> + '_ {
+        |_, size| {
+            self.guard.check()?;
+            self.guard.check_size(size)
+        }
+    }
+
+    /// Single-source BFS shared by `get_descendants` and `get_ancestors`.
+    ///
+    /// `check` is called with the number of expanded concepts and the size
+    /// of the visited set before each concept is expanded.
+    fn traverse<E>(
+        &self,
+        seeds: impl IntoIterator<Item = SctId>,
+        capacity: usize,
+        next: Neighbours,
+        mut check: impl FnMut(usize, usize) -> Result<(), E>,
+    ) -> Result<HashSet<SctId>, E> {
+        let mut visited = HashSet::with_capacity(capacity);
+        let mut queue = VecDeque::with_capacity(capacity / 10);
+
+        // Start with the seeds' direct neighbours
+        for seed in seeds {
+            for neighbour in next(self.store, seed) {
+                if visited.insert(neighbour) {
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+
+        // BFS traversal
+        let mut expanded = 0usize;
+        while let Some(current) = queue.pop_front() {
+            check(expanded, visited.len())?;
+            expanded += 1;
+
+            for neighbour in next(self.store, current) {
+                if visited.insert(neighbour) {
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+
+        check(expanded, visited.len())?;
+        Ok(visited)
+    }
+
+    /// Multi-source BFS shared by the `*_of_set` traversals.
+    ///
+    /// All sources seed one queue and share the visited set. In parallel
+    /// mode the BFS proceeds level by level and large frontiers are expanded
+    /// on the rayon pool; the visited set is only updated on the calling
+    /// thread, so the result is identical to the sequential traversal.
+    fn traverse_set<E>(
+        &self,
+        concept_ids: &HashSet<SctId>,
+        capacity: usize,
+        next: Neighbours,
+        check: impl FnMut(usize, usize) -> Result<(), E>,
+    ) -> Result<HashSet<SctId>, E> {
+        #[cfg(feature = "parallel")]
+        if self.parallel {
+            return self.traverse_set_parallel(concept_ids, capacity, next, check);
+        }
+
+        self.traverse(concept_ids.iter().copied(), capacity, next, check)
+    }
+
+    /// Level-synchronous BFS with parallel frontier expansion.
+    ///
+    /// `check` is called once per BFS level.
+    #[cfg(feature = "parallel")]
+    fn traverse_set_parallel<E>(
+        &self,
+        concept_ids: &HashSet<SctId>,
+        capacity: usize,
+        next: Neighbours,
+        mut check: impl FnMut(usize, usize) -> Result<(), E>,
+    ) -> Result<HashSet<SctId>, E> {
+        use rayon::prelude::*;
+
+        let mut visited = HashSet::with_capacity(capacity);
+        let mut frontier: Vec<SctId> = concept_ids.iter().copied().collect();
+
+        let mut level = 0usize;
+        while !frontier.is_empty() {
+            check(level, visited.len())?;
+            level += 1;
+
+            let neighbours: Vec<SctId> = if frontier.len() >= PARALLEL_FRONTIER_THRESHOLD {
+                frontier
+                    .par_iter()
+                    .flat_map_iter(|&id| next(self.store, id))
+                    .collect()
+            } else {
+                frontier
+                    .iter()
+                    .flat_map(|&id| next(self.store, id))
+                    .collect()
+            };
+
+            frontier = neighbours
+                .into_iter()
+                .filter(|&id| visited.insert(id))
+                .collect();
+        }
+
+        check(level, visited.len())?;
+        Ok(visited)
+    }
+
+    /// Union of the direct neighbours of a set of concepts, calling `check`
+    /// before each source concept is expanded and once at the end.
+    fn neighbours_of_set<E>(
+        &self,
+        concept_ids: &HashSet<SctId>,
+        next: Neighbours,
+        mut check: impl FnMut(usize, usize) -> Result<(), E>,
+    ) -> Result<HashSet<SctId>, E> {
+        let mut result = HashSet::new();
+        for (expanded, &concept_id) in concept_ids.iter().enumerate() {
+            check(expanded, result.len())?;
+            result.extend(next(self.store, concept_id));
+        }
+        check(concept_ids.len(), result.len())?;
+        Ok(result)
+    }
+
+    /// Upward BFS for `has_ancestor_in`, stopping at the first target.
+    fn find_ancestor_in<E>(
+        &self,
+        concept_id: SctId,
+        targets: &HashSet<SctId>,
+        mut check: impl FnMut(usize, usize) -> Result<(), E>,
+    ) -> Result<bool, E> {
+        if targets.is_empty() {
+            return Ok(false);
+        }
+
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::from([concept_id]);
+
+        let mut expanded = 0usize;
+        while let Some(current) = queue.pop_front() {
+            check(expanded, visited.len())?;
+            expanded += 1;
+
+            for parent in self.store.get_parents(current) {
+                if targets.contains(&parent) {
+                    return Ok(true);
+                }
+                if visited.insert(parent) {
+                    queue.push_back(parent);
+                }
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+/// Yields the neighbours of a concept in the traversal direction.
+type Neighbours = fn(&dyn EclQueryable, SctId) -> Vec<SctId>;
+
+fn children(store: &dyn EclQueryable, concept_id: SctId) -> Vec<SctId> {
+    store.get_children(concept_id)
+}
+
+fn parents(store: &dyn EclQueryable, concept_id: SctId) -> Vec<SctId> {
+    store.get_parents(concept_id)
+}
+
+/// The check used by the unguarded traversals: never fails.
+fn unlimited(_expanded: usize, _size: usize) -> Result<(), Infallible> {
+    Ok(())
+}
+
+/// Unwraps the result of a traversal that cannot fail.
+fn into_ok<T>(result: Result<T, Infallible>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(never) => match never {},
+    }
 }
 
 #[cfg(test)]
@@ -534,6 +940,206 @@ mod tests {
         assert!(parents.is_empty());
     }
 
+    // Set traversal tests
+
+    #[test]
+    fn test_get_descendants_of_set() {
+        let store = create_test_hierarchy();
+        let traverser = HierarchyTraverser::new(&store);
+
+        let sources: HashSet<SctId> = [200, 300].into_iter().collect();
+        let descendants = traverser.get_descendants_of_set(&sources);
+
+        // 200's descendants (400, 500, 700) plus 300's descendants (600)
+        assert_eq!(descendants.len(), 4);
+        assert!(descendants.contains(&400));
+        assert!(descendants.contains(&500));
+        assert!(descendants.contains(&600));
+        assert!(descendants.contains(&700));
+        assert!(!descendants.contains(&200));
+        assert!(!descendants.contains(&300));
+    }
+
+    #[test]
+    fn test_get_descendants_of_set_overlapping() {
+        let store = create_test_hierarchy();
+        let traverser = HierarchyTraverser::new(&store);
+
+        // 400 is itself a descendant of 200, so it stays in the result
+        let sources: HashSet<SctId> = [200, 400].into_iter().collect();
+        let descendants = traverser.get_descendants_of_set(&sources);
+
+        assert_eq!(descendants.len(), 3);
+        assert!(descendants.contains(&400));
+        assert!(descendants.contains(&500));
+        assert!(descendants.contains(&700));
+    }
+
+    #[test]
+    fn test_get_descendants_or_self_of_set() {
+        let store = create_test_hierarchy();
+        let traverser = HierarchyTraverser::new(&store);
+
+        let sources: HashSet<SctId> = [400, 600].into_iter().collect();
+        let result = traverser.get_descendants_or_self_of_set(&sources);
+
+        assert_eq!(result.len(), 3);
+        assert!(result.contains(&400));
+        assert!(result.contains(&600));
+        assert!(result.contains(&700));
+    }
+
+    #[test]
+    fn test_get_ancestors_of_set() {
+        let store = create_test_hierarchy();
+        let traverser = HierarchyTraverser::new(&store);
+
+        let sources: HashSet<SctId> = [500, 600].into_iter().collect();
+        let ancestors = traverser.get_ancestors_of_set(&sources);
+
+        // 500 -> 200 -> 100 and 600 -> 300 -> 100
+        assert_eq!(ancestors.len(), 3);
+        assert!(ancestors.contains(&100));
+        assert!(ancestors.contains(&200));
+        assert!(ancestors.contains(&300));
+
+        let result = traverser.get_ancestors_or_self_of_set(&sources);
+        assert_eq!(result.len(), 5);
+    }
+
+    #[test]
+    fn test_get_direct_children_and_parents_of_set() {
+        let store = create_diamond_hierarchy();
+        let traverser = HierarchyTraverser::new(&store);
+
+        let sources: HashSet<SctId> = [200, 300].into_iter().collect();
+
+        let children = traverser.get_direct_children_of_set(&sources);
+        assert_eq!(children.len(), 1);
+        assert!(children.contains(&400));
+
+        let parents = traverser.get_direct_parents_of_set(&sources);
+        assert_eq!(parents.len(), 1);
+        assert!(parents.contains(&100));
+    }
+
+    #[test]
+    fn test_get_descendants_of_empty_set() {
+        let store = create_test_hierarchy();
+        let traverser = HierarchyTraverser::new(&store);
+
+        assert!(traverser.get_descendants_of_set(&HashSet::new()).is_empty());
+        assert!(traverser.get_ancestors_of_set(&HashSet::new()).is_empty());
+    }
+
+    #[test]
+    fn test_set_traversal_stops_when_cancelled() {
+        use crate::error::EclExecutorError;
+        use crate::guard::CancelToken;
+
+        let store = create_test_hierarchy();
+        let token = CancelToken::new();
+        let guard = ExecutionGuard::unlimited().with_cancel_token(token.clone());
+        let traverser = HierarchyTraverser::with_guard(&store, guard);
+
+        let sources: HashSet<SctId> = [100].into_iter().collect();
+        assert_eq!(
+            traverser
+                .try_get_descendants_of_set(&sources)
+                .unwrap()
+                .len(),
+            6
+        );
+
+        token.cancel();
+        assert!(matches!(
+            traverser.try_get_descendants_of_set(&sources),
+            Err(EclExecutorError::Cancelled)
+        ));
+        // The infallible traversals ignore the guard
+        assert_eq!(traverser.get_descendants_of_set(&sources).len(), 6);
+    }
+
+    #[test]
+    fn test_traversal_stops_when_cancelled() {
+        use crate::error::EclExecutorError;
+        use crate::guard::CancelToken;
+
+        let store = create_test_hierarchy();
+        let token = CancelToken::new();
+        let guard = ExecutionGuard::unlimited().with_cancel_token(token.clone());
+        let traverser = HierarchyTraverser::with_guard(&store, guard);
+
+        token.cancel();
+        assert!(matches!(
+            traverser.try_get_descendants(100),
+            Err(EclExecutorError::Cancelled)
+        ));
+        assert!(matches!(
+            traverser.try_get_ancestors(700),
+            Err(EclExecutorError::Cancelled)
+        ));
+        assert!(matches!(
+            traverser.try_has_ancestor_in(700, &HashSet::from([100])),
+            Err(EclExecutorError::Cancelled)
+        ));
+        assert_eq!(traverser.get_descendants(100).len(), 6);
+    }
+
+    #[test]
+    fn test_set_traversal_stops_at_result_budget() {
+        use crate::error::EclExecutorError;
+
+        let store = create_test_hierarchy();
+        let guard = ExecutionGuard::new(None, Some(2));
+        let traverser = HierarchyTraverser::with_guard(&store, guard);
+
+        let root: HashSet<SctId> = [100].into_iter().collect();
+        assert!(matches!(
+            traverser.try_get_descendants_of_set(&root),
+            Err(EclExecutorError::ResultTooLarge { limit: 2, .. })
+        ));
+        assert!(matches!(
+            traverser.try_get_ancestors_or_self_of_set(&[700].into_iter().collect()),
+            Err(EclExecutorError::ResultTooLarge { limit: 2, .. })
+        ));
+
+        // Within the budget, and single-concept lookups are not limited
+        let leaf: HashSet<SctId> = [700].into_iter().collect();
+        assert_eq!(
+            traverser
+                .try_get_direct_parents_of_set(&leaf)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(traverser.try_get_descendants(100).unwrap().len(), 6);
+    }
+
+    // Membership tests
+
+    #[test]
+    fn test_has_ancestor_in() {
+        let store = create_test_hierarchy();
+        let traverser = HierarchyTraverser::new(&store);
+
+        assert!(traverser.has_ancestor_in(700, &HashSet::from([200])));
+        assert!(traverser.has_ancestor_in(700, &HashSet::from([300, 100])));
+        assert!(!traverser.has_ancestor_in(700, &HashSet::from([300])));
+        // Proper ancestors only
+        assert!(!traverser.has_ancestor_in(200, &HashSet::from([200])));
+        assert!(!traverser.has_ancestor_in(700, &HashSet::new()));
+    }
+
+    #[test]
+    fn test_has_ancestor_in_diamond() {
+        let store = create_diamond_hierarchy();
+        let traverser = HierarchyTraverser::new(&store);
+
+        assert!(traverser.has_ancestor_in(400, &HashSet::from([300])));
+        assert!(traverser.has_ancestor_in(400, &HashSet::from([100])));
+    }
+
     // Count tests
 
     #[test]
@@ -2,14 +2,22 @@
 //!
 //! These tests verify that ECL filters work correctly with the executor.
 
-use snomed_ecl_executor::{DescriptionInfo, EclExecutor, EclQueryable};
+use snomed_ecl_executor::{
+    Acceptability, DescriptionInfo, EclExecutor, EclExecutorError, EclQueryable,
+    HistoryAssociationType, LanguageRefsetMember, RefsetFieldValue, RefsetMember,
+};
 use std::collections::{HashMap, HashSet};
 
 /// Mock SNOMED CT store for testing filters.
 struct MockFilterStore {
     concepts: HashMap<u64, ConceptData>,
     descriptions: HashMap<u64, Vec<DescriptionData>>,
+    language_refsets: HashMap<u64, Vec<LanguageRefsetMember>>,
     refset_members: HashMap<u64, Vec<u64>>,
+    refset_member_rows: HashMap<u64, Vec<RefsetMember>>,
+    parents: HashMap<u64, Vec<u64>>,
+    /// Historical associations by target: (association refset, inactive source).
+    historical: HashMap<u64, Vec<(u64, u64)>>,
 }
 
 struct ConceptData {
@@ -25,6 +33,9 @@ struct DescriptionData {
     language_code: String,
     type_id: u64,
     case_significance_id: u64,
+    active: bool,
+    module_id: u64,
+    effective_time: u32,
 }
 
 impl MockFilterStore {
@@ -32,7 +43,11 @@ impl MockFilterStore {
         let mut store = MockFilterStore {
             concepts: HashMap::new(),
             descriptions: HashMap::new(),
+            language_refsets: HashMap::new(),
             refset_members: HashMap::new(),
+            refset_member_rows: HashMap::new(),
+            parents: HashMap::new(),
+            historical: HashMap::new(),
         };
 
         // Add test concepts
@@ -79,6 +94,9 @@ impl MockFilterStore {
                     language_code: "en".to_string(),
                     type_id: 900000000000003001, // FSN
                     case_significance_id: 900000000000448009, // Case insensitive
+                    active: true,
+                    module_id: 900000000000207008,
+                    effective_time: 20020131,
                 },
                 DescriptionData {
                     description_id: 2,
@@ -86,6 +104,9 @@ impl MockFilterStore {
                     language_code: "en".to_string(),
                     type_id: 900000000000013009, // Synonym
                     case_significance_id: 900000000000448009,
+                    active: false,
+                    module_id: 731000124108,
+                    effective_time: 20210301,
                 },
             ],
         );
@@ -99,6 +120,9 @@ impl MockFilterStore {
                     language_code: "en".to_string(),
                     type_id: 900000000000003001, // FSN
                     case_significance_id: 900000000000017005, // Case sensitive
+                    active: true,
+                    module_id: 900000000000207008,
+                    effective_time: 20020131,
                 },
                 DescriptionData {
                     description_id: 4,
@@ -106,6 +130,32 @@ impl MockFilterStore {
                     language_code: "en".to_string(),
                     type_id: 900000000000013009, // Synonym
                     case_significance_id: 900000000000448009,
+                    active: true,
+                    module_id: 900000000000207008,
+                    effective_time: 20190731,
+                },
+            ],
+        );
+
+        // Add language refset members: the FSN is preferred in GB English,
+        // the synonym preferred in US and acceptable in GB English
+        store.language_refsets.insert(
+            3,
+            vec![LanguageRefsetMember {
+                refset_id: 900000000000508004,
+                acceptability: Acceptability::Preferred,
+            }],
+        );
+        store.language_refsets.insert(
+            4,
+            vec![
+                LanguageRefsetMember {
+                    refset_id: 900000000000509007,
+                    acceptability: Acceptability::Preferred,
+                },
+                LanguageRefsetMember {
+                    refset_id: 900000000000508004,
+                    acceptability: Acceptability::Acceptable,
                 },
             ],
         );
@@ -115,6 +165,109 @@ impl MockFilterStore {
             .refset_members
             .insert(700043003, vec![404684003, 73211009]);
 
+        // Add an ICD-10 style map refset: 404684003 has two map groups, and
+        // an inactive row maps 38341003
+        store.concepts.insert(
+            447562003,
+            ConceptData {
+                active: true,
+                definition_status_id: 900000000000074008,
+                module_id: 449080006,
+                effective_time: Some(20200131),
+            },
+        );
+        store
+            .refset_members
+            .insert(447562003, vec![404684003, 73211009]);
+        let map_row = |referenced_component_id, active, effective_time, group, target: &str| {
+            RefsetMember {
+                id: format!("{}-{}", referenced_component_id, group),
+                effective_time: Some(effective_time),
+                active,
+                module_id: 449080006,
+                refset_id: 447562003,
+                referenced_component_id,
+                fields: vec![
+                    ("mapGroup".to_string(), RefsetFieldValue::Integer(group)),
+                    ("mapTarget".to_string(), RefsetFieldValue::String(target.to_string())),
+                    (
+                        "mapCategoryId".to_string(),
+                        RefsetFieldValue::Component(447637006),
+                    ),
+                ],
+            }
+        };
+        store.refset_member_rows.insert(
+            447562003,
+            vec![
+                map_row(73211009, true, 20190731, 1, "E14.9"),
+                map_row(404684003, true, 20200131, 1, "R69"),
+                map_row(404684003, true, 20200131, 2, "Z00"),
+                map_row(38341003, false, 20180131, 1, "I10"),
+            ],
+        );
+
+        // Add module concepts under the IHTSDO maintained module, and a
+        // refset of the English dialect language reference sets
+        for (concept_id, parent_id) in [
+            (900000000000445007, None),
+            (900000000000207008, Some(900000000000445007)),
+            (449080006, Some(900000000000445007)),
+            (900000000000506000, None),
+        ] {
+            store.concepts.insert(
+                concept_id,
+                ConceptData {
+                    active: true,
+                    definition_status_id: 900000000000074008,
+                    module_id: 900000000000207008,
+                    effective_time: Some(20020131),
+                },
+            );
+            store.parents.insert(concept_id, parent_id.into_iter().collect());
+        }
+        store
+            .refset_members
+            .insert(900000000000506000, vec![900000000000509007, 900000000000508004]);
+
+        // Add historical association refsets, including a local one, and
+        // inactive concepts associated with 404684003 and 73211009
+        for (concept_id, parent_id) in [
+            (900000000000522004, None),
+            (900000000000527005, Some(900000000000522004)),
+            (900000000000526001, Some(900000000000522004)),
+            (1000011000000105, Some(900000000000522004)),
+        ] {
+            store.concepts.insert(
+                concept_id,
+                ConceptData {
+                    active: true,
+                    definition_status_id: 900000000000074008,
+                    module_id: 900000000000207008,
+                    effective_time: Some(20020131),
+                },
+            );
+            store.parents.insert(concept_id, parent_id.into_iter().collect());
+        }
+        for concept_id in [190372001, 190368000] {
+            store.concepts.insert(
+                concept_id,
+                ConceptData {
+                    active: false,
+                    definition_status_id: 900000000000074008,
+                    module_id: 900000000000207008,
+                    effective_time: Some(20090731),
+                },
+            );
+        }
+        store
+            .historical
+            .insert(404684003, vec![(900000000000526001, 38341003)]);
+        store.historical.insert(
+            73211009,
+            vec![(900000000000527005, 190372001), (1000011000000105, 190368000)],
+        );
+
         store
     }
 }
@@ -124,12 +277,16 @@ impl EclQueryable for MockFilterStore {
         self.concepts.contains_key(&concept_id)
     }
 
-    fn get_parents(&self, _concept_id: u64) -> Vec<u64> {
-        Vec::new()
+    fn get_parents(&self, concept_id: u64) -> Vec<u64> {
+        self.parents.get(&concept_id).cloned().unwrap_or_default()
     }
 
-    fn get_children(&self, _concept_id: u64) -> Vec<u64> {
-        Vec::new()
+    fn get_children(&self, concept_id: u64) -> Vec<u64> {
+        self.parents
+            .iter()
+            .filter(|(_, parents)| parents.contains(&concept_id))
+            .map(|(&child, _)| child)
+            .collect()
     }
 
     fn all_concept_ids(&self) -> Box<dyn Iterator<Item = u64> + '_> {
@@ -175,14 +332,60 @@ impl EclQueryable for MockFilterStore {
                         language_code: d.language_code.clone(),
                         type_id: d.type_id,
                         case_significance_id: d.case_significance_id,
-                        active: true,
-                        module_id: 900000000000207008,
-                        effective_time: None,
+                        active: d.active,
+                        module_id: d.module_id,
+                        effective_time: Some(d.effective_time),
                     })
                     .collect()
             })
             .unwrap_or_default()
     }
+
+    fn get_refset_member_rows(&self, refset_id: u64) -> Vec<RefsetMember> {
+        self.refset_member_rows
+            .get(&refset_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn get_description_language_refsets(&self, description_id: u64) -> Vec<LanguageRefsetMember> {
+        self.language_refsets
+            .get(&description_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn get_historical_associations(&self, concept_id: u64) -> Vec<u64> {
+        self.historical
+            .get(&concept_id)
+            .map(|associations| associations.iter().map(|&(_, source)| source).collect())
+            .unwrap_or_default()
+    }
+
+    fn get_historical_associations_by_type(
+        &self,
+        concept_id: u64,
+        association_type: HistoryAssociationType,
+    ) -> Vec<u64> {
+        self.historical
+            .get(&concept_id)
+            .map(|associations| {
+                associations
+                    .iter()
+                    .filter(|&&(refset_id, _)| refset_id == association_type.refset_id())
+                    .map(|&(_, source)| source)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn get_historical_associations_by_refset(&self, concept_id: u64) -> HashMap<u64, Vec<u64>> {
+        let mut by_refset: HashMap<u64, Vec<u64>> = HashMap::new();
+        for &(refset_id, source) in self.historical.get(&concept_id).into_iter().flatten() {
+            by_refset.entry(refset_id).or_default().push(source);
+        }
+        by_refset
+    }
 }
 
 #[test]
@@ -261,6 +464,56 @@ fn test_module_filter() {
     assert!(ids.contains(&38341003));
 }
 
+#[test]
+fn test_module_filter_constraint() {
+    let store = MockFilterStore::new();
+    let executor = EclExecutor::new(&store);
+
+    // Concepts in any module below the IHTSDO maintained module
+    let result = executor
+        .execute("* {{ C moduleId = << 900000000000445007 }}")
+        .unwrap();
+    assert!(result.contains(404684003));
+    assert!(result.contains(447562003));
+
+    // Only the map refset is in a child of the IHTSDO maintained module
+    // other than the core module
+    let result = executor
+        .execute("* {{ moduleId = (< 900000000000445007 MINUS 900000000000207008) }}")
+        .unwrap();
+    assert_eq!(result.iter().copied().collect::<Vec<_>>(), vec![447562003]);
+
+    let result = executor
+        .execute("^ 447562003 {{ M moduleId = < 900000000000445007 }}")
+        .unwrap();
+    assert_eq!(result.count(), 2);
+}
+
+#[test]
+fn test_description_type_and_dialect_constraints() {
+    let store = MockFilterStore::new();
+    let executor = EclExecutor::new(&store);
+
+    // Synonyms preferred in a member of the English dialect refset
+    let result = executor
+        .execute("* {{ typeId = (900000000000013009), dialectId = ^ 900000000000506000 prefer }}")
+        .unwrap();
+    assert_eq!(result.iter().copied().collect::<Vec<_>>(), vec![73211009]);
+
+    // Every description type, accepted in one of the dialects
+    let result = executor
+        .execute(
+            "* {{ typeId = (900000000000003001 900000000000013009), \
+             dialectId = (900000000000509007 900000000000508004) accept }}",
+        )
+        .unwrap();
+    assert_eq!(result.iter().copied().collect::<Vec<_>>(), vec![73211009]);
+
+    // An unknown concept in a constraint is reported as for any sub-expression
+    let result = executor.execute("* {{ typeId = << 999999999 }}");
+    assert!(matches!(result, Err(EclExecutorError::ConceptNotFound(999999999))));
+}
+
 #[test]
 fn test_effective_time_filter() {
     let store = MockFilterStore::new();
@@ -290,6 +543,291 @@ fn test_term_filter_contains() {
     assert!(ids.contains(&73211009)); // Has "Diabetes mellitus" and "Diabetes"
 }
 
+#[test]
+fn test_term_filter_word_prefixes() {
+    let store = MockFilterStore::new();
+    let executor = EclExecutor::new(&store);
+
+    // Each search word prefixes a term word, in any order
+    let result = executor.execute(r#"* {{ term = "mell diab" }}"#).unwrap();
+    assert_eq!(result.to_vec(), vec![73211009]);
+
+    let result = executor.execute(r#"* {{ term = "disord" }}"#).unwrap();
+    assert_eq!(result.to_vec(), vec![73211009]);
+
+    // Substrings inside a word do not match
+    let result = executor.execute(r#"* {{ term = "abetes" }}"#).unwrap();
+    assert!(result.is_empty());
+}
+
+#[test]
+fn test_term_filter_wildcard() {
+    let store = MockFilterStore::new();
+    let executor = EclExecutor::new(&store);
+
+    let result = executor.execute(r#"* {{ term wild "diab*tes" }}"#).unwrap();
+    assert_eq!(result.to_vec(), vec![73211009]);
+
+    let result = executor.execute(r#"* {{ term wild "clinical fin?ing" }}"#).unwrap();
+    assert_eq!(result.to_vec(), vec![404684003]);
+
+    // Anchored at both ends: no substring match
+    let result = executor.execute(r#"* {{ term wild "tes" }}"#).unwrap();
+    assert!(result.is_empty());
+    let result = executor.execute(r#"* {{ term wild "diab*" }}"#).unwrap();
+    assert_eq!(result.to_vec(), vec![73211009]);
+}
+
+#[test]
+fn test_term_filter_wildcard_case_significance() {
+    let store = MockFilterStore::new();
+    let executor = EclExecutor::new(&store);
+
+    // The FSN's initial character is case sensitive
+    let result = executor.execute(r#"* {{ term wild "Diab*(DISORDER)" }}"#).unwrap();
+    assert_eq!(result.to_vec(), vec![73211009]);
+    let result = executor.execute(r#"* {{ term wild "diab*(disorder)" }}"#).unwrap();
+    assert!(result.is_empty());
+}
+
+#[test]
+fn test_term_filter_typed_terms_are_a_disjunction() {
+    let store = MockFilterStore::new();
+    let executor = EclExecutor::new(&store);
+
+    let result = executor
+        .execute(r#"* {{ term = (match:"clin find" wild:"diab*tes") }}"#)
+        .unwrap();
+    let mut ids = result.to_vec();
+    ids.sort();
+    assert_eq!(ids, vec![73211009, 404684003]);
+
+    let result = executor.execute(r#"* {{ term = ("clinic" "nothing") }}"#).unwrap();
+    assert_eq!(result.to_vec(), vec![404684003]);
+
+    let result = executor.execute(r#"* {{ term = (wild:"clinic" "nothing") }}"#).unwrap();
+    assert!(result.is_empty());
+}
+
+#[test]
+fn test_description_filters_in_one_block_match_the_same_description() {
+    let store = MockFilterStore::new();
+    let executor = EclExecutor::new(&store);
+
+    // "mellitus" is only in the FSN
+    let result = executor
+        .execute(r#"* {{ term = "mellitus", type = syn }}"#)
+        .unwrap();
+    assert!(result.is_empty());
+    let result = executor
+        .execute(r#"* {{ D term = "mellitus", D type = syn }}"#)
+        .unwrap();
+    assert!(result.is_empty());
+
+    let result = executor
+        .execute(r#"* {{ term = "mellitus", type = fsn }}"#)
+        .unwrap();
+    assert_eq!(result.to_vec(), vec![73211009]);
+
+    // Dialect and acceptability are checked on the matching description
+    let result = executor
+        .execute(r#"* {{ term = "mellitus", dialect = en-US prefer }}"#)
+        .unwrap();
+    assert!(result.is_empty());
+    let result = executor
+        .execute(r#"* {{ term = "diabetes", language = en, dialect = en-US prefer }}"#)
+        .unwrap();
+    assert_eq!(result.to_vec(), vec![73211009]);
+    let result = executor
+        .execute(r#"* {{ type = syn, preferredIn = 900000000000508004 }}"#)
+        .unwrap();
+    assert!(result.is_empty());
+}
+
+#[test]
+fn test_description_filters_in_separate_blocks_match_per_concept() {
+    let store = MockFilterStore::new();
+    let executor = EclExecutor::new(&store);
+
+    let result = executor
+        .execute(r#"* {{ term = "mellitus" }} {{ type = syn }}"#)
+        .unwrap();
+    assert_eq!(result.to_vec(), vec![73211009]);
+
+    // Concept filters in the block stay concept-level
+    let result = executor
+        .execute(r#"* {{ term = "mellitus", active = true, dialect = en-GB prefer }}"#)
+        .unwrap();
+    assert_eq!(result.to_vec(), vec![73211009]);
+    let result = executor
+        .execute(r#"* {{ term = "mellitus", active = false }}"#)
+        .unwrap();
+    assert!(result.is_empty());
+}
+
+#[test]
+fn test_description_domain_filters() {
+    let store = MockFilterStore::new();
+    let executor = EclExecutor::new(&store);
+    let sorted = |ecl: &str| {
+        let mut ids = executor.execute(ecl).unwrap().to_vec();
+        ids.sort();
+        ids
+    };
+
+    // D filters test descriptions, C filters the concept
+    assert_eq!(sorted("* {{ D active = false }}"), vec![404684003]);
+    assert_eq!(
+        sorted("* {{ C active = false }}"),
+        vec![38341003, 190368000, 190372001]
+    );
+    assert_eq!(sorted("* {{ D moduleId = 731000124108 }}"), vec![404684003]);
+    assert!(sorted("* {{ C moduleId = 731000124108 }}").is_empty());
+    assert_eq!(
+        sorted("* {{ D effectiveTime >= 20190101 }}"),
+        vec![73211009, 404684003]
+    );
+    assert_eq!(sorted("* {{ D id = 3 }}"), vec![73211009]);
+
+    // They must hold for the same description as the other description filters
+    assert_eq!(
+        sorted("* {{ D effectiveTime >= 20190101, D active = true }}"),
+        vec![73211009]
+    );
+    assert!(sorted("* {{ D active = false, type = fsn }}").is_empty());
+    assert_eq!(
+        sorted("* {{ D active = false }} {{ type = fsn }}"),
+        vec![404684003]
+    );
+}
+
+#[test]
+fn test_invalid_filter_domain() {
+    use snomed_ecl::{EclExpression, EclFilter, FilterDomain};
+
+    let store = MockFilterStore::new();
+    let executor = EclExecutor::new(&store);
+
+    let expr = EclExpression::Filtered {
+        expression: Box::new(EclExpression::Any),
+        filters: vec![EclFilter::DomainQualified {
+            domain: FilterDomain::Description,
+            filter: Box::new(EclFilter::DefinitionStatus { is_primitive: true }),
+        }],
+    };
+    let result = executor.execute_ast(&expr);
+    assert!(matches!(
+        result,
+        Err(EclExecutorError::InvalidFilterDomain {
+            domain: FilterDomain::Description,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_member_filters() {
+    let store = MockFilterStore::new();
+    let executor = EclExecutor::new(&store);
+    let sorted = |ecl: &str| {
+        let mut ids = executor.execute(ecl).unwrap().to_vec();
+        ids.sort();
+        ids
+    };
+
+    assert_eq!(sorted(r#"^ 447562003 {{ M mapTarget = "E14.9" }}"#), vec![73211009]);
+    assert_eq!(sorted(r#"^ 447562003 {{ M mapTarget != "E14.9" }}"#), vec![404684003]);
+    assert_eq!(sorted("^ 447562003 {{ M mapGroup >= 2 }}"), vec![404684003]);
+    assert_eq!(
+        sorted("^ 447562003 {{ M effectiveTime < 20200101 }}"),
+        vec![73211009]
+    );
+    assert_eq!(
+        sorted("^ 447562003 {{ M mapCategoryId = 447637006 }}"),
+        vec![73211009, 404684003]
+    );
+    assert_eq!(
+        sorted("^ 447562003 {{ M moduleId = 449080006 }}"),
+        vec![73211009, 404684003]
+    );
+
+    // Conditions in one block must hold for the same member row
+    assert!(sorted(r#"^ 447562003 {{ M mapTarget = "R69", M mapGroup = 2 }}"#).is_empty());
+    assert_eq!(
+        sorted(r#"^ 447562003 {{ M mapTarget = "R69" }} {{ M mapGroup = 2 }}"#),
+        vec![404684003]
+    );
+
+    // Inactive rows are skipped
+    assert!(sorted(r#"^ 447562003 {{ M mapTarget = "I10" }}"#).is_empty());
+}
+
+#[test]
+fn test_member_of_field_selection() {
+    let store = MockFilterStore::new();
+    let executor = EclExecutor::new(&store);
+
+    let result = executor
+        .execute_member_rows("^ [referencedComponentId, mapTarget] 447562003")
+        .unwrap();
+    assert_eq!(result.columns, vec!["referencedComponentId", "mapTarget"]);
+    let text = |value: &str| Some(RefsetFieldValue::String(value.to_string()));
+    assert_eq!(
+        result.rows,
+        vec![
+            vec![Some(RefsetFieldValue::Component(73211009)), text("E14.9")],
+            vec![Some(RefsetFieldValue::Component(404684003)), text("R69")],
+            vec![Some(RefsetFieldValue::Component(404684003)), text("Z00")],
+        ]
+    );
+
+    // Member filters select rows, not just components
+    let result = executor
+        .execute_member_rows("^ [mapTarget] 447562003 {{ M mapGroup = 2 }}")
+        .unwrap();
+    assert_eq!(result.rows, vec![vec![text("Z00")]]);
+
+    // Concept filters restrict the referenced components
+    let result = executor
+        .execute_member_rows(r#"^ [mapTarget] 447562003 {{ term = "diabetes" }}"#)
+        .unwrap();
+    assert_eq!(result.rows, vec![vec![text("E14.9")]]);
+
+    let result = executor.execute_member_rows("^ [*] 447562003").unwrap();
+    assert_eq!(result.count(), 3);
+    assert_eq!(result.columns.len(), 9);
+    let target = result.column_index("maptarget").unwrap();
+    assert_eq!(result.rows[0][target], text("E14.9"));
+
+    // Plain member-of is unchanged, and so is execute() with a selection
+    assert_eq!(
+        executor.execute("^ 447562003").unwrap().to_vec(),
+        vec![73211009, 404684003]
+    );
+    assert_eq!(
+        executor.execute("^ [mapTarget] 447562003").unwrap().to_vec(),
+        vec![73211009, 404684003]
+    );
+    assert!(matches!(
+        executor.execute_member_rows("^ 447562003").unwrap_err(),
+        snomed_ecl_executor::EclExecutorError::UnsupportedFeature(_)
+    ));
+}
+
+#[test]
+fn test_member_filter_requires_member_of() {
+    let store = MockFilterStore::new();
+    let executor = EclExecutor::new(&store);
+
+    let err = executor
+        .execute(r#"* {{ M mapTarget = "E14.9" }}"#)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        snomed_ecl_executor::EclExecutorError::UnsupportedFeature(_)
+    ));
+}
+
 #[test]
 fn test_combined_filters() {
     let store = MockFilterStore::new();
@@ -334,3 +872,111 @@ fn test_id_filter_multiple() {
     assert!(ids.contains(&404684003));
     assert!(ids.contains(&73211009));
 }
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_term_filter_regex() {
+    let store = MockFilterStore::new();
+    let executor = EclExecutor::new(&store);
+
+    let result = executor
+        .execute(r#"* {{ term regex "^diab.*\(disorder\)$" }}"#)
+        .unwrap();
+    assert_eq!(result.to_vec(), vec![73211009]);
+
+    // Anchored: "finding" is not at the start of any term
+    let result = executor.execute(r#"* {{ term regex "^finding" }}"#).unwrap();
+    assert!(result.is_empty());
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_term_filter_invalid_regex() {
+    let store = MockFilterStore::new();
+    let executor = EclExecutor::new(&store);
+
+    let err = executor
+        .execute(r#"* {{ term regex "diab(" }}"#)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        snomed_ecl_executor::EclExecutorError::InvalidTermPattern { .. }
+    ));
+}
+
+#[cfg(not(feature = "regex"))]
+#[test]
+fn test_term_filter_regex_requires_feature() {
+    let store = MockFilterStore::new();
+    let executor = EclExecutor::new(&store);
+
+    let err = executor
+        .execute(r#"* {{ term regex "^diab" }}"#)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        snomed_ecl_executor::EclExecutorError::UnsupportedFeature(_)
+    ));
+}
+
+#[test]
+fn test_history_supplement_profiles() {
+    let store = MockFilterStore::new();
+    let executor = EclExecutor::new(&store);
+    let sorted = |ecl: &str| executor.execute(ecl).unwrap().to_vec();
+
+    assert_eq!(sorted("73211009 {{ +HISTORY-MIN }}"), vec![73211009, 190372001]);
+    assert_eq!(
+        sorted("73211009 {{ +HISTORY }}"),
+        vec![73211009, 190368000, 190372001]
+    );
+}
+
+#[test]
+fn test_history_supplement_subset() {
+    let store = MockFilterStore::new();
+    let executor = EclExecutor::new(&store);
+    let sorted = |ecl: &str| executor.execute(ecl).unwrap().to_vec();
+
+    // Any historical association refset, including the local one
+    assert_eq!(
+        sorted("73211009 {{ +HISTORY (<< 900000000000522004) }}"),
+        vec![73211009, 190368000, 190372001]
+    );
+    // Only the local refset
+    assert_eq!(
+        sorted("73211009 {{ +HISTORY (1000011000000105) }}"),
+        vec![73211009, 190368000]
+    );
+    assert_eq!(
+        sorted("(73211009 404684003) {{ +HISTORY (900000000000527005 OR 900000000000526001) }}"),
+        vec![38341003, 73211009, 190372001, 404684003]
+    );
+}
+
+#[test]
+fn test_execute_with_history() {
+    let store = MockFilterStore::new();
+    let executor = EclExecutor::new(&store);
+
+    let result = executor
+        .execute_with_history("(73211009 404684003) {{ active = true }} {{ +HISTORY (<< 900000000000522004) }}")
+        .unwrap();
+    assert_eq!(result.members, HashSet::from([73211009, 404684003]));
+    assert_eq!(result.added, HashSet::from([38341003, 190368000, 190372001]));
+    assert!(result.is_added(190368000));
+    assert!(!result.is_added(73211009));
+    assert_eq!(result.count(), 5);
+    assert_eq!(
+        result.into_query_result().to_vec(),
+        executor
+            .execute("(73211009 404684003) {{ active = true }} {{ +HISTORY (<< 900000000000522004) }}")
+            .unwrap()
+            .to_vec()
+    );
+
+    assert!(matches!(
+        executor.execute_with_history("73211009 {{ active = true }}"),
+        Err(EclExecutorError::UnsupportedFeature(_))
+    ));
+}
@@ -1,9 +1,12 @@
 //! Comprehensive integration tests for ECL executor.
 //!
-//! These tests cover ECL 2.2 compliance and various edge cases.
+//! These tests cover ECL 2.2 compliance and various edge cases. The
+//! store-independent scenarios live in the `snomed-ecl-test-scenarios`
+//! crate, whose scenarios the SQLite store tests run as well.
 
-use snomed_ecl_executor::{EclExecutor, EclQueryable, RelationshipInfo};
+use snomed_ecl_executor::{EclExecutor, EclQueryable, RelationshipInfo, StatisticsService};
 use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 /// Comprehensive mock SNOMED CT store for integration testing.
 struct IntegrationTestStore {
@@ -15,6 +18,8 @@ struct IntegrationTestStore {
     refset_members: HashMap<u64, Vec<u64>>,
     active_concepts: HashSet<u64>,
     primitive_concepts: HashSet<u64>,
+    attribute_index: bool,
+    attribute_scans: AtomicUsize,
 }
 
 impl IntegrationTestStore {
@@ -28,6 +33,8 @@ impl IntegrationTestStore {
             refset_members: HashMap::new(),
             active_concepts: HashSet::new(),
             primitive_concepts: HashSet::new(),
+            attribute_index: false,
+            attribute_scans: AtomicUsize::new(0),
         };
 
         // Build a realistic hierarchy
@@ -97,6 +104,12 @@ impl IntegrationTestStore {
         store
     }
 
+    /// Enables reverse attribute lookups via `get_concepts_with_attribute`.
+    fn with_attribute_index(mut self) -> Self {
+        self.attribute_index = true;
+        self
+    }
+
     fn add_concept(&mut self, id: u64, active: bool, primitive: bool) {
         self.concepts.insert(id);
         if active {
@@ -146,6 +159,7 @@ impl EclQueryable for IntegrationTestStore {
     }
 
     fn get_attributes(&self, concept_id: u64) -> Vec<RelationshipInfo> {
+        self.attribute_scans.fetch_add(1, Ordering::Relaxed);
         self.attributes
             .get(&concept_id)
             .map(|attrs| {
@@ -161,6 +175,23 @@ impl EclQueryable for IntegrationTestStore {
             .unwrap_or_default()
     }
 
+    fn get_concepts_with_attribute(&self, attribute_type_id: u64, target_id: u64) -> Vec<u64> {
+        self.inbound_attributes
+            .get(&target_id)
+            .map(|attrs| {
+                attrs
+                    .iter()
+                    .filter(|(type_id, _, _)| *type_id == attribute_type_id)
+                    .map(|(_, source_id, _)| *source_id)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn supports_attribute_lookup(&self) -> bool {
+        self.attribute_index
+    }
+
     fn get_inbound_relationships(&self, concept_id: u64) -> Vec<RelationshipInfo> {
         self.inbound_attributes
             .get(&concept_id)
@@ -191,578 +222,127 @@ impl EclQueryable for IntegrationTestStore {
 }
 
 // ============================================================================
-// Basic Hierarchy Tests
+// Shared Scenarios
 // ============================================================================
 
-#[test]
-fn test_self_concept() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    let result = executor.execute("73211009").unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    assert_eq!(ids.len(), 1);
-    assert!(ids.contains(&73211009));
-}
-
-#[test]
-fn test_descendant_of() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // Descendants of Diabetes mellitus (not including self)
-    let result = executor.execute("< 73211009").unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    assert_eq!(ids.len(), 2);
-    assert!(ids.contains(&46635009)); // Type 1
-    assert!(ids.contains(&44054006)); // Type 2
-    assert!(!ids.contains(&73211009)); // Self excluded
-}
-
-#[test]
-fn test_descendant_or_self_of() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // Descendants of Diabetes mellitus (including self)
-    let result = executor.execute("<< 73211009").unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    assert_eq!(ids.len(), 3);
-    assert!(ids.contains(&73211009)); // Self
-    assert!(ids.contains(&46635009)); // Type 1
-    assert!(ids.contains(&44054006)); // Type 2
+/// Runs a shared scenario against the mock store.
+fn with_store(scenario: impl FnOnce(&IntegrationTestStore)) {
+    scenario(&IntegrationTestStore::new());
 }
 
-#[test]
-fn test_ancestor_of() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // Ancestors of Type 1 diabetes
-    let result = executor.execute("> 46635009").unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    assert!(ids.contains(&73211009)); // Diabetes mellitus
-    assert!(ids.contains(&64572001)); // Disease
-    assert!(ids.contains(&404684003)); // Clinical finding
-    assert!(ids.contains(&138875005)); // Root
-    assert!(!ids.contains(&46635009)); // Self excluded
-}
-
-#[test]
-fn test_child_of() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // Direct children of Diabetes mellitus
-    let result = executor.execute("<! 73211009").unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    assert_eq!(ids.len(), 2);
-    assert!(ids.contains(&46635009)); // Type 1
-    assert!(ids.contains(&44054006)); // Type 2
-}
-
-#[test]
-fn test_parent_of() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // Direct parents of Type 1 diabetes
-    let result = executor.execute(">! 46635009").unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    assert_eq!(ids.len(), 1);
-    assert!(ids.contains(&73211009)); // Diabetes mellitus
-}
+snomed_ecl_test_scenarios::scenario_tests!(with_store);
 
 // ============================================================================
-// Compound Expression Tests
+// Reverse Attribute Lookup Tests
 // ============================================================================
 
 #[test]
-fn test_and_expression() {
-    let store = IntegrationTestStore::new();
+fn test_reverse_lookup_for_wildcard_focus() {
+    let store = IntegrationTestStore::new().with_attribute_index();
     let executor = EclExecutor::new(&store);
 
-    // Descendants of Disease AND descendants of Clinical finding
-    let result = executor
-        .execute("<< 64572001 AND << 404684003")
-        .unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // Disease is a Clinical finding, so all descendants of Disease are in both
-    assert!(ids.contains(&64572001)); // Disease
-    assert!(ids.contains(&73211009)); // Diabetes
-    assert!(ids.contains(&46635009)); // Type 1
-    assert!(ids.contains(&44054006)); // Type 2
-}
-
-#[test]
-fn test_or_expression() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
+    let plan = executor.explain("* : 363698007 = 80891009").unwrap();
+    assert!(plan.steps.iter().any(|s| s.expression.contains("reverse lookup")));
 
-    // Fever OR diabetes descendants
-    let result = executor
-        .execute("386661006 OR << 73211009")
-        .unwrap();
-    let ids: HashSet<_> = result.iter().collect();
+    let result = executor.execute("* : 363698007 = 80891009").unwrap();
+    assert_eq!(result.to_vec(), vec![73211009]);
 
-    assert!(ids.contains(&386661006)); // Fever
-    assert!(ids.contains(&73211009)); // Diabetes
-    assert!(ids.contains(&46635009)); // Type 1
-    assert!(ids.contains(&44054006)); // Type 2
+    // Only the single candidate's attributes are checked, not every concept's
+    assert_eq!(store.attribute_scans.load(Ordering::Relaxed), 1);
 }
 
 #[test]
-fn test_minus_expression() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
+fn test_forward_scan_for_small_focus() {
+    let store = IntegrationTestStore::new().with_attribute_index();
+    let mut statistics = StatisticsService::new();
+    statistics.register_descendant_count(73211009, 2);
+    let executor = EclExecutor::new(&store).with_statistics(statistics);
 
-    // All diabetes minus Type 1
-    let result = executor
-        .execute("<< 73211009 MINUS 46635009")
-        .unwrap();
-    let ids: HashSet<_> = result.iter().collect();
+    let plan = executor.explain("<< 73211009 : 363698007 = 80891009").unwrap();
+    assert!(plan.steps.iter().any(|s| s.expression.contains("forward scan")));
 
-    assert!(ids.contains(&73211009)); // Diabetes
-    assert!(ids.contains(&44054006)); // Type 2
-    assert!(!ids.contains(&46635009)); // Type 1 excluded
+    let result = executor.execute("<< 73211009 : 363698007 = 80891009").unwrap();
+    assert_eq!(result.to_vec(), vec![73211009]);
+    assert_eq!(store.attribute_scans.load(Ordering::Relaxed), 3);
 }
 
-// ============================================================================
-// MemberOf Tests
-// ============================================================================
-
-#[test]
-fn test_member_of_simple() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    let result = executor.execute("^ 700043003").unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    assert_eq!(ids.len(), 2);
-    assert!(ids.contains(&73211009));
-    assert!(ids.contains(&386661006));
-}
-
-#[test]
-fn test_member_of_with_and() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // Members of refset that are also descendants of Disease
-    let result = executor
-        .execute("^ 700043003 AND << 64572001")
-        .unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // Only diabetes is both a member and a descendant of Disease
-    assert_eq!(ids.len(), 1);
-    assert!(ids.contains(&73211009));
-}
-
-// ============================================================================
-// Refinement Tests
-// ============================================================================
-
 #[test]
-fn test_simple_refinement() {
+fn test_forward_scan_without_attribute_index() {
     let store = IntegrationTestStore::new();
     let executor = EclExecutor::new(&store);
 
-    // Concepts with finding site = heart structure
-    let result = executor
-        .execute("* : 363698007 = 80891009")
-        .unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // Diabetes has finding site = heart in our mock
-    assert!(ids.contains(&73211009));
+    let plan = executor.explain("* : 363698007 = 80891009").unwrap();
+    assert!(plan.steps.iter().any(|s| s.expression.contains("forward scan")));
+    assert!(!plan.steps.iter().any(|s| s.expression.contains("reverse lookup")));
 }
 
 #[test]
-fn test_refinement_with_hierarchy() {
-    let store = IntegrationTestStore::new();
+fn test_profile_shows_refinement_strategy_taken() {
+    let store = IntegrationTestStore::new().with_attribute_index();
     let executor = EclExecutor::new(&store);
 
-    // Clinical findings with finding site = descendant of body structure
-    let result = executor
-        .execute("<< 404684003 : 363698007 = << 123037004")
+    let profile = executor
+        .execute_profiled("* : 363698007 = 80891009")
         .unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // Should include diabetes (has finding site = heart, which is under body structure)
-    assert!(ids.contains(&73211009));
-}
-
-// ============================================================================
-// Reverse Attribute Tests
-// ============================================================================
-
-#[test]
-fn test_reverse_attribute() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // Body structures that are the finding site of something
-    // Uses R (reverse) flag
-    let result = executor
-        .execute("<< 123037004 : R 363698007 = *")
-        .unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // Heart structure is the finding site of diabetes
-    assert!(ids.contains(&80891009));
-}
-
-// ============================================================================
-// Filter Tests
-// ============================================================================
-
-#[test]
-fn test_active_filter() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // All active descendants of Disease
-    let result = executor
-        .execute("<< 64572001 {{ active = true }}")
-        .unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    assert!(ids.contains(&64572001));
-    assert!(ids.contains(&73211009));
-    assert!(!ids.contains(&38341003)); // Inactive
-}
-
-#[test]
-fn test_definition_status_filter() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // All primitive descendants of Disease
-    let result = executor
-        .execute("<< 64572001 {{ definitionStatus = primitive }}")
-        .unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // All descendants except Disease itself are primitive in our mock
-    assert!(!ids.contains(&64572001)); // Defined (not primitive)
-    assert!(ids.contains(&73211009)); // Primitive
-    assert!(ids.contains(&46635009)); // Primitive
-}
-
-// ============================================================================
-// Complex Query Tests
-// ============================================================================
-
-#[test]
-fn test_complex_query() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // Active primitive descendants of clinical finding with finding site
-    let result = executor
-        .execute(
-            "<< 404684003 {{ active = true, definitionStatus = primitive }} : 363698007 = *",
-        )
-        .unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // Diabetes is active, primitive, and has a finding site
-    assert!(ids.contains(&73211009));
-}
-
-#[test]
-fn test_nested_expressions() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // (Diabetes descendants OR Fever) AND Clinical findings
-    let result = executor
-        .execute("(<< 73211009 OR 386661006) AND << 404684003")
-        .unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // All are clinical findings
-    assert!(ids.contains(&73211009));
-    assert!(ids.contains(&46635009));
-    assert!(ids.contains(&44054006));
-    assert!(ids.contains(&386661006));
-}
-
-// ============================================================================
-// Edge Case Tests
-// ============================================================================
-
-#[test]
-fn test_empty_result() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // Descendants of a concept with no children
-    let result = executor.execute("< 46635009").unwrap();
-
-    assert_eq!(result.count(), 0);
-}
-
-#[test]
-fn test_invalid_concept() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // Non-existent concept
-    let result = executor.execute("999999999");
-
-    // Should return error for concept not found
-    assert!(result.is_err() || result.unwrap().count() == 0);
-}
-
-#[test]
-fn test_wildcard() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // All concepts
-    let result = executor.execute("*").unwrap();
-
-    // Should return all concepts in the store
-    assert!(result.count() > 10);
+    assert_eq!(profile.root.operation, "Refined (reverse lookup)");
+
+    // Without statistics the planner expects a large focus and plans a
+    // reverse lookup; the actual focus is small enough to scan
+    let ecl = "<< 73211009 : 363698007 = 80891009";
+    let plan = executor.explain(ecl).unwrap();
+    assert!(plan.steps.iter().any(|s| s.expression.contains("reverse lookup")));
+    let profile = executor.execute_profiled(ecl).unwrap();
+    assert_eq!(profile.root.operation, "Refined (forward scan)");
+    assert_eq!(profile.result.to_vec(), vec![73211009]);
+}
+
+#[test]
+fn test_reverse_lookup_matches_forward_scan() {
+    let plain = IntegrationTestStore::new();
+    let indexed = IntegrationTestStore::new().with_attribute_index();
+    let forward = EclExecutor::new(&plain);
+    let reverse = EclExecutor::new(&indexed);
+
+    for ecl in [
+        "* : 363698007 = 80891009",
+        "* : 363698007 = << 123037004",
+        "* : 363698007 = (80891009 OR 39057004)",
+        "* : [1..1] 363698007 = 80891009",
+        "* : [0..0] 363698007 = 80891009",
+        "* : 363698007 = 80891009, 116676008 = *",
+        "* : { 363698007 = 80891009 }",
+        "* : 363698007 != 80891009",
+    ] {
+        let expected = forward.execute(ecl).unwrap().to_vec();
+        assert_eq!(reverse.execute(ecl).unwrap().to_vec(), expected, "{}", ecl);
+    }
 }
 
 // ============================================================================
-// ECL Specification Compliance Tests
+// Parallel Execution Tests
 // ============================================================================
 
+#[cfg(feature = "parallel")]
 #[test]
-fn test_multiple_hierarchy_levels() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // Test that descendants correctly traverse multiple levels
-    // Root -> Clinical finding -> Disease -> Diabetes -> Type 1/Type 2
-    let result = executor.execute("< 138875005").unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // Should include all descendants at all levels
-    assert!(ids.contains(&404684003)); // Clinical finding (child of root)
-    assert!(ids.contains(&73211009)); // Diabetes (grandchild)
-    assert!(ids.contains(&46635009)); // Type 1 (great-grandchild)
-}
-
-#[test]
-fn test_ancestor_of_multiple_levels() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // Ancestors of Type 2 diabetes should include all levels up to root
-    let result = executor.execute(">> 44054006").unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    assert!(ids.contains(&44054006)); // Self (ancestor-or-self)
-    assert!(ids.contains(&73211009)); // Diabetes (parent)
-    assert!(ids.contains(&64572001)); // Disease (grandparent)
-    assert!(ids.contains(&404684003)); // Clinical finding
-    assert!(ids.contains(&138875005)); // Root
-}
-
-#[test]
-fn test_conjunction_with_hierarchy() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // (Descendants of root) AND (Ancestors of Type 1 diabetes)
-    // Should only include ancestors of Type 1 that are also descendants of root
-    let result = executor
-        .execute("< 138875005 AND > 46635009")
-        .unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // Clinical finding, Disease, and Diabetes are both descendants of root
-    // and ancestors of Type 1
-    assert!(ids.contains(&404684003));
-    assert!(ids.contains(&64572001));
-    assert!(ids.contains(&73211009));
-    // Root is NOT a descendant of itself
-    assert!(!ids.contains(&138875005));
-}
-
-#[test]
-fn test_disjunction_union() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // Children of diabetes OR children of body structure
-    let result = executor
-        .execute("<! 73211009 OR <! 123037004")
-        .unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // Type 1 and Type 2 are children of diabetes
-    assert!(ids.contains(&46635009));
-    assert!(ids.contains(&44054006));
-    // Pulmonary valve and Heart are children of body structure
-    assert!(ids.contains(&39057004));
-    assert!(ids.contains(&80891009));
-}
-
-#[test]
-fn test_exclusion_difference() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // All descendants of Clinical finding MINUS all descendants of Disease
-    let result = executor
-        .execute("<< 404684003 MINUS << 64572001")
-        .unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // Clinical finding itself should be included (not a descendant of Disease)
-    assert!(ids.contains(&404684003));
-    // Fever should be included (not under Disease)
-    assert!(ids.contains(&386661006));
-    // Diabetes should NOT be included (is descendant of Disease)
-    assert!(!ids.contains(&73211009));
-    // Type 1 should NOT be included
-    assert!(!ids.contains(&46635009));
-}
-
-#[test]
-fn test_deeply_nested_parentheses() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // ((Type 1 OR Type 2) AND descendants of diabetes)
-    let result = executor
-        .execute("((46635009 OR 44054006) AND << 73211009)")
-        .unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // Both Type 1 and Type 2 are descendants of diabetes
-    assert!(ids.contains(&46635009));
-    assert!(ids.contains(&44054006));
-    assert_eq!(ids.len(), 2);
-}
-
-#[test]
-fn test_member_of_intersection() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // Members of problem list refset that are diabetes types
-    let result = executor
-        .execute("^ 723264001 AND << 73211009")
-        .unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // 723264001 contains Type 1 and Type 2
-    // Both are descendants of diabetes
-    assert!(ids.contains(&46635009));
-    assert!(ids.contains(&44054006));
-}
-
-#[test]
-fn test_attribute_with_wildcard_value() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // Any concept with any finding site
-    let result = executor.execute("* : 363698007 = *").unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // Diabetes has finding site = heart
-    assert!(ids.contains(&73211009));
-}
+fn test_parallel_results_match_sequential() {
+    use snomed_ecl_executor::ExecutorConfig;
 
-#[test]
-fn test_combined_filters_active_and_primitive() {
     let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
+    let sequential = EclExecutor::new(&store);
+    let parallel =
+        EclExecutor::with_config(&store, ExecutorConfig::builder().with_parallel(true).build());
 
-    // Active AND primitive descendants of clinical finding
-    let result = executor
-        .execute("<< 404684003 {{ active = true, definitionStatus = primitive }}")
-        .unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // Should include active primitive concepts only
-    // Diabetes, Type 1, Type 2, Fever are all active and primitive
-    assert!(ids.contains(&73211009));
-    assert!(ids.contains(&46635009));
-    assert!(ids.contains(&386661006));
-    // Hypertension is inactive
-    assert!(!ids.contains(&38341003));
-    // Disease is defined (not primitive)
-    assert!(!ids.contains(&64572001));
-}
-
-#[test]
-fn test_child_or_self_of() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // Child-or-self of diabetes
-    let result = executor.execute("<<! 73211009").unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // Should include diabetes itself plus direct children
-    assert!(ids.contains(&73211009)); // Self
-    assert!(ids.contains(&46635009)); // Type 1 (child)
-    assert!(ids.contains(&44054006)); // Type 2 (child)
-    assert_eq!(ids.len(), 3);
-}
-
-#[test]
-fn test_parent_or_self_of() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // Parent-or-self of Type 1 diabetes
-    let result = executor.execute(">>! 46635009").unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // Should include Type 1 itself plus direct parent
-    assert!(ids.contains(&46635009)); // Self
-    assert!(ids.contains(&73211009)); // Diabetes (parent)
-    assert_eq!(ids.len(), 2);
-}
-
-#[test]
-fn test_multiple_member_of_refsets() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // Members of first refset OR members of second refset
-    let result = executor
-        .execute("^ 700043003 OR ^ 723264001")
-        .unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // 700043003 contains: Diabetes, Fever
-    // 723264001 contains: Type 1, Type 2
-    assert!(ids.contains(&73211009));
-    assert!(ids.contains(&386661006));
-    assert!(ids.contains(&46635009));
-    assert!(ids.contains(&44054006));
-}
-
-#[test]
-fn test_refinement_with_concept_value() {
-    let store = IntegrationTestStore::new();
-    let executor = EclExecutor::new(&store);
-
-    // Clinical findings with finding site = heart structure exactly
-    let result = executor
-        .execute("<< 404684003 : 363698007 = 80891009")
-        .unwrap();
-    let ids: HashSet<_> = result.iter().collect();
-
-    // Diabetes has finding site = heart
-    assert!(ids.contains(&73211009));
+    for ecl in [
+        "<< 404684003 : 363698007 = << 123037004",
+        "<< 123037004 : R 363698007 = *",
+        "<< 404684003 {{ active = true, definitionStatus = primitive }} : 363698007 = *",
+        "(<< 73211009 OR 386661006) AND << 404684003",
+        "^ 700043003 OR ^ 723264001",
+        "<< 64572001 MINUS ^ 723264001",
+    ] {
+        let expected = sequential.execute(ecl).unwrap().to_vec();
+        for _ in 0..10 {
+            assert_eq!(parallel.execute(ecl).unwrap().to_vec(), expected, "{}", ecl);
+        }
+    }
 }
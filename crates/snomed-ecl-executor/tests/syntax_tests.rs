@@ -122,11 +122,7 @@ impl EclQueryable for MockSyntaxStore {
 // Concept Reference Set Tests
 // ============================================================================
 
-// Note: ConceptSet syntax "(id1 id2)" is not yet implemented in the parser.
-// These tests are marked as ignore until the feature is added.
-
 #[test]
-#[ignore = "ConceptSet syntax not yet implemented in parser"]
 fn test_concept_reference_set_basic() {
     let store = MockSyntaxStore::new();
     let executor = EclExecutor::new(&store);
@@ -141,7 +137,6 @@ fn test_concept_reference_set_basic() {
 }
 
 #[test]
-#[ignore = "ConceptSet syntax not yet implemented in parser"]
 fn test_concept_reference_set_with_invalid_id() {
     let store = MockSyntaxStore::new();
     let executor = EclExecutor::new(&store);
@@ -155,7 +150,6 @@ fn test_concept_reference_set_with_invalid_id() {
 }
 
 #[test]
-#[ignore = "ConceptSet syntax not yet implemented in parser"]
 fn test_concept_reference_set_with_and() {
     let store = MockSyntaxStore::new();
     let executor = EclExecutor::new(&store);
@@ -298,8 +292,8 @@ fn test_wildcard_term_filter_parsing() {
     match expr {
         snomed_ecl::EclExpression::Filtered { filters, .. } => {
             match &filters[0] {
-                snomed_ecl::EclFilter::Term { match_type, .. } => {
-                    assert!(matches!(match_type, snomed_ecl::TermMatchType::Wildcard));
+                snomed_ecl::EclFilter::Term { terms } => {
+                    assert!(matches!(terms[0].match_type, snomed_ecl::TermMatchType::Wildcard));
                 }
                 _ => panic!("Expected Term filter"),
             }
@@ -30,7 +30,7 @@ mod stats;
 pub use stats::ClosureStats;
 
 use snomed_ecl::SctId;
-use snomed_ecl_executor::EclQueryable;
+use snomed_ecl_executor::{EclQueryable, StatisticsService};
 use std::collections::{HashMap, HashSet, VecDeque};
 
 /// Precomputed transitive closure of the IS-A hierarchy.
@@ -368,6 +368,46 @@ impl TransitiveClosure {
     pub fn memory_usage(&self) -> usize {
         self.stats.memory_estimate_bytes
     }
+
+    /// Returns planner statistics with exact hierarchy counts.
+    ///
+    /// The closure has no attribute or refset data; see
+    /// [`register_statistics`](Self::register_statistics) to combine its
+    /// counts with statistics collected from the full store.
+    pub fn statistics(&self) -> StatisticsService {
+        let mut statistics = StatisticsService::with_counts(HashMap::new());
+        self.register_statistics(&mut statistics);
+        statistics
+    }
+
+    /// Registers exact descendant, ancestor, child and parent counts and the
+    /// concept count with a statistics service.
+    ///
+    /// This is much cheaper than collecting the same counts by traversal.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut statistics = StatisticsBuilder::new()
+    ///     .with_hierarchy(false)
+    ///     .build(&store);
+    /// closure.register_statistics(&mut statistics);
+    /// ```
+    pub fn register_statistics(&self, statistics: &mut StatisticsService) {
+        for &concept_id in &self.concepts {
+            statistics.register_descendant_count(
+                concept_id,
+                self.descendants.get(&concept_id).map_or(0, HashSet::len),
+            );
+            statistics.register_ancestor_count(
+                concept_id,
+                self.ancestors.get(&concept_id).map_or(0, HashSet::len),
+            );
+            statistics.register_child_count(concept_id, self.get_direct_children(concept_id).len());
+            statistics.register_parent_count(concept_id, self.get_direct_parents(concept_id).len());
+        }
+        statistics.register_total_concepts(self.concepts.len());
+    }
 }
 
 /// Implement `EclQueryable` so the closure can be used directly with `EclExecutor`.
@@ -633,4 +673,19 @@ mod tests {
         let descendants = closure.get_descendants(100).unwrap();
         assert_eq!(descendants.len(), 3);
     }
+
+    #[test]
+    fn test_statistics() {
+        let store = create_test_store();
+        let closure = TransitiveClosure::build(&store);
+        let statistics = closure.statistics();
+
+        assert_eq!(statistics.estimated_total_concepts(), 6);
+        assert_eq!(statistics.estimated_descendants(100), 5);
+        assert_eq!(statistics.estimated_descendants(200), 2);
+        assert_eq!(statistics.estimated_descendants(600), 0);
+        assert_eq!(statistics.estimated_ancestors(400), 2);
+        assert_eq!(statistics.estimated_children(100), 2);
+        assert_eq!(statistics.estimated_parents(100), 0);
+    }
 }
@@ -212,8 +212,8 @@ impl BitsetFile {
         let computed_hash = Self::hash_ecl(&ecl_expression);
         if computed_hash != ecl_hash {
             return Err(OptimizerError::HashMismatch {
-                expected: hex::encode(&ecl_hash),
-                actual: hex::encode(&computed_hash),
+                expected: hex::encode(ecl_hash),
+                actual: hex::encode(computed_hash),
             });
         }
 
@@ -260,7 +260,7 @@ impl BitsetFile {
 
     /// Returns the ECL hash as a hex string.
     pub fn ecl_hash_hex(&self) -> String {
-        hex::encode(&self.ecl_hash)
+        hex::encode(self.ecl_hash)
     }
 
     /// Validates that a file matches the expected ECL expression.
@@ -270,13 +270,6 @@ impl BitsetFile {
     }
 }
 
-/// Helper module for hex encoding (minimal implementation).
-mod hex {
-    pub fn encode(bytes: &[u8]) -> String {
-        bytes.iter().map(|b| format!("{:02x}", b)).collect()
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,161 @@
+//! Loading a store from RF2 text vs. from a binary snapshot.
+//!
+//! The release is synthetic: `CONCEPTS` concepts in an 8-ary IS-A tree, each
+//! with one attribute, an FSN and a synonym, and a US English language
+//! reference set member for every description.
+//!
+//! Run with:
+//!
+//! ```text
+//! cargo bench -p snomed-ecl-store --features binary --bench binary_bench
+//! ```
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use snomed_ecl_store::Rf2Store;
+
+/// Number of concepts in the synthetic release.
+const CONCEPTS: u64 = 100_000;
+/// Number of children per concept.
+const FAN_OUT: u64 = 8;
+
+const MODULE: u64 = 900000000000207008;
+const PRIMITIVE: u64 = 900000000000074008;
+const IS_A: u64 = 116680003;
+const FINDING_SITE: u64 = 363698007;
+const INFERRED: u64 = 900000000000011006;
+const EXISTENTIAL: u64 = 900000000000451002;
+const FSN: u64 = 900000000000003001;
+const SYNONYM: u64 = 900000000000013009;
+const CASE_INSENSITIVE: u64 = 900000000000448009;
+const US_ENGLISH: u64 = 900000000000509007;
+const PREFERRED: u64 = 900000000000548007;
+
+/// Writes a tab-separated RF2 file with a header row.
+fn write_rf2<F>(path: &Path, header: &str, mut rows: F)
+where
+    F: FnMut(&mut dyn Write) -> std::io::Result<()>,
+{
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let mut writer = BufWriter::new(File::create(path).unwrap());
+    writeln!(writer, "{}", header).unwrap();
+    rows(&mut writer).unwrap();
+    writer.flush().unwrap();
+}
+
+/// Writes the synthetic release under `dir`.
+fn write_release(dir: &Path) {
+    let terminology = dir.join("Snapshot/Terminology");
+    let language = dir.join("Snapshot/Refset/Language");
+
+    write_rf2(
+        &terminology.join("sct2_Concept_Snapshot_INT_20240101.txt"),
+        "id\teffectiveTime\tactive\tmoduleId\tdefinitionStatusId",
+        |w| {
+            for id in 1..=CONCEPTS {
+                writeln!(w, "{}\t20240101\t1\t{}\t{}", id, MODULE, PRIMITIVE)?;
+            }
+            Ok(())
+        },
+    );
+
+    write_rf2(
+        &terminology.join("sct2_Relationship_Snapshot_INT_20240101.txt"),
+        "id\teffectiveTime\tactive\tmoduleId\tsourceId\tdestinationId\trelationshipGroup\ttypeId\tcharacteristicTypeId\tmodifierId",
+        |w| {
+            for id in 2..=CONCEPTS {
+                let parent = (id - 2) / FAN_OUT + 1;
+                for (n, (destination, group, type_id)) in
+                    [(parent, 0, IS_A), (id % 1_000 + 1, 1, FINDING_SITE)]
+                        .into_iter()
+                        .enumerate()
+                {
+                    writeln!(
+                        w,
+                        "{}\t20240101\t1\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                        id * 2 + n as u64,
+                        MODULE,
+                        id,
+                        destination,
+                        group,
+                        type_id,
+                        INFERRED,
+                        EXISTENTIAL
+                    )?;
+                }
+            }
+            Ok(())
+        },
+    );
+
+    write_rf2(
+        &terminology.join("sct2_Description_Snapshot-en_INT_20240101.txt"),
+        "id\teffectiveTime\tactive\tmoduleId\tconceptId\tlanguageCode\ttypeId\tterm\tcaseSignificanceId",
+        |w| {
+            for id in 1..=CONCEPTS {
+                for (n, (type_id, term)) in [
+                    (FSN, format!("Concept {} (finding)", id)),
+                    (SYNONYM, format!("Concept {}", id)),
+                ]
+                .into_iter()
+                .enumerate()
+                {
+                    writeln!(
+                        w,
+                        "{}\t20240101\t1\t{}\t{}\ten\t{}\t{}\t{}",
+                        id * 2 + n as u64,
+                        MODULE,
+                        id,
+                        type_id,
+                        term,
+                        CASE_INSENSITIVE
+                    )?;
+                }
+            }
+            Ok(())
+        },
+    );
+
+    write_rf2(
+        &language.join("der2_cRefset_LanguageSnapshot-en_INT_20240101.txt"),
+        "id\teffectiveTime\tactive\tmoduleId\trefsetId\treferencedComponentId\tacceptabilityId",
+        |w| {
+            for description_id in 2..=CONCEPTS * 2 + 1 {
+                writeln!(
+                    w,
+                    "{:08x}-0000-5000-8000-{:012x}\t20240101\t1\t{}\t{}\t{}\t{}",
+                    description_id, description_id, MODULE, US_ENGLISH, description_id, PREFERRED
+                )?;
+            }
+            Ok(())
+        },
+    );
+}
+
+fn bench_load(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    write_release(dir.path());
+    let snapshot = dir.path().join("snomed.ecls");
+    Rf2Store::load_snapshot(dir.path())
+        .unwrap()
+        .save_binary(&snapshot, "20240101")
+        .unwrap();
+
+    let mut group = c.benchmark_group("load");
+    group.sample_size(10);
+
+    group.bench_function("rf2_text", |b| {
+        b.iter(|| black_box(Rf2Store::load_snapshot(dir.path()).unwrap()))
+    });
+    group.bench_function("binary_snapshot", |b| {
+        b.iter(|| black_box(Rf2Store::load_binary(&snapshot, Some("20240101")).unwrap()))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_load);
+criterion_main!(benches);
@@ -0,0 +1,374 @@
+//! Save/load loaded stores as binary snapshots.
+//!
+//! Parsing RF2 text takes a while for a full release. A binary snapshot holds
+//! the compacted [`Rf2Store`] - hierarchy, attributes, concrete values,
+//! descriptions, language reference sets, other reference sets, historical
+//! associations and alternate identifiers - so a service can save it once
+//! and load it on every start without parsing RF2 text again.
+//!
+//! Loading decodes the whole store into memory: the file is read while its
+//! hash is checked and its payload decoded, then dropped. See
+//! `benches/binary_bench.rs` for load times compared with RF2 text.
+//!
+//! # File Format
+//!
+//! The snapshot file format (`.ecls`) is a binary format:
+//!
+//! ```text
+//! [4 bytes]  Magic: "ECLS"
+//! [4 bytes]  Version (u32 LE)
+//! [4 bytes]  SNOMED release length (u32 LE)
+//! [var]      SNOMED release (UTF-8)
+//! [8 bytes]  Payload length (u64 LE)
+//! [32 bytes] SHA-256 hash of payload
+//! [var]      Payload: the store, bincode-encoded
+//! ```
+//!
+//! # Example
+//!
+//! ```ignore
+//! use snomed_ecl_store::Rf2Store;
+//!
+//! // Once, e.g. in a build step
+//! let store = Rf2Store::load_snapshot("SnomedCT_InternationalRF2_PRODUCTION_20240101T120000Z")?;
+//! store.save_binary("snomed.ecls", "20240101")?;
+//!
+//! // On every start
+//! let store = Rf2Store::load_binary("snomed.ecls", Some("20240101"))?;
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+use crate::error::{StoreError, StoreResult};
+use crate::store::Rf2Store;
+
+/// Magic bytes for snapshot files.
+const SNAPSHOT_MAGIC: &[u8; 4] = b"ECLS";
+
+/// Current snapshot file format version.
+///
+/// Bump this whenever the layout of [`Rf2Store`] changes.
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// The header of a binary snapshot file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotHeader {
+    /// File format version.
+    pub version: u32,
+    /// SNOMED CT release identifier.
+    pub snomed_release: String,
+    /// Length of the encoded store in bytes.
+    pub payload_len: u64,
+    /// SHA-256 hash of the encoded store.
+    pub payload_hash: [u8; 32],
+}
+
+impl SnapshotHeader {
+    /// Reads the header of a snapshot file without loading the store.
+    ///
+    /// Fails if the file is not a snapshot or was written by another version
+    /// of this crate.
+    pub fn read<P: AsRef<Path>>(path: P) -> StoreResult<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| StoreError::io_error(path, e))?;
+        Self::parse(&mut BufReader::new(file), path)
+    }
+
+    /// Returns the payload hash as a hex string.
+    pub fn payload_hash_hex(&self) -> String {
+        hex::encode(self.payload_hash)
+    }
+
+    /// Parses the header from the start of `input`, leaving it positioned at
+    /// the payload.
+    fn parse<R: Read>(input: &mut R, path: &Path) -> StoreResult<Self> {
+        let mut reader = HeaderReader { input, path };
+
+        // Verify magic bytes
+        if &reader.array()? != SNAPSHOT_MAGIC {
+            return Err(StoreError::invalid_format("Invalid magic bytes"));
+        }
+
+        // Verify version
+        let version = u32::from_le_bytes(reader.array()?);
+        if version != SNAPSHOT_VERSION {
+            return Err(StoreError::invalid_format(format!(
+                "Unsupported version: {} (expected {})",
+                version, SNAPSHOT_VERSION
+            )));
+        }
+
+        // SNOMED release
+        let release_len = u32::from_le_bytes(reader.array()?) as usize;
+        let snomed_release = String::from_utf8_lossy(&reader.take(release_len)?).to_string();
+
+        let payload_len = u64::from_le_bytes(reader.array()?);
+        let payload_hash = reader.array()?;
+
+        Ok(Self {
+            version,
+            snomed_release,
+            payload_len,
+            payload_hash,
+        })
+    }
+}
+
+impl Rf2Store {
+    /// Saves the store as a binary snapshot, tagged with the SNOMED CT
+    /// release it was loaded from (e.g. `"20240101"`).
+    ///
+    /// Dialects set with [`with_dialects`](Self::with_dialects) are saved too.
+    pub fn save_binary<P: AsRef<Path>>(&self, path: P, snomed_release: &str) -> StoreResult<()> {
+        let path = path.as_ref();
+        let payload = bincode::encode_to_vec(self, bincode::config::standard())
+            .map_err(|e| StoreError::SerializationError(e.to_string()))?;
+        let payload_hash: [u8; 32] = Sha256::digest(&payload).into();
+
+        let file = File::create(path).map_err(|e| StoreError::io_error(path, e))?;
+        let mut writer = BufWriter::new(file);
+        let release_bytes = snomed_release.as_bytes();
+
+        for bytes in [
+            SNAPSHOT_MAGIC.as_slice(),
+            &SNAPSHOT_VERSION.to_le_bytes(),
+            &(release_bytes.len() as u32).to_le_bytes(),
+            release_bytes,
+            &(payload.len() as u64).to_le_bytes(),
+            &payload_hash,
+            &payload,
+        ] {
+            writer
+                .write_all(bytes)
+                .map_err(|e| StoreError::io_error(path, e))?;
+        }
+
+        writer.flush().map_err(|e| StoreError::io_error(path, e))?;
+
+        Ok(())
+    }
+
+    /// Loads a store from a binary snapshot.
+    ///
+    /// The payload is checked against the header hash, then decoded into an
+    /// owned store. If `expected_release` is given, fails with
+    /// [`StoreError::ReleaseMismatch`] when the file was saved from another
+    /// release. A file written by another format version fails with
+    /// [`StoreError::InvalidFormat`].
+    pub fn load_binary<P: AsRef<Path>>(
+        path: P,
+        expected_release: Option<&str>,
+    ) -> StoreResult<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| StoreError::io_error(path, e))?;
+        let mut payload = bytes.as_slice();
+        let header = SnapshotHeader::parse(&mut payload, path)?;
+
+        if let Some(expected) = expected_release {
+            if header.snomed_release != expected {
+                return Err(StoreError::ReleaseMismatch {
+                    expected: expected.to_string(),
+                    actual: header.snomed_release,
+                });
+            }
+        }
+
+        if payload.len() as u64 != header.payload_len {
+            return Err(StoreError::invalid_format(format!(
+                "Payload length mismatch: expected {}, got {}",
+                header.payload_len,
+                payload.len()
+            )));
+        }
+
+        // Verify payload hash
+        let computed_hash: [u8; 32] = Sha256::digest(payload).into();
+        if computed_hash != header.payload_hash {
+            return Err(StoreError::HashMismatch {
+                expected: hex::encode(header.payload_hash),
+                actual: hex::encode(computed_hash),
+            });
+        }
+
+        let (store, _) = bincode::decode_from_slice(payload, bincode::config::standard())
+            .map_err(|e| StoreError::DeserializationError(e.to_string()))?;
+
+        Ok(store)
+    }
+}
+
+/// Reads the fields of a snapshot header.
+struct HeaderReader<'a, R> {
+    input: &'a mut R,
+    path: &'a Path,
+}
+
+impl<R: Read> HeaderReader<'_, R> {
+    fn take(&mut self, len: usize) -> StoreResult<Vec<u8>> {
+        // Don't trust `len` with an allocation: a corrupt file could claim gigabytes
+        let mut bytes = Vec::new();
+        (&mut *self.input)
+            .take(len as u64)
+            .read_to_end(&mut bytes)
+            .map_err(|e| StoreError::io_error(self.path, e))?;
+        if bytes.len() < len {
+            return Err(StoreError::invalid_format("Unexpected end of file"));
+        }
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> StoreResult<[u8; N]> {
+        let mut array = [0u8; N];
+        self.input
+            .read_exact(&mut array)
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::UnexpectedEof => {
+                    StoreError::invalid_format("Unexpected end of file")
+                }
+                _ => StoreError::io_error(self.path, e),
+            })?;
+        Ok(array)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snomed_ecl_executor::{EclExecutor, EclQueryable};
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    fn fixture_store() -> Rf2Store {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/rf2");
+        Rf2Store::load_snapshot(dir).unwrap()
+    }
+
+    fn saved(dir: &Path) -> PathBuf {
+        let path = dir.join("snomed.ecls");
+        fixture_store().save_binary(&path, "20240101").unwrap();
+        path
+    }
+
+    fn ids(store: &Rf2Store, ecl: &str) -> Vec<u64> {
+        let mut ids: Vec<u64> = EclExecutor::new(store)
+            .execute(ecl)
+            .unwrap()
+            .iter()
+            .copied()
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempdir().unwrap();
+        let path = saved(dir.path());
+        let original = fixture_store();
+        let loaded = Rf2Store::load_binary(&path, Some("20240101")).unwrap();
+
+        assert_eq!(loaded.concept_count(), original.concept_count());
+        assert_eq!(loaded.description_count(), original.description_count());
+        assert_eq!(loaded.relationship_count(), original.relationship_count());
+
+        for ecl in [
+            "<< 404684003",
+            "<< 404684003 : 363698007 = << 123037004",
+            "< 373873005 : 1142135004 = #500",
+            "^ 447562003 {{ M mapTarget = \"E11.9\" }}",
+            "<< 73211009 {{ term = \"type ii\", preferredIn = 900000000000508004 }}",
+            "<< 73211009 {{ +HISTORY-MOD }}",
+            "* {{ C active = false }}",
+        ] {
+            assert_eq!(ids(&loaded, ecl), ids(&original, ecl), "{}", ecl);
+        }
+        assert_eq!(
+            loaded.get_preferred_term(44054006),
+            original.get_preferred_term(44054006)
+        );
+    }
+
+    #[test]
+    fn test_load_without_release_check() {
+        let dir = tempdir().unwrap();
+        let path = saved(dir.path());
+        let loaded = Rf2Store::load_binary(&path, None).unwrap();
+        assert_eq!(loaded.concept_count(), fixture_store().concept_count());
+    }
+
+    #[test]
+    fn test_read_header() {
+        let dir = tempdir().unwrap();
+        let path = saved(dir.path());
+        let header = SnapshotHeader::read(&path).unwrap();
+
+        assert_eq!(header.version, SNAPSHOT_VERSION);
+        assert_eq!(header.snomed_release, "20240101");
+        assert_eq!(header.payload_hash_hex().len(), 64);
+        let header_len = 4 + 4 + 4 + 8 + 8 + 32;
+        assert_eq!(
+            header.payload_len + header_len,
+            std::fs::metadata(&path).unwrap().len()
+        );
+    }
+
+    #[test]
+    fn test_release_mismatch() {
+        let dir = tempdir().unwrap();
+        let path = saved(dir.path());
+        let err = Rf2Store::load_binary(&path, Some("20240701")).unwrap_err();
+        assert!(matches!(
+            err,
+            StoreError::ReleaseMismatch { ref expected, ref actual }
+                if expected == "20240701" && actual == "20240101"
+        ));
+    }
+
+    #[test]
+    fn test_version_mismatch() {
+        let dir = tempdir().unwrap();
+        let path = saved(dir.path());
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[4..8].copy_from_slice(&(SNAPSHOT_VERSION + 1).to_le_bytes());
+        std::fs::write(&path, bytes).unwrap();
+
+        let err = Rf2Store::load_binary(&path, None).unwrap_err();
+        assert!(matches!(err, StoreError::InvalidFormat { .. }));
+        assert!(err.to_string().contains("Unsupported version"));
+    }
+
+    #[test]
+    fn test_corrupted_payload() {
+        let dir = tempdir().unwrap();
+        let path = saved(dir.path());
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        std::fs::write(&path, bytes).unwrap();
+
+        let err = Rf2Store::load_binary(&path, None).unwrap_err();
+        assert!(matches!(err, StoreError::HashMismatch { .. }));
+    }
+
+    #[test]
+    fn test_invalid_magic() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("invalid.ecls");
+
+        // Write invalid file
+        std::fs::write(&path, b"BAAD").unwrap();
+        let err = Rf2Store::load_binary(&path, None).unwrap_err();
+        assert!(matches!(err, StoreError::InvalidFormat { .. }));
+
+        // Truncated header
+        std::fs::write(&path, b"ECLS").unwrap();
+        let err = Rf2Store::load_binary(&path, None).unwrap_err();
+        assert!(err.to_string().contains("Unexpected end of file"));
+        let err = SnapshotHeader::read(&path).unwrap_err();
+        assert!(err.to_string().contains("Unexpected end of file"));
+    }
+}
@@ -0,0 +1,121 @@
+//! Error types for the store crate.
+
+use std::path::PathBuf;
+
+/// Result type for store operations.
+pub type StoreResult<T> = Result<T, StoreError>;
+
+/// Errors that can occur while loading a store.
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    /// I/O error while reading release files.
+    #[error("I/O error at {path}: {source}")]
+    IoError {
+        /// The file or directory being read.
+        path: PathBuf,
+        /// The underlying error.
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A row of an RF2 file could not be parsed.
+    #[error("Invalid RF2 row at {path}:{line}: {message}")]
+    InvalidRow {
+        /// The RF2 file.
+        path: PathBuf,
+        /// The 1-based line number.
+        line: usize,
+        /// What is wrong with the row.
+        message: String,
+    },
+
+    /// A file required to build the store was not found.
+    #[error("No {kind} file found under {path}")]
+    MissingFile {
+        /// The kind of RF2 file, e.g. "concept".
+        kind: &'static str,
+        /// The directory that was searched.
+        path: PathBuf,
+    },
+
+    /// Invalid binary store file.
+    #[cfg(feature = "binary")]
+    #[error("Invalid binary store file: {message}")]
+    InvalidFormat {
+        /// What is wrong with the file.
+        message: String,
+    },
+
+    /// The binary store file was built from a different release.
+    #[cfg(feature = "binary")]
+    #[error("Release mismatch: expected {expected}, file contains {actual}")]
+    ReleaseMismatch {
+        /// The release the caller asked for.
+        expected: String,
+        /// The release recorded in the file.
+        actual: String,
+    },
+
+    /// The binary store file's payload does not match its hash.
+    #[cfg(feature = "binary")]
+    #[error("Hash mismatch: expected {expected}, got {actual}")]
+    HashMismatch {
+        /// The hash recorded in the file header.
+        expected: String,
+        /// The hash of the payload as read.
+        actual: String,
+    },
+
+    /// Serialization error.
+    #[cfg(feature = "binary")]
+    #[error("Serialization error: {0}")]
+    SerializationError(String),
+
+    /// Deserialization error.
+    #[cfg(feature = "binary")]
+    #[error("Deserialization error: {0}")]
+    DeserializationError(String),
+
+    /// SQLite error while importing into or opening a store database.
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+
+    /// The database was not created by the importer, or by an incompatible
+    /// version of it.
+    #[cfg(feature = "sqlite")]
+    #[error("Invalid store database at {path}: {message}")]
+    InvalidDatabase {
+        /// The database file.
+        path: PathBuf,
+        /// What is wrong with the database.
+        message: String,
+    },
+}
+
+impl StoreError {
+    /// Creates an I/O error with path context.
+    pub fn io_error(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        Self::IoError {
+            path: path.into(),
+            source,
+        }
+    }
+
+    /// Creates an invalid row error.
+    pub fn invalid_row(path: impl Into<PathBuf>, line: usize, message: impl Into<String>) -> Self {
+        Self::InvalidRow {
+            path: path.into(),
+            line,
+            message: message.into(),
+        }
+    }
+
+    /// Creates an invalid format error.
+    #[cfg(feature = "binary")]
+    pub fn invalid_format(message: impl Into<String>) -> Self {
+        Self::InvalidFormat {
+            message: message.into(),
+        }
+    }
+}
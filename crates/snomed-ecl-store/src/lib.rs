@@ -0,0 +1,100 @@
+//! # snomed-ecl-store
+//!
+//! Ready-made SNOMED CT stores for the `snomed-ecl-executor` crate.
+//!
+//! The executor runs ECL against any store implementing
+//! [`EclQueryable`](snomed_ecl_executor::EclQueryable). This crate provides
+//! one, so applications do not have to write their own:
+//!
+//! - [`Rf2Store`] - an in-memory store loaded from an RF2 release
+//! - [`Rf2Loader`] - builds an [`Rf2Store`] from Snapshot, Full and Delta
+//!   files, optionally as of a past effective date
+//! - `SqliteStore` - the same release imported into a SQLite database and
+//!   queried in place (`sqlite` feature)
+//!
+//! ## Quick Start
+//!
+//! ```ignore
+//! use snomed_ecl_executor::EclExecutor;
+//! use snomed_ecl_store::Rf2Store;
+//!
+//! // Load the Snapshot files of an RF2 release
+//! let store = Rf2Store::load_snapshot("SnomedCT_InternationalRF2_PRODUCTION_20240101T120000Z")?;
+//! println!("Loaded {} concepts", store.concept_count());
+//!
+//! let executor = EclExecutor::new(&store);
+//! let result = executor.execute("<< 73211009 |Diabetes mellitus| {{ term = \"type\" }}")?;
+//! ```
+//!
+//! ## RF2 Files
+//!
+//! Files are found anywhere under the given directory by their RF2 names:
+//!
+//! | File | Used for |
+//! |------|----------|
+//! | `sct2_Concept_Snapshot` | Concepts, concept filters |
+//! | `sct2_Description_Snapshot`, `sct2_TextDefinition_Snapshot` | Description filters, terms |
+//! | `sct2_Relationship_Snapshot` | Hierarchy, attribute refinements |
+//! | `sct2_RelationshipConcreteValues_Snapshot` | Concrete value refinements |
+//! | `sct2_Identifier_Snapshot` | Alternate identifiers |
+//! | `der2_cRefset_LanguageSnapshot` | Dialect filters, preferred terms |
+//! | `der2_cRefset_AssociationSnapshot` | History supplements |
+//! | Any other `der2_*Refset_*Snapshot` | Member of, member filters |
+//!
+//! Full and Delta files follow the same names with `Full` or `Delta` in
+//! place of `Snapshot`.
+//!
+//! ## Point-in-Time Views
+//!
+//! ```ignore
+//! use snomed_ecl_store::Rf2Loader;
+//!
+//! // The release as published on 2023-01-31, rebuilt from Full files
+//! let store = Rf2Loader::full("SnomedCT_InternationalRF2_PRODUCTION_20240101T120000Z")
+//!     .at_date(20230131)
+//!     .load()?;
+//! ```
+//!
+//! ## Binary Snapshots
+//!
+//! With the `binary` feature, a loaded store can be saved as a versioned
+//! binary snapshot and loaded from it on the next start instead of parsing
+//! RF2 text again:
+//!
+//! ```ignore
+//! store.save_binary("snomed.ecls", "20240101")?;
+//! let store = Rf2Store::load_binary("snomed.ecls", Some("20240101"))?;
+//! ```
+//!
+//! ## SQLite
+//!
+//! With the `sqlite` feature, a release can be imported into a SQLite
+//! database and queried from disk, keeping memory use small:
+//!
+//! ```ignore
+//! use snomed_ecl_store::{Rf2Loader, SqliteStore};
+//!
+//! SqliteStore::import("snomed.db", &Rf2Loader::snapshot("SnomedCT_InternationalRF2_PRODUCTION_20240101T120000Z"))?;
+//! let store = SqliteStore::open("snomed.db")?;
+//! ```
+
+#![warn(missing_docs)]
+#![warn(rustdoc::missing_crate_level_docs)]
+
+#[cfg(feature = "binary")]
+mod binary;
+mod error;
+mod loader;
+mod rf2;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod store;
+
+// Public re-exports
+#[cfg(feature = "binary")]
+pub use binary::SnapshotHeader;
+pub use error::{StoreError, StoreResult};
+pub use loader::Rf2Loader;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+pub use store::Rf2Store;
@@ -0,0 +1,742 @@
+//! RF2 release file discovery and row parsing.
+//!
+//! An RF2 release is a tree of tab-separated text files whose names follow
+//! the pattern `<prefix>_<type>_<ComponentOrRefset><ReleaseType>[-lang]_<namespace>_<date>.txt`,
+//! for example `sct2_Concept_Snapshot_INT_20240101.txt` or
+//! `der2_cRefset_LanguageSnapshot-en_INT_20240101.txt`. Files are found by
+//! walking the release directory and classifying each name, so the layout of
+//! the directory does not matter.
+//!
+//! Rows are collected into [`Rf2Rows`], keyed by component or member ID, and
+//! later compacted into an [`Rf2Store`](crate::Rf2Store) or written to a
+//! SQLite database. When several versions of a row are read - from a Full
+//! file, or from a Delta applied on top of a Snapshot - the latest version up
+//! to the cutoff date wins.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use snomed_ecl::SctId;
+
+use crate::error::{StoreError, StoreResult};
+
+/// Definition status of a primitive concept.
+const PRIMITIVE: SctId = 900000000000074008;
+
+/// Characteristic type of stated relationships, which ECL does not evaluate.
+const STATED_RELATIONSHIP: SctId = 900000000000010007;
+
+/// Reference set patterns that are never loaded, because they are large and
+/// not used by ECL.
+const SKIPPED_REFSETS: &[&str] = &["OWLExpression", "OWLAxiom", "OWLOntology"];
+
+// =============================================================================
+// Release files
+// =============================================================================
+
+/// The RF2 release type of a set of files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReleaseType {
+    /// The latest version of every component.
+    Snapshot,
+    /// Every version of every component.
+    Full,
+    /// The versions added since the previous release.
+    Delta,
+}
+
+impl ReleaseType {
+    /// The release type as it appears in RF2 file names.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReleaseType::Snapshot => "Snapshot",
+            ReleaseType::Full => "Full",
+            ReleaseType::Delta => "Delta",
+        }
+    }
+}
+
+/// The kind of reference set held by an RF2 refset file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "binary", derive(bincode::Encode, bincode::Decode))]
+pub(crate) enum RefsetKind {
+    /// Language reference set (`cRefset_Language`).
+    Language,
+    /// Historical association reference set (`cRefset_Association`).
+    Association,
+    /// Any other reference set (simple, map, attribute value, ...).
+    Other,
+}
+
+/// An RF2 reference set file.
+#[derive(Debug, Clone)]
+pub(crate) struct RefsetFile {
+    /// Path of the file.
+    pub path: PathBuf,
+    /// The field pattern from the file name, e.g. `iissscc` for `der2_iisssccRefset_...`.
+    pub pattern: String,
+    /// The kind of reference set.
+    pub kind: RefsetKind,
+}
+
+/// The RF2 files of one release type found under a directory.
+#[derive(Debug, Default)]
+pub(crate) struct ReleaseFiles {
+    pub concepts: Vec<PathBuf>,
+    pub descriptions: Vec<PathBuf>,
+    pub relationships: Vec<PathBuf>,
+    pub concrete_relationships: Vec<PathBuf>,
+    pub identifiers: Vec<PathBuf>,
+    pub refsets: Vec<RefsetFile>,
+}
+
+impl ReleaseFiles {
+    /// Finds the files of the given release type anywhere under `dir`.
+    pub fn discover(dir: &Path, release_type: ReleaseType) -> StoreResult<Self> {
+        let mut paths = Vec::new();
+        collect_files(dir, &mut paths)?;
+        paths.sort();
+
+        let mut files = Self::default();
+        for path in paths {
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            files.classify(&path, name, release_type.as_str());
+        }
+        Ok(files)
+    }
+
+    /// Returns true if no RF2 file was found.
+    pub fn is_empty(&self) -> bool {
+        self.concepts.is_empty()
+            && self.descriptions.is_empty()
+            && self.relationships.is_empty()
+            && self.concrete_relationships.is_empty()
+            && self.identifiers.is_empty()
+            && self.refsets.is_empty()
+    }
+
+    fn classify(&mut self, path: &Path, name: &str, release_type: &str) {
+        let Some(stem) = name.strip_suffix(".txt") else {
+            return;
+        };
+        let mut parts = stem.split('_');
+        let (Some(prefix), Some(file_type), Some(content)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return;
+        };
+        // Strip the language suffix of description and language refset files
+        let content = content.split('-').next().unwrap_or(content);
+
+        match prefix {
+            "sct2" => {
+                if content != release_type {
+                    return;
+                }
+                let target = match file_type {
+                    "Concept" => &mut self.concepts,
+                    "Description" | "TextDefinition" => &mut self.descriptions,
+                    "Relationship" => &mut self.relationships,
+                    "RelationshipConcreteValues" => &mut self.concrete_relationships,
+                    "Identifier" => &mut self.identifiers,
+                    _ => return,
+                };
+                target.push(path.to_path_buf());
+            }
+            "der2" => {
+                let (Some(pattern), Some(refset_name)) = (
+                    file_type.strip_suffix("Refset"),
+                    content.strip_suffix(release_type),
+                ) else {
+                    return;
+                };
+                if SKIPPED_REFSETS.contains(&refset_name) {
+                    return;
+                }
+                let kind = match refset_name {
+                    "Language" => RefsetKind::Language,
+                    name if name.starts_with("Association") => RefsetKind::Association,
+                    _ => RefsetKind::Other,
+                };
+                self.refsets.push(RefsetFile {
+                    path: path.to_path_buf(),
+                    pattern: pattern.to_string(),
+                    kind,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+fn collect_files(dir: &Path, paths: &mut Vec<PathBuf>) -> StoreResult<()> {
+    let entries = std::fs::read_dir(dir).map_err(|e| StoreError::io_error(dir, e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| StoreError::io_error(dir, e))?.path();
+        if path.is_dir() {
+            collect_files(&path, paths)?;
+        } else {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+// =============================================================================
+// Rows
+// =============================================================================
+
+/// A concept row.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "binary", derive(bincode::Encode, bincode::Decode))]
+pub(crate) struct ConceptRow {
+    pub id: SctId,
+    pub effective_time: u32,
+    pub active: bool,
+    pub module_id: SctId,
+    pub primitive: bool,
+}
+
+/// A description or text definition row.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DescriptionRow {
+    pub id: SctId,
+    pub effective_time: u32,
+    pub active: bool,
+    pub module_id: SctId,
+    pub concept_id: SctId,
+    pub language_code: String,
+    pub type_id: SctId,
+    pub term: String,
+    pub case_significance_id: SctId,
+}
+
+/// An inferred relationship row.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RelationshipRow {
+    pub id: SctId,
+    pub effective_time: u32,
+    pub active: bool,
+    pub module_id: SctId,
+    pub source_id: SctId,
+    pub destination_id: SctId,
+    pub group: u16,
+    pub type_id: SctId,
+}
+
+/// A concrete value of a concrete relationship.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "binary", derive(bincode::Encode, bincode::Decode))]
+pub(crate) enum ConcreteValue {
+    Integer(i64),
+    Decimal(f64),
+    String(Box<str>),
+}
+
+/// A concrete relationship row.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ConcreteRow {
+    pub id: SctId,
+    pub effective_time: u32,
+    pub active: bool,
+    pub module_id: SctId,
+    pub source_id: SctId,
+    pub value: ConcreteValue,
+    pub group: u16,
+    pub type_id: SctId,
+}
+
+/// An alternate identifier row.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct IdentifierRow {
+    pub alternate_identifier: String,
+    pub effective_time: u32,
+    pub active: bool,
+    pub module_id: SctId,
+    pub scheme_id: SctId,
+    pub referenced_component_id: SctId,
+}
+
+/// A typed additional field of a reference set member.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "binary", derive(bincode::Encode, bincode::Decode))]
+pub(crate) enum FieldValue {
+    Component(SctId),
+    Integer(i64),
+    String(Box<str>),
+}
+
+/// A reference set member row.
+///
+/// Language reference set members are rows too; their single field is the
+/// acceptability ID.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "binary", derive(bincode::Encode, bincode::Decode))]
+pub(crate) struct MemberRow {
+    pub id: u128,
+    pub effective_time: u32,
+    pub active: bool,
+    pub module_id: SctId,
+    pub refset_id: SctId,
+    pub referenced_component_id: SctId,
+    pub fields: Box<[FieldValue]>,
+}
+
+/// The additional columns and kind of a reference set.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RefsetColumns {
+    pub kind: RefsetKind,
+    pub names: Arc<[String]>,
+}
+
+/// Rows read from RF2 files, keyed by component or member ID.
+#[derive(Debug, Default)]
+pub(crate) struct Rf2Rows {
+    pub concepts: HashMap<SctId, ConceptRow>,
+    pub descriptions: HashMap<SctId, DescriptionRow>,
+    pub relationships: HashMap<SctId, RelationshipRow>,
+    pub concrete_relationships: HashMap<SctId, ConcreteRow>,
+    pub identifiers: HashMap<(SctId, String), IdentifierRow>,
+    pub members: HashMap<u128, MemberRow>,
+    pub refsets: HashMap<SctId, RefsetColumns>,
+}
+
+impl Rf2Rows {
+    /// Reads every file in `files`, ignoring row versions effective after
+    /// `cutoff` (YYYYMMDD).
+    ///
+    /// A row replaces an already read version of the same component unless
+    /// that version is newer. Rows without an effective time (unpublished
+    /// changes in a Delta) count as newer than any date, so they are only
+    /// kept when the cutoff is `u32::MAX`.
+    pub fn read(&mut self, files: &ReleaseFiles, cutoff: u32) -> StoreResult<()> {
+        for path in &files.concepts {
+            for_each_row(path, 5, |row| {
+                let concept = ConceptRow {
+                    id: row.sct_id(0)?,
+                    effective_time: row.effective_time(1)?,
+                    active: row.flag(2)?,
+                    module_id: row.sct_id(3)?,
+                    primitive: row.sct_id(4)? == PRIMITIVE,
+                };
+                upsert(&mut self.concepts, concept.id, concept, cutoff);
+                Ok(())
+            })?;
+        }
+
+        for path in &files.descriptions {
+            for_each_row(path, 9, |row| {
+                let description = DescriptionRow {
+                    id: row.sct_id(0)?,
+                    effective_time: row.effective_time(1)?,
+                    active: row.flag(2)?,
+                    module_id: row.sct_id(3)?,
+                    concept_id: row.sct_id(4)?,
+                    language_code: row.field(5).to_string(),
+                    type_id: row.sct_id(6)?,
+                    term: row.field(7).to_string(),
+                    case_significance_id: row.sct_id(8)?,
+                };
+                upsert(&mut self.descriptions, description.id, description, cutoff);
+                Ok(())
+            })?;
+        }
+
+        for path in &files.relationships {
+            for_each_row(path, 10, |row| {
+                if row.sct_id(8)? == STATED_RELATIONSHIP {
+                    return Ok(());
+                }
+                let relationship = RelationshipRow {
+                    id: row.sct_id(0)?,
+                    effective_time: row.effective_time(1)?,
+                    active: row.flag(2)?,
+                    module_id: row.sct_id(3)?,
+                    source_id: row.sct_id(4)?,
+                    destination_id: row.sct_id(5)?,
+                    group: row.group(6)?,
+                    type_id: row.sct_id(7)?,
+                };
+                upsert(
+                    &mut self.relationships,
+                    relationship.id,
+                    relationship,
+                    cutoff,
+                );
+                Ok(())
+            })?;
+        }
+
+        for path in &files.concrete_relationships {
+            for_each_row(path, 10, |row| {
+                let relationship = ConcreteRow {
+                    id: row.sct_id(0)?,
+                    effective_time: row.effective_time(1)?,
+                    active: row.flag(2)?,
+                    module_id: row.sct_id(3)?,
+                    source_id: row.sct_id(4)?,
+                    value: row.concrete_value(5)?,
+                    group: row.group(6)?,
+                    type_id: row.sct_id(7)?,
+                };
+                upsert(
+                    &mut self.concrete_relationships,
+                    relationship.id,
+                    relationship,
+                    cutoff,
+                );
+                Ok(())
+            })?;
+        }
+
+        for path in &files.identifiers {
+            for_each_row(path, 6, |row| {
+                let identifier = IdentifierRow {
+                    alternate_identifier: row.field(0).to_string(),
+                    effective_time: row.effective_time(1)?,
+                    active: row.flag(2)?,
+                    module_id: row.sct_id(3)?,
+                    scheme_id: row.sct_id(4)?,
+                    referenced_component_id: row.sct_id(5)?,
+                };
+                let key = (
+                    identifier.scheme_id,
+                    identifier.alternate_identifier.clone(),
+                );
+                upsert(&mut self.identifiers, key, identifier, cutoff);
+                Ok(())
+            })?;
+        }
+
+        for file in &files.refsets {
+            self.read_refset(file, cutoff)?;
+        }
+        Ok(())
+    }
+
+    fn read_refset(&mut self, file: &RefsetFile, cutoff: u32) -> StoreResult<()> {
+        let kinds: Vec<char> = file.pattern.chars().collect();
+        let mut names: Option<Arc<[String]>> = None;
+
+        for_each_row(&file.path, 6 + kinds.len(), |row| {
+            let names = names.get_or_insert_with(|| row.header[6..].iter().cloned().collect());
+            let fields = kinds
+                .iter()
+                .enumerate()
+                .map(|(i, &kind)| row.field_value(6 + i, kind))
+                .collect::<StoreResult<Box<[FieldValue]>>>()?;
+            let member = MemberRow {
+                id: row.uuid(0)?,
+                effective_time: row.effective_time(1)?,
+                active: row.flag(2)?,
+                module_id: row.sct_id(3)?,
+                refset_id: row.sct_id(4)?,
+                referenced_component_id: row.sct_id(5)?,
+                fields,
+            };
+            self.refsets
+                .entry(member.refset_id)
+                .or_insert_with(|| RefsetColumns {
+                    kind: file.kind,
+                    names: names.clone(),
+                });
+            upsert(&mut self.members, member.id, member, cutoff);
+            Ok(())
+        })
+    }
+}
+
+/// A row with an effective time.
+pub(crate) trait Versioned {
+    /// The effective time in YYYYMMDD format, or 0 if unpublished.
+    fn effective_time(&self) -> u32;
+
+    /// The effective time for ordering versions; unpublished rows are the newest.
+    fn version(&self) -> u32 {
+        match self.effective_time() {
+            0 => u32::MAX,
+            time => time,
+        }
+    }
+}
+
+macro_rules! impl_versioned {
+    ($($row:ty),*) => {
+        $(impl Versioned for $row {
+            fn effective_time(&self) -> u32 {
+                self.effective_time
+            }
+        })*
+    };
+}
+
+impl_versioned!(
+    ConceptRow,
+    DescriptionRow,
+    RelationshipRow,
+    ConcreteRow,
+    IdentifierRow,
+    MemberRow
+);
+
+/// Keeps `row` if it is effective by `cutoff` and not older than the version
+/// already read.
+fn upsert<K: Hash + Eq, R: Versioned>(map: &mut HashMap<K, R>, key: K, row: R, cutoff: u32) {
+    if row.version() > cutoff {
+        return;
+    }
+    match map.entry(key) {
+        Entry::Occupied(mut entry) => {
+            if row.version() >= entry.get().version() {
+                entry.insert(row);
+            }
+        }
+        Entry::Vacant(entry) => {
+            entry.insert(row);
+        }
+    }
+}
+
+// =============================================================================
+// Parsing
+// =============================================================================
+
+/// A row of an RF2 file, split into its tab-separated fields.
+struct Row<'a> {
+    path: &'a Path,
+    line: usize,
+    header: &'a [String],
+    fields: Vec<&'a str>,
+}
+
+impl Row<'_> {
+    fn error(&self, column: usize, message: &str) -> StoreError {
+        let name = self.header.get(column).map_or("?", String::as_str);
+        StoreError::invalid_row(self.path, self.line, format!("{}: {}", name, message))
+    }
+
+    fn field(&self, column: usize) -> &str {
+        self.fields[column]
+    }
+
+    fn sct_id(&self, column: usize) -> StoreResult<SctId> {
+        self.field(column)
+            .parse()
+            .map_err(|_| self.error(column, "expected an SCTID"))
+    }
+
+    fn flag(&self, column: usize) -> StoreResult<bool> {
+        match self.field(column) {
+            "1" => Ok(true),
+            "0" => Ok(false),
+            _ => Err(self.error(column, "expected 0 or 1")),
+        }
+    }
+
+    /// Parses a YYYYMMDD effective time; an empty value (unpublished) is 0.
+    fn effective_time(&self, column: usize) -> StoreResult<u32> {
+        let value = self.field(column);
+        if value.is_empty() {
+            return Ok(0);
+        }
+        value
+            .parse()
+            .map_err(|_| self.error(column, "expected a YYYYMMDD date"))
+    }
+
+    fn group(&self, column: usize) -> StoreResult<u16> {
+        self.field(column)
+            .parse()
+            .map_err(|_| self.error(column, "expected a relationship group"))
+    }
+
+    fn uuid(&self, column: usize) -> StoreResult<u128> {
+        parse_uuid(self.field(column)).ok_or_else(|| self.error(column, "expected a UUID"))
+    }
+
+    fn concrete_value(&self, column: usize) -> StoreResult<ConcreteValue> {
+        let value = self.field(column);
+        if let Some(number) = value.strip_prefix('#') {
+            let parsed = if number.contains('.') {
+                number.parse().ok().map(ConcreteValue::Decimal)
+            } else {
+                number.parse().ok().map(ConcreteValue::Integer)
+            };
+            parsed.ok_or_else(|| self.error(column, "expected a number after '#'"))
+        } else if let Some(string) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            Ok(ConcreteValue::String(string.into()))
+        } else {
+            Err(self.error(column, "expected #number or \"string\""))
+        }
+    }
+
+    fn field_value(&self, column: usize, kind: char) -> StoreResult<FieldValue> {
+        match kind {
+            'c' => self.sct_id(column).map(FieldValue::Component),
+            'i' => self
+                .field(column)
+                .parse()
+                .map(FieldValue::Integer)
+                .map_err(|_| self.error(column, "expected an integer")),
+            _ => Ok(FieldValue::String(self.field(column).into())),
+        }
+    }
+}
+
+/// Calls `on_row` for every data row of an RF2 file.
+///
+/// The first line is the header. Rows with fewer than `columns` fields are
+/// rejected.
+fn for_each_row(
+    path: &Path,
+    columns: usize,
+    mut on_row: impl FnMut(&Row<'_>) -> StoreResult<()>,
+) -> StoreResult<()> {
+    let file = File::open(path).map_err(|e| StoreError::io_error(path, e))?;
+    let mut lines = BufReader::new(file).lines();
+
+    let header: Vec<String> = match lines.next() {
+        Some(line) => line
+            .map_err(|e| StoreError::io_error(path, e))?
+            .trim_end_matches('\r')
+            .split('\t')
+            .map(str::to_string)
+            .collect(),
+        None => return Ok(()),
+    };
+    if header.len() < columns {
+        return Err(StoreError::invalid_row(
+            path,
+            1,
+            format!("expected {} columns, found {}", columns, header.len()),
+        ));
+    }
+
+    for (index, line) in lines.enumerate() {
+        let line = line.map_err(|e| StoreError::io_error(path, e))?;
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() {
+            continue;
+        }
+        let row = Row {
+            path,
+            line: index + 2,
+            header: &header,
+            fields: line.split('\t').collect(),
+        };
+        if row.fields.len() < columns {
+            return Err(StoreError::invalid_row(
+                path,
+                row.line,
+                format!("expected {} columns, found {}", columns, row.fields.len()),
+            ));
+        }
+        on_row(&row)?;
+    }
+    Ok(())
+}
+
+/// Parses a UUID such as `80a4a9c4-2b8d-5c0e-a5bc-4d8a4b1c7e21`.
+pub(crate) fn parse_uuid(value: &str) -> Option<u128> {
+    let hex: String = value.chars().filter(|&c| c != '-').collect();
+    if hex.len() != 32 {
+        return None;
+    }
+    u128::from_str_radix(&hex, 16).ok()
+}
+
+/// Formats a UUID in its canonical hyphenated form.
+pub(crate) fn format_uuid(id: u128) -> String {
+    let hex = format!("{:032x}", id);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uuid_round_trip() {
+        let uuid = "80a4a9c4-2b8d-5c0e-a5bc-4d8a4b1c7e21";
+        assert_eq!(format_uuid(parse_uuid(uuid).unwrap()), uuid);
+        assert_eq!(parse_uuid("not-a-uuid"), None);
+    }
+
+    #[test]
+    fn test_upsert_keeps_latest_version_by_cutoff() {
+        let concept = |effective_time, active| ConceptRow {
+            id: 73211009,
+            effective_time,
+            active,
+            module_id: 900000000000207008,
+            primitive: true,
+        };
+        let mut concepts = HashMap::new();
+        upsert(&mut concepts, 73211009, concept(20020131, true), 20230131);
+        upsert(&mut concepts, 73211009, concept(20240101, false), 20230131);
+        assert_eq!(concepts[&73211009].effective_time, 20020131);
+
+        // Older versions never replace newer ones, whatever the file order
+        upsert(&mut concepts, 73211009, concept(20200131, false), u32::MAX);
+        upsert(&mut concepts, 73211009, concept(20100131, true), u32::MAX);
+        assert_eq!(concepts[&73211009].effective_time, 20200131);
+
+        // Unpublished rows are newer than any date
+        upsert(&mut concepts, 73211009, concept(0, true), 20990101);
+        assert_eq!(concepts[&73211009].effective_time, 20200131);
+        upsert(&mut concepts, 73211009, concept(0, true), u32::MAX);
+        assert_eq!(concepts[&73211009].effective_time, 0);
+    }
+
+    #[test]
+    fn test_classify_release_files() {
+        let mut files = ReleaseFiles::default();
+        for name in [
+            "sct2_Concept_Snapshot_INT_20240101.txt",
+            "sct2_Concept_Full_INT_20240101.txt",
+            "sct2_Description_Snapshot-en_INT_20240101.txt",
+            "sct2_TextDefinition_Snapshot-en_INT_20240101.txt",
+            "sct2_StatedRelationship_Snapshot_INT_20240101.txt",
+            "sct2_RelationshipConcreteValues_Snapshot_INT_20240101.txt",
+            "der2_cRefset_LanguageSnapshot-en_INT_20240101.txt",
+            "der2_cRefset_AssociationSnapshot_INT_20240101.txt",
+            "der2_iisssccRefset_ExtendedMapSnapshot_INT_20240101.txt",
+            "der2_sRefset_OWLExpressionSnapshot_INT_20240101.txt",
+            "Readme_en_20240101.txt",
+        ] {
+            files.classify(Path::new(name), name, "Snapshot");
+        }
+
+        assert_eq!(files.concepts.len(), 1);
+        assert_eq!(files.descriptions.len(), 2);
+        assert!(files.relationships.is_empty());
+        assert_eq!(files.concrete_relationships.len(), 1);
+        let refsets: Vec<_> = files
+            .refsets
+            .iter()
+            .map(|file| (file.pattern.as_str(), file.kind))
+            .collect();
+        assert_eq!(
+            refsets,
+            vec![
+                ("c", RefsetKind::Language),
+                ("c", RefsetKind::Association),
+                ("iissscc", RefsetKind::Other),
+            ]
+        );
+    }
+}
@@ -0,0 +1,1030 @@
+//! SQLite-backed SNOMED CT store.
+//!
+//! [`SqliteStore`] keeps a release in an embedded SQLite database instead of
+//! in memory. [`SqliteStore::import`] reads RF2 files with an [`Rf2Loader`]
+//! and writes the same components an [`Rf2Store`](crate::Rf2Store) would
+//! hold; [`SqliteStore::open`] then queries the file in place with cached
+//! prepared statements.
+//!
+//! # Schema
+//!
+//! ```sql
+//! -- Key/value pairs; `schema_version` identifies the layout below
+//! CREATE TABLE metadata (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+//!
+//! -- Every concept, active or not
+//! CREATE TABLE concept (
+//!     id INTEGER PRIMARY KEY,
+//!     effective_time INTEGER NOT NULL,   -- YYYYMMDD, 0 if unpublished
+//!     active INTEGER NOT NULL,
+//!     module_id INTEGER NOT NULL,
+//!     primitive INTEGER NOT NULL
+//! );
+//!
+//! -- Active inferred relationships, IS_A (116680003) included
+//! CREATE TABLE relationship (
+//!     source_id INTEGER NOT NULL,
+//!     destination_id INTEGER NOT NULL,
+//!     type_id INTEGER NOT NULL,
+//!     relationship_group INTEGER NOT NULL
+//! );
+//!
+//! -- Active concrete relationships; `value` is an INTEGER, REAL or TEXT
+//! CREATE TABLE concrete_relationship (
+//!     source_id INTEGER NOT NULL,
+//!     type_id INTEGER NOT NULL,
+//!     relationship_group INTEGER NOT NULL,
+//!     value NOT NULL
+//! );
+//!
+//! -- Descriptions and text definitions, active or not
+//! CREATE TABLE description (
+//!     id INTEGER PRIMARY KEY,
+//!     effective_time INTEGER NOT NULL,
+//!     active INTEGER NOT NULL,
+//!     module_id INTEGER NOT NULL,
+//!     concept_id INTEGER NOT NULL,
+//!     language_code TEXT NOT NULL,
+//!     type_id INTEGER NOT NULL,
+//!     term TEXT NOT NULL,
+//!     case_significance_id INTEGER NOT NULL
+//! );
+//!
+//! -- Active language reference set members
+//! CREATE TABLE language_member (
+//!     id TEXT PRIMARY KEY,               -- member UUID
+//!     effective_time INTEGER NOT NULL,
+//!     module_id INTEGER NOT NULL,
+//!     refset_id INTEGER NOT NULL,
+//!     description_id INTEGER NOT NULL,
+//!     acceptability_id INTEGER NOT NULL
+//! );
+//!
+//! -- Reference sets; `columns` holds the additional column names,
+//! -- tab-separated, and `kind` is 'language', 'association' or 'other'
+//! CREATE TABLE refset (id INTEGER PRIMARY KEY, kind TEXT NOT NULL, columns TEXT NOT NULL);
+//!
+//! -- Members of other reference sets, active or not
+//! CREATE TABLE refset_member (
+//!     id TEXT PRIMARY KEY,               -- member UUID
+//!     effective_time INTEGER NOT NULL,
+//!     active INTEGER NOT NULL,
+//!     module_id INTEGER NOT NULL,
+//!     refset_id INTEGER NOT NULL,
+//!     referenced_component_id INTEGER NOT NULL
+//! );
+//!
+//! -- Additional member fields; `kind` is 'c' (component), 'i' or 's'
+//! CREATE TABLE refset_member_field (
+//!     member_id TEXT NOT NULL,
+//!     position INTEGER NOT NULL,
+//!     kind TEXT NOT NULL,
+//!     value NOT NULL,
+//!     PRIMARY KEY (member_id, position)
+//! );
+//!
+//! -- Active historical associations: `source_id` is the inactive concept
+//! CREATE TABLE association (
+//!     target_id INTEGER NOT NULL,
+//!     refset_id INTEGER NOT NULL,
+//!     source_id INTEGER NOT NULL
+//! );
+//!
+//! -- Active alternate identifiers
+//! CREATE TABLE identifier (
+//!     scheme_id INTEGER NOT NULL,
+//!     alternate_identifier TEXT NOT NULL,
+//!     referenced_component_id INTEGER NOT NULL
+//! );
+//! ```
+//!
+//! Indexes on relationship source and destination, description concept,
+//! language member description and reference set, member reference set,
+//! association target and alternate identifier are created after the
+//! import.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection, OpenFlags, Params, Row};
+use snomed_ecl::SctId;
+use snomed_ecl_executor::{
+    Acceptability, ConceptInfo, ConcreteRelationshipInfo, ConcreteValueRef, DescriptionInfo,
+    EclQueryable, HistoryAssociationType, LanguageRefsetMember, RefsetFieldValue, RefsetMember,
+    RelationshipInfo,
+};
+
+use crate::error::{StoreError, StoreResult};
+use crate::loader::Rf2Loader;
+use crate::rf2::{format_uuid, ConcreteValue, FieldValue, RefsetKind, Rf2Rows};
+use crate::store::{DEFAULT_DIALECTS, FSN, PREFERRED, SYNONYM};
+
+/// Current database schema version.
+const SCHEMA_VERSION: &str = "1";
+
+const SCHEMA: &str = "
+CREATE TABLE metadata (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+CREATE TABLE concept (
+    id INTEGER PRIMARY KEY,
+    effective_time INTEGER NOT NULL,
+    active INTEGER NOT NULL,
+    module_id INTEGER NOT NULL,
+    primitive INTEGER NOT NULL
+);
+CREATE TABLE relationship (
+    source_id INTEGER NOT NULL,
+    destination_id INTEGER NOT NULL,
+    type_id INTEGER NOT NULL,
+    relationship_group INTEGER NOT NULL
+);
+CREATE TABLE concrete_relationship (
+    source_id INTEGER NOT NULL,
+    type_id INTEGER NOT NULL,
+    relationship_group INTEGER NOT NULL,
+    value NOT NULL
+);
+CREATE TABLE description (
+    id INTEGER PRIMARY KEY,
+    effective_time INTEGER NOT NULL,
+    active INTEGER NOT NULL,
+    module_id INTEGER NOT NULL,
+    concept_id INTEGER NOT NULL,
+    language_code TEXT NOT NULL,
+    type_id INTEGER NOT NULL,
+    term TEXT NOT NULL,
+    case_significance_id INTEGER NOT NULL
+);
+CREATE TABLE language_member (
+    id TEXT PRIMARY KEY,
+    effective_time INTEGER NOT NULL,
+    module_id INTEGER NOT NULL,
+    refset_id INTEGER NOT NULL,
+    description_id INTEGER NOT NULL,
+    acceptability_id INTEGER NOT NULL
+);
+CREATE TABLE refset (id INTEGER PRIMARY KEY, kind TEXT NOT NULL, columns TEXT NOT NULL);
+CREATE TABLE refset_member (
+    id TEXT PRIMARY KEY,
+    effective_time INTEGER NOT NULL,
+    active INTEGER NOT NULL,
+    module_id INTEGER NOT NULL,
+    refset_id INTEGER NOT NULL,
+    referenced_component_id INTEGER NOT NULL
+);
+CREATE TABLE refset_member_field (
+    member_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    value NOT NULL,
+    PRIMARY KEY (member_id, position)
+);
+CREATE TABLE association (
+    target_id INTEGER NOT NULL,
+    refset_id INTEGER NOT NULL,
+    source_id INTEGER NOT NULL
+);
+CREATE TABLE identifier (
+    scheme_id INTEGER NOT NULL,
+    alternate_identifier TEXT NOT NULL,
+    referenced_component_id INTEGER NOT NULL
+);
+";
+
+const INDEXES: &str = "
+CREATE INDEX relationship_source ON relationship (source_id, type_id);
+CREATE INDEX relationship_destination ON relationship (destination_id, type_id);
+CREATE INDEX concrete_relationship_source ON concrete_relationship (source_id);
+CREATE INDEX description_concept ON description (concept_id);
+CREATE INDEX language_member_description ON language_member (description_id);
+CREATE INDEX language_member_refset ON language_member (refset_id);
+CREATE INDEX refset_member_refset ON refset_member (refset_id, referenced_component_id);
+CREATE INDEX association_target ON association (target_id, refset_id);
+CREATE INDEX identifier_alternate ON identifier (alternate_identifier);
+";
+
+// Queries, prepared when the database is opened and cached
+
+const CONCEPT_COUNT: &str = "SELECT COUNT(*) FROM concept";
+const DESCRIPTION_COUNT: &str = "SELECT COUNT(*) FROM description";
+const RELATIONSHIP_COUNT: &str = "SELECT COUNT(*) FROM relationship";
+const HAS_CONCEPT: &str = "SELECT id FROM concept WHERE id = ?1";
+const ALL_CONCEPTS: &str = "SELECT id FROM concept ORDER BY id";
+const CONCEPT_INFO: &str =
+    "SELECT primitive, module_id, effective_time, active FROM concept WHERE id = ?1";
+const GET_CHILDREN: &str = "SELECT DISTINCT source_id FROM relationship
+    WHERE destination_id = ?1 AND type_id = 116680003 ORDER BY source_id";
+const GET_PARENTS: &str = "SELECT DISTINCT destination_id FROM relationship
+    WHERE source_id = ?1 AND type_id = 116680003 ORDER BY destination_id";
+const GET_ATTRIBUTES: &str = "SELECT type_id, destination_id, relationship_group FROM relationship
+    WHERE source_id = ?1 AND type_id <> 116680003
+    ORDER BY relationship_group, type_id, destination_id";
+const GET_INBOUND: &str = "SELECT type_id, source_id, relationship_group FROM relationship
+    WHERE destination_id = ?1 AND type_id <> 116680003
+    ORDER BY source_id, relationship_group, type_id";
+const CONCEPTS_WITH_ATTRIBUTE: &str = "SELECT DISTINCT source_id FROM relationship
+    WHERE destination_id = ?1 AND type_id = ?2 ORDER BY source_id";
+const GET_CONCRETE_VALUES: &str = "SELECT type_id, value, relationship_group
+    FROM concrete_relationship WHERE source_id = ?1 ORDER BY relationship_group, type_id, rowid";
+const GET_DESCRIPTIONS: &str = "SELECT id, term, language_code, type_id, case_significance_id,
+    active, effective_time, module_id FROM description WHERE concept_id = ?1 ORDER BY id";
+const PREFERRED_SYNONYM: &str = "SELECT d.term FROM description d
+    JOIN language_member l ON l.description_id = d.id
+    WHERE d.concept_id = ?1 AND d.active = 1 AND d.type_id = ?2
+    AND l.refset_id = ?3 AND l.acceptability_id = ?4 ORDER BY d.id LIMIT 1";
+const ACTIVE_TERM: &str = "SELECT term FROM description
+    WHERE concept_id = ?1 AND active = 1 AND type_id = ?2 ORDER BY id LIMIT 1";
+const LANGUAGE_REFSETS: &str = "SELECT refset_id, acceptability_id FROM language_member
+    WHERE description_id = ?1 ORDER BY refset_id, id";
+const GET_REFSET_MEMBERS: &str = "SELECT referenced_component_id FROM refset_member
+    WHERE refset_id = ?1 AND active = 1
+    UNION SELECT description_id FROM language_member WHERE refset_id = ?1 ORDER BY 1";
+const REFSET: &str = "SELECT kind, columns FROM refset WHERE id = ?1";
+const LANGUAGE_MEMBER_ROWS: &str = "SELECT id, effective_time, module_id, description_id,
+    acceptability_id FROM language_member WHERE refset_id = ?1 ORDER BY description_id, id";
+const MEMBER_ROWS: &str = "SELECT m.id, m.effective_time, m.active, m.module_id,
+    m.referenced_component_id, f.kind, f.value FROM refset_member m
+    LEFT JOIN refset_member_field f ON f.member_id = m.id
+    WHERE m.refset_id = ?1 ORDER BY m.referenced_component_id, m.id, f.position";
+const ASSOCIATIONS: &str = "SELECT DISTINCT source_id FROM association
+    WHERE target_id = ?1 ORDER BY source_id";
+const ASSOCIATIONS_BY_TYPE: &str = "SELECT DISTINCT source_id FROM association
+    WHERE target_id = ?1 AND refset_id = ?2 ORDER BY source_id";
+const ASSOCIATIONS_BY_REFSET: &str = "SELECT refset_id, source_id FROM association
+    WHERE target_id = ?1 ORDER BY refset_id, source_id";
+const IDENTIFIERS: &str = "SELECT scheme_id, referenced_component_id FROM identifier
+    WHERE alternate_identifier = ?1 ORDER BY rowid";
+const HAS_ACTIVE_TERM: &str = "SELECT id FROM description
+    WHERE concept_id = ?1 AND active = 1 AND term = ?2 COLLATE NOCASE";
+
+const QUERIES: [&str; 25] = [
+    CONCEPT_COUNT,
+    DESCRIPTION_COUNT,
+    RELATIONSHIP_COUNT,
+    HAS_CONCEPT,
+    ALL_CONCEPTS,
+    CONCEPT_INFO,
+    GET_CHILDREN,
+    GET_PARENTS,
+    GET_ATTRIBUTES,
+    GET_INBOUND,
+    CONCEPTS_WITH_ATTRIBUTE,
+    GET_CONCRETE_VALUES,
+    GET_DESCRIPTIONS,
+    PREFERRED_SYNONYM,
+    ACTIVE_TERM,
+    LANGUAGE_REFSETS,
+    GET_REFSET_MEMBERS,
+    REFSET,
+    LANGUAGE_MEMBER_ROWS,
+    MEMBER_ROWS,
+    ASSOCIATIONS,
+    ASSOCIATIONS_BY_TYPE,
+    ASSOCIATIONS_BY_REFSET,
+    IDENTIFIERS,
+    HAS_ACTIVE_TERM,
+];
+
+/// A SNOMED CT store kept in a SQLite database.
+///
+/// Create the database once with [`import`](Self::import), then
+/// [`open`](Self::open) it wherever the terminology is needed. Only the
+/// queries an ECL evaluation touches are read, so memory use stays small
+/// regardless of the release size. The results are the same as for an
+/// [`Rf2Store`](crate::Rf2Store) loaded from the same files.
+///
+/// The connection is shared behind a mutex, so queries from several threads
+/// run one at a time. [`open`](Self::open) prepares every query, so a
+/// database with missing tables or columns is rejected up front. A query
+/// that still fails at execution time (for example because the file was
+/// removed or is corrupt) returns an empty result, and the first such error
+/// is kept until [`take_error`](Self::take_error) is called; check it after
+/// evaluating an expression to tell a failed query from an empty one.
+///
+/// # Example
+///
+/// ```ignore
+/// use snomed_ecl_executor::EclExecutor;
+/// use snomed_ecl_store::{Rf2Loader, SqliteStore};
+///
+/// // Once
+/// SqliteStore::import(
+///     "snomed.db",
+///     &Rf2Loader::snapshot("SnomedCT_InternationalRF2_PRODUCTION_20240101T120000Z"),
+/// )?;
+///
+/// // On every start
+/// let store = SqliteStore::open("snomed.db")?;
+/// let result = EclExecutor::new(&store).execute("<< 73211009 |Diabetes mellitus|")?;
+/// if let Some(err) = store.take_error() {
+///     return Err(err.into());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+    /// The first error a query failed with since the last `take_error`.
+    error: Mutex<Option<rusqlite::Error>>,
+    /// Language reference sets used to pick preferred terms, in order.
+    dialects: Vec<SctId>,
+}
+
+impl SqliteStore {
+    /// Reads the releases described by `loader` and writes them to a new
+    /// database at `path`, returning the store opened on it.
+    ///
+    /// The database is created if needed and must not already hold a store.
+    /// Rows are filtered as for [`Rf2Loader::load`]: inactive relationships
+    /// and language members, and components of missing concepts, are left
+    /// out.
+    pub fn import(path: impl AsRef<Path>, loader: &Rf2Loader) -> StoreResult<Self> {
+        let path = path.as_ref();
+        let rows = loader.read_rows()?;
+
+        let mut connection = Connection::open(path)?;
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(SCHEMA)?;
+        write_rows(&transaction, rows)?;
+        transaction.execute_batch(INDEXES)?;
+        transaction.execute(
+            "INSERT INTO metadata (key, value) VALUES ('schema_version', ?1)",
+            [SCHEMA_VERSION],
+        )?;
+        transaction.commit()?;
+
+        Self::from_connection(path, connection)
+    }
+
+    /// Opens a database created by [`import`](Self::import), read-only.
+    ///
+    /// Fails with [`StoreError::InvalidDatabase`] if the file was not
+    /// created by the importer, has another schema version, or any of the
+    /// store's queries cannot be prepared against it.
+    pub fn open(path: impl AsRef<Path>) -> StoreResult<Self> {
+        let path = path.as_ref();
+        let connection = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        Self::from_connection(path, connection)
+    }
+
+    fn from_connection(path: &Path, connection: Connection) -> StoreResult<Self> {
+        let invalid = |message: String| StoreError::InvalidDatabase {
+            path: PathBuf::from(path),
+            message,
+        };
+        let version: String = connection
+            .query_row(
+                "SELECT value FROM metadata WHERE key = 'schema_version'",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| invalid(format!("No schema version: {}", e)))?;
+        if version != SCHEMA_VERSION {
+            return Err(invalid(format!(
+                "Unsupported schema version: {} (expected {})",
+                version, SCHEMA_VERSION
+            )));
+        }
+
+        connection.set_prepared_statement_cache_capacity(QUERIES.len());
+        for sql in QUERIES {
+            connection
+                .prepare_cached(sql)
+                .map_err(|e| invalid(format!("Cannot prepare query: {}", e)))?;
+        }
+        Ok(Self {
+            connection: Mutex::new(connection),
+            error: Mutex::new(None),
+            dialects: DEFAULT_DIALECTS.to_vec(),
+        })
+    }
+
+    /// Sets the language reference sets used to pick preferred terms, as
+    /// [`Rf2Store::with_dialects`](crate::Rf2Store::with_dialects) does.
+    pub fn with_dialects(mut self, dialects: impl IntoIterator<Item = SctId>) -> Self {
+        self.dialects = dialects.into_iter().collect();
+        self
+    }
+
+    /// Returns the number of concepts, active or not.
+    pub fn concept_count(&self) -> usize {
+        self.count(CONCEPT_COUNT)
+    }
+
+    /// Returns the number of descriptions, active or not.
+    pub fn description_count(&self) -> usize {
+        self.count(DESCRIPTION_COUNT)
+    }
+
+    /// Returns the number of active inferred relationships, including IS_A.
+    pub fn relationship_count(&self) -> usize {
+        self.count(RELATIONSHIP_COUNT)
+    }
+
+    /// Returns the first error a query failed with since the last call, and
+    /// clears it.
+    ///
+    /// [`EclQueryable`] methods cannot return errors, so a failing query
+    /// answers as if nothing matched. Returns `None` if every query since
+    /// the last call succeeded.
+    pub fn take_error(&self) -> Option<StoreError> {
+        self.error
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+            .map(StoreError::SqliteError)
+    }
+
+    // =========================================================================
+    // Queries
+    // =========================================================================
+
+    /// Runs a cached prepared statement and maps each row, treating a
+    /// failing query as an empty result and keeping the first error for
+    /// [`take_error`](Self::take_error).
+    fn query<T, P: Params>(
+        &self,
+        sql: &str,
+        params: P,
+        map: impl FnMut(&Row<'_>) -> rusqlite::Result<T>,
+    ) -> Vec<T> {
+        let connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let rows = connection
+            .prepare_cached(sql)
+            .and_then(|mut statement| statement.query_map(params, map)?.collect());
+        rows.unwrap_or_else(|e| {
+            self.error
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get_or_insert(e);
+            Vec::new()
+        })
+    }
+
+    fn ids<P: Params>(&self, sql: &str, params: P) -> Vec<SctId> {
+        self.query(sql, params, |row| row.get(0))
+    }
+
+    fn count(&self, sql: &str) -> usize {
+        self.query(sql, [], |row| row.get::<_, i64>(0))
+            .first()
+            .map_or(0, |&count| count as usize)
+    }
+
+    fn active_term(&self, concept_id: SctId, type_id: SctId) -> Option<String> {
+        self.query(ACTIVE_TERM, params![concept_id, type_id], |row| row.get(0))
+            .pop()
+    }
+}
+
+/// Writes rows to the tables of a new database, applying the same filters
+/// as [`Rf2Store::from_rows`](crate::Rf2Store).
+fn write_rows(connection: &Connection, rows: Rf2Rows) -> rusqlite::Result<()> {
+    let Rf2Rows {
+        concepts,
+        descriptions,
+        relationships,
+        concrete_relationships,
+        identifiers,
+        members,
+        refsets,
+    } = rows;
+
+    let mut statement = connection.prepare(
+        "INSERT INTO concept (id, effective_time, active, module_id, primitive)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for concept in concepts.values() {
+        statement.execute(params![
+            concept.id,
+            concept.effective_time,
+            concept.active,
+            concept.module_id,
+            concept.primitive
+        ])?;
+    }
+
+    let mut relationship_rows: Vec<_> = relationships
+        .into_values()
+        .filter(|row| row.active)
+        .filter(|row| {
+            concepts.contains_key(&row.source_id) && concepts.contains_key(&row.destination_id)
+        })
+        .collect();
+    relationship_rows.sort_unstable_by_key(|row| row.id);
+    let mut statement = connection.prepare(
+        "INSERT INTO relationship (source_id, destination_id, type_id, relationship_group)
+         VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for row in relationship_rows {
+        statement.execute(params![
+            row.source_id,
+            row.destination_id,
+            row.type_id,
+            row.group
+        ])?;
+    }
+
+    let mut concrete_rows: Vec<_> = concrete_relationships
+        .into_values()
+        .filter(|row| row.active && concepts.contains_key(&row.source_id))
+        .collect();
+    concrete_rows.sort_unstable_by_key(|row| row.id);
+    let mut statement = connection.prepare(
+        "INSERT INTO concrete_relationship (source_id, type_id, relationship_group, value)
+         VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for row in concrete_rows {
+        let value = match row.value {
+            ConcreteValue::Integer(value) => rusqlite::types::Value::Integer(value),
+            ConcreteValue::Decimal(value) => rusqlite::types::Value::Real(value),
+            ConcreteValue::String(value) => rusqlite::types::Value::Text(value.into()),
+        };
+        statement.execute(params![row.source_id, row.type_id, row.group, value])?;
+    }
+
+    let mut statement = connection.prepare(
+        "INSERT INTO description (id, effective_time, active, module_id, concept_id,
+         language_code, type_id, term, case_significance_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+    )?;
+    for row in descriptions.values() {
+        if !concepts.contains_key(&row.concept_id) {
+            continue;
+        }
+        statement.execute(params![
+            row.id,
+            row.effective_time,
+            row.active,
+            row.module_id,
+            row.concept_id,
+            row.language_code,
+            row.type_id,
+            row.term,
+            row.case_significance_id
+        ])?;
+    }
+
+    let mut statement =
+        connection.prepare("INSERT INTO refset (id, kind, columns) VALUES (?1, ?2, ?3)")?;
+    for (id, columns) in &refsets {
+        let kind = match columns.kind {
+            RefsetKind::Language => "language",
+            RefsetKind::Association => "association",
+            RefsetKind::Other => "other",
+        };
+        statement.execute(params![id, kind, columns.names.join("\t")])?;
+    }
+
+    let mut language_statement = connection.prepare(
+        "INSERT INTO language_member (id, effective_time, module_id, refset_id,
+         description_id, acceptability_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    let mut member_statement = connection.prepare(
+        "INSERT INTO refset_member (id, effective_time, active, module_id, refset_id,
+         referenced_component_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    let mut field_statement = connection.prepare(
+        "INSERT INTO refset_member_field (member_id, position, kind, value)
+         VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    let mut association_statement = connection
+        .prepare("INSERT INTO association (target_id, refset_id, source_id) VALUES (?1, ?2, ?3)")?;
+    let kind_of = |refset_id: SctId| {
+        refsets
+            .get(&refset_id)
+            .map_or(RefsetKind::Other, |columns| columns.kind)
+    };
+    for member in members.into_values() {
+        let id = format_uuid(member.id);
+        let kind = kind_of(member.refset_id);
+        if kind == RefsetKind::Language {
+            if let (true, Some(FieldValue::Component(acceptability_id))) =
+                (member.active, member.fields.first())
+            {
+                language_statement.execute(params![
+                    id,
+                    member.effective_time,
+                    member.module_id,
+                    member.refset_id,
+                    member.referenced_component_id,
+                    acceptability_id
+                ])?;
+            }
+            continue;
+        }
+
+        if let (RefsetKind::Association, true, Some(FieldValue::Component(target))) =
+            (kind, member.active, member.fields.first())
+        {
+            association_statement.execute(params![
+                target,
+                member.refset_id,
+                member.referenced_component_id
+            ])?;
+        }
+        member_statement.execute(params![
+            id,
+            member.effective_time,
+            member.active,
+            member.module_id,
+            member.refset_id,
+            member.referenced_component_id
+        ])?;
+        for (position, field) in member.fields.iter().enumerate() {
+            let (kind, value) = match field {
+                FieldValue::Component(id) => ("c", rusqlite::types::Value::Integer(*id as i64)),
+                FieldValue::Integer(value) => ("i", rusqlite::types::Value::Integer(*value)),
+                FieldValue::String(value) => ("s", rusqlite::types::Value::Text(value.to_string())),
+            };
+            field_statement.execute(params![id, position, kind, value])?;
+        }
+    }
+
+    let mut statement = connection.prepare(
+        "INSERT INTO identifier (scheme_id, alternate_identifier, referenced_component_id)
+         VALUES (?1, ?2, ?3)",
+    )?;
+    let mut identifier_rows: Vec<_> = identifiers.into_values().filter(|row| row.active).collect();
+    identifier_rows.sort_unstable_by(|a, b| {
+        (a.scheme_id, &a.alternate_identifier).cmp(&(b.scheme_id, &b.alternate_identifier))
+    });
+    for row in identifier_rows {
+        statement.execute(params![
+            row.scheme_id,
+            row.alternate_identifier,
+            row.referenced_component_id
+        ])?;
+    }
+
+    Ok(())
+}
+
+/// Converts a stored effective time, where 0 means unpublished.
+fn effective_time(value: u32) -> Option<u32> {
+    (value != 0).then_some(value)
+}
+
+impl EclQueryable for SqliteStore {
+    fn get_children(&self, concept_id: SctId) -> Vec<SctId> {
+        self.ids(GET_CHILDREN, [concept_id])
+    }
+
+    fn get_parents(&self, concept_id: SctId) -> Vec<SctId> {
+        self.ids(GET_PARENTS, [concept_id])
+    }
+
+    fn has_concept(&self, concept_id: SctId) -> bool {
+        !self.ids(HAS_CONCEPT, [concept_id]).is_empty()
+    }
+
+    fn all_concept_ids(&self) -> Box<dyn Iterator<Item = SctId> + '_> {
+        Box::new(self.ids(ALL_CONCEPTS, []).into_iter())
+    }
+
+    fn get_refset_members(&self, refset_id: SctId) -> Vec<SctId> {
+        self.ids(GET_REFSET_MEMBERS, [refset_id])
+    }
+
+    fn get_attributes(&self, concept_id: SctId) -> Vec<RelationshipInfo> {
+        self.query(GET_ATTRIBUTES, [concept_id], |row| {
+            Ok(RelationshipInfo {
+                type_id: row.get(0)?,
+                destination_id: row.get(1)?,
+                group: row.get(2)?,
+            })
+        })
+    }
+
+    fn get_concepts_with_attribute(
+        &self,
+        attribute_type_id: SctId,
+        target_id: SctId,
+    ) -> Vec<SctId> {
+        self.ids(CONCEPTS_WITH_ATTRIBUTE, [target_id, attribute_type_id])
+    }
+
+    fn supports_attribute_lookup(&self) -> bool {
+        true
+    }
+
+    fn get_concrete_values(&self, concept_id: SctId) -> Vec<ConcreteRelationshipInfo> {
+        self.query(GET_CONCRETE_VALUES, [concept_id], |row| {
+            let value = match row.get_ref(1)? {
+                ValueRef::Integer(value) => ConcreteValueRef::Integer(value),
+                ValueRef::Real(value) => ConcreteValueRef::Decimal(value),
+                _ => ConcreteValueRef::String(row.get(1)?),
+            };
+            Ok(ConcreteRelationshipInfo {
+                type_id: row.get(0)?,
+                value,
+                group: row.get(2)?,
+            })
+        })
+    }
+
+    fn get_descriptions(&self, concept_id: SctId) -> Vec<DescriptionInfo> {
+        self.query(GET_DESCRIPTIONS, [concept_id], |row| {
+            Ok(DescriptionInfo {
+                description_id: row.get(0)?,
+                term: row.get(1)?,
+                language_code: row.get(2)?,
+                type_id: row.get(3)?,
+                case_significance_id: row.get(4)?,
+                active: row.get(5)?,
+                effective_time: effective_time(row.get(6)?),
+                module_id: row.get(7)?,
+            })
+        })
+    }
+
+    /// Returns the active synonym preferred in the first configured dialect
+    /// that has one, falling back to the active fully specified name.
+    fn get_preferred_term(&self, concept_id: SctId) -> Option<String> {
+        self.dialects
+            .iter()
+            .find_map(|&dialect| {
+                self.query(
+                    PREFERRED_SYNONYM,
+                    params![concept_id, SYNONYM, dialect, PREFERRED],
+                    |row| row.get(0),
+                )
+                .pop()
+            })
+            .or_else(|| self.active_term(concept_id, FSN))
+    }
+
+    /// Returns the inactive concepts associated with the concept in any
+    /// historical association reference set.
+    fn get_historical_associations(&self, concept_id: SctId) -> Vec<SctId> {
+        self.ids(ASSOCIATIONS, [concept_id])
+    }
+
+    fn is_concept_active(&self, concept_id: SctId) -> bool {
+        self.get_concept_info(concept_id)
+            .is_some_and(|info| info.active)
+    }
+
+    fn get_concept_module(&self, concept_id: SctId) -> Option<SctId> {
+        self.get_concept_info(concept_id).map(|info| info.module_id)
+    }
+
+    fn get_concept_info(&self, concept_id: SctId) -> Option<ConceptInfo> {
+        self.query(CONCEPT_INFO, [concept_id], |row| {
+            Ok(ConceptInfo {
+                is_primitive: row.get(0)?,
+                module_id: row.get(1)?,
+                effective_time: effective_time(row.get(2)?),
+                active: row.get(3)?,
+            })
+        })
+        .pop()
+    }
+
+    fn get_description_language_refsets(&self, description_id: SctId) -> Vec<LanguageRefsetMember> {
+        self.query(LANGUAGE_REFSETS, [description_id], |row| {
+            Ok(LanguageRefsetMember {
+                refset_id: row.get(0)?,
+                acceptability: if row.get::<_, SctId>(1)? == PREFERRED {
+                    Acceptability::Preferred
+                } else {
+                    Acceptability::Acceptable
+                },
+            })
+        })
+    }
+
+    /// Returns every member row of the reference set, active or not, except
+    /// for language reference sets, whose inactive members are not imported.
+    fn get_refset_member_rows(&self, refset_id: SctId) -> Vec<RefsetMember> {
+        let Some((kind, columns)) = self
+            .query(REFSET, [refset_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .pop()
+        else {
+            return Vec::new();
+        };
+        let columns: Vec<&str> = columns.split('\t').filter(|c| !c.is_empty()).collect();
+
+        if kind == "language" {
+            let column = columns.first().copied().unwrap_or_default().to_string();
+            return self.query(LANGUAGE_MEMBER_ROWS, [refset_id], |row| {
+                Ok(RefsetMember {
+                    id: row.get(0)?,
+                    effective_time: effective_time(row.get(1)?),
+                    active: true,
+                    module_id: row.get(2)?,
+                    refset_id,
+                    referenced_component_id: row.get(3)?,
+                    fields: vec![(column.clone(), RefsetFieldValue::Component(row.get(4)?))],
+                })
+            });
+        }
+
+        let mut members: Vec<RefsetMember> = Vec::new();
+        let rows = self.query(MEMBER_ROWS, [refset_id], |row| {
+            let field = match row.get::<_, Option<String>>(5)?.as_deref() {
+                Some("c") => Some(RefsetFieldValue::Component(row.get(6)?)),
+                Some("i") => Some(RefsetFieldValue::Integer(row.get(6)?)),
+                Some(_) => Some(RefsetFieldValue::String(row.get(6)?)),
+                None => None,
+            };
+            let member = RefsetMember {
+                id: row.get(0)?,
+                effective_time: effective_time(row.get(1)?),
+                active: row.get(2)?,
+                module_id: row.get(3)?,
+                refset_id,
+                referenced_component_id: row.get(4)?,
+                fields: Vec::new(),
+            };
+            Ok((member, field))
+        });
+        for (member, field) in rows {
+            if members.last().is_none_or(|last| last.id != member.id) {
+                members.push(member);
+            }
+            let last = members.last_mut().expect("member was just pushed");
+            if let Some(value) = field {
+                if let Some(name) = columns.get(last.fields.len()) {
+                    last.fields.push((name.to_string(), value));
+                }
+            }
+        }
+        members
+    }
+
+    /// Extracts the semantic tag from the active fully specified name.
+    fn get_semantic_tag(&self, concept_id: SctId) -> Option<String> {
+        let term = self.active_term(concept_id, FSN)?;
+        let start = term.rfind('(')?;
+        let end = term.rfind(')')?;
+        (start < end).then(|| term[start + 1..end].to_string())
+    }
+
+    fn get_inbound_relationships(&self, concept_id: SctId) -> Vec<RelationshipInfo> {
+        self.query(GET_INBOUND, [concept_id], |row| {
+            Ok(RelationshipInfo {
+                type_id: row.get(0)?,
+                destination_id: row.get(1)?,
+                group: row.get(2)?,
+            })
+        })
+    }
+
+    fn get_historical_associations_by_type(
+        &self,
+        concept_id: SctId,
+        association_type: HistoryAssociationType,
+    ) -> Vec<SctId> {
+        self.ids(
+            ASSOCIATIONS_BY_TYPE,
+            [concept_id, association_type.refset_id()],
+        )
+    }
+
+    fn get_historical_associations_by_refset(
+        &self,
+        concept_id: SctId,
+    ) -> HashMap<SctId, Vec<SctId>> {
+        let mut by_refset: HashMap<SctId, Vec<SctId>> = HashMap::new();
+        let rows = self.query(ASSOCIATIONS_BY_REFSET, [concept_id], |row| {
+            Ok((row.get::<_, SctId>(0)?, row.get::<_, SctId>(1)?))
+        });
+        for (refset_id, source) in rows {
+            by_refset.entry(refset_id).or_default().push(source);
+        }
+        by_refset
+    }
+
+    fn resolve_alternate_identifier(&self, scheme: &str, identifier: &str) -> Option<SctId> {
+        if scheme == "http://snomed.info/id" || scheme == "http://snomed.info/sct" {
+            return identifier
+                .parse::<SctId>()
+                .ok()
+                .filter(|&id| self.has_concept(id));
+        }
+
+        let names_scheme = |scheme_id: SctId| {
+            scheme.parse::<SctId>().ok() == Some(scheme_id)
+                || !self
+                    .ids(HAS_ACTIVE_TERM, params![scheme_id, scheme])
+                    .is_empty()
+        };
+        self.query(IDENTIFIERS, [identifier], |row| {
+            Ok((row.get::<_, SctId>(0)?, row.get::<_, SctId>(1)?))
+        })
+        .into_iter()
+        .find(|&(scheme_id, _)| names_scheme(scheme_id))
+        .map(|(_, referenced_component_id)| referenced_component_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_open_checks_schema_version() {
+        let dir = tempdir().unwrap();
+
+        // A database without the store schema
+        let path = dir.path().join("other.db");
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch("CREATE TABLE other (id INTEGER)")
+            .unwrap();
+        let err = SqliteStore::open(&path).unwrap_err();
+        assert!(err.to_string().contains("No schema version"));
+
+        // Another schema version
+        let path = dir.path().join("old.db");
+        let connection = Connection::open(&path).unwrap();
+        connection.execute_batch(SCHEMA).unwrap();
+        connection
+            .execute(
+                "INSERT INTO metadata (key, value) VALUES ('schema_version', '0')",
+                [],
+            )
+            .unwrap();
+        let err = SqliteStore::open(&path).unwrap_err();
+        assert!(matches!(err, StoreError::InvalidDatabase { .. }));
+        assert!(err
+            .to_string()
+            .contains("Unsupported schema version: 0 (expected 1)"));
+    }
+
+    /// Creates an empty database with the store schema, returning a
+    /// writable connection to it.
+    fn create_empty(path: &Path) -> Connection {
+        let connection = Connection::open(path).unwrap();
+        connection.execute_batch(SCHEMA).unwrap();
+        connection
+            .execute(
+                "INSERT INTO metadata (key, value) VALUES ('schema_version', ?1)",
+                [SCHEMA_VERSION],
+            )
+            .unwrap();
+        connection
+    }
+
+    #[test]
+    fn test_open_prepares_every_query() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("partial.db");
+        create_empty(&path)
+            .execute_batch("DROP TABLE identifier")
+            .unwrap();
+
+        let err = SqliteStore::open(&path).unwrap_err();
+        assert!(matches!(err, StoreError::InvalidDatabase { .. }));
+        assert!(err.to_string().contains("Cannot prepare query"));
+        assert!(err.to_string().contains("no such table: identifier"));
+    }
+
+    #[test]
+    fn test_failed_queries_are_kept() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("snomed.db");
+        let connection = create_empty(&path);
+        let store = SqliteStore::open(&path).unwrap();
+        assert!(store.take_error().is_none());
+
+        // The tables go away after the store was opened
+        connection
+            .execute_batch("DROP TABLE concept; DROP TABLE relationship")
+            .unwrap();
+        assert!(!store.has_concept(73211009));
+        assert!(store.get_children(73211009).is_empty());
+
+        // Only the first error is kept, and taking it clears it
+        let err = store.take_error().unwrap();
+        assert!(matches!(err, StoreError::SqliteError(_)));
+        assert!(err.to_string().contains("no such table: concept"));
+        assert!(store.take_error().is_none());
+    }
+
+    #[test]
+    fn test_empty_results_for_unknown_ids() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("empty.db");
+        create_empty(&path);
+        let store = SqliteStore::open(&path).unwrap();
+
+        assert_eq!(store.concept_count(), 0);
+        assert!(!store.has_concept(73211009));
+        assert!(store.get_children(73211009).is_empty());
+        assert!(store.get_concept_info(73211009).is_none());
+        assert!(store.get_refset_member_rows(723264001).is_empty());
+        assert_eq!(store.get_preferred_term(73211009), None);
+        assert!(store.take_error().is_none());
+    }
+}
@@ -0,0 +1,829 @@
+//! In-memory SNOMED CT store loaded from RF2 files.
+//!
+//! [`Rf2Store`] keeps each component once, in vectors sorted by concept, and
+//! addresses the hierarchy and attribute indexes by 32-bit concept position
+//! instead of by SCTID. Lookups are binary searches plus slices of
+//! compressed adjacency lists, so the store needs no hash maps at query time.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use snomed_ecl::SctId;
+use snomed_ecl_executor::{
+    Acceptability, ConceptInfo, ConcreteRelationshipInfo, ConcreteValueRef, DescriptionInfo,
+    EclQueryable, HistoryAssociationType, LanguageRefsetMember, RefsetFieldValue, RefsetMember,
+    RelationshipInfo,
+};
+
+use crate::error::StoreResult;
+use crate::loader::Rf2Loader;
+use crate::rf2::{
+    format_uuid, ConceptRow, ConcreteValue, FieldValue, MemberRow, RefsetKind, Rf2Rows,
+};
+
+/// The IS_A relationship type.
+pub(crate) const IS_A: SctId = 116680003;
+
+/// Description type of a fully specified name.
+pub(crate) const FSN: SctId = 900000000000003001;
+
+/// Description type of a synonym.
+pub(crate) const SYNONYM: SctId = 900000000000013009;
+
+/// Acceptability of a preferred description.
+pub(crate) const PREFERRED: SctId = 900000000000548007;
+
+/// US English and GB English language reference sets.
+pub(crate) const DEFAULT_DIALECTS: [SctId; 2] = [900000000000509007, 900000000000508004];
+
+/// An active inferred relationship, with its ends as concept positions.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "binary", derive(bincode::Encode, bincode::Decode))]
+struct Relationship {
+    source: u32,
+    destination: u32,
+    type_id: SctId,
+    group: u16,
+}
+
+/// An active concrete relationship.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "binary", derive(bincode::Encode, bincode::Decode))]
+struct ConcreteRelationship {
+    source: u32,
+    type_id: SctId,
+    group: u16,
+    value: ConcreteValue,
+}
+
+/// A description, with its language code interned.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "binary", derive(bincode::Encode, bincode::Decode))]
+struct Description {
+    id: SctId,
+    effective_time: u32,
+    active: bool,
+    module_id: SctId,
+    concept: u32,
+    language: u16,
+    type_id: SctId,
+    case_significance_id: SctId,
+    term: Box<str>,
+}
+
+/// An active language reference set member.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "binary", derive(bincode::Encode, bincode::Decode))]
+struct LanguageMember {
+    id: u128,
+    effective_time: u32,
+    module_id: SctId,
+    refset_id: SctId,
+    description_id: SctId,
+    acceptability_id: SctId,
+}
+
+/// The additional columns of a reference set.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "binary", derive(bincode::Encode, bincode::Decode))]
+struct Refset {
+    id: SctId,
+    kind: RefsetKind,
+    columns: Arc<[String]>,
+}
+
+/// An active historical association, from an inactive concept to its target.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "binary", derive(bincode::Encode, bincode::Decode))]
+struct Association {
+    target: SctId,
+    refset_id: SctId,
+    source: SctId,
+}
+
+/// An active alternate identifier.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "binary", derive(bincode::Encode, bincode::Decode))]
+struct Identifier {
+    scheme_id: SctId,
+    alternate_identifier: Box<str>,
+    referenced_component_id: SctId,
+}
+
+/// A SNOMED CT store loaded from an RF2 release.
+///
+/// Implements every [`EclQueryable`] method, so any ECL query the executor
+/// supports can run against a release without writing a store.
+///
+/// # What is loaded
+///
+/// - Concepts, descriptions and text definitions, active or not
+/// - Active inferred relationships and concrete relationships (stated
+///   relationships are skipped)
+/// - Alternate identifiers
+/// - Every reference set except the OWL axiom reference sets. Language
+///   reference sets keep their active members only; historical association
+///   reference sets also feed the history supplement lookups.
+///
+/// # Example
+///
+/// ```ignore
+/// use snomed_ecl_executor::EclExecutor;
+/// use snomed_ecl_store::Rf2Store;
+///
+/// let store = Rf2Store::load_snapshot("SnomedCT_InternationalRF2_PRODUCTION_20240101T120000Z/Snapshot")?;
+/// let executor = EclExecutor::new(&store);
+/// let result = executor.execute("<< 73211009 |Diabetes mellitus|")?;
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "binary", derive(bincode::Encode, bincode::Decode))]
+pub struct Rf2Store {
+    /// Concepts sorted by ID; a concept's position is its index.
+    concepts: Vec<ConceptRow>,
+    /// Relationships grouped by source position.
+    relationships: Vec<Relationship>,
+    relationship_offsets: Vec<u32>,
+    /// Relationship indexes grouped by destination position.
+    inbound: Vec<u32>,
+    inbound_offsets: Vec<u32>,
+    /// Child positions grouped by parent position.
+    children: Vec<u32>,
+    children_offsets: Vec<u32>,
+    /// Concrete relationships grouped by source position.
+    concrete: Vec<ConcreteRelationship>,
+    concrete_offsets: Vec<u32>,
+    /// Descriptions grouped by concept position.
+    descriptions: Vec<Description>,
+    description_offsets: Vec<u32>,
+    language_codes: Vec<Box<str>>,
+    /// Language reference set members sorted by description ID.
+    language_members: Vec<LanguageMember>,
+    /// Indexes into `language_members` sorted by reference set.
+    language_members_by_refset: Vec<u32>,
+    /// Other reference set members sorted by reference set and referenced component.
+    members: Vec<MemberRow>,
+    /// Reference sets sorted by ID.
+    refsets: Vec<Refset>,
+    /// Historical associations sorted by target.
+    associations: Vec<Association>,
+    /// Alternate identifiers sorted by scheme and identifier.
+    identifiers: Vec<Identifier>,
+    /// Language reference sets used to pick preferred terms, in order.
+    dialects: Vec<SctId>,
+}
+
+impl Rf2Store {
+    /// Loads the RF2 Snapshot files found anywhere under `dir`.
+    ///
+    /// `dir` may be a release root or its `Snapshot` directory. Fails with
+    /// [`StoreError::MissingFile`](crate::StoreError::MissingFile) if no
+    /// concept file is found. Use [`Rf2Loader`] to load Full or Delta files.
+    pub fn load_snapshot(dir: impl AsRef<Path>) -> StoreResult<Self> {
+        Rf2Loader::snapshot(dir).load()
+    }
+
+    /// Sets the language reference sets used to pick preferred terms, in
+    /// order of preference.
+    ///
+    /// Defaults to US English (900000000000509007) then GB English
+    /// (900000000000508004).
+    pub fn with_dialects(mut self, dialects: impl IntoIterator<Item = SctId>) -> Self {
+        self.dialects = dialects.into_iter().collect();
+        self
+    }
+
+    /// Returns the number of concepts, active or not.
+    pub fn concept_count(&self) -> usize {
+        self.concepts.len()
+    }
+
+    /// Returns the number of descriptions, active or not.
+    pub fn description_count(&self) -> usize {
+        self.descriptions.len()
+    }
+
+    /// Returns the number of active inferred relationships, including IS_A.
+    pub fn relationship_count(&self) -> usize {
+        self.relationships.len()
+    }
+
+    /// Compacts rows into a store, dropping inactive relationships, inactive
+    /// language members and any component whose concept is missing.
+    pub(crate) fn from_rows(rows: Rf2Rows) -> Self {
+        let Rf2Rows {
+            concepts,
+            descriptions,
+            relationships,
+            concrete_relationships,
+            identifiers,
+            members,
+            refsets,
+        } = rows;
+
+        let mut concepts: Vec<ConceptRow> = concepts.into_values().collect();
+        concepts.sort_unstable_by_key(|concept| concept.id);
+        let position = |id: SctId| {
+            concepts
+                .binary_search_by_key(&id, |concept| concept.id)
+                .ok()
+                .map(|index| index as u32)
+        };
+
+        // Relationships and the hierarchy
+        let mut relationships: Vec<Relationship> = relationships
+            .into_values()
+            .filter(|row| row.active)
+            .filter_map(|row| {
+                Some(Relationship {
+                    source: position(row.source_id)?,
+                    destination: position(row.destination_id)?,
+                    type_id: row.type_id,
+                    group: row.group,
+                })
+            })
+            .collect();
+        relationships.sort_unstable_by_key(|r| (r.source, r.group, r.type_id, r.destination));
+        let relationship_offsets = offsets(concepts.len(), relationships.iter().map(|r| r.source));
+
+        let mut inbound: Vec<u32> = (0..relationships.len() as u32).collect();
+        inbound.sort_by_key(|&index| relationships[index as usize].destination);
+        let inbound_offsets = offsets(
+            concepts.len(),
+            inbound
+                .iter()
+                .map(|&index| relationships[index as usize].destination),
+        );
+
+        let mut is_a: Vec<(u32, u32)> = relationships
+            .iter()
+            .filter(|r| r.type_id == IS_A)
+            .map(|r| (r.destination, r.source))
+            .collect();
+        is_a.sort_unstable();
+        is_a.dedup();
+        let children_offsets = offsets(concepts.len(), is_a.iter().map(|&(parent, _)| parent));
+        let children = is_a.into_iter().map(|(_, child)| child).collect();
+
+        // Concrete relationships
+        let mut concrete_rows: Vec<_> = concrete_relationships.into_values().collect();
+        concrete_rows.sort_unstable_by_key(|row| row.id);
+        let mut concrete: Vec<ConcreteRelationship> = concrete_rows
+            .into_iter()
+            .filter(|row| row.active)
+            .filter_map(|row| {
+                Some(ConcreteRelationship {
+                    source: position(row.source_id)?,
+                    type_id: row.type_id,
+                    group: row.group,
+                    value: row.value,
+                })
+            })
+            .collect();
+        concrete.sort_by_key(|r| (r.source, r.group, r.type_id));
+        let concrete_offsets = offsets(concepts.len(), concrete.iter().map(|r| r.source));
+
+        // Descriptions
+        let mut language_codes: Vec<Box<str>> = Vec::new();
+        let mut descriptions: Vec<Description> = descriptions
+            .into_values()
+            .filter_map(|row| {
+                let language = match language_codes.iter().position(|c| **c == row.language_code) {
+                    Some(language) => language,
+                    None => {
+                        language_codes.push(row.language_code.into_boxed_str());
+                        language_codes.len() - 1
+                    }
+                };
+                Some(Description {
+                    id: row.id,
+                    effective_time: row.effective_time,
+                    active: row.active,
+                    module_id: row.module_id,
+                    concept: position(row.concept_id)?,
+                    language: language as u16,
+                    type_id: row.type_id,
+                    case_significance_id: row.case_significance_id,
+                    term: row.term.into_boxed_str(),
+                })
+            })
+            .collect();
+        descriptions.sort_unstable_by_key(|d| (d.concept, d.id));
+        let description_offsets = offsets(concepts.len(), descriptions.iter().map(|d| d.concept));
+
+        // Reference sets
+        let mut refsets: Vec<Refset> = refsets
+            .into_iter()
+            .map(|(id, columns)| Refset {
+                id,
+                kind: columns.kind,
+                columns: columns.names,
+            })
+            .collect();
+        refsets.sort_unstable_by_key(|refset| refset.id);
+        let kind_of = |refset_id: SctId| {
+            refsets
+                .binary_search_by_key(&refset_id, |refset| refset.id)
+                .map_or(RefsetKind::Other, |index| refsets[index].kind)
+        };
+
+        let mut language_members = Vec::new();
+        let mut associations = Vec::new();
+        let mut other_members = Vec::new();
+        for member in members.into_values() {
+            match kind_of(member.refset_id) {
+                RefsetKind::Language => {
+                    if let (true, Some(FieldValue::Component(acceptability_id))) =
+                        (member.active, member.fields.first())
+                    {
+                        language_members.push(LanguageMember {
+                            id: member.id,
+                            effective_time: member.effective_time,
+                            module_id: member.module_id,
+                            refset_id: member.refset_id,
+                            description_id: member.referenced_component_id,
+                            acceptability_id: *acceptability_id,
+                        });
+                    }
+                }
+                kind => {
+                    if let (RefsetKind::Association, true, Some(FieldValue::Component(target))) =
+                        (kind, member.active, member.fields.first())
+                    {
+                        associations.push(Association {
+                            target: *target,
+                            refset_id: member.refset_id,
+                            source: member.referenced_component_id,
+                        });
+                    }
+                    other_members.push(member);
+                }
+            }
+        }
+        language_members.sort_unstable_by_key(|m| (m.description_id, m.refset_id, m.id));
+        let mut language_members_by_refset: Vec<u32> = (0..language_members.len() as u32).collect();
+        // Stable, so each reference set keeps its members in description order
+        language_members_by_refset.sort_by_key(|&index| language_members[index as usize].refset_id);
+        associations.sort_unstable_by_key(|a| (a.target, a.refset_id, a.source));
+        other_members.sort_unstable_by_key(|m| (m.refset_id, m.referenced_component_id, m.id));
+
+        // Alternate identifiers
+        let mut identifiers: Vec<Identifier> = identifiers
+            .into_values()
+            .filter(|row| row.active)
+            .map(|row| Identifier {
+                scheme_id: row.scheme_id,
+                alternate_identifier: row.alternate_identifier.into_boxed_str(),
+                referenced_component_id: row.referenced_component_id,
+            })
+            .collect();
+        identifiers.sort_unstable_by(|a, b| {
+            (a.scheme_id, &a.alternate_identifier).cmp(&(b.scheme_id, &b.alternate_identifier))
+        });
+
+        Self {
+            concepts,
+            relationships,
+            relationship_offsets,
+            inbound,
+            inbound_offsets,
+            children,
+            children_offsets,
+            concrete,
+            concrete_offsets,
+            descriptions,
+            description_offsets,
+            language_codes,
+            language_members,
+            language_members_by_refset,
+            members: other_members,
+            refsets,
+            associations,
+            identifiers,
+            dialects: DEFAULT_DIALECTS.to_vec(),
+        }
+    }
+
+    // =========================================================================
+    // Lookups
+    // =========================================================================
+
+    fn position(&self, concept_id: SctId) -> Option<u32> {
+        self.concepts
+            .binary_search_by_key(&concept_id, |concept| concept.id)
+            .ok()
+            .map(|index| index as u32)
+    }
+
+    fn concept(&self, concept_id: SctId) -> Option<&ConceptRow> {
+        self.position(concept_id)
+            .map(|position| &self.concepts[position as usize])
+    }
+
+    fn outbound_of(&self, concept_id: SctId) -> &[Relationship] {
+        self.position(concept_id).map_or(&[], |position| {
+            &self.relationships[range(&self.relationship_offsets, position)]
+        })
+    }
+
+    fn inbound_of(&self, concept_id: SctId) -> impl Iterator<Item = &Relationship> {
+        let indexes = self.position(concept_id).map_or(&[][..], |position| {
+            &self.inbound[range(&self.inbound_offsets, position)]
+        });
+        indexes
+            .iter()
+            .map(|&index| &self.relationships[index as usize])
+    }
+
+    fn descriptions_of(&self, concept_id: SctId) -> &[Description] {
+        self.position(concept_id).map_or(&[], |position| {
+            &self.descriptions[range(&self.description_offsets, position)]
+        })
+    }
+
+    fn language_members_of(&self, description_id: SctId) -> &[LanguageMember] {
+        let start = self
+            .language_members
+            .partition_point(|m| m.description_id < description_id);
+        let end = self
+            .language_members
+            .partition_point(|m| m.description_id <= description_id);
+        &self.language_members[start..end]
+    }
+
+    fn language_members_in(&self, refset_id: SctId) -> impl Iterator<Item = &LanguageMember> {
+        let refset_of = |&index: &u32| self.language_members[index as usize].refset_id;
+        let start = self
+            .language_members_by_refset
+            .partition_point(|index| refset_of(index) < refset_id);
+        let end = self
+            .language_members_by_refset
+            .partition_point(|index| refset_of(index) <= refset_id);
+        self.language_members_by_refset[start..end]
+            .iter()
+            .map(|&index| &self.language_members[index as usize])
+    }
+
+    fn members_of(&self, refset_id: SctId) -> &[MemberRow] {
+        let start = self.members.partition_point(|m| m.refset_id < refset_id);
+        let end = self.members.partition_point(|m| m.refset_id <= refset_id);
+        &self.members[start..end]
+    }
+
+    fn refset(&self, refset_id: SctId) -> Option<&Refset> {
+        self.refsets
+            .binary_search_by_key(&refset_id, |refset| refset.id)
+            .ok()
+            .map(|index| &self.refsets[index])
+    }
+
+    fn associations_of(&self, concept_id: SctId) -> &[Association] {
+        let start = self.associations.partition_point(|a| a.target < concept_id);
+        let end = self
+            .associations
+            .partition_point(|a| a.target <= concept_id);
+        &self.associations[start..end]
+    }
+
+    fn concept_id(&self, position: u32) -> SctId {
+        self.concepts[position as usize].id
+    }
+
+    fn is_preferred_in(&self, description: &Description, dialect: SctId) -> bool {
+        self.language_members_of(description.id)
+            .iter()
+            .any(|m| m.refset_id == dialect && m.acceptability_id == PREFERRED)
+    }
+
+    fn active_fsn(&self, concept_id: SctId) -> Option<&Description> {
+        self.descriptions_of(concept_id)
+            .iter()
+            .find(|d| d.active && d.type_id == FSN)
+    }
+}
+
+/// Computes the start offset of each key's run in a list grouped by key.
+///
+/// The result has `len + 1` entries, so the run of key `k` is
+/// `offsets[k]..offsets[k + 1]`.
+fn offsets(len: usize, keys: impl Iterator<Item = u32>) -> Vec<u32> {
+    let mut offsets = vec![0u32; len + 1];
+    for key in keys {
+        offsets[key as usize + 1] += 1;
+    }
+    for index in 1..offsets.len() {
+        offsets[index] += offsets[index - 1];
+    }
+    offsets
+}
+
+fn range(offsets: &[u32], position: u32) -> Range<usize> {
+    offsets[position as usize] as usize..offsets[position as usize + 1] as usize
+}
+
+fn sorted_unique(mut ids: Vec<SctId>) -> Vec<SctId> {
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+impl EclQueryable for Rf2Store {
+    fn get_children(&self, concept_id: SctId) -> Vec<SctId> {
+        self.position(concept_id).map_or_else(Vec::new, |position| {
+            self.children[range(&self.children_offsets, position)]
+                .iter()
+                .map(|&child| self.concept_id(child))
+                .collect()
+        })
+    }
+
+    fn get_parents(&self, concept_id: SctId) -> Vec<SctId> {
+        sorted_unique(
+            self.outbound_of(concept_id)
+                .iter()
+                .filter(|r| r.type_id == IS_A)
+                .map(|r| self.concept_id(r.destination))
+                .collect(),
+        )
+    }
+
+    fn has_concept(&self, concept_id: SctId) -> bool {
+        self.position(concept_id).is_some()
+    }
+
+    fn all_concept_ids(&self) -> Box<dyn Iterator<Item = SctId> + '_> {
+        Box::new(self.concepts.iter().map(|concept| concept.id))
+    }
+
+    fn get_refset_members(&self, refset_id: SctId) -> Vec<SctId> {
+        match self.refset(refset_id).map(|refset| refset.kind) {
+            Some(RefsetKind::Language) => sorted_unique(
+                self.language_members_in(refset_id)
+                    .map(|m| m.description_id)
+                    .collect(),
+            ),
+            Some(_) => sorted_unique(
+                self.members_of(refset_id)
+                    .iter()
+                    .filter(|m| m.active)
+                    .map(|m| m.referenced_component_id)
+                    .collect(),
+            ),
+            None => Vec::new(),
+        }
+    }
+
+    fn get_attributes(&self, concept_id: SctId) -> Vec<RelationshipInfo> {
+        self.outbound_of(concept_id)
+            .iter()
+            .filter(|r| r.type_id != IS_A)
+            .map(|r| RelationshipInfo {
+                type_id: r.type_id,
+                destination_id: self.concept_id(r.destination),
+                group: r.group,
+            })
+            .collect()
+    }
+
+    fn get_concepts_with_attribute(
+        &self,
+        attribute_type_id: SctId,
+        target_id: SctId,
+    ) -> Vec<SctId> {
+        sorted_unique(
+            self.inbound_of(target_id)
+                .filter(|r| r.type_id == attribute_type_id)
+                .map(|r| self.concept_id(r.source))
+                .collect(),
+        )
+    }
+
+    fn supports_attribute_lookup(&self) -> bool {
+        true
+    }
+
+    fn get_concrete_values(&self, concept_id: SctId) -> Vec<ConcreteRelationshipInfo> {
+        self.position(concept_id).map_or_else(Vec::new, |position| {
+            self.concrete[range(&self.concrete_offsets, position)]
+                .iter()
+                .map(|r| ConcreteRelationshipInfo {
+                    type_id: r.type_id,
+                    value: match &r.value {
+                        ConcreteValue::Integer(value) => ConcreteValueRef::Integer(*value),
+                        ConcreteValue::Decimal(value) => ConcreteValueRef::Decimal(*value),
+                        ConcreteValue::String(value) => ConcreteValueRef::String(value.to_string()),
+                    },
+                    group: r.group,
+                })
+                .collect()
+        })
+    }
+
+    fn get_descriptions(&self, concept_id: SctId) -> Vec<DescriptionInfo> {
+        self.descriptions_of(concept_id)
+            .iter()
+            .map(|d| DescriptionInfo {
+                description_id: d.id,
+                term: d.term.to_string(),
+                language_code: self.language_codes[d.language as usize].to_string(),
+                type_id: d.type_id,
+                case_significance_id: d.case_significance_id,
+                active: d.active,
+                effective_time: (d.effective_time != 0).then_some(d.effective_time),
+                module_id: d.module_id,
+            })
+            .collect()
+    }
+
+    /// Returns the active synonym preferred in the first configured dialect
+    /// that has one, falling back to the active fully specified name.
+    fn get_preferred_term(&self, concept_id: SctId) -> Option<String> {
+        let descriptions = self.descriptions_of(concept_id);
+        self.dialects
+            .iter()
+            .find_map(|&dialect| {
+                descriptions
+                    .iter()
+                    .find(|d| d.active && d.type_id == SYNONYM && self.is_preferred_in(d, dialect))
+            })
+            .or_else(|| self.active_fsn(concept_id))
+            .map(|d| d.term.to_string())
+    }
+
+    /// Returns the inactive concepts associated with the concept in any
+    /// historical association reference set.
+    fn get_historical_associations(&self, concept_id: SctId) -> Vec<SctId> {
+        sorted_unique(
+            self.associations_of(concept_id)
+                .iter()
+                .map(|a| a.source)
+                .collect(),
+        )
+    }
+
+    fn is_concept_active(&self, concept_id: SctId) -> bool {
+        self.concept(concept_id)
+            .is_some_and(|concept| concept.active)
+    }
+
+    fn get_concept_module(&self, concept_id: SctId) -> Option<SctId> {
+        self.concept(concept_id).map(|concept| concept.module_id)
+    }
+
+    fn get_concept_info(&self, concept_id: SctId) -> Option<ConceptInfo> {
+        self.concept(concept_id).map(|concept| ConceptInfo {
+            is_primitive: concept.primitive,
+            module_id: concept.module_id,
+            effective_time: (concept.effective_time != 0).then_some(concept.effective_time),
+            active: concept.active,
+        })
+    }
+
+    fn get_description_language_refsets(&self, description_id: SctId) -> Vec<LanguageRefsetMember> {
+        self.language_members_of(description_id)
+            .iter()
+            .map(|m| LanguageRefsetMember {
+                refset_id: m.refset_id,
+                acceptability: if m.acceptability_id == PREFERRED {
+                    Acceptability::Preferred
+                } else {
+                    Acceptability::Acceptable
+                },
+            })
+            .collect()
+    }
+
+    /// Returns every member row of the reference set, active or not, except
+    /// for language reference sets, whose inactive members are not loaded.
+    fn get_refset_member_rows(&self, refset_id: SctId) -> Vec<RefsetMember> {
+        let Some(refset) = self.refset(refset_id) else {
+            return Vec::new();
+        };
+
+        if refset.kind == RefsetKind::Language {
+            let column = refset.columns.first().cloned().unwrap_or_default();
+            return self
+                .language_members_in(refset_id)
+                .map(|m| RefsetMember {
+                    id: format_uuid(m.id),
+                    effective_time: (m.effective_time != 0).then_some(m.effective_time),
+                    active: true,
+                    module_id: m.module_id,
+                    refset_id,
+                    referenced_component_id: m.description_id,
+                    fields: vec![(
+                        column.clone(),
+                        RefsetFieldValue::Component(m.acceptability_id),
+                    )],
+                })
+                .collect();
+        }
+
+        self.members_of(refset_id)
+            .iter()
+            .map(|m| RefsetMember {
+                id: format_uuid(m.id),
+                effective_time: (m.effective_time != 0).then_some(m.effective_time),
+                active: m.active,
+                module_id: m.module_id,
+                refset_id,
+                referenced_component_id: m.referenced_component_id,
+                fields: refset
+                    .columns
+                    .iter()
+                    .zip(m.fields.iter())
+                    .map(|(name, value)| {
+                        let value = match value {
+                            FieldValue::Component(id) => RefsetFieldValue::Component(*id),
+                            FieldValue::Integer(value) => RefsetFieldValue::Integer(*value),
+                            FieldValue::String(value) => {
+                                RefsetFieldValue::String(value.to_string())
+                            }
+                        };
+                        (name.clone(), value)
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Extracts the semantic tag from the active fully specified name.
+    fn get_semantic_tag(&self, concept_id: SctId) -> Option<String> {
+        let term = &self.active_fsn(concept_id)?.term;
+        let start = term.rfind('(')?;
+        let end = term.rfind(')')?;
+        (start < end).then(|| term[start + 1..end].to_string())
+    }
+
+    fn get_concept_effective_time(&self, concept_id: SctId) -> Option<u32> {
+        self.concept(concept_id)
+            .map(|concept| concept.effective_time)
+            .filter(|&time| time != 0)
+    }
+
+    fn is_concept_primitive(&self, concept_id: SctId) -> Option<bool> {
+        self.concept(concept_id).map(|concept| concept.primitive)
+    }
+
+    fn get_inbound_relationships(&self, concept_id: SctId) -> Vec<RelationshipInfo> {
+        self.inbound_of(concept_id)
+            .filter(|r| r.type_id != IS_A)
+            .map(|r| RelationshipInfo {
+                type_id: r.type_id,
+                destination_id: self.concept_id(r.source),
+                group: r.group,
+            })
+            .collect()
+    }
+
+    fn get_historical_associations_by_type(
+        &self,
+        concept_id: SctId,
+        association_type: HistoryAssociationType,
+    ) -> Vec<SctId> {
+        let refset_id = association_type.refset_id();
+        sorted_unique(
+            self.associations_of(concept_id)
+                .iter()
+                .filter(|a| a.refset_id == refset_id)
+                .map(|a| a.source)
+                .collect(),
+        )
+    }
+
+    fn get_historical_associations_by_refset(
+        &self,
+        concept_id: SctId,
+    ) -> HashMap<SctId, Vec<SctId>> {
+        let mut by_refset: HashMap<SctId, Vec<SctId>> = HashMap::new();
+        for association in self.associations_of(concept_id) {
+            by_refset
+                .entry(association.refset_id)
+                .or_default()
+                .push(association.source);
+        }
+        by_refset
+    }
+
+    fn resolve_alternate_identifier(&self, scheme: &str, identifier: &str) -> Option<SctId> {
+        if scheme == "http://snomed.info/id" || scheme == "http://snomed.info/sct" {
+            return identifier
+                .parse::<SctId>()
+                .ok()
+                .filter(|&id| self.has_concept(id));
+        }
+
+        let names_scheme = |scheme_id: SctId| {
+            scheme.parse::<SctId>().ok() == Some(scheme_id)
+                || self
+                    .descriptions_of(scheme_id)
+                    .iter()
+                    .any(|d| d.active && d.term.eq_ignore_ascii_case(scheme))
+        };
+        self.identifiers
+            .iter()
+            .find(|i| &*i.alternate_identifier == identifier && names_scheme(i.scheme_id))
+            .map(|i| i.referenced_component_id)
+    }
+}
@@ -0,0 +1,219 @@
+//! Integration tests for loading RF2 Full and Delta files.
+//!
+//! `tests/fixtures/rf2/Full` holds every version of the fixture release:
+//! most components were first published on 2002-01-31, Type 2 diabetes lost
+//! its IS_A to Clinical finding on 2020-01-31, and on 2024-01-01 it became
+//! fully defined, two diabetes concepts and the IDDM synonym were
+//! inactivated, the historical associations were added and the paracetamol
+//! tablet was created. `tests/fixtures/rf2-delta` is a later Delta
+//! (2024-07-01) that adds a concept and retires a synonym and a finding site.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use snomed_ecl_executor::{EclExecutor, EclQueryable, HistoryAssociationType};
+use snomed_ecl_store::{Rf2Loader, Rf2Store, StoreError};
+
+fn fixtures() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+fn full_at(date: u32) -> Rf2Store {
+    Rf2Loader::full(fixtures().join("rf2"))
+        .at_date(date)
+        .load()
+        .expect("fixture should load")
+}
+
+fn ids(store: &Rf2Store, ecl: &str) -> HashSet<u64> {
+    EclExecutor::new(store)
+        .execute(ecl)
+        .unwrap()
+        .iter()
+        .copied()
+        .collect()
+}
+
+fn set(ids: &[u64]) -> HashSet<u64> {
+    ids.iter().copied().collect()
+}
+
+// ============================================================================
+// Full
+// ============================================================================
+
+#[test]
+fn test_full_without_date_matches_snapshot() {
+    let full = Rf2Loader::full(fixtures().join("rf2")).load().unwrap();
+    let snapshot = Rf2Store::load_snapshot(fixtures().join("rf2")).unwrap();
+
+    assert_eq!(full.concept_count(), snapshot.concept_count());
+    assert_eq!(ids(&full, "<< 404684003"), ids(&snapshot, "<< 404684003"));
+    assert_eq!(
+        full.get_concept_info(44054006).unwrap().effective_time,
+        snapshot.get_concept_info(44054006).unwrap().effective_time
+    );
+}
+
+#[test]
+fn test_full_at_date_rolls_back_concepts() {
+    let store = full_at(20230131);
+
+    // Created after the date
+    assert!(!store.has_concept(322236009));
+
+    // Type 2 diabetes was still primitive, as published in 2002
+    let info = store.get_concept_info(44054006).unwrap();
+    assert!(info.is_primitive);
+    assert_eq!(info.effective_time, Some(20020131));
+
+    // Type 1 diabetes keeps its 2020 version
+    assert_eq!(store.get_concept_effective_time(46635009), Some(20200131));
+
+    // Not yet inactivated
+    assert!(store.is_concept_active(190368000));
+    assert!(ids(&store, "* {{ C active = false }}").is_empty());
+}
+
+#[test]
+fn test_full_at_date_rolls_back_descriptions() {
+    let store = full_at(20230131);
+    let iddm = store
+        .get_descriptions(46635009)
+        .into_iter()
+        .find(|d| d.term == "IDDM")
+        .unwrap();
+    assert!(iddm.active);
+    assert_eq!(iddm.effective_time, Some(20020131));
+    assert!(!store
+        .get_description_language_refsets(iddm.description_id)
+        .is_empty());
+
+    let store = full_at(20240101);
+    let iddm = store
+        .get_descriptions(46635009)
+        .into_iter()
+        .find(|d| d.term == "IDDM")
+        .unwrap();
+    assert!(!iddm.active);
+    assert_eq!(iddm.effective_time, Some(20240101));
+    assert!(store
+        .get_description_language_refsets(iddm.description_id)
+        .is_empty());
+}
+
+#[test]
+fn test_full_at_date_rolls_back_relationships() {
+    let store = full_at(20190131);
+    assert_eq!(store.get_parents(44054006), vec![73211009, 404684003]);
+    assert!(ids(&store, "<! 404684003").contains(&44054006));
+
+    let store = full_at(20200131);
+    assert_eq!(store.get_parents(44054006), vec![73211009]);
+}
+
+#[test]
+fn test_full_at_date_rolls_back_refsets() {
+    let store = full_at(20230131);
+    assert!(store
+        .get_historical_associations_by_type(46635009, HistoryAssociationType::SameAs)
+        .is_empty());
+
+    let store = full_at(20240101);
+    assert_eq!(
+        store.get_historical_associations_by_type(46635009, HistoryAssociationType::SameAs),
+        vec![190368000]
+    );
+}
+
+#[test]
+fn test_date_before_first_release_is_empty() {
+    let store = full_at(20010131);
+    assert_eq!(store.concept_count(), 0);
+    assert!(ids(&store, "*").is_empty());
+}
+
+// ============================================================================
+// Delta
+// ============================================================================
+
+#[test]
+fn test_delta_on_snapshot() {
+    let store = Rf2Loader::snapshot(fixtures().join("rf2"))
+        .with_delta(fixtures().join("rf2-delta"))
+        .load()
+        .unwrap();
+
+    // Added concept
+    assert_eq!(store.get_children(44054006), vec![609567009]);
+    assert_eq!(store.get_concept_effective_time(609567009), Some(20240701));
+    assert_eq!(
+        store.get_preferred_term(609567009).as_deref(),
+        Some("Type 2 diabetes mellitus in obese")
+    );
+    assert_eq!(
+        ids(&store, "<< 73211009"),
+        set(&[73211009, 46635009, 44054006, 609567009])
+    );
+
+    // Inactivated finding site and synonym
+    assert!(store.get_attributes(46635009).is_empty());
+    assert_eq!(
+        ids(&store, "<< 404684003 : 363698007 = 15776009"),
+        set(&[44054006])
+    );
+    let insulin = store
+        .get_descriptions(46635009)
+        .into_iter()
+        .find(|d| d.term == "Insulin dependent diabetes mellitus")
+        .unwrap();
+    assert!(!insulin.active);
+    assert_eq!(insulin.effective_time, Some(20240701));
+
+    // Untouched components are unchanged
+    assert_eq!(
+        store.get_refset_members(723264001),
+        vec![15776009, 123037004]
+    );
+}
+
+#[test]
+fn test_delta_on_full_at_date() {
+    // The Delta is newer than the date, so it is ignored
+    let store = Rf2Loader::full(fixtures().join("rf2"))
+        .with_delta(fixtures().join("rf2-delta"))
+        .at_date(20240101)
+        .load()
+        .unwrap();
+    assert!(!store.has_concept(609567009));
+    assert_eq!(store.get_attributes(46635009).len(), 1);
+
+    let store = Rf2Loader::full(fixtures().join("rf2"))
+        .with_delta(fixtures().join("rf2-delta"))
+        .load()
+        .unwrap();
+    assert!(store.has_concept(609567009));
+    assert!(store.get_attributes(46635009).is_empty());
+}
+
+#[test]
+fn test_missing_delta_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let err = Rf2Loader::snapshot(fixtures().join("rf2"))
+        .with_delta(dir.path())
+        .load()
+        .unwrap_err();
+    assert!(matches!(err, StoreError::MissingFile { kind: "Delta", .. }));
+
+    // A Snapshot directory has no Full files
+    let err = Rf2Loader::full(fixtures().join("rf2/Snapshot"))
+        .load()
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        StoreError::MissingFile {
+            kind: "concept",
+            ..
+        }
+    ));
+}
@@ -0,0 +1,418 @@
+//! Integration tests for the RF2 snapshot store.
+//!
+//! The fixture under `tests/fixtures/rf2` is a tiny hand-written RF2
+//! Snapshot release: a diabetes hierarchy with a finding site, a drug with
+//! concrete values, two inactive concepts with historical associations, and
+//! simple, map and language reference sets.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use snomed_ecl_executor::{
+    Acceptability, ConcreteValueRef, EclExecutor, EclQueryable, HistoryAssociationType,
+    RefsetFieldValue,
+};
+use snomed_ecl_store::{Rf2Store, StoreError};
+
+const US_ENGLISH: u64 = 900000000000509007;
+const GB_ENGLISH: u64 = 900000000000508004;
+
+fn fixture_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/rf2")
+}
+
+fn load() -> Rf2Store {
+    Rf2Store::load_snapshot(fixture_dir()).expect("fixture should load")
+}
+
+fn ids(store: &Rf2Store, ecl: &str) -> HashSet<u64> {
+    EclExecutor::new(store)
+        .execute(ecl)
+        .unwrap()
+        .iter()
+        .copied()
+        .collect()
+}
+
+fn set(ids: &[u64]) -> HashSet<u64> {
+    ids.iter().copied().collect()
+}
+
+// ============================================================================
+// Loading
+// ============================================================================
+
+#[test]
+fn test_load_counts() {
+    let store = load();
+    assert_eq!(store.concept_count(), 24);
+    // 24 FSNs, 24 synonyms, 3 extra synonyms and a text definition
+    assert_eq!(store.description_count(), 52);
+    // 21 active IS_A relationships and two finding sites; the inactive
+    // and stated relationships are skipped
+    assert_eq!(store.relationship_count(), 23);
+}
+
+#[test]
+fn test_load_from_snapshot_directory() {
+    let store = Rf2Store::load_snapshot(fixture_dir().join("Snapshot")).unwrap();
+    assert_eq!(store.concept_count(), 24);
+}
+
+#[test]
+fn test_missing_concept_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let err = Rf2Store::load_snapshot(dir.path()).unwrap_err();
+    assert!(matches!(
+        err,
+        StoreError::MissingFile {
+            kind: "concept",
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_invalid_row_reports_position() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("sct2_Concept_Snapshot_INT_20240101.txt"),
+        "id\teffectiveTime\tactive\tmoduleId\tdefinitionStatusId\n\
+         138875005\t20240101\tyes\t900000000000207008\t900000000000074008\n",
+    )
+    .unwrap();
+
+    let err = Rf2Store::load_snapshot(dir.path()).unwrap_err();
+    match err {
+        StoreError::InvalidRow { line, message, .. } => {
+            assert_eq!(line, 2);
+            assert!(message.contains("active"), "{}", message);
+        }
+        other => panic!("unexpected error: {}", other),
+    }
+}
+
+// ============================================================================
+// Hierarchy and attributes
+// ============================================================================
+
+#[test]
+fn test_hierarchy() {
+    let store = load();
+    assert_eq!(store.get_children(73211009), vec![44054006, 46635009]);
+    // The inactive IS_A to Clinical finding is not loaded
+    assert_eq!(store.get_parents(44054006), vec![73211009]);
+    assert!(store.get_parents(190368000).is_empty());
+
+    assert_eq!(
+        ids(&store, "<< 73211009"),
+        set(&[73211009, 46635009, 44054006])
+    );
+    assert_eq!(
+        ids(&store, "> 46635009"),
+        set(&[73211009, 64572001, 404684003, 138875005])
+    );
+}
+
+#[test]
+fn test_attributes() {
+    let store = load();
+    let attributes = store.get_attributes(44054006);
+    assert_eq!(attributes.len(), 1);
+    assert_eq!(attributes[0].type_id, 363698007);
+    assert_eq!(attributes[0].destination_id, 15776009);
+    assert_eq!(attributes[0].group, 1);
+
+    assert!(store.supports_attribute_lookup());
+    assert_eq!(
+        store.get_concepts_with_attribute(363698007, 15776009),
+        vec![44054006, 46635009]
+    );
+    assert_eq!(
+        ids(&store, "<< 404684003 : 363698007 = << 123037004"),
+        set(&[46635009, 44054006])
+    );
+}
+
+#[test]
+fn test_inbound_relationships() {
+    let store = load();
+    let inbound = store.get_inbound_relationships(15776009);
+    let sources: HashSet<_> = inbound.iter().map(|r| r.destination_id).collect();
+    assert_eq!(sources, set(&[46635009, 44054006]));
+    assert!(inbound.iter().all(|r| r.type_id == 363698007));
+
+    assert_eq!(
+        ids(&store, "<< 123037004 : R 363698007 = 44054006"),
+        set(&[15776009])
+    );
+}
+
+#[test]
+fn test_concrete_values() {
+    let store = load();
+    let values = store.get_concrete_values(322236009);
+    assert_eq!(values.len(), 2);
+    assert!(values
+        .iter()
+        .any(|v| v.type_id == 1142135004 && v.value == ConcreteValueRef::Integer(500)));
+    assert!(values
+        .iter()
+        .any(|v| v.type_id == 1142137007 && v.value == ConcreteValueRef::Decimal(0.5)));
+
+    assert_eq!(
+        ids(&store, "< 373873005 : 1142135004 = #500"),
+        set(&[322236009])
+    );
+    assert!(ids(&store, "< 373873005 : 1142135004 = #250").is_empty());
+}
+
+// ============================================================================
+// Concepts and descriptions
+// ============================================================================
+
+#[test]
+fn test_concept_info() {
+    let store = load();
+    let info = store.get_concept_info(46635009).unwrap();
+    assert!(!info.is_primitive);
+    assert!(info.active);
+    assert_eq!(info.effective_time, Some(20200131));
+    assert_eq!(info.module_id, 900000000000207008);
+
+    assert!(!store.is_concept_active(190368000));
+    assert_eq!(store.is_concept_primitive(73211009), Some(true));
+    assert_eq!(store.get_concept_effective_time(73211009), Some(20240101));
+    assert_eq!(store.get_concept_module(73211009), Some(900000000000207008));
+    assert!(store.get_concept_info(999999999).is_none());
+
+    assert_eq!(
+        ids(&store, "<< 73211009 {{ C definitionStatus = defined }}"),
+        set(&[46635009, 44054006])
+    );
+}
+
+#[test]
+fn test_descriptions() {
+    let store = load();
+    let descriptions = store.get_descriptions(46635009);
+    assert_eq!(descriptions.len(), 4);
+    let iddm = descriptions.iter().find(|d| d.term == "IDDM").unwrap();
+    assert!(!iddm.active);
+    assert_eq!(iddm.language_code, "en");
+
+    // Text definitions are descriptions too
+    assert!(store
+        .get_descriptions(73211009)
+        .iter()
+        .any(|d| d.type_id == 900000000000550004));
+
+    assert_eq!(
+        store.get_semantic_tag(73211009).as_deref(),
+        Some("disorder")
+    );
+    assert_eq!(
+        ids(&store, "<< 73211009 {{ term = \"insulin\" }}"),
+        set(&[46635009])
+    );
+}
+
+#[test]
+fn test_language_refsets() {
+    let store = load();
+    let type_2_synonyms: Vec<_> = store
+        .get_descriptions(44054006)
+        .into_iter()
+        .filter(|d| d.type_id == 900000000000013009)
+        .collect();
+    let us_term = type_2_synonyms
+        .iter()
+        .find(|d| d.term == "Type 2 diabetes mellitus")
+        .unwrap();
+
+    let memberships = store.get_description_language_refsets(us_term.description_id);
+    assert_eq!(memberships.len(), 2);
+    assert!(memberships
+        .iter()
+        .any(|m| m.refset_id == US_ENGLISH && m.acceptability == Acceptability::Preferred));
+    assert!(memberships
+        .iter()
+        .any(|m| m.refset_id == GB_ENGLISH && m.acceptability == Acceptability::Acceptable));
+
+    // The inactive language member of IDDM is not loaded
+    let iddm = store
+        .get_descriptions(46635009)
+        .into_iter()
+        .find(|d| d.term == "IDDM")
+        .unwrap();
+    assert!(store
+        .get_description_language_refsets(iddm.description_id)
+        .is_empty());
+
+    assert_eq!(
+        ids(
+            &store,
+            "<< 73211009 {{ term = \"type ii\", preferredIn = 900000000000508004 }}"
+        ),
+        set(&[44054006])
+    );
+}
+
+#[test]
+fn test_preferred_term_follows_dialects() {
+    let store = load();
+    assert_eq!(
+        store.get_preferred_term(44054006).as_deref(),
+        Some("Type 2 diabetes mellitus")
+    );
+
+    let store = store.with_dialects([GB_ENGLISH]);
+    assert_eq!(
+        store.get_preferred_term(44054006).as_deref(),
+        Some("Type II diabetes mellitus")
+    );
+
+    // Falls back to the fully specified name
+    let store = store.with_dialects([]);
+    assert_eq!(
+        store.get_preferred_term(44054006).as_deref(),
+        Some("Diabetes mellitus type 2 (disorder)")
+    );
+}
+
+// ============================================================================
+// Reference sets
+// ============================================================================
+
+#[test]
+fn test_simple_refset() {
+    let store = load();
+    assert_eq!(
+        store.get_refset_members(723264001),
+        vec![15776009, 123037004]
+    );
+    assert_eq!(ids(&store, "^ 723264001"), set(&[15776009, 123037004]));
+    assert!(store.get_refset_members(12345678).is_empty());
+
+    // Member rows include the inactive member
+    let rows = store.get_refset_member_rows(723264001);
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows.iter().filter(|row| row.active).count(), 2);
+    assert!(rows.iter().all(|row| row.fields.is_empty()));
+}
+
+#[test]
+fn test_map_refsets() {
+    let store = load();
+    let rows = store.get_refset_member_rows(447562003);
+    assert_eq!(rows.len(), 2);
+    let type_2 = rows
+        .iter()
+        .find(|row| row.referenced_component_id == 44054006)
+        .unwrap();
+    assert_eq!(
+        type_2.field("mapTarget"),
+        Some(RefsetFieldValue::String("E11.9".to_string()))
+    );
+    assert_eq!(type_2.field("mapGroup"), Some(RefsetFieldValue::Integer(1)));
+    assert_eq!(
+        type_2.field("correlationId"),
+        Some(RefsetFieldValue::Component(447561005))
+    );
+    assert_eq!(type_2.id.len(), 36);
+
+    assert_eq!(
+        ids(&store, "^ 447562003 {{ M mapTarget = \"E11.9\" }}"),
+        set(&[44054006])
+    );
+    assert_eq!(ids(&store, "^ 900000000000497000"), set(&[73211009]));
+}
+
+#[test]
+fn test_language_refset_members() {
+    let store = load();
+    let members = store.get_refset_members(GB_ENGLISH);
+    // Every FSN and synonym, except the inactive IDDM description
+    assert_eq!(members.len(), 50);
+
+    let rows = store.get_refset_member_rows(GB_ENGLISH);
+    assert_eq!(rows.len(), 50);
+    assert!(rows
+        .iter()
+        .all(|row| row.field("acceptabilityId").is_some()));
+    assert!(rows.iter().all(|row| row.refset_id == GB_ENGLISH));
+    assert!(rows
+        .windows(2)
+        .all(|pair| pair[0].referenced_component_id <= pair[1].referenced_component_id));
+}
+
+// ============================================================================
+// History
+// ============================================================================
+
+#[test]
+fn test_historical_associations() {
+    let store = load();
+    assert_eq!(
+        store.get_historical_associations_by_type(46635009, HistoryAssociationType::SameAs),
+        vec![190368000]
+    );
+    assert_eq!(
+        store.get_historical_associations_by_type(44054006, HistoryAssociationType::ReplacedBy),
+        vec![197761008]
+    );
+    assert!(store
+        .get_historical_associations_by_type(44054006, HistoryAssociationType::SameAs)
+        .is_empty());
+    // The inactive REPLACED_BY row to Diabetes mellitus is not loaded
+    assert!(store.get_historical_associations(73211009).is_empty());
+
+    let by_refset = store.get_historical_associations_by_refset(44054006);
+    assert_eq!(by_refset.get(&900000000000526001), Some(&vec![197761008]));
+
+    assert_eq!(
+        ids(&store, "<< 73211009 {{ +HISTORY-MIN }}"),
+        set(&[73211009, 46635009, 44054006, 190368000])
+    );
+    assert_eq!(
+        ids(&store, "<< 73211009 {{ +HISTORY-MOD }}"),
+        set(&[73211009, 46635009, 44054006, 190368000, 197761008])
+    );
+}
+
+// ============================================================================
+// Identifiers
+// ============================================================================
+
+#[test]
+fn test_alternate_identifiers() {
+    let store = load();
+    assert_eq!(
+        store.resolve_alternate_identifier("http://snomed.info/id", "73211009"),
+        Some(73211009)
+    );
+    assert_eq!(
+        store.resolve_alternate_identifier("LOINC", "LA-44054-6"),
+        Some(44054006)
+    );
+    assert_eq!(
+        store.resolve_alternate_identifier("705114005", "LA-44054-6"),
+        Some(44054006)
+    );
+    assert_eq!(
+        store.resolve_alternate_identifier("ICD-10", "LA-44054-6"),
+        None
+    );
+}
+
+#[test]
+fn test_wildcard_includes_inactive_concepts() {
+    let store = load();
+    let all = ids(&store, "*");
+    assert_eq!(all.len(), 24);
+    assert!(all.contains(&190368000));
+    assert_eq!(
+        ids(&store, "* {{ C active = false }}"),
+        set(&[190368000, 197761008])
+    );
+}
@@ -0,0 +1,384 @@
+//! Integration tests for the SQLite store.
+//!
+//! The first part runs the scenarios shared with the executor's tests (the
+//! `snomed-ecl-test-scenarios` crate) against a database imported from
+//! `tests/fixtures/rf2-integration`, an RF2 release holding the same
+//! hierarchy, attribute and reference sets as the executor's mock store. The
+//! second part checks that a database imported from `tests/fixtures/rf2`
+//! answers every query like an `Rf2Store` loaded from the same files.
+
+#![cfg(feature = "sqlite")]
+
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::path::PathBuf;
+
+use snomed_ecl_executor::{
+    EclExecutor, EclQueryable, HistoryAssociationType, RelationshipInfo, StatisticsService,
+};
+use snomed_ecl_store::{Rf2Loader, Rf2Store, SqliteStore, StoreError};
+use tempfile::TempDir;
+
+fn fixtures() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// Imports a fixture release into a new database, then opens it read-only.
+fn import(fixture: &str) -> (TempDir, SqliteStore) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("snomed.db");
+    SqliteStore::import(&path, &Rf2Loader::snapshot(fixtures().join(fixture))).unwrap();
+    let store = SqliteStore::open(&path).unwrap();
+    (dir, store)
+}
+
+fn integration_store() -> (TempDir, SqliteStore) {
+    import("rf2-integration")
+}
+
+fn ids(store: &impl EclQueryable, ecl: &str) -> HashSet<u64> {
+    EclExecutor::new(store)
+        .execute(ecl)
+        .unwrap()
+        .iter()
+        .copied()
+        .collect()
+}
+
+/// Compares values of executor types that do not implement `PartialEq`.
+fn debug(value: impl Debug) -> String {
+    format!("{:?}", value)
+}
+
+// ============================================================================
+// Shared Scenarios
+// ============================================================================
+
+/// Runs a shared scenario against the integration database, then checks
+/// that no query failed.
+fn with_integration_store(scenario: impl FnOnce(&SqliteStore)) {
+    let (_dir, store) = integration_store();
+    scenario(&store);
+    assert!(store.take_error().is_none());
+}
+
+snomed_ecl_test_scenarios::scenario_tests!(with_integration_store);
+
+#[test]
+fn test_counts() {
+    let (_dir, store) = integration_store();
+    assert_eq!(store.concept_count(), 15);
+    assert_eq!(store.relationship_count(), 11);
+}
+
+// ============================================================================
+// Reverse Attribute Lookup Tests
+// ============================================================================
+
+#[test]
+fn test_reverse_lookup_for_wildcard_focus() {
+    let (_dir, store) = integration_store();
+    let executor = EclExecutor::new(&store);
+
+    let plan = executor.explain("* : 363698007 = 80891009").unwrap();
+    assert!(plan
+        .steps
+        .iter()
+        .any(|s| s.expression.contains("reverse lookup")));
+    assert_eq!(
+        executor
+            .execute("* : 363698007 = 80891009")
+            .unwrap()
+            .to_vec(),
+        vec![73211009]
+    );
+    assert!(store.take_error().is_none());
+}
+
+#[test]
+fn test_forward_scan_for_small_focus() {
+    let (_dir, store) = integration_store();
+    let mut statistics = StatisticsService::new();
+    statistics.register_descendant_count(73211009, 2);
+    let executor = EclExecutor::new(&store).with_statistics(statistics);
+
+    let plan = executor
+        .explain("<< 73211009 : 363698007 = 80891009")
+        .unwrap();
+    assert!(plan
+        .steps
+        .iter()
+        .any(|s| s.expression.contains("forward scan")));
+    assert_eq!(
+        executor
+            .execute("<< 73211009 : 363698007 = 80891009")
+            .unwrap()
+            .to_vec(),
+        vec![73211009]
+    );
+    assert!(store.take_error().is_none());
+}
+
+/// Hides the store's reverse attribute index, so refinements are evaluated
+/// by scanning the attributes of every focus concept.
+struct ForwardScan<'a>(&'a SqliteStore);
+
+impl EclQueryable for ForwardScan<'_> {
+    fn get_children(&self, concept_id: u64) -> Vec<u64> {
+        self.0.get_children(concept_id)
+    }
+
+    fn get_parents(&self, concept_id: u64) -> Vec<u64> {
+        self.0.get_parents(concept_id)
+    }
+
+    fn has_concept(&self, concept_id: u64) -> bool {
+        self.0.has_concept(concept_id)
+    }
+
+    fn all_concept_ids(&self) -> Box<dyn Iterator<Item = u64> + '_> {
+        self.0.all_concept_ids()
+    }
+
+    fn get_refset_members(&self, refset_id: u64) -> Vec<u64> {
+        self.0.get_refset_members(refset_id)
+    }
+
+    fn get_attributes(&self, concept_id: u64) -> Vec<RelationshipInfo> {
+        self.0.get_attributes(concept_id)
+    }
+}
+
+#[test]
+fn test_reverse_lookup_matches_forward_scan() {
+    let (_dir, store) = integration_store();
+    let plain = ForwardScan(&store);
+    let forward = EclExecutor::new(&plain);
+    let reverse = EclExecutor::new(&store);
+
+    let plan = forward.explain("* : 363698007 = 80891009").unwrap();
+    assert!(!plan
+        .steps
+        .iter()
+        .any(|s| s.expression.contains("reverse lookup")));
+
+    for ecl in [
+        "* : 363698007 = 80891009",
+        "* : 363698007 = << 123037004",
+        "* : 363698007 = (80891009 OR 39057004)",
+        "* : [1..1] 363698007 = 80891009",
+        "* : [0..0] 363698007 = 80891009",
+        "* : 363698007 = 80891009, 116676008 = *",
+        "* : { 363698007 = 80891009 }",
+        "* : 363698007 != 80891009",
+    ] {
+        let expected = forward.execute(ecl).unwrap().to_vec();
+        assert_eq!(reverse.execute(ecl).unwrap().to_vec(), expected, "{}", ecl);
+    }
+    assert!(store.take_error().is_none());
+}
+
+// ============================================================================
+// Parity with Rf2Store
+// ============================================================================
+
+#[test]
+fn test_queries_match_rf2_store() {
+    let (_dir, store) = import("rf2");
+    let rf2 = Rf2Store::load_snapshot(fixtures().join("rf2")).unwrap();
+
+    assert_eq!(store.concept_count(), rf2.concept_count());
+    assert_eq!(store.description_count(), rf2.description_count());
+    assert_eq!(store.relationship_count(), rf2.relationship_count());
+
+    for ecl in [
+        "*",
+        "<< 404684003",
+        "> 46635009",
+        "<< 404684003 : 363698007 = << 123037004",
+        "<< 123037004 : R 363698007 = 44054006",
+        "< 373873005 : 1142135004 = #500",
+        "<< 73211009 {{ C definitionStatus = defined }}",
+        "<< 73211009 {{ term = \"insulin\" }}",
+        "<< 73211009 {{ term = \"type ii\", preferredIn = 900000000000508004 }}",
+        "<< 404684003 {{ semanticTag = \"disorder\" }}",
+        "^ 723264001",
+        "^ 447562003 {{ M mapTarget = \"E11.9\" }}",
+        "^ 900000000000497000",
+        "<< 73211009 {{ +HISTORY-MIN }}",
+        "<< 73211009 {{ +HISTORY-MOD }}",
+        "<< 73211009 {{ +HISTORY (<< 900000000000527005) }}",
+        "* {{ C active = false }}",
+    ] {
+        assert_eq!(ids(&store, ecl), ids(&rf2, ecl), "{}", ecl);
+    }
+    assert!(store.take_error().is_none());
+}
+
+#[test]
+fn test_lookups_match_rf2_store() {
+    let (_dir, store) = import("rf2");
+    let rf2 = Rf2Store::load_snapshot(fixtures().join("rf2")).unwrap();
+
+    for id in rf2.all_concept_ids() {
+        assert_eq!(store.get_children(id), rf2.get_children(id), "{}", id);
+        assert_eq!(store.get_parents(id), rf2.get_parents(id), "{}", id);
+        assert_eq!(store.get_attributes(id), rf2.get_attributes(id), "{}", id);
+        assert_eq!(
+            store.get_inbound_relationships(id),
+            rf2.get_inbound_relationships(id),
+            "{}",
+            id
+        );
+        assert_eq!(
+            store.get_concrete_values(id),
+            rf2.get_concrete_values(id),
+            "{}",
+            id
+        );
+        assert_eq!(
+            debug(store.get_descriptions(id)),
+            debug(rf2.get_descriptions(id)),
+            "{}",
+            id
+        );
+        assert_eq!(
+            store.get_preferred_term(id),
+            rf2.get_preferred_term(id),
+            "{}",
+            id
+        );
+        assert_eq!(
+            store.get_semantic_tag(id),
+            rf2.get_semantic_tag(id),
+            "{}",
+            id
+        );
+        assert_eq!(
+            debug(store.get_concept_info(id)),
+            debug(rf2.get_concept_info(id)),
+            "{}",
+            id
+        );
+        assert_eq!(
+            store.get_refset_members(id),
+            rf2.get_refset_members(id),
+            "{}",
+            id
+        );
+        assert_eq!(
+            store.get_refset_member_rows(id),
+            rf2.get_refset_member_rows(id),
+            "{}",
+            id
+        );
+        assert_eq!(
+            store.get_historical_associations(id),
+            rf2.get_historical_associations(id),
+            "{}",
+            id
+        );
+        assert_eq!(
+            store.get_historical_associations_by_refset(id),
+            rf2.get_historical_associations_by_refset(id),
+            "{}",
+            id
+        );
+        for description in rf2.get_descriptions(id) {
+            assert_eq!(
+                debug(store.get_description_language_refsets(description.description_id)),
+                debug(rf2.get_description_language_refsets(description.description_id))
+            );
+        }
+    }
+
+    assert_eq!(
+        store.get_historical_associations_by_type(46635009, HistoryAssociationType::SameAs),
+        vec![190368000]
+    );
+    for scheme in [
+        "LOINC",
+        "loinc",
+        "705114005",
+        "ICD-10",
+        "http://snomed.info/id",
+    ] {
+        for identifier in ["LA-44054-6", "73211009"] {
+            assert_eq!(
+                store.resolve_alternate_identifier(scheme, identifier),
+                rf2.resolve_alternate_identifier(scheme, identifier),
+                "{} {}",
+                scheme,
+                identifier
+            );
+        }
+    }
+    assert_eq!(
+        store.resolve_alternate_identifier("LOINC", "LA-44054-6"),
+        Some(44054006)
+    );
+    assert!(store.take_error().is_none());
+}
+
+#[test]
+fn test_dialects() {
+    let (_dir, store) = import("rf2");
+    let rf2 = Rf2Store::load_snapshot(fixtures().join("rf2")).unwrap();
+    let gb = [900000000000508004];
+    let store = store.with_dialects(gb);
+    let rf2 = rf2.with_dialects(gb);
+    for id in rf2.all_concept_ids() {
+        assert_eq!(
+            store.get_preferred_term(id),
+            rf2.get_preferred_term(id),
+            "{}",
+            id
+        );
+    }
+    assert!(store.take_error().is_none());
+}
+
+// ============================================================================
+// Import and Open
+// ============================================================================
+
+#[test]
+fn test_import_returns_open_store() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("snomed.db");
+    let store = SqliteStore::import(
+        &path,
+        &Rf2Loader::full(fixtures().join("rf2")).at_date(20230131),
+    )
+    .unwrap();
+
+    // Point-in-time imports keep the loader's view
+    assert!(!store.has_concept(322236009));
+    assert!(store.is_concept_active(190368000));
+}
+
+#[test]
+fn test_import_into_existing_store_fails() {
+    let (dir, _store) = integration_store();
+    let err = SqliteStore::import(
+        dir.path().join("snomed.db"),
+        &Rf2Loader::snapshot(fixtures().join("rf2-integration")),
+    )
+    .unwrap_err();
+    assert!(matches!(err, StoreError::SqliteError(_)));
+}
+
+#[test]
+fn test_open_invalid_database() {
+    let dir = tempfile::tempdir().unwrap();
+
+    // Missing file
+    let err = SqliteStore::open(dir.path().join("missing.db")).unwrap_err();
+    assert!(matches!(err, StoreError::SqliteError(_)));
+
+    // Not a database
+    let path = dir.path().join("snomed.db");
+    std::fs::write(&path, "id\teffectiveTime\tactive\n").unwrap();
+    let err = SqliteStore::open(&path).unwrap_err();
+    assert!(matches!(err, StoreError::InvalidDatabase { .. }));
+}
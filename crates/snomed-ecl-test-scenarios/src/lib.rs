@@ -0,0 +1,348 @@
+//! ECL scenarios shared by the store integration tests.
+//!
+//! Each scenario runs expressions against a store holding the integration
+//! hierarchy: the executor's mock store in `tests/integration_tests.rs`, and
+//! the SQLite store tests' database imported from
+//! `snomed-ecl-store/tests/fixtures/rf2-integration`. [`scenario_tests!`]
+//! turns every scenario into a `#[test]`, given a function that builds the
+//! store and passes it to the scenario.
+//!
+//! ```text
+//! 138875005 SNOMED CT Concept
+//! ├── 404684003 Clinical finding
+//! │   ├── 64572001 Disease (defined)
+//! │   │   ├── 73211009 Diabetes mellitus       363698007 = 80891009
+//! │   │   │   ├── 46635009 Type 1 diabetes
+//! │   │   │   └── 44054006 Type 2 diabetes
+//! │   │   └── 38341003 Hypertension (inactive)
+//! │   └── 386661006 Fever
+//! └── 123037004 Body structure
+//!     ├── 39057004 Pulmonary valve
+//!     └── 80891009 Heart structure
+//! ```
+//!
+//! Reference set 700043003 holds 73211009 and 386661006, 723264001 holds
+//! 46635009 and 44054006. The root, 404684003, 64572001, 123037004 and the
+//! attribute and reference set concepts are defined; the others are
+//! primitive.
+
+use std::collections::HashSet;
+
+use snomed_ecl_executor::{EclExecutor, EclQueryable};
+
+fn ids(store: &impl EclQueryable, ecl: &str) -> HashSet<u64> {
+    EclExecutor::new(store)
+        .execute(ecl)
+        .unwrap()
+        .iter()
+        .copied()
+        .collect()
+}
+
+fn set(ids: &[u64]) -> HashSet<u64> {
+    ids.iter().copied().collect()
+}
+
+/// Generates a `#[test]` for every scenario, calling `$with_store` with the
+/// scenario to run.
+///
+/// ```ignore
+/// fn with_store(scenario: impl FnOnce(&MyStore)) {
+///     scenario(&MyStore::load_integration_fixture());
+/// }
+///
+/// snomed_ecl_test_scenarios::scenario_tests!(with_store);
+/// ```
+#[macro_export]
+macro_rules! scenario_tests {
+    ($with_store:path) => {
+        $crate::scenario_tests!(
+            $with_store;
+            test_self_concept,
+            test_descendant_of,
+            test_descendant_or_self_of,
+            test_ancestor_of,
+            test_child_of,
+            test_parent_of,
+            test_multiple_hierarchy_levels,
+            test_ancestor_of_multiple_levels,
+            test_child_or_self_of,
+            test_parent_or_self_of,
+            test_and_expression,
+            test_or_expression,
+            test_minus_expression,
+            test_nested_expressions,
+            test_conjunction_with_hierarchy,
+            test_disjunction_union,
+            test_exclusion_difference,
+            test_deeply_nested_parentheses,
+            test_member_of_simple,
+            test_member_of_with_and,
+            test_member_of_intersection,
+            test_multiple_member_of_refsets,
+            test_simple_refinement,
+            test_refinement_with_hierarchy,
+            test_refinement_with_concept_value,
+            test_attribute_with_wildcard_value,
+            test_reverse_attribute,
+            test_active_filter,
+            test_definition_status_filter,
+            test_combined_filters_active_and_primitive,
+            test_complex_query,
+            test_empty_result,
+            test_invalid_concept,
+            test_wildcard,
+        );
+    };
+    ($with_store:path; $($scenario:ident),* $(,)?) => {
+        $(
+            #[test]
+            fn $scenario() {
+                $with_store(|store| $crate::$scenario(store));
+            }
+        )*
+    };
+}
+
+// ============================================================================
+// Basic Hierarchy Tests
+// ============================================================================
+
+pub fn test_self_concept(store: &impl EclQueryable) {
+    assert_eq!(ids(store, "73211009"), set(&[73211009]));
+}
+
+pub fn test_descendant_of(store: &impl EclQueryable) {
+    // Descendants of Diabetes mellitus, self excluded
+    assert_eq!(ids(store, "< 73211009"), set(&[46635009, 44054006]));
+}
+
+pub fn test_descendant_or_self_of(store: &impl EclQueryable) {
+    assert_eq!(
+        ids(store, "<< 73211009"),
+        set(&[73211009, 46635009, 44054006])
+    );
+}
+
+pub fn test_ancestor_of(store: &impl EclQueryable) {
+    // Ancestors of Type 1 diabetes, self excluded
+    assert_eq!(
+        ids(store, "> 46635009"),
+        set(&[73211009, 64572001, 404684003, 138875005])
+    );
+}
+
+pub fn test_child_of(store: &impl EclQueryable) {
+    assert_eq!(ids(store, "<! 73211009"), set(&[46635009, 44054006]));
+}
+
+pub fn test_parent_of(store: &impl EclQueryable) {
+    assert_eq!(ids(store, ">! 46635009"), set(&[73211009]));
+}
+
+pub fn test_multiple_hierarchy_levels(store: &impl EclQueryable) {
+    // Root -> Clinical finding -> Disease -> Diabetes -> Type 1/Type 2
+    let result = ids(store, "< 138875005");
+    assert!(result.contains(&404684003)); // Child of root
+    assert!(result.contains(&73211009)); // Great-grandchild
+    assert!(result.contains(&46635009)); // Great-great-grandchild
+}
+
+pub fn test_ancestor_of_multiple_levels(store: &impl EclQueryable) {
+    assert_eq!(
+        ids(store, ">> 44054006"),
+        set(&[44054006, 73211009, 64572001, 404684003, 138875005])
+    );
+}
+
+pub fn test_child_or_self_of(store: &impl EclQueryable) {
+    assert_eq!(
+        ids(store, "<<! 73211009"),
+        set(&[73211009, 46635009, 44054006])
+    );
+}
+
+pub fn test_parent_or_self_of(store: &impl EclQueryable) {
+    assert_eq!(ids(store, ">>! 46635009"), set(&[46635009, 73211009]));
+}
+
+// ============================================================================
+// Compound Expression Tests
+// ============================================================================
+
+pub fn test_and_expression(store: &impl EclQueryable) {
+    // Disease is a Clinical finding, so all descendants of Disease are in both
+    let result = ids(store, "<< 64572001 AND << 404684003");
+    for id in [64572001, 73211009, 46635009, 44054006] {
+        assert!(result.contains(&id), "{}", id);
+    }
+}
+
+pub fn test_or_expression(store: &impl EclQueryable) {
+    assert_eq!(
+        ids(store, "386661006 OR << 73211009"),
+        set(&[386661006, 73211009, 46635009, 44054006])
+    );
+}
+
+pub fn test_minus_expression(store: &impl EclQueryable) {
+    assert_eq!(
+        ids(store, "<< 73211009 MINUS 46635009"),
+        set(&[73211009, 44054006])
+    );
+}
+
+pub fn test_nested_expressions(store: &impl EclQueryable) {
+    assert_eq!(
+        ids(store, "(<< 73211009 OR 386661006) AND << 404684003"),
+        set(&[73211009, 46635009, 44054006, 386661006])
+    );
+}
+
+pub fn test_conjunction_with_hierarchy(store: &impl EclQueryable) {
+    // Ancestors of Type 1 that are also descendants of the root, which is
+    // not a descendant of itself
+    assert_eq!(
+        ids(store, "< 138875005 AND > 46635009"),
+        set(&[404684003, 64572001, 73211009])
+    );
+}
+
+pub fn test_disjunction_union(store: &impl EclQueryable) {
+    assert_eq!(
+        ids(store, "<! 73211009 OR <! 123037004"),
+        set(&[46635009, 44054006, 39057004, 80891009])
+    );
+}
+
+pub fn test_exclusion_difference(store: &impl EclQueryable) {
+    let result = ids(store, "<< 404684003 MINUS << 64572001");
+    assert!(result.contains(&404684003)); // Not a descendant of Disease
+    assert!(result.contains(&386661006)); // Fever is not under Disease
+    assert!(!result.contains(&73211009));
+    assert!(!result.contains(&46635009));
+}
+
+pub fn test_deeply_nested_parentheses(store: &impl EclQueryable) {
+    assert_eq!(
+        ids(store, "((46635009 OR 44054006) AND << 73211009)"),
+        set(&[46635009, 44054006])
+    );
+}
+
+// ============================================================================
+// MemberOf Tests
+// ============================================================================
+
+pub fn test_member_of_simple(store: &impl EclQueryable) {
+    assert_eq!(ids(store, "^ 700043003"), set(&[73211009, 386661006]));
+}
+
+pub fn test_member_of_with_and(store: &impl EclQueryable) {
+    // Only diabetes is both a member and a descendant of Disease
+    assert_eq!(ids(store, "^ 700043003 AND << 64572001"), set(&[73211009]));
+}
+
+pub fn test_member_of_intersection(store: &impl EclQueryable) {
+    assert_eq!(
+        ids(store, "^ 723264001 AND << 73211009"),
+        set(&[46635009, 44054006])
+    );
+}
+
+pub fn test_multiple_member_of_refsets(store: &impl EclQueryable) {
+    assert_eq!(
+        ids(store, "^ 700043003 OR ^ 723264001"),
+        set(&[73211009, 386661006, 46635009, 44054006])
+    );
+}
+
+// ============================================================================
+// Refinement Tests
+// ============================================================================
+
+pub fn test_simple_refinement(store: &impl EclQueryable) {
+    assert_eq!(ids(store, "* : 363698007 = 80891009"), set(&[73211009]));
+}
+
+pub fn test_refinement_with_hierarchy(store: &impl EclQueryable) {
+    // Heart structure is under Body structure
+    assert_eq!(
+        ids(store, "<< 404684003 : 363698007 = << 123037004"),
+        set(&[73211009])
+    );
+}
+
+pub fn test_refinement_with_concept_value(store: &impl EclQueryable) {
+    assert_eq!(
+        ids(store, "<< 404684003 : 363698007 = 80891009"),
+        set(&[73211009])
+    );
+}
+
+pub fn test_attribute_with_wildcard_value(store: &impl EclQueryable) {
+    assert_eq!(ids(store, "* : 363698007 = *"), set(&[73211009]));
+}
+
+pub fn test_reverse_attribute(store: &impl EclQueryable) {
+    // Heart structure is the finding site of diabetes
+    assert_eq!(
+        ids(store, "<< 123037004 : R 363698007 = *"),
+        set(&[80891009])
+    );
+}
+
+// ============================================================================
+// Filter Tests
+// ============================================================================
+
+pub fn test_active_filter(store: &impl EclQueryable) {
+    let result = ids(store, "<< 64572001 {{ active = true }}");
+    assert!(result.contains(&64572001));
+    assert!(result.contains(&73211009));
+    assert!(!result.contains(&38341003)); // Inactive
+}
+
+pub fn test_definition_status_filter(store: &impl EclQueryable) {
+    let result = ids(store, "<< 64572001 {{ definitionStatus = primitive }}");
+    assert!(!result.contains(&64572001)); // Defined
+    assert!(result.contains(&73211009));
+    assert!(result.contains(&46635009));
+}
+
+pub fn test_combined_filters_active_and_primitive(store: &impl EclQueryable) {
+    assert_eq!(
+        ids(
+            store,
+            "<< 404684003 {{ active = true, definitionStatus = primitive }}"
+        ),
+        set(&[73211009, 46635009, 44054006, 386661006])
+    );
+}
+
+pub fn test_complex_query(store: &impl EclQueryable) {
+    assert_eq!(
+        ids(
+            store,
+            "<< 404684003 {{ active = true, definitionStatus = primitive }} : 363698007 = *"
+        ),
+        set(&[73211009])
+    );
+}
+
+// ============================================================================
+// Edge Case Tests
+// ============================================================================
+
+pub fn test_empty_result(store: &impl EclQueryable) {
+    assert!(ids(store, "< 46635009").is_empty());
+}
+
+pub fn test_invalid_concept(store: &impl EclQueryable) {
+    let result = EclExecutor::new(store).execute("999999999");
+    assert!(result.is_err() || result.unwrap().count() == 0);
+}
+
+pub fn test_wildcard(store: &impl EclQueryable) {
+    assert_eq!(ids(store, "*").len(), 15);
+}
@@ -244,7 +244,7 @@ impl std::fmt::Display for ComparisonOperator {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TermMatchType {
-    /// Contains the term (default): `term = "x"`
+    /// Every search word prefixes some word of the term (default): `term = "x"`
     Contains,
     /// Starts with the term: `term startsWith "x"`
     StartsWith,
@@ -256,6 +256,21 @@ pub enum TermMatchType {
     Wildcard,
 }
 
+impl TermMatchType {
+    /// Returns the prefix used for this match type in a typed search term,
+    /// e.g. `wild` in `wild:"diab*"`.
+    ///
+    /// Only `match` and `wild` are defined by ECL; the other match types can
+    /// only be given by the term filter's operator and return `None`.
+    pub fn prefix(&self) -> Option<&'static str> {
+        match self {
+            TermMatchType::Contains => Some("match"),
+            TermMatchType::Wildcard => Some("wild"),
+            TermMatchType::StartsWith | TermMatchType::Regex | TermMatchType::Exact => None,
+        }
+    }
+}
+
 impl std::fmt::Display for TermMatchType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -268,6 +283,35 @@ impl std::fmt::Display for TermMatchType {
     }
 }
 
+/// A search term with its match type: `"heart"` or `wild:"*itis"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypedSearchTerm {
+    /// How to match the term.
+    pub match_type: TermMatchType,
+    /// The term value to match.
+    pub term: String,
+}
+
+impl TypedSearchTerm {
+    /// Create a typed search term.
+    pub fn new(match_type: TermMatchType, term: impl Into<String>) -> Self {
+        Self {
+            match_type,
+            term: term.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for TypedSearchTerm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.match_type.prefix() {
+            Some(prefix) => write!(f, "{}:\"{}\"", prefix, self.term),
+            None => write!(f, "{} \"{}\"", self.match_type, self.term),
+        }
+    }
+}
+
 /// History supplement profile for historical associations.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -335,12 +379,12 @@ pub enum EclFilter {
     // Description Filters
     // =========================================================================
 
-    /// Term filter: `{{ term = "heart" }}`
+    /// Term filter: `{{ term = "heart" }}` or `{{ term = (match:"heart" wild:"*itis") }}`
+    ///
+    /// A concept matches if any of the search terms matches.
     Term {
-        /// How to match the term.
-        match_type: TermMatchType,
-        /// The term value to match.
-        value: String,
+        /// The search terms, at least one.
+        terms: Vec<TypedSearchTerm>,
     },
 
     /// Language filter: `{{ language = en }}` or `{{ language = (en es) }}`
@@ -349,16 +393,22 @@ pub enum EclFilter {
         codes: Vec<String>,
     },
 
-    /// Type filter: `{{ typeId = 900000000000003001 }}` or `{{ type = syn }}`
+    /// Type filter: `{{ typeId = 900000000000003001 }}`, `{{ typeId = << X }}`
+    /// or `{{ type = syn }}`
     DescriptionType {
-        /// Description type IDs.
-        type_ids: Vec<SctId>,
+        /// Constraint on the description type IDs.
+        ///
+        /// Type aliases parse to a concept reference or concept set.
+        types: Box<EclExpression>,
     },
 
-    /// Dialect filter: `{{ dialect = en-US }}` or `{{ dialectId = 900000000000509007 }}`
+    /// Dialect filter: `{{ dialect = en-US }}`, `{{ dialectId = 900000000000509007 }}`
+    /// or `{{ dialectId = ^ X }}`
     Dialect {
-        /// Dialect reference set IDs.
-        dialect_ids: Vec<SctId>,
+        /// Constraint on the dialect reference set IDs.
+        ///
+        /// Dialect aliases parse to a concept reference or concept set.
+        dialects: Box<EclExpression>,
         /// Optional acceptability constraint.
         acceptability: Option<FilterAcceptability>,
     },
@@ -376,10 +426,10 @@ pub enum EclFilter {
     /// Active filter: `{{ active = true }}`
     Active(bool),
 
-    /// Module filter: `{{ moduleId = 900000000000207008 }}`
+    /// Module filter: `{{ moduleId = 900000000000207008 }}` or `{{ moduleId = << X }}`
     Module {
-        /// Module IDs to filter by.
-        module_ids: Vec<SctId>,
+        /// Constraint on the module IDs to filter by.
+        modules: Box<EclExpression>,
     },
 
     /// Effective time filter: `{{ effectiveTime >= 20200101 }}`
@@ -448,10 +498,14 @@ pub enum EclFilter {
         ids: Vec<SctId>,
     },
 
-    /// History supplement: `{{ +HISTORY }}` or `{{ +HISTORY-MIN }}`
+    /// History supplement: `{{ +HISTORY }}`, `{{ +HISTORY-MIN }}` or
+    /// `{{ +HISTORY (<< 900000000000527005) }}`
     History {
         /// Optional profile (MIN, MOD, MAX).
         profile: Option<HistoryProfile>,
+        /// Optional constraint on the historical association reference sets
+        /// to follow, used instead of a profile.
+        subset: Option<Box<EclExpression>>,
     },
 
     /// Domain-qualified filter: `{{ C active = true }}` or `{{ D term = "heart" }}`
@@ -482,6 +536,25 @@ pub enum MemberFieldValue {
 
 impl Eq for MemberFieldValue {}
 
+/// Refset fields selected by a member-of expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MemberFieldSelection {
+    /// Every field: `^ [*] 447562003`
+    All,
+    /// The named fields, in order: `^ [referencedComponentId, mapTarget] 447562003`
+    Fields(Vec<String>),
+}
+
+impl std::fmt::Display for MemberFieldSelection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MemberFieldSelection::All => write!(f, "[*]"),
+            MemberFieldSelection::Fields(fields) => write!(f, "[{}]", fields.join(", ")),
+        }
+    }
+}
+
 impl std::fmt::Display for MemberFieldValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -494,12 +567,74 @@ impl std::fmt::Display for MemberFieldValue {
     }
 }
 
+impl EclFilter {
+    /// Returns true if the filter can be qualified with `domain`.
+    ///
+    /// `active`, `moduleId` and `effectiveTime` apply to every domain,
+    /// `id` to concepts and descriptions, and the remaining filters to a
+    /// single domain: `definitionStatus` and `semanticTag` to concepts, term,
+    /// language, type, dialect and acceptability filters to descriptions and
+    /// field filters to members. History supplements and already qualified
+    /// filters cannot be qualified.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use snomed_ecl::{EclFilter, FilterDomain};
+    ///
+    /// assert!(EclFilter::Active(true).applies_to(FilterDomain::Description));
+    /// let filter = EclFilter::DefinitionStatus { is_primitive: true };
+    /// assert!(!filter.applies_to(FilterDomain::Description));
+    /// ```
+    pub fn applies_to(&self, domain: FilterDomain) -> bool {
+        match self {
+            EclFilter::Active(_) | EclFilter::Module { .. } | EclFilter::EffectiveTime { .. } => true,
+            EclFilter::Id { .. } => domain != FilterDomain::Member,
+            EclFilter::DefinitionStatus { .. } | EclFilter::SemanticTag { .. } => {
+                domain == FilterDomain::Concept
+            }
+            EclFilter::Term { .. }
+            | EclFilter::Language { .. }
+            | EclFilter::DescriptionType { .. }
+            | EclFilter::Dialect { .. }
+            | EclFilter::CaseSignificance { .. }
+            | EclFilter::PreferredIn { .. }
+            | EclFilter::AcceptableIn { .. }
+            | EclFilter::LanguageRefSet { .. } => domain == FilterDomain::Description,
+            EclFilter::Member { .. } => domain == FilterDomain::Member,
+            EclFilter::History { .. } | EclFilter::DomainQualified { .. } => false,
+        }
+    }
+}
+
 impl std::fmt::Display for EclFilter {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            EclFilter::Term { match_type, value } => {
-                write!(f, "{} \"{}\"", match_type, value)
-            }
+            EclFilter::Term { terms } => match terms.as_slice() {
+                [single] => write!(f, "{} \"{}\"", single.match_type, single.term),
+                [first, ..] if terms.iter().all(|t| t.match_type == first.match_type) => {
+                    let values: Vec<String> =
+                        terms.iter().map(|t| format!("\"{}\"", t.term)).collect();
+                    write!(f, "{} ({})", first.match_type, values.join(" "))
+                }
+                _ => {
+                    // Match types without a prefix are given by the operator, so
+                    // a set can only hold one of them
+                    let operator = terms
+                        .iter()
+                        .map(|t| t.match_type)
+                        .find(|m| m.prefix().is_none())
+                        .unwrap_or(TermMatchType::Contains);
+                    let values: Vec<String> = terms
+                        .iter()
+                        .map(|t| match t.match_type.prefix() {
+                            Some(prefix) => format!("{}:\"{}\"", prefix, t.term),
+                            None => format!("\"{}\"", t.term),
+                        })
+                        .collect();
+                    write!(f, "{} ({})", operator, values.join(" "))
+                }
+            },
             EclFilter::Language { codes } => {
                 if codes.len() == 1 {
                     write!(f, "language = {}", codes[0])
@@ -507,21 +642,9 @@ impl std::fmt::Display for EclFilter {
                     write!(f, "language = ({})", codes.join(" "))
                 }
             }
-            EclFilter::DescriptionType { type_ids } => {
-                if type_ids.len() == 1 {
-                    write!(f, "typeId = {}", type_ids[0])
-                } else {
-                    let ids: Vec<String> = type_ids.iter().map(|id| id.to_string()).collect();
-                    write!(f, "typeId = ({})", ids.join(" "))
-                }
-            }
-            EclFilter::Dialect { dialect_ids, acceptability } => {
-                if dialect_ids.len() == 1 {
-                    write!(f, "dialectId = {}", dialect_ids[0])?;
-                } else {
-                    let ids: Vec<String> = dialect_ids.iter().map(|id| id.to_string()).collect();
-                    write!(f, "dialectId = ({})", ids.join(" "))?;
-                }
+            EclFilter::DescriptionType { types } => write!(f, "typeId = {}", types),
+            EclFilter::Dialect { dialects, acceptability } => {
+                write!(f, "dialectId = {}", dialects)?;
                 if let Some(acc) = acceptability {
                     match acc {
                         FilterAcceptability::Preferred => write!(f, " prefer")?,
@@ -536,14 +659,7 @@ impl std::fmt::Display for EclFilter {
             EclFilter::Active(active) => {
                 write!(f, "active = {}", active)
             }
-            EclFilter::Module { module_ids } => {
-                if module_ids.len() == 1 {
-                    write!(f, "moduleId = {}", module_ids[0])
-                } else {
-                    let ids: Vec<String> = module_ids.iter().map(|id| id.to_string()).collect();
-                    write!(f, "moduleId = ({})", ids.join(" "))
-                }
-            }
+            EclFilter::Module { modules } => write!(f, "moduleId = {}", modules),
             EclFilter::EffectiveTime { operator, date } => {
                 write!(f, "effectiveTime {} {}", operator, date)
             }
@@ -597,11 +713,14 @@ impl std::fmt::Display for EclFilter {
                     write!(f, "id = ({})", id_strs.join(" "))
                 }
             }
-            EclFilter::History { profile } => {
+            EclFilter::History { profile, subset } => {
                 write!(f, "+HISTORY")?;
                 if let Some(p) = profile {
                     write!(f, "{}", p)?;
                 }
+                if let Some(subset) = subset {
+                    write!(f, " ({})", subset)?;
+                }
                 Ok(())
             }
             EclFilter::DomainQualified { domain, filter } => {
@@ -700,9 +819,12 @@ pub enum EclExpression {
     /// Syntax: `^ refsetId` or `^ (expression)`
     /// Example: `^ 700043003 |Example problem list concepts reference set|`
     /// Example with nested expression: `^ (< 723264001)` (members of any refset that is a descendant)
+    /// Example with field selection: `^ [referencedComponentId, mapTarget] 447562003`
     MemberOf {
         /// The reference set expression (can be a single concept or a more complex expression).
         refset: Box<EclExpression>,
+        /// The refset fields to return instead of the referenced components, if any.
+        fields: Option<MemberFieldSelection>,
     },
 
     /// Wildcard matching any concept.
@@ -843,6 +965,7 @@ impl EclExpression {
                 concept_id: refset_id,
                 term: None,
             }),
+            fields: None,
         }
     }
 
@@ -850,6 +973,15 @@ impl EclExpression {
     pub fn member_of_expression(refset: EclExpression) -> Self {
         EclExpression::MemberOf {
             refset: Box::new(refset),
+            fields: None,
+        }
+    }
+
+    /// Creates a member-of expression that selects refset fields.
+    pub fn member_fields_of(fields: MemberFieldSelection, refset: EclExpression) -> Self {
+        EclExpression::MemberOf {
+            refset: Box::new(refset),
+            fields: Some(fields),
         }
     }
 
@@ -919,18 +1051,22 @@ impl std::fmt::Display for EclExpression {
             EclExpression::And(left, right) => write!(f, "{} AND {}", left, right),
             EclExpression::Or(left, right) => write!(f, "{} OR {}", left, right),
             EclExpression::Minus(left, right) => write!(f, "{} MINUS {}", left, right),
-            EclExpression::MemberOf { refset } => {
+            EclExpression::MemberOf { refset, fields } => {
+                write!(f, "^ ")?;
+                if let Some(fields) = fields {
+                    write!(f, "{} ", fields)?;
+                }
                 // For simple concept references, display without parentheses
                 // For complex expressions, wrap in parentheses
                 match refset.as_ref() {
                     EclExpression::ConceptReference { concept_id, term } => {
                         if let Some(t) = term {
-                            write!(f, "^ {} |{}|", concept_id, t)
+                            write!(f, "{} |{}|", concept_id, t)
                         } else {
-                            write!(f, "^ {}", concept_id)
+                            write!(f, "{}", concept_id)
                         }
                     }
-                    _ => write!(f, "^ ({})", refset),
+                    _ => write!(f, "({})", refset),
                 }
             }
             EclExpression::Any => write!(f, "*"),
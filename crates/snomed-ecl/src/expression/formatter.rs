@@ -74,7 +74,7 @@ impl Formatter {
     // =========================================================================
 
     fn format_brief(expr: &Expression) -> String {
-        let separator = Self::operator_separator(expr.operator, false);
+        let separator = Self::operator_separator(expr.operator);
         let focus = expr
             .focus
             .iter()
@@ -126,11 +126,11 @@ impl Formatter {
     // =========================================================================
 
     fn format_long(expr: &Expression) -> String {
-        let separator = Self::operator_separator(expr.operator, true);
+        let separator = Self::operator_separator(expr.operator);
         let focus = expr
             .focus
             .iter()
-            .map(|c| Self::format_concept(c))
+            .map(Self::format_concept)
             .collect::<Vec<_>>()
             .join(&separator);
 
@@ -196,7 +196,7 @@ impl Formatter {
         let focus = expr
             .focus
             .iter()
-            .map(|c| Self::format_concept(c))
+            .map(Self::format_concept)
             .collect::<Vec<_>>()
             .join(&format!("{}\n{}", separator, base_indent));
         lines.push(format!("{}{}", base_indent, focus));
@@ -266,17 +266,13 @@ impl Formatter {
         }
     }
 
-    fn operator_separator(operator: Option<ExpressionOperator>, spaced: bool) -> String {
+    fn operator_separator(operator: Option<ExpressionOperator>) -> String {
         let sep = match operator {
             Some(ExpressionOperator::And) | None => "+",
             Some(ExpressionOperator::Or) => "OR",
             Some(ExpressionOperator::Minus) => "MINUS",
         };
-        if spaced {
-            format!(" {} ", sep)
-        } else {
-            format!(" {} ", sep)
-        }
+        format!(" {} ", sep)
     }
 
     fn operator_separator_nested(operator: Option<ExpressionOperator>) -> String {
@@ -69,8 +69,9 @@ mod parser;
 
 pub use ast::{
     AttributeConstraint, AttributeGroup, Cardinality, ComparisonOperator, ConcreteValue,
-    EclExpression, EclFilter, FilterAcceptability, HistoryProfile, MemberFieldValue,
-    Refinement, RefinementOperator, TermMatchType,
+    EclExpression, EclFilter, FilterAcceptability, FilterDomain, HistoryProfile,
+    MemberFieldSelection, MemberFieldValue, Refinement, RefinementOperator, TermMatchType,
+    TypedSearchTerm,
 };
 pub use error::{EclError, EclResult};
 pub use parser::parse;
@@ -7,7 +7,7 @@ use nom::{
     branch::alt,
     bytes::complete::{tag, tag_no_case, take_until, take_while, take_while1},
     character::complete::{char, digit1, multispace0, multispace1},
-    combinator::{all_consuming, map, opt, recognize, value},
+    combinator::{all_consuming, map, opt, recognize, value, verify},
     multi::{many0, separated_list1},
     sequence::{delimited, pair, preceded, tuple},
     IResult,
@@ -16,7 +16,8 @@ use nom::{
 use crate::ast::{
     AttributeConstraint, AttributeGroup, Cardinality, ComparisonOperator, ConcreteValue,
     EclExpression, EclFilter, FilterAcceptability, FilterDomain, HistoryProfile,
-    MemberFieldValue, Refinement, RefinementOperator, TermMatchType,
+    MemberFieldSelection, MemberFieldValue, Refinement, RefinementOperator, TermMatchType,
+    TypedSearchTerm,
 };
 use crate::error::{EclError, EclResult};
 use crate::SctId;
@@ -151,7 +152,6 @@ fn concept_reference_set(input: &str) -> IResult<&str, EclExpression> {
 
     // Parse the first ID to verify this is a concept set, not a nested expression
     let (input, first_id) = sct_id(input)?;
-    let (input, _) = ws(input)?;
 
     // Try to parse more IDs separated by whitespace
     let (input, mut ids) = many0(preceded(multispace1, sct_id))(input)?;
@@ -255,10 +255,34 @@ fn constraint_operator(input: &str) -> IResult<&str, ConstraintOp> {
     ))(input)
 }
 
+/// Parse a refset field selection: `[referencedComponentId, mapTarget]` or `[*]`
+fn member_field_selection(input: &str) -> IResult<&str, MemberFieldSelection> {
+    delimited(
+        pair(char('['), ws),
+        alt((
+            value(MemberFieldSelection::All, char('*')),
+            map(
+                separated_list1(
+                    tuple((ws, char(','), ws)),
+                    take_while1(|c: char| c.is_alphanumeric() || c == '_'),
+                ),
+                |fields: Vec<&str>| {
+                    MemberFieldSelection::Fields(fields.into_iter().map(String::from).collect())
+                },
+            ),
+        )),
+        pair(ws, char(']')),
+    )(input)
+}
+
 fn member_of_expression(input: &str) -> IResult<&str, EclExpression> {
     let (input, _) = char('^')(input)?;
     let (input, _) = ws(input)?;
 
+    // Optional field selection: `^ [mapTarget] refsetId`
+    let (input, fields) = opt(member_field_selection)(input)?;
+    let (input, _) = ws(input)?;
+
     // Try to parse nested expression first: `^ (expression)`
     let mut nested = delimited(
         pair(char('('), ws),
@@ -271,6 +295,7 @@ fn member_of_expression(input: &str) -> IResult<&str, EclExpression> {
             remaining,
             EclExpression::MemberOf {
                 refset: Box::new(inner),
+                fields,
             },
         ));
     }
@@ -282,6 +307,7 @@ fn member_of_expression(input: &str) -> IResult<&str, EclExpression> {
         input,
         EclExpression::MemberOf {
             refset: Box::new(inner),
+            fields,
         },
     ))
 }
@@ -723,7 +749,29 @@ fn quoted_string(input: &str) -> IResult<&str, String> {
     Ok((input, content.to_string()))
 }
 
-/// Parse a term filter: `term = "value"` or `term startsWith "value"` or `term wild "diab*"`
+/// Parse a typed search term: `"heart"` or `match:"heart"` or `wild:"*itis"`.
+///
+/// Untyped terms take the match type of the filter's operator.
+fn typed_search_term(default: TermMatchType) -> impl Fn(&str) -> IResult<&str, TypedSearchTerm> {
+    move |input| {
+        let (input, match_type) = opt(tuple((
+            alt((
+                value(TermMatchType::Contains, tag_no_case("match")),
+                value(TermMatchType::Wildcard, tag_no_case("wild")),
+            )),
+            ws,
+            char(':'),
+            ws,
+        )))(input)?;
+        let (input, term) = quoted_string(input)?;
+
+        let match_type = match_type.map_or(default, |(match_type, ..)| match_type);
+        Ok((input, TypedSearchTerm { match_type, term }))
+    }
+}
+
+/// Parse a term filter: `term = "value"` or `term startsWith "value"` or `term wild "diab*"`,
+/// with typed terms `term = wild:"diab*"` and sets `term = (match:"heart" wild:"*itis")`
 fn term_filter(input: &str) -> IResult<&str, EclFilter> {
     let (input, _) = tag_no_case("term")(input)?;
     let (input, _) = ws(input)?;
@@ -737,15 +785,22 @@ fn term_filter(input: &str) -> IResult<&str, EclFilter> {
     ))(input)?;
 
     let (input, _) = ws(input)?;
-    let (input, term_value) = quoted_string(input)?;
 
-    Ok((
-        input,
-        EclFilter::Term {
-            match_type,
-            value: term_value,
-        },
-    ))
+    // Support a single term or multiple terms in parentheses
+    let (input, terms) = alt((
+        delimited(
+            char('('),
+            delimited(
+                ws,
+                separated_list1(multispace1, typed_search_term(match_type)),
+                ws,
+            ),
+            char(')'),
+        ),
+        map(typed_search_term(match_type), |term| vec![term]),
+    ))(input)?;
+
+    Ok((input, EclFilter::Term { terms }))
 }
 
 /// Parse a member field value: string, integer, decimal, boolean, or sctid
@@ -801,7 +856,8 @@ fn member_filter(input: &str) -> IResult<&str, EclFilter> {
     ))
 }
 
-/// Parse a history supplement filter: `+HISTORY` or `+HISTORY-MIN` or `+HISTORY-MOD` or `+HISTORY-MAX`
+/// Parse a history supplement filter: `+HISTORY`, `+HISTORY-MIN`, `+HISTORY-MOD`,
+/// `+HISTORY-MAX` or `+HISTORY (<< 900000000000527005)`
 fn history_filter(input: &str) -> IResult<&str, EclFilter> {
     let (input, _) = tag("+")(input)?;
     let (input, _) = tag_no_case("HISTORY")(input)?;
@@ -813,7 +869,18 @@ fn history_filter(input: &str) -> IResult<&str, EclFilter> {
         value(HistoryProfile::Max, tag_no_case("-MAX")),
     )))(input)?;
 
-    Ok((input, EclFilter::History { profile }))
+    // Without a profile, optionally parse an association subset
+    let (input, subset) = if profile.is_none() {
+        opt(delimited(
+            tuple((ws, char('('), ws)),
+            map(expression_constraint, Box::new),
+            pair(ws, char(')')),
+        ))(input)?
+    } else {
+        (input, None)
+    };
+
+    Ok((input, EclFilter::History { profile, subset }))
 }
 
 /// Parse an active filter: `active = true/false`
@@ -832,26 +899,47 @@ fn active_filter(input: &str) -> IResult<&str, EclFilter> {
     Ok((input, EclFilter::Active(active)))
 }
 
-/// Parse a module filter: `moduleId = 900000000000207008` or `moduleId = (id1 id2)`
+/// Parse a filter constraint value: a sub-expression constraint such as
+/// `900000000000207008`, `(id1 id2)` or `<< 900000000000445007`
+fn filter_constraint(input: &str) -> IResult<&str, Box<EclExpression>> {
+    map(sub_expression_constraint, Box::new)(input)
+}
+
+/// Parse a list of filter value aliases, single or in parentheses, as a
+/// concept reference or concept set
+fn filter_alias_set<'a, F>(alias: F) -> impl FnMut(&'a str) -> IResult<&'a str, Box<EclExpression>>
+where
+    F: Fn(&'a str) -> IResult<&'a str, SctId> + Copy,
+{
+    map(
+        alt((
+            delimited(
+                preceded(ws, char('(')),
+                separated_list1(multispace1, alias),
+                preceded(ws, char(')')),
+            ),
+            map(alias, |id| vec![id]),
+        )),
+        |mut ids| {
+            Box::new(if ids.len() == 1 {
+                EclExpression::concept(ids.remove(0))
+            } else {
+                EclExpression::ConceptSet(ids)
+            })
+        },
+    )
+}
+
+/// Parse a module filter: `moduleId = 900000000000207008`, `moduleId = (id1 id2)`
+/// or `moduleId = << 900000000000445007`
 fn module_filter(input: &str) -> IResult<&str, EclFilter> {
     let (input, _) = tag_no_case("moduleId")(input)?;
     let (input, _) = ws(input)?;
     let (input, _) = char('=')(input)?;
     let (input, _) = ws(input)?;
+    let (input, modules) = filter_constraint(input)?;
 
-    // Support single ID or multiple IDs in parentheses
-    let (input, module_ids) = alt((
-        // Multiple IDs: (id1 id2 id3)
-        delimited(
-            preceded(ws, char('(')),
-            separated_list1(multispace1, sct_id),
-            preceded(ws, char(')')),
-        ),
-        // Single ID
-        map(sct_id, |id| vec![id]),
-    ))(input)?;
-
-    Ok((input, EclFilter::Module { module_ids }))
+    Ok((input, EclFilter::Module { modules }))
 }
 
 /// Parse a language code (2-3 letter code)
@@ -897,25 +985,18 @@ fn description_type_alias(input: &str) -> IResult<&str, SctId> {
     ))(input)
 }
 
-/// Parse a description type filter: `typeId = 900000000000003001` or `type = syn`
+/// Parse a description type filter: `typeId = 900000000000003001`,
+/// `typeId = << 900000000000446008` or `type = syn`
 fn description_type_filter(input: &str) -> IResult<&str, EclFilter> {
-    // First try typeId with numeric IDs
+    // First try typeId with a constraint
     let type_id_parser = |input| {
         let (input, _) = tag_no_case("typeId")(input)?;
         let (input, _) = ws(input)?;
         let (input, _) = char('=')(input)?;
         let (input, _) = ws(input)?;
+        let (input, types) = filter_constraint(input)?;
 
-        let (input, type_ids) = alt((
-            delimited(
-                preceded(ws, char('(')),
-                separated_list1(multispace1, sct_id),
-                preceded(ws, char(')')),
-            ),
-            map(sct_id, |id| vec![id]),
-        ))(input)?;
-
-        Ok((input, EclFilter::DescriptionType { type_ids }))
+        Ok((input, EclFilter::DescriptionType { types }))
     };
 
     // Then try type with aliases
@@ -924,17 +1005,9 @@ fn description_type_filter(input: &str) -> IResult<&str, EclFilter> {
         let (input, _) = ws(input)?;
         let (input, _) = char('=')(input)?;
         let (input, _) = ws(input)?;
+        let (input, types) = filter_alias_set(description_type_alias)(input)?;
 
-        let (input, type_ids) = alt((
-            delimited(
-                preceded(ws, char('(')),
-                separated_list1(multispace1, description_type_alias),
-                preceded(ws, char(')')),
-            ),
-            map(description_type_alias, |id| vec![id]),
-        ))(input)?;
-
-        Ok((input, EclFilter::DescriptionType { type_ids }))
+        Ok((input, EclFilter::DescriptionType { types }))
     };
 
     alt((type_id_parser, type_alias_parser))(input)
@@ -962,7 +1035,8 @@ fn filter_acceptability(input: &str) -> IResult<&str, FilterAcceptability> {
     ))(input)
 }
 
-/// Parse a dialect filter: `dialectId = 900000000000509007` or `dialect = en-US`
+/// Parse a dialect filter: `dialectId = 900000000000509007`, `dialectId = ^ X`
+/// or `dialect = en-US`
 fn dialect_filter(input: &str) -> IResult<&str, EclFilter> {
     // dialectId variant
     let dialect_id_parser = |input| {
@@ -970,20 +1044,12 @@ fn dialect_filter(input: &str) -> IResult<&str, EclFilter> {
         let (input, _) = ws(input)?;
         let (input, _) = char('=')(input)?;
         let (input, _) = ws(input)?;
-
-        let (input, dialect_ids) = alt((
-            delimited(
-                preceded(ws, char('(')),
-                separated_list1(multispace1, sct_id),
-                preceded(ws, char(')')),
-            ),
-            map(sct_id, |id| vec![id]),
-        ))(input)?;
+        let (input, dialects) = filter_constraint(input)?;
 
         // Optional acceptability
         let (input, acceptability) = opt(preceded(ws, filter_acceptability))(input)?;
 
-        Ok((input, EclFilter::Dialect { dialect_ids, acceptability }))
+        Ok((input, EclFilter::Dialect { dialects, acceptability }))
     };
 
     // dialect alias variant
@@ -992,19 +1058,11 @@ fn dialect_filter(input: &str) -> IResult<&str, EclFilter> {
         let (input, _) = ws(input)?;
         let (input, _) = char('=')(input)?;
         let (input, _) = ws(input)?;
-
-        let (input, dialect_ids) = alt((
-            delimited(
-                preceded(ws, char('(')),
-                separated_list1(multispace1, dialect_alias),
-                preceded(ws, char(')')),
-            ),
-            map(dialect_alias, |id| vec![id]),
-        ))(input)?;
+        let (input, dialects) = filter_alias_set(dialect_alias)(input)?;
 
         let (input, acceptability) = opt(preceded(ws, filter_acceptability))(input)?;
 
-        Ok((input, EclFilter::Dialect { dialect_ids, acceptability }))
+        Ok((input, EclFilter::Dialect { dialects, acceptability }))
     };
 
     alt((dialect_id_parser, dialect_alias_parser))(input)
@@ -1206,8 +1264,11 @@ fn single_filter(input: &str) -> IResult<&str, EclFilter> {
     if let Ok((remaining, domain)) = domain_prefix(input) {
         // Must have whitespace after domain prefix to be valid
         if let Ok((remaining, _)) = mws(remaining) {
-            // Parse the inner filter
-            if let Ok((remaining, filter)) = inner_filter(remaining) {
+            // Parse the inner filter, which must apply to the domain
+            if let Ok((remaining, filter)) = verify(inner_filter, |filter: &EclFilter| {
+                filter.applies_to(domain)
+            })(remaining)
+            {
                 return Ok((
                     remaining,
                     EclFilter::DomainQualified {
@@ -1419,7 +1480,7 @@ mod tests {
         fn test_1_6_member_of() {
             let expr = parse("^ 700043003 |example problem list concepts reference set|").unwrap();
             match expr {
-                EclExpression::MemberOf { refset } => {
+                EclExpression::MemberOf { refset, .. } => {
                     match refset.as_ref() {
                         EclExpression::ConceptReference { concept_id, term } => {
                             assert_eq!(*concept_id, 700043003);
@@ -1439,7 +1500,7 @@ mod tests {
         fn test_1_6_member_of_no_term() {
             let expr = parse("^700043003").unwrap();
             match expr {
-                EclExpression::MemberOf { refset } => {
+                EclExpression::MemberOf { refset, .. } => {
                     match refset.as_ref() {
                         EclExpression::ConceptReference { concept_id, term } => {
                             assert_eq!(*concept_id, 700043003);
@@ -1452,13 +1513,48 @@ mod tests {
             }
         }
 
+        /// Test: Member-of with a refset field selection
+        /// Example: ^ [referencedComponentId, mapTarget] 447562003
+        #[test]
+        fn test_member_of_field_selection() {
+            let expr = parse("^ [referencedComponentId, mapTarget] 447562003").unwrap();
+            assert_eq!(
+                expr,
+                EclExpression::member_fields_of(
+                    MemberFieldSelection::Fields(vec![
+                        "referencedComponentId".to_string(),
+                        "mapTarget".to_string(),
+                    ]),
+                    EclExpression::concept(447562003),
+                )
+            );
+
+            let expr = parse("^[ * ](< 447562003)").unwrap();
+            match expr {
+                EclExpression::MemberOf { refset, fields } => {
+                    assert_eq!(fields, Some(MemberFieldSelection::All));
+                    assert!(matches!(refset.as_ref(), EclExpression::DescendantOf(_)));
+                }
+                _ => panic!("Expected MemberOf"),
+            }
+
+            // Plain member-of has no field selection
+            match parse("^ 447562003").unwrap() {
+                EclExpression::MemberOf { fields, .. } => assert_eq!(fields, None),
+                _ => panic!("Expected MemberOf"),
+            }
+
+            assert!(parse("^ [] 447562003").is_err());
+            assert!(parse("^ [mapTarget 447562003").is_err());
+        }
+
         /// Test: Enhanced member-of with nested expression
         /// Example: ^ (< 723264001)
         #[test]
         fn test_member_of_with_nested_expression() {
             let expr = parse("^ (< 723264001)").unwrap();
             match expr {
-                EclExpression::MemberOf { refset } => {
+                EclExpression::MemberOf { refset, .. } => {
                     // The refset should be a descendant-of expression
                     assert!(matches!(refset.as_ref(), EclExpression::DescendantOf(_)));
                 }
@@ -1472,7 +1568,7 @@ mod tests {
         fn test_member_of_with_complex_nested() {
             let expr = parse("^ (<< 723264001 OR << 900000000000496009)").unwrap();
             match expr {
-                EclExpression::MemberOf { refset } => {
+                EclExpression::MemberOf { refset, .. } => {
                     // The refset should be an OR expression
                     assert!(matches!(refset.as_ref(), EclExpression::Or(_, _)));
                 }
@@ -1785,7 +1881,7 @@ mod tests {
             let expr =
                 parse("^700043003 |Example problem list concepts reference set|").unwrap();
             match expr {
-                EclExpression::MemberOf { refset } => {
+                EclExpression::MemberOf { refset, .. } => {
                     match refset.as_ref() {
                         EclExpression::ConceptReference { concept_id, term } => {
                             assert_eq!(*concept_id, 700043003);
@@ -2101,6 +2197,60 @@ mod tests {
             let output = roundtrip("404684003 |Clinical finding|");
             assert_eq!(output, "404684003 |Clinical finding|");
         }
+
+        #[test]
+        fn test_member_of_fields_roundtrip() {
+            for input in [
+                "^ 447562003",
+                "^ [*] 447562003",
+                "^ [referencedComponentId, mapTarget] 447562003 |ICD-10 map|",
+                "^ [mapTarget] (<< 447562003)",
+            ] {
+                assert_eq!(roundtrip(input), input);
+            }
+        }
+
+        #[test]
+        fn test_term_filter_roundtrip() {
+            for input in [
+                r#"<< 404684003 {{ term = "heart" }}"#,
+                r#"<< 404684003 {{ term wild "diab*" }}"#,
+                r#"<< 404684003 {{ term = ("heart" "cardiac") }}"#,
+                r#"<< 404684003 {{ term = (match:"heart" wild:"*itis") }}"#,
+                r#"<< 404684003 {{ term startsWith ("hea" wild:"*itis") }}"#,
+                r#"<< 404684003 {{ term == ("Heart" match:"cardiac") }}"#,
+            ] {
+                let output = roundtrip(input);
+                assert_eq!(parse(&output).unwrap(), parse(input).unwrap(), "{}", output);
+            }
+
+            assert_eq!(
+                roundtrip(r#"<< 404684003 {{ term = (match:"heart" wild:"*itis") }}"#),
+                r#"<< 404684003 {{ term = (match:"heart" wild:"*itis") }}"#
+            );
+            assert_eq!(
+                roundtrip(r#"<< 404684003 {{ term = wild:"diab*" }}"#),
+                r#"<< 404684003 {{ term wild "diab*" }}"#
+            );
+            assert_eq!(
+                roundtrip(r#"<< 404684003 {{ term regex ("^card" match:"heart") }}"#),
+                r#"<< 404684003 {{ term regex ("^card" match:"heart") }}"#
+            );
+        }
+
+        #[test]
+        fn test_filter_constraint_roundtrip() {
+            for input in [
+                "<< 404684003 {{ moduleId = << 900000000000445007 }}",
+                "<< 404684003 {{ typeId = (900000000000003001 900000000000013009) }}",
+                "<< 404684003 {{ dialectId = ^ 900000000000506000 prefer }}",
+                "^ 447562003 {{ M moduleId = < 900000000000445007 }}",
+                "< 404684003 {{ +HISTORY (<< 900000000000527005) }}",
+                "< 404684003 {{ +HISTORY-MOD }}",
+            ] {
+                assert_eq!(roundtrip(input), input);
+            }
+        }
     }
 
     // ========================================================================
@@ -2349,6 +2499,7 @@ mod tests {
 
             /// Test: Decimal concrete value
             #[test]
+            #[allow(clippy::approx_constant)] // 3.14 is test data, not π
             fn test_decimal_concrete_value() {
                 let expr = parse("< 404684003 : 363698007 = #3.14").unwrap();
                 match expr {
@@ -2544,6 +2695,7 @@ mod tests {
 
             /// Test: Decimal comparison with less than
             #[test]
+            #[allow(clippy::approx_constant)] // 3.14 is test data, not π
             fn test_decimal_less_than() {
                 let expr = parse("< 404684003 : 363698007 < #3.14").unwrap();
                 match expr {
@@ -2577,9 +2729,8 @@ mod tests {
                         assert!(matches!(expression.as_ref(), EclExpression::DescendantOf(_)));
                         assert_eq!(filters.len(), 1);
                         match &filters[0] {
-                            EclFilter::Term { match_type, value, .. } => {
-                                assert!(matches!(match_type, TermMatchType::Contains));
-                                assert_eq!(value, "heart");
+                            EclFilter::Term { terms } => {
+                                assert_eq!(terms, &[TypedSearchTerm::new(TermMatchType::Contains, "heart")]);
                             }
                             _ => panic!("Expected Term filter"),
                         }
@@ -2595,9 +2746,8 @@ mod tests {
                 match expr {
                     EclExpression::Filtered { filters, .. } => {
                         match &filters[0] {
-                            EclFilter::Term { match_type, value, .. } => {
-                                assert!(matches!(match_type, TermMatchType::StartsWith));
-                                assert_eq!(value, "card");
+                            EclFilter::Term { terms } => {
+                                assert_eq!(terms, &[TypedSearchTerm::new(TermMatchType::StartsWith, "card")]);
                             }
                             _ => panic!("Expected Term filter"),
                         }
@@ -2624,7 +2774,7 @@ mod tests {
                 let expr = parse("< 404684003 {{ +HISTORY }}").unwrap();
                 match expr {
                     EclExpression::Filtered { filters, .. } => {
-                        assert!(matches!(&filters[0], EclFilter::History { profile: None }));
+                        assert!(matches!(&filters[0], EclFilter::History { profile: None, subset: None }));
                     }
                     _ => panic!("Expected Filtered expression"),
                 }
@@ -2638,13 +2788,31 @@ mod tests {
                     EclExpression::Filtered { filters, .. } => {
                         assert!(matches!(
                             &filters[0],
-                            EclFilter::History { profile: Some(HistoryProfile::Min) }
+                            EclFilter::History { profile: Some(HistoryProfile::Min), subset: None }
                         ));
                     }
                     _ => panic!("Expected Filtered expression"),
                 }
             }
 
+            /// Test: History supplement with an association subset
+            #[test]
+            fn test_history_supplement_with_subset() {
+                let expr = parse("< 404684003 {{ +HISTORY (<< 900000000000527005 OR 900000000000526001) }}")
+                    .unwrap();
+                match expr {
+                    EclExpression::Filtered { filters, .. } => match &filters[0] {
+                        EclFilter::History { profile: None, subset: Some(subset) } => {
+                            assert!(matches!(subset.as_ref(), EclExpression::Or(_, _)));
+                        }
+                        other => panic!("Expected History filter with subset, got {:?}", other),
+                    },
+                    _ => panic!("Expected Filtered expression"),
+                }
+
+                assert!(parse("< 404684003 {{ +HISTORY-MIN (<< 900000000000527005) }}").is_err());
+            }
+
             /// Test: Member filter
             #[test]
             fn test_member_filter() {
@@ -2705,8 +2873,8 @@ mod tests {
                 match expr {
                     EclExpression::Filtered { filters, .. } => {
                         match &filters[0] {
-                            EclFilter::DescriptionType { type_ids } => {
-                                assert_eq!(type_ids, &vec![900000000000003001u64]);
+                            EclFilter::DescriptionType { types } => {
+                                assert_eq!(**types, EclExpression::concept(900000000000003001));
                             }
                             _ => panic!("Expected DescriptionType filter"),
                         }
@@ -2722,8 +2890,8 @@ mod tests {
                 match expr {
                     EclExpression::Filtered { filters, .. } => {
                         match &filters[0] {
-                            EclFilter::DescriptionType { type_ids } => {
-                                assert_eq!(type_ids, &vec![900000000000013009u64]); // SYN_ID
+                            EclFilter::DescriptionType { types } => {
+                                assert_eq!(**types, EclExpression::concept(900000000000013009)); // SYN_ID
                             }
                             _ => panic!("Expected DescriptionType filter"),
                         }
@@ -2739,8 +2907,8 @@ mod tests {
                 match expr {
                     EclExpression::Filtered { filters, .. } => {
                         match &filters[0] {
-                            EclFilter::Dialect { dialect_ids, acceptability } => {
-                                assert_eq!(dialect_ids, &vec![900000000000509007u64]);
+                            EclFilter::Dialect { dialects, acceptability } => {
+                                assert_eq!(**dialects, EclExpression::concept(900000000000509007));
                                 assert!(acceptability.is_none());
                             }
                             _ => panic!("Expected Dialect filter"),
@@ -2757,8 +2925,8 @@ mod tests {
                 match expr {
                     EclExpression::Filtered { filters, .. } => {
                         match &filters[0] {
-                            EclFilter::Dialect { dialect_ids, acceptability } => {
-                                assert_eq!(dialect_ids, &vec![900000000000509007u64]);
+                            EclFilter::Dialect { dialects, acceptability } => {
+                                assert_eq!(**dialects, EclExpression::concept(900000000000509007));
                                 assert!(matches!(acceptability, Some(FilterAcceptability::Preferred)));
                             }
                             _ => panic!("Expected Dialect filter"),
@@ -2946,8 +3114,11 @@ mod tests {
                 match expr {
                     EclExpression::Filtered { filters, .. } => {
                         match &filters[0] {
-                            EclFilter::Module { module_ids } => {
-                                assert_eq!(module_ids, &vec![900000000000207008u64, 900000000000012004u64]);
+                            EclFilter::Module { modules } => {
+                                assert_eq!(
+                                    **modules,
+                                    EclExpression::ConceptSet(vec![900000000000207008, 900000000000012004])
+                                );
                             }
                             _ => panic!("Expected Module filter"),
                         }
@@ -2956,6 +3127,42 @@ mod tests {
                 }
             }
 
+            /// Test: Expression-valued module, type and dialect filters
+            #[test]
+            fn test_filter_constraint_expressions() {
+                let expr = parse(
+                    "<< 404684003 {{ C moduleId = << 900000000000445007 }} \
+                     {{ typeId = << 900000000000446008, dialectId = ^ 900000000000506000 accept }}",
+                )
+                .unwrap();
+                let EclExpression::Filtered { expression, filters } = expr else {
+                    panic!("Expected Filtered expression");
+                };
+                assert!(matches!(
+                    &filters[1],
+                    EclFilter::Dialect { dialects, acceptability: Some(FilterAcceptability::Acceptable) }
+                        if **dialects == EclExpression::member_of(900000000000506000)
+                ));
+                assert!(matches!(
+                    &filters[..],
+                    [EclFilter::DescriptionType { types }, _]
+                        if **types == EclExpression::descendant_or_self_of(EclExpression::concept(900000000000446008))
+                ));
+                let EclExpression::Filtered { filters, .. } = *expression else {
+                    panic!("Expected Filtered expression");
+                };
+                match &filters[0] {
+                    EclFilter::DomainQualified { domain: FilterDomain::Concept, filter } => {
+                        assert!(matches!(
+                            filter.as_ref(),
+                            EclFilter::Module { modules }
+                                if **modules == EclExpression::descendant_or_self_of(EclExpression::concept(900000000000445007))
+                        ));
+                    }
+                    other => panic!("Expected concept module filter, got {:?}", other),
+                }
+            }
+
             /// Test: Wildcard term filter
             #[test]
             fn test_term_wildcard_filter() {
@@ -2963,9 +3170,8 @@ mod tests {
                 match expr {
                     EclExpression::Filtered { filters, .. } => {
                         match &filters[0] {
-                            EclFilter::Term { match_type, value } => {
-                                assert!(matches!(match_type, TermMatchType::Wildcard));
-                                assert_eq!(value, "diab*");
+                            EclFilter::Term { terms } => {
+                                assert_eq!(terms, &[TypedSearchTerm::new(TermMatchType::Wildcard, "diab*")]);
                             }
                             _ => panic!("Expected Term filter"),
                         }
@@ -2974,6 +3180,48 @@ mod tests {
                 }
             }
 
+            /// Test: Typed search terms
+            #[test]
+            fn test_term_typed_search_terms() {
+                let cases = [
+                    (r#"term = wild:"diab*""#, vec![(TermMatchType::Wildcard, "diab*")]),
+                    (r#"term = match:"heart att""#, vec![(TermMatchType::Contains, "heart att")]),
+                    (
+                        r#"term = (match:"heart" wild:"*itis")"#,
+                        vec![(TermMatchType::Contains, "heart"), (TermMatchType::Wildcard, "*itis")],
+                    ),
+                    (
+                        r#"term = ( "heart"  wild : "*itis" )"#,
+                        vec![(TermMatchType::Contains, "heart"), (TermMatchType::Wildcard, "*itis")],
+                    ),
+                    (
+                        r#"term wild ("card*" "*itis")"#,
+                        vec![(TermMatchType::Wildcard, "card*"), (TermMatchType::Wildcard, "*itis")],
+                    ),
+                ];
+
+                for (filter, expected) in cases {
+                    let expr = parse(&format!("<< 404684003 {{{{ {} }}}}", filter)).unwrap();
+                    let expected: Vec<TypedSearchTerm> = expected
+                        .into_iter()
+                        .map(|(match_type, term)| TypedSearchTerm::new(match_type, term))
+                        .collect();
+                    match expr {
+                        EclExpression::Filtered { filters, .. } => {
+                            assert_eq!(filters, vec![EclFilter::Term { terms: expected }], "{}", filter);
+                        }
+                        _ => panic!("Expected Filtered expression"),
+                    }
+                }
+
+                assert!(parse(r#"<< 404684003 {{ term = () }}"#).is_err());
+                assert!(parse(r#"<< 404684003 {{ term = fuzzy:"heart" }}"#).is_err());
+                for prefix in ["startsWith", "regex", "exact"] {
+                    let input = format!(r#"<< 404684003 {{{{ term = {}:"heart" }}}}"#, prefix);
+                    assert!(parse(&input).is_err(), "{}", input);
+                }
+            }
+
             /// Test: Multiple filters combined
             #[test]
             fn test_multiple_filters() {
@@ -3018,9 +3266,8 @@ mod tests {
                             EclFilter::DomainQualified { domain, filter } => {
                                 assert!(matches!(domain, FilterDomain::Description));
                                 match filter.as_ref() {
-                                    EclFilter::Term { match_type, value } => {
-                                        assert!(matches!(match_type, TermMatchType::Contains));
-                                        assert_eq!(value, "heart");
+                                    EclFilter::Term { terms } => {
+                                        assert_eq!(terms, &[TypedSearchTerm::new(TermMatchType::Contains, "heart")]);
                                     }
                                     _ => panic!("Expected Term filter"),
                                 }
@@ -3050,6 +3297,20 @@ mod tests {
                     _ => panic!("Expected Filtered expression"),
                 }
             }
+
+            /// Test: Filters that do not apply to their domain are rejected
+            #[test]
+            fn test_domain_prefix_invalid_filter() {
+                for input in [
+                    "<< 404684003 {{ D definitionStatus = primitive }}",
+                    r#"<< 404684003 {{ C term = "heart" }}"#,
+                    "<< 404684003 {{ C type = syn }}",
+                    "<< 404684003 {{ D +HISTORY }}",
+                ] {
+                    assert!(parse(input).is_err(), "{}", input);
+                }
+                assert!(parse("<< 404684003 {{ D moduleId = 900000000000207008, D id = 1 }}").is_ok());
+            }
         }
 
         // Top/Bottom of Set Tests